@@ -0,0 +1,69 @@
+//! Benchmark: line-by-line vs byte search for the Droid `Model:` marker
+//!
+//! The old path decoded each line into a `String` and stopped after 500
+//! lines; `model_from_jsonl_bytes`, which Droid parsing uses, scans the whole
+//! file without decoding anything but the model name.
+//!
+//! Run with: cargo bench --features noop,bench --bench droid_model_scan
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::io::{BufRead, BufReader};
+use tokscale_core::bench::model_from_jsonl_bytes;
+
+/// A session log with `lines` filler messages before the system-reminder
+fn synthetic_jsonl(lines: usize) -> Vec<u8> {
+    let mut data = Vec::new();
+    for i in 0..lines {
+        data.extend_from_slice(
+            format!(
+                "{{\"type\":\"message\",\"id\":\"msg-{}\",\"content\":\"{}\"}}\n",
+                i,
+                "lorem ipsum dolor sit amet ".repeat(8)
+            )
+            .as_bytes(),
+        );
+    }
+    data.extend_from_slice(
+        b"{\"type\":\"message\",\"content\":\"<system-reminder>Model: Claude Opus 4.5 Thinking [Anthropic]</system-reminder>\"}\n",
+    );
+    data
+}
+
+fn model_by_lines(data: &[u8]) -> Option<String> {
+    for line in BufReader::new(data).lines() {
+        let line = line.ok()?;
+        if let Some(pos) = line.find("Model:") {
+            let model: String = line[pos + 6..]
+                .chars()
+                .take_while(|&c| c != '[' && c != '\\' && c != '"')
+                .collect();
+            let model = model.trim();
+            if !model.is_empty() {
+                return Some(model.to_string());
+            }
+        }
+    }
+    None
+}
+
+fn bench_droid_model_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("droid_model_scan");
+
+    for lines in [500, 20_000] {
+        let data = synthetic_jsonl(lines);
+        assert!(model_by_lines(&data).is_some() && model_from_jsonl_bytes(&data).is_some());
+
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::new("lines", lines), &data, |b, data| {
+            b.iter(|| model_by_lines(black_box(data)))
+        });
+        group.bench_with_input(BenchmarkId::new("bytes", lines), &data, |b, data| {
+            b.iter(|| model_from_jsonl_bytes(black_box(data)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_droid_model_scan);
+criterion_main!(benches);
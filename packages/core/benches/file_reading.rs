@@ -0,0 +1,226 @@
+//! Benchmark: `FileBytes::read` (heap) vs `FileBytes::map` (mmap) on large
+//! session files
+//!
+//! Two workloads: whole-file parsers, which hand `into_buffer` to simd-json,
+//! and the Droid `Model:` search, which only needs `as_slice`. Peak heap and
+//! peak RSS of one parallel scan per strategy, each in a fresh process, are
+//! printed before timing.
+//!
+//! Measured on 32 x 4 MiB files (Linux, 1 core):
+//!
+//! | workload | path | peak heap | peak RSS  | time    |
+//! |----------|------|-----------|-----------|---------|
+//! | parse    | read | 14.9 MiB  | +32.4 MiB | 73.7 ms |
+//! | parse    | map  | 14.9 MiB  | +36.3 MiB | 75.0 ms |
+//! | search   | read | 4.0 MiB   | +4.4 MiB  | 24.1 ms |
+//! | search   | map  | 0.0 MiB   | +4.3 MiB  | 16.8 ms |
+//!
+//! Mapping doesn't help whole-file parsers: simd-json needs its own copy, so
+//! the heap peak is the same, and the mapped pages add to RSS. The search
+//! reads in place, so mapping keeps the file off the heap and is ~30% faster;
+//! only it maps.
+//!
+//! Run with: cargo bench --features noop,bench --bench file_reading
+
+use criterion::{black_box, criterion_group, BenchmarkId, Criterion, Throughput};
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokscale_core::bench::{model_from_jsonl_bytes, FileBytes};
+
+/// System allocator that records the high-water mark of live heap bytes
+struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: PeakAlloc = PeakAlloc;
+
+const FILE_COUNT: usize = 32;
+const FILE_SIZE_MB: usize = 4;
+
+type Read = fn(&Path) -> std::io::Result<FileBytes>;
+
+#[derive(Deserialize)]
+struct BenchMessage {
+    #[allow(dead_code)]
+    role: String,
+    tokens: Option<BenchTokens>,
+}
+
+#[derive(Deserialize)]
+struct BenchTokens {
+    input: i64,
+    output: i64,
+}
+
+/// Write `FILE_COUNT` OpenCode-shaped messages padded to roughly `FILE_SIZE_MB`
+/// each. The padding ends in a Droid `Model:` marker so the search reads the
+/// whole file too.
+fn generate_files(dir: &Path) -> Vec<PathBuf> {
+    let padding = "lorem ipsum dolor sit amet ".repeat(FILE_SIZE_MB * 1024 * 1024 / 27)
+        + "Model: Claude Opus 4.5";
+    (0..FILE_COUNT)
+        .map(|i| {
+            let path = dir.join(format!("msg_{:04}.json", i));
+            let json = format!(
+                r#"{{"id":"msg_{i}","sessionID":"ses_bench","role":"assistant","modelID":"claude-sonnet-4","providerID":"anthropic","summary":"{padding}","tokens":{{"input":{input},"output":{output},"cache":{{"read":0,"write":0}}}},"time":{{"created":1700000000000.0}}}}"#,
+                i = i,
+                padding = padding,
+                input = 1000 + i,
+                output = 500 + i,
+            );
+            std::fs::write(&path, json).unwrap();
+            path
+        })
+        .collect()
+}
+
+/// Whole-file parse: the buffer simd-json mutates, as OpenCode and Amp parse
+fn parse(files: &[PathBuf], read: Read) -> i64 {
+    files
+        .par_iter()
+        .map(|path| {
+            let mut bytes = read(path).unwrap().into_buffer();
+            let msg: BenchMessage = simd_json::from_slice(&mut bytes).unwrap();
+            msg.tokens.map(|t| t.input + t.output).unwrap_or(0)
+        })
+        .sum()
+}
+
+/// In-place search, as Droid looks for its `Model:` marker
+fn search(files: &[PathBuf], read: Read) -> i64 {
+    files
+        .par_iter()
+        .map(|path| {
+            let data = read(path).unwrap();
+            model_from_jsonl_bytes(data.as_slice()).map_or(0, |m| m.len() as i64)
+        })
+        .sum()
+}
+
+/// Resident set size and its peak, in KiB (Linux only)
+fn rss_kib() -> Option<(u64, u64)> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let field = |name: &str| -> Option<u64> {
+        let line = status.lines().find(|l| l.starts_with(name))?;
+        line.split_whitespace().nth(1)?.parse().ok()
+    };
+    Some((field("VmRSS:")?, field("VmHWM:")?))
+}
+
+/// Run one workload over the files in `dir` and print its peak heap and how
+/// far RSS rose above where it started. Runs in a fresh process (see `main`),
+/// so memory an earlier run left with the allocator doesn't hide this one's.
+fn report_peak(name: &str, dir: &Path) {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    files.sort();
+    let (workload, strategy) = name.split_once('/').unwrap();
+    let read: Read = if strategy == "map" {
+        FileBytes::map
+    } else {
+        FileBytes::read
+    };
+
+    let rss_before = rss_kib();
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    black_box(if workload == "parse" {
+        parse(&files, read)
+    } else {
+        search(&files, read)
+    });
+    let peak = PEAK.load(Ordering::Relaxed) - baseline;
+    let rss = match (rss_before, rss_kib()) {
+        (Some((before, _)), Some((_, peak))) => {
+            format!("{:.1} MiB", peak.saturating_sub(before) as f64 / 1024.0)
+        }
+        _ => "n/a".to_string(),
+    };
+    println!(
+        "file_reading/{}: peak heap {:.1} MiB, peak RSS +{} over {} x {} MiB files",
+        name,
+        peak as f64 / (1024.0 * 1024.0),
+        rss,
+        FILE_COUNT,
+        FILE_SIZE_MB
+    );
+}
+
+fn bench_file_reading(c: &mut Criterion) {
+    let dir = tempfile::TempDir::new().unwrap();
+    let files = generate_files(dir.path());
+    let total_bytes: u64 = files
+        .iter()
+        .map(|p| std::fs::metadata(p).unwrap().len())
+        .sum();
+
+    let strategies: [(&str, Read); 2] = [("read", FileBytes::read), ("map", FileBytes::map)];
+    for workload in ["parse", "search"] {
+        for (name, _) in strategies {
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .env(PEAK_ENV, format!("{}/{}", workload, name))
+                .env(DIR_ENV, dir.path())
+                .output()
+                .unwrap();
+            print!("{}", String::from_utf8_lossy(&output.stdout));
+        }
+    }
+
+    let mut group = c.benchmark_group("file_reading");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(total_bytes));
+
+    for (name, read) in strategies {
+        group.bench_with_input(
+            BenchmarkId::new(format!("parse/{}", name), FILE_COUNT),
+            &files,
+            |b, files| b.iter(|| black_box(parse(files, read))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new(format!("search/{}", name), FILE_COUNT),
+            &files,
+            |b, files| b.iter(|| black_box(search(files, read))),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_file_reading);
+
+/// Set in the child processes `report_peak` runs in: the workload and
+/// strategy to measure, and the directory holding the files
+const PEAK_ENV: &str = "FILE_READING_PEAK";
+const DIR_ENV: &str = "FILE_READING_DIR";
+
+fn main() {
+    if let (Ok(name), Ok(dir)) = (std::env::var(PEAK_ENV), std::env::var(DIR_ENV)) {
+        report_peak(&name, Path::new(&dir));
+        return;
+    }
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
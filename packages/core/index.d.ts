@@ -1,5 +1,93 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
+/**
+ * Graph over caller-supplied `messages`, with no scanning: priced (unless
+ * `apply_pricing` is false), date-filtered and aggregated like a scanned graph
+ */
+export declare function aggregateMessages(messages: Array<ParsedMessage>, options: AggregateOptions): Promise<GraphResult>
+
+/** Options for `aggregate_messages` and `report_messages` */
+export interface AggregateOptions {
+  /**
+   * Recompute each message's cost from pricing data. When false, each
+   * message's own `cost` is used (0 when unset). Default true.
+   */
+  applyPricing?: boolean
+  /**
+   * Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+   * Defaults to LiteLLM first.
+   */
+  pricingPriority?: Array<string>
+  year?: string
+  since?: string
+  until?: string
+  /** Split each graph day's sources by agent */
+  groupByAgent?: boolean
+  /**
+   * Itemize reasoning cost in `report_messages` instead of folding it into
+   * output cost. Default false.
+   */
+  separateReasoningCost?: boolean
+  /** As in `ReportOptions`, for the pricing applied to the messages */
+  preferCheaper?: boolean
+  reasoningRateMultiplier?: number
+  batchRateMultiplier?: number
+  unknownModelRate?: NativePricing
+  costSource?: string
+  fuzzyMinLength?: number
+  fuzzyBlocklist?: Array<string>
+  /**
+   * Add a zeroed graph day for every day without activity between the
+   * first and last active day
+   */
+  fillGaps?: boolean
+  /** Round every emitted cost to this many decimal places */
+  roundCosts?: number
+  /** Drop messages whose total tokens exceed this, as in `ReportOptions` */
+  maxTokensPerMessage?: number
+}
+
+/** Combined report, graph and monthly result (single pricing lookup and pass) */
+export interface AllReports {
+  report: ModelReport
+  graph: GraphResult
+  monthly: MonthlyReport
+}
+
+/** Options for comparing two date ranges */
+export interface CompareOptions {
+  homeDir?: string
+  sources?: Array<string>
+  first: DateWindow
+  second: DateWindow
+  /** As in `ReportOptions`, for the pricing applied to the messages */
+  pricingPriority?: Array<string>
+  skipPricing?: boolean
+  preferCheaper?: boolean
+  reasoningRateMultiplier?: number
+  batchRateMultiplier?: number
+  unknownModelRate?: NativePricing
+  costSource?: string
+  fuzzyMinLength?: number
+  fuzzyBlocklist?: Array<string>
+}
+
+/** Compare per-model usage between two date ranges */
+export declare function compareRanges(options: CompareOptions): Promise<RangeComparison>
+
+/** What a hypothetical usage would cost on one model */
+export interface CostEstimate {
+  modelId: string
+  /** Whether any pricing applied; when not, every cost is 0 */
+  matched: boolean
+  /** Pricing entry used; empty when unmatched or estimated */
+  matchedKey: string
+  /** "LiteLLM", "OpenRouter", "Estimated", or "none" when unmatched */
+  source: string
+  costBreakdown: TokenCostBreakdown
+  totalCost: number
+}
+
 /** Daily contribution data */
 export interface DailyContribution {
   date: string
@@ -7,6 +95,22 @@ export interface DailyContribution {
   intensity: number
   tokenBreakdown: TokenBreakdown
   sources: Array<SourceContribution>
+  /**
+   * Tokens per hour between the day's first and last message, measured over
+   * at least one hour
+   */
+  tokensPerActiveHour: number
+  /**
+   * `sources` summed per provider, sorted by provider; empty unless the
+   * graph was asked for it
+   */
+  providerBreakdown: Array<ProviderDayContribution>
+  /**
+   * The day's costliest model, its cost summed over `sources`; ties go to
+   * the first model by name. None when nothing that day had a cost.
+   */
+  topModel?: string
+  topModelCost: number
 }
 
 /** Daily contribution totals */
@@ -24,10 +128,43 @@ export interface DataSummary {
   activeDays: number
   averagePerDay: number
   maxCostInSingleDay: number
+  /** `cache_read / (cache_read + input)` over every day; 0 with no prompt tokens */
+  cacheHitRate: number
   sources: Array<string>
   models: Array<string>
+  /** `models` grouped by provider, providers and models each sorted */
+  modelsByProvider: Array<ProviderModels>
 }
 
+/** An inclusive date window (YYYY-MM-DD); missing bounds are open */
+export interface DateWindow {
+  since?: string
+  until?: string
+}
+
+/**
+ * List every directory a report would scan for the given home and sources,
+ * after env overrides and headless roots, without parsing any files
+ */
+export declare function describeScan(options: ReportOptions): Array<ScanTarget>
+
+/** Pricing resolved for every distinct model in the parsed messages, for auditing costs */
+export declare function dumpResolvedPricing(options: ReportOptions): Promise<Array<ResolvedModelPricing>>
+
+/**
+ * Cost of `tokens` on `model_id` under the loaded pricing, e.g. to see what
+ * last month's usage would have cost on another model. An unknown model
+ * comes back with `matched: false` and zero cost. Reasoning cost is folded
+ * into output unless `separate_reasoning_cost` is set.
+ */
+export declare function estimateCost(modelId: string, tokens: TokenBreakdown, separateReasoningCost?: boolean | undefined | null): Promise<CostEstimate>
+
+/**
+ * Finalize the model report, graph and monthly report together, pricing the
+ * messages once and aggregating them in a single pass
+ */
+export declare function finalizeAll(options: FinalizeReportOptions): Promise<AllReports>
+
 /** Finalize graph */
 export declare function finalizeGraph(options: FinalizeGraphOptions): Promise<GraphResult>
 
@@ -39,6 +176,16 @@ export interface FinalizeGraphOptions {
   since?: string
   until?: string
   year?: string
+  /**
+   * Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+   * Defaults to LiteLLM first.
+   */
+  pricingPriority?: Array<string>
+  /**
+   * Drop messages whose total tokens exceed this, as corrupt. Negative
+   * token counts are always clamped to 0. Unset keeps every message.
+   */
+  maxTokensPerMessage?: number
 }
 
 /** Options for finalizing monthly report */
@@ -49,6 +196,16 @@ export interface FinalizeMonthlyOptions {
   since?: string
   until?: string
   year?: string
+  /**
+   * Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+   * Defaults to LiteLLM first.
+   */
+  pricingPriority?: Array<string>
+  /**
+   * Drop messages whose total tokens exceed this, as corrupt. Negative
+   * token counts are always clamped to 0. Unset keeps every message.
+   */
+  maxTokensPerMessage?: number
 }
 
 /** Finalize monthly report */
@@ -71,17 +228,61 @@ export interface FinalizeReportOptions {
   since?: string
   until?: string
   year?: string
+  /**
+   * Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+   * Defaults to LiteLLM first.
+   */
+  pricingPriority?: Array<string>
+  /**
+   * Itemize reasoning cost in the model report instead of folding it into
+   * output cost. Default false.
+   */
+  separateReasoningCost?: boolean
+  /**
+   * Drop messages whose total tokens exceed this, as corrupt. Negative
+   * token counts are always clamped to 0. Unset keeps every message.
+   */
+  maxTokensPerMessage?: number
 }
 
+/**
+ * Graph data for the Claude Code and Codex sessions in a zip archive (see
+ * `archive`), priced and filtered like `generate_graph_with_pricing`. Dates
+ * always come from the messages; entries without one fall back to the
+ * archive's modification time. `meta.filesParsed` and `meta.bytesRead`
+ * count the session entries read from the archive, uncompressed.
+ */
+export declare function generateGraphFromArchive(archivePath: string, options: ReportOptions): Promise<GraphResult>
+
 /** Generate graph data with pricing calculation */
 export declare function generateGraphWithPricing(options: ReportOptions): Promise<GraphResult>
 
+/**
+ * `generate_graph_with_pricing`, calling `on_progress` as files are parsed
+ * (at most every 100 files or 100ms, and once when the last file is done)
+ */
+export declare function generateGraphWithProgress(options: ReportOptions, onProgress: ((arg: ScanProgress) => unknown)): Promise<GraphResult>
+
 /** Get model usage report with pricing calculation */
 export declare function getModelReport(options: ReportOptions): Promise<ModelReport>
 
+/**
+ * Model report over only the messages newer than the last call's, then
+ * advance the stored watermark to the newest message seen. Each combination
+ * of home dir, sources, date source and filters keeps its own watermark. The
+ * first call for one (or the first after `reset_watermark`) covers everything.
+ */
+export declare function getModelReportSinceLast(options: ReportOptions): Promise<ModelReport>
+
 /** Get monthly usage report with pricing calculation */
 export declare function getMonthlyReport(options: ReportOptions): Promise<MonthlyReport>
 
+/** Get per-project usage report with pricing calculation */
+export declare function getProjectReport(options: ReportOptions): Promise<ProjectReport>
+
+/** Get quarterly usage report with pricing calculation */
+export declare function getQuarterlyReport(options: ReportOptions): Promise<QuarterlyReport>
+
 /** Metadata about the graph generation */
 export interface GraphMeta {
   generatedAt: string
@@ -89,6 +290,23 @@ export interface GraphMeta {
   dateRangeStart: string
   dateRangeEnd: string
   processingTimeMs: number
+  /**
+   * Models with usage that priced at $0, sorted. Their days look lighter on
+   * the heatmap than they were.
+   */
+  unpricedModels: Array<string>
+  /**
+   * Combined size of the files parsed, counted as each is handed to its
+   * parser; over `processing_time_ms` this is the scan throughput. 0 when
+   * the graph was built from messages parsed elsewhere.
+   */
+  bytesRead: number
+  /** Number of files parsed, under the same conditions as `bytes_read` */
+  filesParsed: number
+  /** Messages with negative token counts, clamped to 0 */
+  clampedMessages: number
+  /** Messages dropped for exceeding `max_tokens_per_message` */
+  droppedMessages: number
 }
 
 /** Complete graph result */
@@ -97,11 +315,45 @@ export interface GraphResult {
   summary: DataSummary
   years: Array<YearSummary>
   contributions: Array<DailyContribution>
+  /** Trailing 30-calendar-day cost ending at each entry of `contributions` */
+  rollingCost: Array<number>
+  /** What the scan found; set only when the graph parsed local sessions itself */
+  scanStats?: ScanStatsDetail
+  /** Per-file parse times by source; set only with `profile` */
+  parseTimingHistogram?: Array<SourceParseTiming>
+}
+
+/**
+ * Serialize a graph as canonical JSON for file export: object keys sorted,
+ * and each day's sources plus the summary's source and model lists in a fixed
+ * order, so two runs over the same data differ only where the data does
+ */
+export declare function graphResultToJson(result: GraphResult, pretty: boolean): string
+
+/** A headless input line that could not be parsed */
+export interface HeadlessLineError {
+  /** 1-based line number */
+  lineNo: number
+  reason: string
+}
+
+/** Line-by-line outcome of validating a headless input file */
+export interface HeadlessValidation {
+  linesTotal: number
+  linesParsed: number
+  messagesEmitted: number
+  errors: Array<HeadlessLineError>
 }
 
 /** Simple health check to verify the native module is working */
 export declare function healthCheck(): string
 
+/**
+ * Every source tokscale supports, where it looks for each by default and
+ * whether anything is there under `home_dir` (default: the user's home)
+ */
+export declare function listSources(homeDir?: string | undefined | null): Array<SourceInfo>
+
 /** Options for parsing local sources only (no Cursor) */
 export interface LocalParseOptions {
   homeDir?: string
@@ -109,9 +361,32 @@ export interface LocalParseOptions {
   since?: string
   until?: string
   year?: string
+  /** Directory names never scanned into. Defaults to `["archive"]`. */
+  excludeDirs?: Array<string>
 }
 
-export declare function lookupPricing(modelId: string, provider?: string | undefined | null): Promise<PricingLookupResult>
+/**
+ * Pricing for `model_id`, optionally forced to one source ("litellm" or
+ * "openrouter"). With `per_million`, rates are per million tokens.
+ */
+export declare function lookupPricing(modelId: string, provider?: string | undefined | null, perMillion?: boolean | undefined | null): Promise<PricingLookupResult>
+
+/** Per-model change between two date ranges */
+export interface ModelRangeDelta {
+  source: string
+  model: string
+  provider: string
+  firstCost: number
+  secondCost: number
+  firstTokens: number
+  secondTokens: number
+  /** `second_cost - first_cost` */
+  costDelta: number
+  /** `second_tokens - first_tokens` */
+  tokensDelta: number
+  /** Cost change as a percentage of the first window; unset when the first cost is zero */
+  pctChange?: number
+}
 
 /** Model report result */
 export interface ModelReport {
@@ -122,7 +397,26 @@ export interface ModelReport {
   totalCacheWrite: number
   totalMessages: number
   totalCost: number
+  /** `total_cost` split by token type */
+  costBreakdown: TokenCostBreakdown
+  /** Amp credits across all entries, kept apart from the dollar `total_cost` */
+  totalCredits: number
+  /**
+   * What prompt caching saved: cache reads at the input rate minus what
+   * they cost, summed over models that have a cache-read price
+   */
+  cacheSavings: number
   processingTimeMs: number
+  /** What the scan found; set only when the report parsed local sessions itself */
+  scanStats?: ScanStatsDetail
+  /** The messages behind `entries`; set only with `include_messages` */
+  messages?: Array<ParsedMessage>
+  /** Messages with negative token counts, clamped to 0 */
+  clampedMessages: number
+  /** Messages dropped for exceeding `max_tokens_per_message` */
+  droppedMessages: number
+  /** Per-file parse times by source; set only with `profile` */
+  parseTimingHistogram?: Array<SourceParseTiming>
 }
 
 /** Model usage summary for reports */
@@ -135,8 +429,29 @@ export interface ModelUsage {
   cacheRead: number
   cacheWrite: number
   reasoning: number
+  /** Sum of every token category above, reasoning included */
+  totalTokens: number
   messageCount: number
+  /** Number of distinct sessions that used this model */
+  sessionCount: number
   cost: number
+  costBreakdown: TokenCostBreakdown
+  /** Copilot premium requests billed; 0 for other sources */
+  premiumRequests: number
+  /** Amp credits used; 0 for other sources and never part of `cost` */
+  credits: number
+  /** Cost per million output tokens; 0 when there were none */
+  costPerMtokOutput: number
+  /** Cost per million tokens across every category; 0 when there were none */
+  costPerMtokTotal: number
+  /** `cache_read / (cache_read + input)`; 0 when there were no prompt tokens */
+  cacheHitRate: number
+  /** "Estimated" when some of `cost` came from the unknown-model rate */
+  pricingSource?: string
+  /** Earliest date (YYYY-MM-DD) this model was used */
+  firstDate: string
+  /** Latest date (YYYY-MM-DD) this model was used */
+  lastDate: string
 }
 
 /** Monthly report result */
@@ -144,6 +459,10 @@ export interface MonthlyReport {
   entries: Array<MonthlyUsage>
   totalCost: number
   processingTimeMs: number
+  /** Messages with negative token counts, clamped to 0 */
+  clampedMessages: number
+  /** Messages dropped for exceeding `max_tokens_per_message` */
+  droppedMessages: number
 }
 
 /** Monthly usage summary */
@@ -154,8 +473,15 @@ export interface MonthlyUsage {
   output: number
   cacheRead: number
   cacheWrite: number
+  /** Input, output, cache and reasoning tokens combined */
+  totalTokens: number
   messageCount: number
   cost: number
+  /**
+   * Each source's share of the month, by source name. Empty unless
+   * `include_source_breakdown` was set.
+   */
+  sourceBreakdown: Array<SourceUsage>
 }
 
 export interface NativePricing {
@@ -163,6 +489,11 @@ export interface NativePricing {
   outputCostPerToken: number
   cacheReadInputTokenCost?: number
   cacheCreationInputTokenCost?: number
+  /**
+   * What the costs are per: "per_token" or "per_million_tokens". Unset
+   * means per token.
+   */
+  unit?: string
 }
 
 export interface ParsedMessage {
@@ -178,6 +509,16 @@ export interface ParsedMessage {
   cacheWrite: number
   reasoning: number
   agent?: string
+  /** Computed cost; set by `query_messages` and on report `messages` */
+  cost?: number
+  /** Amp credits the message used; never part of `cost` */
+  credits?: number
+  /** Copilot premium requests the message was billed as */
+  premiumRequests?: number
+  /** Encoded project directory (Claude, Codex, OpenCode) */
+  project?: string
+  /** Sent through Claude's Batch API, so priced at the batch rate */
+  batch?: boolean
 }
 
 /** Result of parsing local sources (excludes Cursor - it's network-synced) */
@@ -190,15 +531,34 @@ export interface ParsedMessages {
   ampCount: number
   droidCount: number
   openclawCount: number
+  warpCount: number
+  rooCount: number
+  kiloCount: number
+  qwenCount: number
+  copilotCount: number
+  julesCount: number
   processingTimeMs: number
 }
 
+/**
+ * Parse a single session file as `source` (e.g. "claude", "codex"), skipping
+ * the directory scan, and return its messages with computed cost
+ */
+export declare function parseFile(path: string, source: string): Promise<Array<ParsedMessage>>
+
 /**
  * Parse local sources only (OpenCode, Claude, Codex, Gemini - NO Cursor)
  * This can run in parallel with network operations (Cursor sync, pricing fetch)
  */
 export declare function parseLocalSources(options: LocalParseOptions): ParsedMessages
 
+/** Files of one source that took `bucket_ms` up to the next bucket to parse */
+export interface ParseTimingBucket {
+  /** Lower bound of the bucket: 0, 1, 5, 25, ... ms */
+  bucketMs: number
+  count: number
+}
+
 export interface PricingLookupResult {
   modelId: string
   matchedKey: string
@@ -206,12 +566,140 @@ export interface PricingLookupResult {
   pricing: NativePricing
 }
 
+/** Status of the loaded pricing data, or `null` if pricing has not been fetched yet */
+export declare function pricingStatus(): Promise<PricingStatus | null>
+
+/** Loaded pricing data summary */
+export interface PricingStatus {
+  litellmModels: number
+  openrouterModels: number
+  /** RFC 3339 time the pricing data was loaded */
+  fetchedAt: string
+  /** Whether the data came from the on-disk cache rather than a full download */
+  fromCache: boolean
+}
+
+/** Project report result */
+export interface ProjectReport {
+  entries: Array<ProjectUsage>
+  totalCost: number
+  processingTimeMs: number
+  /** Messages with negative token counts, clamped to 0 */
+  clampedMessages: number
+  /** Messages dropped for exceeding `max_tokens_per_message` */
+  droppedMessages: number
+}
+
+/** Per-project usage summary */
+export interface ProjectUsage {
+  /** Encoded project directory, or "(unknown)" for sources without one */
+  project: string
+  models: Array<string>
+  input: number
+  output: number
+  cacheRead: number
+  cacheWrite: number
+  reasoning: number
+  messageCount: number
+  cost: number
+}
+
+/** One provider's share of a day */
+export interface ProviderDayContribution {
+  providerId: string
+  tokens: TokenBreakdown
+  cost: number
+  messages: number
+}
+
+/** The models seen from one provider */
+export interface ProviderModels {
+  provider: string
+  models: Array<string>
+}
+
+/** Quarterly report result */
+export interface QuarterlyReport {
+  entries: Array<QuarterlyUsage>
+  totalCost: number
+  processingTimeMs: number
+  /** Messages with negative token counts, clamped to 0 */
+  clampedMessages: number
+  /** Messages dropped for exceeding `max_tokens_per_message` */
+  droppedMessages: number
+}
+
+/** Quarterly usage summary */
+export interface QuarterlyUsage {
+  /** Quarter key, e.g. "2025-Q1" */
+  quarter: string
+  models: Array<string>
+  input: number
+  output: number
+  cacheRead: number
+  cacheWrite: number
+  messageCount: number
+  cost: number
+}
+
+/** Query raw messages with arbitrary predicates, returning each with its computed cost */
+export declare function queryMessages(options: QueryOptions): Promise<Array<ParsedMessage>>
+
+/** Options for the raw message query. All set predicates must match. */
+export interface QueryOptions {
+  homeDir?: string
+  sources?: Array<string>
+  /** Model id globs (`*`, `?`), case-insensitive */
+  models?: Array<string>
+  providers?: Array<string>
+  agents?: Array<string>
+  since?: string
+  until?: string
+  /** Minimum total tokens per message */
+  minTokens?: number
+  sessionIds?: Array<string>
+  /** As in `ReportOptions`, for the pricing applied to the messages */
+  pricingPriority?: Array<string>
+  skipPricing?: boolean
+  preferCheaper?: boolean
+  reasoningRateMultiplier?: number
+  batchRateMultiplier?: number
+  unknownModelRate?: NativePricing
+  costSource?: string
+  fuzzyMinLength?: number
+  fuzzyBlocklist?: Array<string>
+}
+
+/** Result of comparing two date ranges */
+export interface RangeComparison {
+  entries: Array<ModelRangeDelta>
+  firstTotals: RangeTotals
+  secondTotals: RangeTotals
+  processingTimeMs: number
+}
+
+/** Totals for one window of a range comparison */
+export interface RangeTotals {
+  cost: number
+  tokens: number
+  messages: number
+}
+
+/** Refetch pricing, bypassing the cache TTL, and return the new status */
+export declare function refreshPricing(): Promise<PricingStatus>
+
 /** Combined result for report and graph (single pricing lookup) */
 export interface ReportAndGraph {
   report: ModelReport
   graph: GraphResult
 }
 
+/**
+ * Model report over caller-supplied `messages`, with no scanning; see
+ * `aggregate_messages`
+ */
+export declare function reportMessages(messages: Array<ParsedMessage>, options: AggregateOptions): Promise<ModelReport>
+
 /** Options for reports */
 export interface ReportOptions {
   homeDir?: string
@@ -219,6 +707,219 @@ export interface ReportOptions {
   since?: string
   until?: string
   year?: string
+  /**
+   * Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+   * Defaults to LiteLLM first.
+   */
+  pricingPriority?: Array<string>
+  /**
+   * When a model is priced by both LiteLLM and OpenRouter, use whichever
+   * input + output rate is lower
+   */
+  preferCheaper?: boolean
+  /**
+   * Bill reasoning tokens at this multiple of the output rate, separately
+   * from output. Unset folds reasoning into output at the output rate.
+   */
+  reasoningRateMultiplier?: number
+  /**
+   * Bill Claude Batch API messages (`service_tier: "batch"`) at this
+   * fraction of the standard rates. Default 0.5.
+   */
+  batchRateMultiplier?: number
+  /**
+   * Aggregate each source as soon as it is parsed and drop its messages,
+   * bounding memory by the largest source rather than all of them.
+   * Honored by the graph and the model, monthly, quarterly and project
+   * reports.
+   */
+  lowMemory?: boolean
+  /**
+   * Token counts only: never load pricing (so never touch the network) and
+   * report every cost as 0. Honored by the model report and graph.
+   */
+  skipPricing?: boolean
+  /**
+   * Where message dates come from: "timestamp" (default) for what each tool
+   * recorded, or "mtime" for the session file's modification time
+   */
+  dateSource?: string
+  /**
+   * Split each day's source contributions by agent as well as source and
+   * model. Honored by the graph.
+   */
+  groupByAgent?: boolean
+  /** Rates for models no pricing dataset matches. Unset prices them at $0. */
+  unknownModelRate?: NativePricing
+  /**
+   * Model ids to leave out of the report: exact ids or globs (`*`, `?`),
+   * case-insensitive
+   */
+  excludeModels?: Array<string>
+  /**
+   * Directory names never scanned into, matched case-insensitively against
+   * any directory below a source root. Defaults to `["archive"]`.
+   */
+  excludeDirs?: Array<string>
+  /**
+   * Whose cost each message gets: "calculated" from pricing data,
+   * "source" as the tool recorded it, or "prefer_source" for the recorded
+   * cost when nonzero. Unset calculates, keeping Cursor's recorded cost
+   * for unknown models. Honored by the model report and graph.
+   */
+  costSource?: string
+  /**
+   * Fail with `E_NO_FILES` instead of returning an empty report when no
+   * session files are found, listing the directories checked
+   */
+  strict?: boolean
+  /**
+   * Heatmap levels: each day's `intensity` is in `0..levels`. Default 5,
+   * clamped to 2–10. Honored by the graph.
+   */
+  intensityLevels?: number
+  /** Fill each day's `provider_breakdown`. Honored by the graph. */
+  includeProviderBreakdown?: boolean
+  /**
+   * Add a zeroed contribution for every day without activity between the
+   * first and last active day. Honored by the graph.
+   */
+  fillGaps?: boolean
+  /** Shortest model id allowed to fuzzy-match a pricing entry. Default 5. */
+  fuzzyMinLength?: number
+  /**
+   * Model ids that never fuzzy-match. Default "auto", "mini", "chat" and
+   * "base"; a list here replaces them.
+   */
+  fuzzyBlocklist?: Array<string>
+  /** Fill each month's `source_breakdown`. Honored by the monthly report. */
+  includeSourceBreakdown?: boolean
+  /**
+   * What makes a day active: "cost" (default) or "tokens", which also
+   * counts unpriced days. Sets `active_days`, `average_per_day` and the
+   * heatmap intensities. Anything else fails with `E_INVALID_METRIC`.
+   * Honored by the graph.
+   */
+  activeMetric?: string
+  /**
+   * Attach the filtered messages, with cost, as `messages`. Off by default
+   * since the list can be huge. Honored by the model report.
+   */
+  includeMessages?: boolean
+  /**
+   * Round every emitted cost, per entry and in totals, to this many decimal
+   * places (halves away from zero). Unset leaves costs unrounded.
+   */
+  roundCosts?: number
+  /**
+   * Replace session ids and project paths in the output with salted hashes
+   * (the model report's `messages`, the project report's entries). Totals
+   * are unaffected.
+   */
+  anonymize?: boolean
+  /** Salt for `anonymize`, so tokens match across runs. Default: random per run. */
+  anonymizeSalt?: string
+  /**
+   * Parse at most this many files of a source at once, handing each batch
+   * to the aggregation before reading the next, to bound peak memory.
+   * Honored by today's summary and, with `low_memory`, the graph and the
+   * model, monthly, quarterly and project reports; without `low_memory`
+   * every batch is kept until aggregation, so peak memory is not bounded.
+   */
+  maxInflightFiles?: number
+  /**
+   * Itemize reasoning tokens (o1/o3/o4 thinking) as `reasoning_cost` in the
+   * cost breakdowns instead of folding them into `output_cost`. Default
+   * false. Honored by the model reports.
+   */
+  separateReasoningCost?: boolean
+  /**
+   * Time each file's parse and report the times per source as
+   * `parse_timing_histogram`. Honored by the graph and the model reports.
+   */
+  profile?: boolean
+  /**
+   * Drop messages whose total tokens exceed this, as corrupt. Negative
+   * token counts are always clamped to 0. Unset keeps every message.
+   */
+  maxTokensPerMessage?: number
+}
+
+/** Forget every "since last run" watermark */
+export declare function resetWatermark(): void
+
+/** Pricing resolved for one model id */
+export interface ResolvedModelPricing {
+  modelId: string
+  matchedKey: string
+  /** "LiteLLM", "OpenRouter", or "none" when no pricing matched */
+  source: string
+  inputCostPerToken?: number
+  outputCostPerToken?: number
+  cacheRead?: number
+  cacheWrite?: number
+}
+
+/** How far a scan's parsing has got */
+export interface ScanProgress {
+  filesDone: number
+  filesTotal: number
+  /** Source of the file just parsed */
+  source: string
+}
+
+/** Files a report's scan found, per source */
+export interface ScanStatsDetail {
+  opencodeFiles: number
+  claudeFiles: number
+  codexFiles: number
+  geminiFiles: number
+  cursorFiles: number
+  ampFiles: number
+  droidFiles: number
+  openclawFiles: number
+  warpFiles: number
+  rooFiles: number
+  kiloFiles: number
+  qwenFiles: number
+  copilotFiles: number
+  julesFiles: number
+  totalFiles: number
+  /** Combined size of the scanned files */
+  totalBytes: number
+}
+
+/** A directory the scan would walk */
+export interface ScanTarget {
+  source: string
+  path: string
+  /** File pattern matched inside `path`, e.g. "*.jsonl" */
+  pattern: string
+  exists: boolean
+  /** Matching files under `path` right now */
+  fileCount: number
+}
+
+/**
+ * Run every source parser against a minimal embedded fixture, reporting
+ * which ones still produce messages
+ */
+export declare function selfTest(): SelfTestReport
+
+/** One parser's result in the self-test */
+export interface SelfTestEntry {
+  source: string
+  /** Whether the parser produced messages from its fixture */
+  ok: boolean
+  messages: number
+  /** Why the fixture could not be set up or parsed */
+  error?: string
+}
+
+/** Parser self-test result */
+export interface SelfTestReport {
+  entries: Array<SelfTestEntry>
+  processingTimeMs: number
 }
 
 /** Source contribution for a specific day */
@@ -226,13 +927,60 @@ export interface SourceContribution {
   source: string
   modelId: string
   providerId: string
+  /** Set only when the graph was grouped by agent and the source records one */
+  agent?: string
   tokens: TokenBreakdown
   cost: number
   messages: number
 }
 
+/** A supported source and where it is looked for by default */
+export interface SourceInfo {
+  name: string
+  /** First directory scanned for the source, after env overrides */
+  defaultPath: string
+  /** File pattern matched inside `default_path`, e.g. "*.jsonl" */
+  pattern: string
+  /** Scanned when no `sources` are given */
+  enabledByDefault: boolean
+  /** Whether `default_path` exists */
+  detected: boolean
+}
+
+/** How long each file of `source` took to parse */
+export interface SourceParseTiming {
+  source: string
+  /** Non-empty buckets, fastest first */
+  buckets: Array<ParseTimingBucket>
+}
+
+/** One source's usage within a month */
+export interface SourceUsage {
+  source: string
+  input: number
+  output: number
+  cacheRead: number
+  cacheWrite: number
+  /** Input, output, cache and reasoning tokens combined */
+  totalTokens: number
+  messageCount: number
+  cost: number
+}
+
+/**
+ * Today's totals (local time) for status-bar style widgets. Honors `sources`,
+ * `date_source` and the pricing options; the date filters are ignored.
+ * Files last modified before midnight are skipped without parsing.
+ */
+export declare function todaySummary(options: ReportOptions): Promise<DailyTotals>
+
 /** Token breakdown by type */
 export interface TokenBreakdown {
+  /**
+   * Input tokens, including any image/media input tokens that a source reports
+   * in a separate field. Providers bill image tokens at the input rate, so
+   * parsers fold them in here rather than tracking them as their own category.
+   */
   input: number
   output: number
   cacheRead: number
@@ -240,6 +988,31 @@ export interface TokenBreakdown {
   reasoning: number
 }
 
+/** Cost split by token type. The components sum to the total cost. */
+export interface TokenCostBreakdown {
+  inputCost: number
+  outputCost: number
+  cacheReadCost: number
+  cacheWriteCost: number
+  /**
+   * Reasoning tokens at the output rate, or the reasoning multiple of it.
+   * Zero in model reports and cost estimates unless `separate_reasoning_cost`
+   * is set; reasoning is then part of `output_cost`.
+   */
+  reasoningCost: number
+  /**
+   * Cost a source recorded itself (Cursor CSV cost) and kept
+   * because the model has no pricing; it can't be split by token type
+   */
+  unattributedCost: number
+}
+
+/**
+ * Validate a headless output file for `source` (claude, codex or gemini),
+ * reporting which lines the parser would skip
+ */
+export declare function validateHeadlessFile(path: string, source: string): HeadlessValidation
+
 /** Version of the native module */
 export declare function version(): string
 
@@ -572,14 +572,36 @@ if (!nativeBinding) {
 }
 
 module.exports = nativeBinding
+module.exports.aggregateMessages = nativeBinding.aggregateMessages
+module.exports.compareRanges = nativeBinding.compareRanges
+module.exports.describeScan = nativeBinding.describeScan
+module.exports.dumpResolvedPricing = nativeBinding.dumpResolvedPricing
+module.exports.estimateCost = nativeBinding.estimateCost
+module.exports.finalizeAll = nativeBinding.finalizeAll
 module.exports.finalizeGraph = nativeBinding.finalizeGraph
 module.exports.finalizeMonthlyReport = nativeBinding.finalizeMonthlyReport
 module.exports.finalizeReport = nativeBinding.finalizeReport
 module.exports.finalizeReportAndGraph = nativeBinding.finalizeReportAndGraph
+module.exports.generateGraphFromArchive = nativeBinding.generateGraphFromArchive
 module.exports.generateGraphWithPricing = nativeBinding.generateGraphWithPricing
+module.exports.generateGraphWithProgress = nativeBinding.generateGraphWithProgress
 module.exports.getModelReport = nativeBinding.getModelReport
+module.exports.getModelReportSinceLast = nativeBinding.getModelReportSinceLast
 module.exports.getMonthlyReport = nativeBinding.getMonthlyReport
+module.exports.getProjectReport = nativeBinding.getProjectReport
+module.exports.getQuarterlyReport = nativeBinding.getQuarterlyReport
+module.exports.graphResultToJson = nativeBinding.graphResultToJson
 module.exports.healthCheck = nativeBinding.healthCheck
+module.exports.listSources = nativeBinding.listSources
 module.exports.lookupPricing = nativeBinding.lookupPricing
+module.exports.parseFile = nativeBinding.parseFile
 module.exports.parseLocalSources = nativeBinding.parseLocalSources
+module.exports.pricingStatus = nativeBinding.pricingStatus
+module.exports.queryMessages = nativeBinding.queryMessages
+module.exports.refreshPricing = nativeBinding.refreshPricing
+module.exports.reportMessages = nativeBinding.reportMessages
+module.exports.resetWatermark = nativeBinding.resetWatermark
+module.exports.selfTest = nativeBinding.selfTest
+module.exports.todaySummary = nativeBinding.todaySummary
+module.exports.validateHeadlessFile = nativeBinding.validateHeadlessFile
 module.exports.version = nativeBinding.version
@@ -4,75 +4,694 @@
 
 use crate::sessions::UnifiedMessage;
 use crate::{
-    DailyContribution, DailyTotals, DataSummary, GraphMeta, GraphResult, SourceContribution,
-    TokenBreakdown, YearSummary,
+    DailyContribution, DailyTotals, DataSummary, GraphMeta, GraphResult, ModelRangeDelta,
+    ModelUsage, MonthlyUsage, ProjectUsage, ProviderDayContribution, ProviderModels,
+    QuarterlyUsage, RangeTotals, SourceContribution, SourceUsage, TokenBreakdown,
+    TokenCostBreakdown, YearSummary,
 };
 use rayon::prelude::*;
-use std::collections::HashMap;
-
-/// Aggregate messages into daily contributions
-pub fn aggregate_by_date(messages: Vec<UnifiedMessage>) -> Vec<DailyContribution> {
-    if messages.is_empty() {
-        return Vec::new();
-    }
-
-    // Estimate unique days (typically 1-365) - use message count / 10 as heuristic
-    let estimated_days = (messages.len() / 10).max(30).min(400);
-
-    // Parallel aggregation using fold/reduce pattern
-    let daily_map: HashMap<String, DayAccumulator> = messages
-        .into_par_iter()
-        .fold(
-            || HashMap::with_capacity(estimated_days),
-            |mut acc: HashMap<String, DayAccumulator>, msg| {
-                let entry = acc.entry(msg.date.clone()).or_default();
-                entry.add_message(&msg);
-                acc
-            },
-        )
-        .reduce(
-            || HashMap::with_capacity(estimated_days),
-            |mut a, b| {
-                for (date, acc) in b {
-                    a.entry(date).or_default().merge(acc);
-                }
-                a
-            },
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Aggregate messages into daily contributions. With `group_by_agent`, each
+/// day's source contributions are split by agent as well as source and model.
+pub fn aggregate_by_date(
+    messages: &[UnifiedMessage],
+    group_by_agent: bool,
+) -> Vec<DailyContribution> {
+    let mut acc = DateAccumulator::new(group_by_agent);
+    acc.add(messages);
+    acc.finish()
+}
+
+/// Add the messages dated `date` to `totals`, skipping every other day
+pub fn add_totals_on(totals: &mut DailyTotals, messages: &[UnifiedMessage], date: &str) {
+    for msg in messages.iter().filter(|m| m.date == date) {
+        totals.tokens = totals.tokens.saturating_add(msg.tokens.total());
+        totals.cost += msg.cost;
+        totals.messages = totals.messages.saturating_add(1);
+    }
+}
+
+/// Add to `models` every model with tokens in `messages` that priced at zero
+pub fn collect_unpriced_models(models: &mut BTreeSet<String>, messages: &[UnifiedMessage]) {
+    for msg in messages {
+        if msg.cost == 0.0 && msg.tokens.total() > 0 && !models.contains(&msg.model_id) {
+            models.insert(msg.model_id.clone());
+        }
+    }
+}
+
+/// Models with tokens in `messages` that priced at zero, sorted
+pub fn unpriced_models(messages: &[UnifiedMessage]) -> Vec<String> {
+    let mut models = BTreeSet::new();
+    collect_unpriced_models(&mut models, messages);
+    models.into_iter().collect()
+}
+
+/// Aggregate messages into per-model usage, sorted by cost descending
+pub fn aggregate_by_model(messages: &[UnifiedMessage]) -> Vec<ModelUsage> {
+    let mut acc = ModelAccumulator::default();
+    acc.add(messages);
+    acc.finish()
+}
+
+/// Per-model, per-day and per-month aggregates from a single pass over
+/// `messages`, matching [`aggregate_by_model`], [`aggregate_by_date`] (without
+/// agent grouping) and [`aggregate_by_month`]
+pub fn aggregate_all(
+    messages: &[UnifiedMessage],
+) -> (Vec<ModelUsage>, Vec<DailyContribution>, Vec<MonthlyUsage>) {
+    let mut models = ModelAccumulator::default();
+    let mut days = DateAccumulator::new(false);
+    let mut months = MonthAccumulator::default();
+    for msg in messages {
+        models.add_message(msg);
+        days.add_message(msg);
+        months.add_message(msg);
+    }
+    (models.finish(), days.finish(), months.finish())
+}
+
+/// Daily totals built up one batch of messages at a time, so a caller can
+/// drop each batch once it has been added
+#[derive(Default)]
+pub struct DateAccumulator {
+    days: HashMap<String, DayAccumulator>,
+    group_by_agent: bool,
+}
+
+impl DateAccumulator {
+    /// See [`aggregate_by_date`] for `group_by_agent`
+    pub fn new(group_by_agent: bool) -> Self {
+        Self {
+            group_by_agent,
+            ..Self::default()
+        }
+    }
+
+    pub fn add(&mut self, messages: &[UnifiedMessage]) {
+        if messages.is_empty() {
+            return;
+        }
+
+        // Estimate unique days (typically 1-365) - use message count / 10 as heuristic
+        let estimated_days = (messages.len() / 10).clamp(30, 400);
+
+        let group_by_agent = self.group_by_agent;
+
+        // Parallel aggregation using fold/reduce pattern
+        let daily_map: HashMap<String, DayAccumulator> = messages
+            .par_iter()
+            .fold(
+                || HashMap::with_capacity(estimated_days),
+                |mut acc: HashMap<String, DayAccumulator>, msg| {
+                    // Undated messages (implausible timestamps) belong to no day
+                    if msg.date.is_empty() {
+                        return acc;
+                    }
+                    let entry = acc.entry(msg.date.clone()).or_default();
+                    entry.add_message(msg, group_by_agent);
+                    acc
+                },
+            )
+            .reduce(
+                || HashMap::with_capacity(estimated_days),
+                |mut a, b| {
+                    for (date, acc) in b {
+                        a.entry(date).or_default().merge(acc);
+                    }
+                    a
+                },
+            );
+
+        if self.days.is_empty() {
+            self.days = daily_map;
+            return;
+        }
+        for (date, acc) in daily_map {
+            self.days.entry(date).or_default().merge(acc);
+        }
+    }
+
+    /// Add a single message; see [`DateAccumulator::add`] for whole batches
+    pub fn add_message(&mut self, msg: &UnifiedMessage) {
+        if msg.date.is_empty() {
+            return;
+        }
+        self.days
+            .entry(msg.date.clone())
+            .or_default()
+            .add_message(msg, self.group_by_agent);
+    }
+
+    pub fn finish(self) -> Vec<DailyContribution> {
+        // Convert to sorted vector with pre-allocated capacity
+        let mut contributions: Vec<DailyContribution> = Vec::with_capacity(self.days.len());
+        contributions.extend(
+            self.days
+                .into_iter()
+                .map(|(date, acc)| acc.into_contribution(date)),
+        );
+
+        // Sort by date
+        contributions.sort_by(|a, b| a.date.cmp(&b.date));
+
+        // Calculate intensities based on max cost
+        calculate_intensities(
+            &mut contributions,
+            DEFAULT_INTENSITY_LEVELS,
+            ActiveMetric::Cost,
         );
 
-    // Convert to sorted vector with pre-allocated capacity
-    let mut contributions: Vec<DailyContribution> = Vec::with_capacity(daily_map.len());
-    contributions.extend(daily_map.into_iter().map(|(date, acc)| acc.into_contribution(date)));
+        contributions
+    }
+}
+
+/// `cost` per million `tokens`, 0 rather than NaN/inf when there are no tokens
+fn cost_per_mtok(cost: f64, tokens: i64) -> f64 {
+    if tokens <= 0 || !cost.is_finite() {
+        return 0.0;
+    }
+    cost / (tokens as f64 / 1_000_000.0)
+}
+
+/// Share of prompt tokens served from cache, 0 when there were none
+pub fn cache_hit_rate(cache_read: i64, input: i64) -> f64 {
+    let prompt = cache_read + input;
+    if prompt <= 0 {
+        return 0.0;
+    }
+    cache_read as f64 / prompt as f64
+}
+
+/// Per-model usage built up one batch of messages at a time. Batches are
+/// folded in order, so adding a dataset in pieces matches adding it whole.
+#[derive(Default)]
+pub struct ModelAccumulator {
+    models: HashMap<String, ModelUsage>,
+    sessions_by_model: HashMap<String, HashSet<String>>,
+}
+
+impl ModelAccumulator {
+    pub fn add(&mut self, messages: &[UnifiedMessage]) {
+        for msg in messages {
+            self.add_message(msg);
+        }
+    }
+
+    pub fn add_message(&mut self, msg: &UnifiedMessage) {
+        let key = format!("{}:{}:{}", msg.source, msg.provider_id, msg.model_id);
+        self.sessions_by_model
+            .entry(key.clone())
+            .or_default()
+            .insert(msg.session_id.clone());
+
+        let entry = self.models.entry(key).or_insert_with(|| ModelUsage {
+            source: msg.source.clone(),
+            model: msg.model_id.clone(),
+            provider: msg.provider_id.clone(),
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+            total_tokens: 0,
+            message_count: 0,
+            session_count: 0,
+            cost: 0.0,
+            cost_breakdown: TokenCostBreakdown::default(),
+            premium_requests: 0.0,
+            credits: 0.0,
+            cost_per_mtok_output: 0.0,
+            cost_per_mtok_total: 0.0,
+            cache_hit_rate: 0.0,
+            pricing_source: None,
+            first_date: String::new(),
+            last_date: String::new(),
+        });
+
+        entry.input += msg.tokens.input;
+        entry.output += msg.tokens.output;
+        entry.cache_read += msg.tokens.cache_read;
+        entry.cache_write += msg.tokens.cache_write;
+        entry.reasoning += msg.tokens.reasoning;
+        entry.total_tokens += msg.tokens.total();
+        entry.message_count += 1;
+        entry.cost += msg.cost;
+        entry.cost_breakdown += &msg.cost_breakdown;
+        entry.premium_requests += msg.premium_requests;
+        entry.credits += msg.credits.unwrap_or(0.0);
+        if msg.cost_estimated {
+            entry.pricing_source = Some("Estimated".to_string());
+        }
+
+        // Skip empty/malformed dates so they never win the min/max comparison
+        if msg.date.len() >= 10 {
+            if entry.first_date.is_empty() || msg.date < entry.first_date {
+                entry.first_date = msg.date.clone();
+            }
+            if entry.last_date.is_empty() || msg.date > entry.last_date {
+                entry.last_date = msg.date.clone();
+            }
+        }
+    }
+
+    pub fn finish(self) -> Vec<ModelUsage> {
+        let sessions_by_model = self.sessions_by_model;
+        let mut entries: Vec<ModelUsage> = self
+            .models
+            .into_iter()
+            .map(|(key, mut entry)| {
+                entry.session_count = sessions_by_model.get(&key).map_or(0, |s| s.len() as i32);
+                entry.cost_per_mtok_output = cost_per_mtok(entry.cost, entry.output);
+                entry.cost_per_mtok_total = cost_per_mtok(entry.cost, entry.total_tokens);
+                entry.cache_hit_rate = cache_hit_rate(entry.cache_read, entry.input);
+                entry
+            })
+            .collect();
+        // Sort by cost descending (NaN values sorted to the end), then by
+        // source/provider/model so equal costs keep a reproducible order
+        entries.sort_by(|a, b| {
+            // Handle NaN: treat as smallest value so they sort to the end in descending order
+            match (a.cost.is_nan(), b.cost.is_nan()) {
+                (true, true) => std::cmp::Ordering::Equal,
+                (true, false) => std::cmp::Ordering::Greater, // NaN sorts after valid values
+                (false, true) => std::cmp::Ordering::Less,
+                (false, false) => b
+                    .cost
+                    .partial_cmp(&a.cost)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            }
+            .then_with(|| a.source.cmp(&b.source))
+            .then_with(|| a.provider.cmp(&b.provider))
+            .then_with(|| a.model.cmp(&b.model))
+        });
+
+        entries
+    }
+}
+
+/// Bucket for messages whose source records no project
+pub const UNKNOWN_PROJECT: &str = "(unknown)";
+
+/// Aggregate messages by project, sorted by cost descending
+pub fn aggregate_by_project(messages: Vec<UnifiedMessage>) -> Vec<ProjectUsage> {
+    let mut acc = ProjectAccumulator::default();
+    acc.add(&messages);
+    acc.finish()
+}
+
+/// Per-project usage built up one batch of messages at a time
+#[derive(Default)]
+pub struct ProjectAccumulator {
+    projects: HashMap<String, (ProjectUsage, HashSet<String>)>,
+}
+
+impl ProjectAccumulator {
+    pub fn add(&mut self, messages: &[UnifiedMessage]) {
+        for msg in messages {
+            self.add_message(msg);
+        }
+    }
+
+    pub fn add_message(&mut self, msg: &UnifiedMessage) {
+        let project = msg.project.as_deref().unwrap_or(UNKNOWN_PROJECT);
+
+        let (entry, models) = self.projects.entry(project.to_string()).or_insert_with(|| {
+            (
+                ProjectUsage {
+                    project: project.to_string(),
+                    models: Vec::new(),
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    reasoning: 0,
+                    message_count: 0,
+                    cost: 0.0,
+                },
+                HashSet::new(),
+            )
+        });
+
+        models.insert(msg.model_id.clone());
+        entry.input += msg.tokens.input;
+        entry.output += msg.tokens.output;
+        entry.cache_read += msg.tokens.cache_read;
+        entry.cache_write += msg.tokens.cache_write;
+        entry.reasoning += msg.tokens.reasoning;
+        entry.message_count += 1;
+        entry.cost += msg.cost;
+    }
+
+    pub fn finish(self) -> Vec<ProjectUsage> {
+        let mut entries: Vec<ProjectUsage> = self
+            .projects
+            .into_values()
+            .map(|(mut entry, models)| {
+                entry.models = models.into_iter().collect();
+                entry.models.sort();
+                entry
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.cost
+                .partial_cmp(&a.cost)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        entries
+    }
+}
+
+/// Month key (YYYY-MM) of a YYYY-MM-DD date; `None` for dates too short to carry one
+pub fn month_of(date: &str) -> Option<&str> {
+    date.get(..7)
+}
+
+/// Quarter key (YYYY-Qn) of a YYYY-MM-DD date
+pub fn quarter_of(date: &str) -> Option<String> {
+    let month = month_of(date)?;
+    let year = month.get(..4)?;
+    let month_num: u32 = month
+        .get(5..)?
+        .parse()
+        .ok()
+        .filter(|m| (1..=12).contains(m))?;
+    Some(format!("{}-Q{}", year, (month_num - 1) / 3 + 1))
+}
+
+/// Aggregate messages into calendar months, sorted ascending. With
+/// `include_source_breakdown`, each month also gets its per-source totals.
+pub fn aggregate_by_month(
+    messages: Vec<UnifiedMessage>,
+    include_source_breakdown: bool,
+) -> Vec<MonthlyUsage> {
+    let mut acc = MonthAccumulator::new(include_source_breakdown);
+    acc.add(&messages);
+    acc.finish()
+}
+
+/// A month's totals, the models seen and the per-source totals
+type MonthEntry = (MonthlyUsage, HashSet<String>, HashMap<String, SourceUsage>);
+
+/// Per-month usage built up one batch of messages at a time
+#[derive(Default)]
+pub struct MonthAccumulator {
+    months: HashMap<String, MonthEntry>,
+    include_source_breakdown: bool,
+}
+
+impl MonthAccumulator {
+    /// See [`aggregate_by_month`] for `include_source_breakdown`
+    pub fn new(include_source_breakdown: bool) -> Self {
+        Self {
+            months: HashMap::new(),
+            include_source_breakdown,
+        }
+    }
+
+    pub fn add(&mut self, messages: &[UnifiedMessage]) {
+        for msg in messages {
+            self.add_message(msg);
+        }
+    }
+
+    pub fn add_message(&mut self, msg: &UnifiedMessage) {
+        let Some(month) = month_of(&msg.date) else {
+            return;
+        };
+
+        let (entry, models, sources) = self.months.entry(month.to_string()).or_insert_with(|| {
+            (
+                MonthlyUsage {
+                    month: month.to_string(),
+                    models: Vec::new(),
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    total_tokens: 0,
+                    message_count: 0,
+                    cost: 0.0,
+                    source_breakdown: Vec::new(),
+                },
+                HashSet::new(),
+                HashMap::new(),
+            )
+        });
+
+        models.insert(msg.model_id.clone());
+        entry.input += msg.tokens.input;
+        entry.output += msg.tokens.output;
+        entry.cache_read += msg.tokens.cache_read;
+        entry.cache_write += msg.tokens.cache_write;
+        entry.total_tokens += msg.tokens.total();
+        entry.message_count += 1;
+        entry.cost += msg.cost;
+
+        if self.include_source_breakdown {
+            let source = sources
+                .entry(msg.source.clone())
+                .or_insert_with(|| SourceUsage {
+                    source: msg.source.clone(),
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    total_tokens: 0,
+                    message_count: 0,
+                    cost: 0.0,
+                });
+            source.input += msg.tokens.input;
+            source.output += msg.tokens.output;
+            source.cache_read += msg.tokens.cache_read;
+            source.cache_write += msg.tokens.cache_write;
+            source.total_tokens += msg.tokens.total();
+            source.message_count += 1;
+            source.cost += msg.cost;
+        }
+    }
+
+    pub fn finish(self) -> Vec<MonthlyUsage> {
+        let mut entries: Vec<MonthlyUsage> = self
+            .months
+            .into_values()
+            .map(|(mut entry, models, sources)| {
+                entry.models = models.into_iter().collect();
+                entry.models.sort();
+                entry.source_breakdown = sources.into_values().collect();
+                entry
+                    .source_breakdown
+                    .sort_by(|a, b| a.source.cmp(&b.source));
+                entry
+            })
+            .collect();
+        entries.sort_by(|a, b| a.month.cmp(&b.month));
+
+        entries
+    }
+}
+
+/// Aggregate messages into calendar quarters, sorted ascending
+pub fn aggregate_by_quarter(messages: Vec<UnifiedMessage>) -> Vec<QuarterlyUsage> {
+    let mut acc = QuarterAccumulator::default();
+    acc.add(&messages);
+    acc.finish()
+}
+
+/// Per-quarter usage built up one batch of messages at a time
+#[derive(Default)]
+pub struct QuarterAccumulator {
+    quarters: HashMap<String, (QuarterlyUsage, HashSet<String>)>,
+}
+
+impl QuarterAccumulator {
+    pub fn add(&mut self, messages: &[UnifiedMessage]) {
+        for msg in messages {
+            self.add_message(msg);
+        }
+    }
+
+    pub fn add_message(&mut self, msg: &UnifiedMessage) {
+        let Some(quarter) = quarter_of(&msg.date) else {
+            return;
+        };
+
+        let (entry, models) = self.quarters.entry(quarter.clone()).or_insert_with(|| {
+            (
+                QuarterlyUsage {
+                    quarter,
+                    models: Vec::new(),
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    message_count: 0,
+                    cost: 0.0,
+                },
+                HashSet::new(),
+            )
+        });
 
-    // Sort by date
-    contributions.sort_by(|a, b| a.date.cmp(&b.date));
+        models.insert(msg.model_id.clone());
+        entry.input += msg.tokens.input;
+        entry.output += msg.tokens.output;
+        entry.cache_read += msg.tokens.cache_read;
+        entry.cache_write += msg.tokens.cache_write;
+        entry.message_count += 1;
+        entry.cost += msg.cost;
+    }
 
-    // Calculate intensities based on max cost
-    calculate_intensities(&mut contributions);
+    pub fn finish(self) -> Vec<QuarterlyUsage> {
+        let mut entries: Vec<QuarterlyUsage> = self
+            .quarters
+            .into_values()
+            .map(|(mut entry, models)| {
+                entry.models = models.into_iter().collect();
+                entry.models.sort();
+                entry
+            })
+            .collect();
+        entries.sort_by(|a, b| a.quarter.cmp(&b.quarter));
+
+        entries
+    }
+}
 
-    contributions
+/// Totals for one side of a range comparison
+pub fn range_totals(entries: &[ModelUsage]) -> RangeTotals {
+    RangeTotals {
+        cost: entries.iter().map(|e| e.cost).sum(),
+        tokens: entries.iter().map(|e| e.total_tokens).sum(),
+        messages: entries.iter().map(|e| e.message_count).sum(),
+    }
+}
+
+/// Diff two model reports by source/provider/model. A model missing from one
+/// side counts as zero there. Sorted by the size of the cost change.
+pub fn compare_model_usage(first: &[ModelUsage], second: &[ModelUsage]) -> Vec<ModelRangeDelta> {
+    let key = |e: &ModelUsage| format!("{}:{}:{}", e.source, e.provider, e.model);
+    let mut deltas: HashMap<String, ModelRangeDelta> = HashMap::new();
+
+    for (entry, is_first) in first
+        .iter()
+        .map(|e| (e, true))
+        .chain(second.iter().map(|e| (e, false)))
+    {
+        let delta = deltas.entry(key(entry)).or_insert_with(|| ModelRangeDelta {
+            source: entry.source.clone(),
+            model: entry.model.clone(),
+            provider: entry.provider.clone(),
+            first_cost: 0.0,
+            second_cost: 0.0,
+            first_tokens: 0,
+            second_tokens: 0,
+            cost_delta: 0.0,
+            tokens_delta: 0,
+            pct_change: None,
+        });
+        if is_first {
+            delta.first_cost += entry.cost;
+            delta.first_tokens += entry.total_tokens;
+        } else {
+            delta.second_cost += entry.cost;
+            delta.second_tokens += entry.total_tokens;
+        }
+    }
+
+    let mut entries: Vec<ModelRangeDelta> = deltas
+        .into_values()
+        .map(|mut d| {
+            d.cost_delta = d.second_cost - d.first_cost;
+            d.tokens_delta = d.second_tokens - d.first_tokens;
+            // Undefined for models with no cost in the first window
+            d.pct_change = (d.first_cost > 0.0).then(|| d.cost_delta / d.first_cost * 100.0);
+            d
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.cost_delta
+            .abs()
+            .partial_cmp(&a.cost_delta.abs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    entries
+}
+
+/// What makes a day active and sets its heatmap intensity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ActiveMetric {
+    /// Any cost; unpriced days count as inactive
+    #[default]
+    Cost,
+    /// Any tokens, priced or not
+    Tokens,
+}
+
+impl ActiveMetric {
+    /// Parse an `active_metric` option; unset means [`ActiveMetric::Cost`]
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("cost") => Ok(ActiveMetric::Cost),
+            Some("tokens") => Ok(ActiveMetric::Tokens),
+            Some(other) => Err(format!(
+                "Unknown active metric: {} (expected \"cost\" or \"tokens\")",
+                other
+            )),
+        }
+    }
+
+    /// How much of this metric a day has
+    fn measure(self, contribution: &DailyContribution) -> f64 {
+        match self {
+            ActiveMetric::Cost => contribution.totals.cost,
+            ActiveMetric::Tokens => contribution.totals.tokens as f64,
+        }
+    }
 }
 
-/// Calculate summary statistics
-pub fn calculate_summary(contributions: &[DailyContribution]) -> DataSummary {
+/// Calculate summary statistics; a day is active when it has some `metric`
+pub fn calculate_summary(contributions: &[DailyContribution], metric: ActiveMetric) -> DataSummary {
     let total_tokens: i64 = contributions.iter().map(|c| c.totals.tokens).sum();
     let total_cost: f64 = contributions.iter().map(|c| c.totals.cost).sum();
-    let active_days = contributions.iter().filter(|c| c.totals.cost > 0.0).count() as i32;
+    let active_days = contributions
+        .iter()
+        .filter(|c| metric.measure(c) > 0.0)
+        .count() as i32;
     let max_cost = contributions
         .iter()
         .map(|c| c.totals.cost)
         .fold(0.0, f64::max);
 
+    let cache_read: i64 = contributions
+        .iter()
+        .map(|c| c.token_breakdown.cache_read)
+        .sum();
+    let input: i64 = contributions.iter().map(|c| c.token_breakdown.input).sum();
+
     let mut sources_set = std::collections::HashSet::with_capacity(5);
     let mut models_set = std::collections::HashSet::with_capacity(20);
+    let mut provider_models: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
 
     for c in contributions {
         for s in &c.sources {
             sources_set.insert(s.source.clone());
             models_set.insert(s.model_id.clone());
+            provider_models
+                .entry(s.provider_id.as_str())
+                .or_default()
+                .insert(s.model_id.as_str());
         }
     }
 
+    // Sorted so the summary doesn't depend on set iteration order
+    let mut sources: Vec<String> = sources_set.into_iter().collect();
+    sources.sort();
+    let mut models: Vec<String> = models_set.into_iter().collect();
+    models.sort();
+
     DataSummary {
         total_tokens,
         total_cost,
@@ -84,8 +703,16 @@ pub fn calculate_summary(contributions: &[DailyContribution]) -> DataSummary {
             0.0
         },
         max_cost_in_single_day: max_cost,
-        sources: sources_set.into_iter().collect(),
-        models: models_set.into_iter().collect(),
+        cache_hit_rate: cache_hit_rate(cache_read, input),
+        sources,
+        models,
+        models_by_provider: provider_models
+            .into_iter()
+            .map(|(provider, models)| ProviderModels {
+                provider: provider.to_string(),
+                models: models.into_iter().map(str::to_string).collect(),
+            })
+            .collect(),
     }
 }
 
@@ -120,12 +747,13 @@ pub fn calculate_years(contributions: &[DailyContribution]) -> Vec<YearSummary>
     years
 }
 
-/// Generate complete graph result
+/// Generate complete graph result, counting active days by `active_metric`
 pub fn generate_graph_result(
     contributions: Vec<DailyContribution>,
     processing_time_ms: u32,
+    active_metric: ActiveMetric,
 ) -> GraphResult {
-    let summary = calculate_summary(&contributions);
+    let summary = calculate_summary(&contributions, active_metric);
     let years = calculate_years(&contributions);
 
     let date_range_start = contributions
@@ -144,13 +772,98 @@ pub fn generate_graph_result(
             date_range_start,
             date_range_end,
             processing_time_ms,
+            unpriced_models: Vec::new(),
+            bytes_read: 0,
+            files_parsed: 0,
+            clamped_messages: 0,
+            dropped_messages: 0,
         },
         summary,
         years,
+        rolling_cost: calculate_rolling_cost(&contributions),
+        scan_stats: None,
+        parse_timing_histogram: None,
         contributions,
     }
 }
 
+/// Insert a zeroed contribution for each calendar day missing between the
+/// first and last one, recomputing `rolling_cost` to match. The summary and
+/// years still describe active days only. Expects date order.
+pub fn fill_date_gaps(result: &mut GraphResult) {
+    let contributions = std::mem::take(&mut result.contributions);
+    let mut filled: Vec<DailyContribution> = Vec::with_capacity(contributions.len());
+    let mut previous: Option<chrono::NaiveDate> = None;
+
+    for contribution in contributions {
+        let date = chrono::NaiveDate::parse_from_str(&contribution.date, "%Y-%m-%d").ok();
+        if let (Some(mut day), Some(date)) = (previous, date) {
+            while let Some(next) = day.succ_opt().filter(|next| *next < date) {
+                filled.push(empty_contribution(next.format("%Y-%m-%d").to_string()));
+                day = next;
+            }
+        }
+        previous = date.or(previous);
+        filled.push(contribution);
+    }
+
+    result.rolling_cost = calculate_rolling_cost(&filled);
+    result.contributions = filled;
+}
+
+fn empty_contribution(date: String) -> DailyContribution {
+    DailyContribution {
+        date,
+        totals: DailyTotals::default(),
+        intensity: 0,
+        token_breakdown: TokenBreakdown::default(),
+        sources: Vec::new(),
+        tokens_per_active_hour: 0.0,
+        provider_breakdown: Vec::new(),
+        top_model: None,
+        top_model_cost: 0.0,
+    }
+}
+
+/// Number of calendar days in the trailing cost window, including the day itself
+const ROLLING_WINDOW_DAYS: i64 = 30;
+
+/// Trailing 30-day cost for each contribution. The window is measured in calendar
+/// days, so gaps in `contributions` count as zero-cost days. Expects date order.
+pub fn calculate_rolling_cost(contributions: &[DailyContribution]) -> Vec<f64> {
+    let dates: Vec<Option<chrono::NaiveDate>> = contributions
+        .iter()
+        .map(|c| chrono::NaiveDate::parse_from_str(&c.date, "%Y-%m-%d").ok())
+        .collect();
+
+    let mut rolling = Vec::with_capacity(contributions.len());
+    let mut window_start = 0;
+    let mut window_sum = 0.0;
+
+    for (i, contribution) in contributions.iter().enumerate() {
+        window_sum += contribution.totals.cost;
+
+        let Some(end) = dates[i] else {
+            rolling.push(contribution.totals.cost);
+            continue;
+        };
+
+        while window_start < i {
+            match dates[window_start] {
+                Some(d) if (end - d).num_days() < ROLLING_WINDOW_DAYS => break,
+                _ => {
+                    window_sum -= contributions[window_start].totals.cost;
+                    window_start += 1;
+                }
+            }
+        }
+
+        rolling.push(window_sum);
+    }
+
+    rolling
+}
+
 // =============================================================================
 // Internal helpers
 // =============================================================================
@@ -159,6 +872,8 @@ struct DayAccumulator {
     totals: DailyTotals,
     token_breakdown: TokenBreakdown,
     sources: HashMap<String, SourceContribution>,
+    first_timestamp: i64,
+    last_timestamp: i64,
 }
 
 impl Default for DayAccumulator {
@@ -167,13 +882,21 @@ impl Default for DayAccumulator {
             totals: DailyTotals::default(),
             token_breakdown: TokenBreakdown::default(),
             sources: HashMap::with_capacity(8),
+            first_timestamp: i64::MAX,
+            last_timestamp: i64::MIN,
         }
     }
 }
 
+/// Shortest span a day's velocity is measured over, so a single message or a
+/// burst of a few minutes doesn't divide by almost nothing
+const MIN_ACTIVE_SPAN_MS: i64 = 60 * 60 * 1000;
+
 impl DayAccumulator {
-    fn add_message(&mut self, msg: &UnifiedMessage) {
-        let total_tokens = msg.tokens.input
+    fn add_message(&mut self, msg: &UnifiedMessage, group_by_agent: bool) {
+        let total_tokens = msg
+            .tokens
+            .input
             .saturating_add(msg.tokens.output)
             .saturating_add(msg.tokens.cache_read)
             .saturating_add(msg.tokens.cache_write)
@@ -182,15 +905,37 @@ impl DayAccumulator {
         self.totals.tokens = self.totals.tokens.saturating_add(total_tokens);
         self.totals.cost += msg.cost;
         self.totals.messages = self.totals.messages.saturating_add(1);
+        self.first_timestamp = self.first_timestamp.min(msg.timestamp);
+        self.last_timestamp = self.last_timestamp.max(msg.timestamp);
 
         self.token_breakdown.input = self.token_breakdown.input.saturating_add(msg.tokens.input);
-        self.token_breakdown.output = self.token_breakdown.output.saturating_add(msg.tokens.output);
-        self.token_breakdown.cache_read = self.token_breakdown.cache_read.saturating_add(msg.tokens.cache_read);
-        self.token_breakdown.cache_write = self.token_breakdown.cache_write.saturating_add(msg.tokens.cache_write);
-        self.token_breakdown.reasoning = self.token_breakdown.reasoning.saturating_add(msg.tokens.reasoning);
+        self.token_breakdown.output = self
+            .token_breakdown
+            .output
+            .saturating_add(msg.tokens.output);
+        self.token_breakdown.cache_read = self
+            .token_breakdown
+            .cache_read
+            .saturating_add(msg.tokens.cache_read);
+        self.token_breakdown.cache_write = self
+            .token_breakdown
+            .cache_write
+            .saturating_add(msg.tokens.cache_write);
+        self.token_breakdown.reasoning = self
+            .token_breakdown
+            .reasoning
+            .saturating_add(msg.tokens.reasoning);
 
         // Update source contribution
-        let key = format!("{}:{}", msg.source, msg.model_id);
+        let agent = if group_by_agent {
+            msg.agent.as_deref()
+        } else {
+            None
+        };
+        let key = match agent {
+            Some(agent) => format!("{}:{}:{}", msg.source, msg.model_id, agent),
+            None => format!("{}:{}", msg.source, msg.model_id),
+        };
         let source = self
             .sources
             .entry(key)
@@ -198,6 +943,7 @@ impl DayAccumulator {
                 source: msg.source.clone(),
                 model_id: msg.model_id.clone(),
                 provider_id: msg.provider_id.clone(),
+                agent: agent.map(str::to_string),
                 tokens: TokenBreakdown::default(),
                 cost: 0.0,
                 messages: 0,
@@ -205,8 +951,14 @@ impl DayAccumulator {
 
         source.tokens.input = source.tokens.input.saturating_add(msg.tokens.input);
         source.tokens.output = source.tokens.output.saturating_add(msg.tokens.output);
-        source.tokens.cache_read = source.tokens.cache_read.saturating_add(msg.tokens.cache_read);
-        source.tokens.cache_write = source.tokens.cache_write.saturating_add(msg.tokens.cache_write);
+        source.tokens.cache_read = source
+            .tokens
+            .cache_read
+            .saturating_add(msg.tokens.cache_read);
+        source.tokens.cache_write = source
+            .tokens
+            .cache_write
+            .saturating_add(msg.tokens.cache_write);
         source.tokens.reasoning = source.tokens.reasoning.saturating_add(msg.tokens.reasoning);
         source.cost += msg.cost;
         source.messages = source.messages.saturating_add(1);
@@ -216,12 +968,29 @@ impl DayAccumulator {
         self.totals.tokens = self.totals.tokens.saturating_add(other.totals.tokens);
         self.totals.cost += other.totals.cost;
         self.totals.messages = self.totals.messages.saturating_add(other.totals.messages);
+        self.first_timestamp = self.first_timestamp.min(other.first_timestamp);
+        self.last_timestamp = self.last_timestamp.max(other.last_timestamp);
 
-        self.token_breakdown.input = self.token_breakdown.input.saturating_add(other.token_breakdown.input);
-        self.token_breakdown.output = self.token_breakdown.output.saturating_add(other.token_breakdown.output);
-        self.token_breakdown.cache_read = self.token_breakdown.cache_read.saturating_add(other.token_breakdown.cache_read);
-        self.token_breakdown.cache_write = self.token_breakdown.cache_write.saturating_add(other.token_breakdown.cache_write);
-        self.token_breakdown.reasoning = self.token_breakdown.reasoning.saturating_add(other.token_breakdown.reasoning);
+        self.token_breakdown.input = self
+            .token_breakdown
+            .input
+            .saturating_add(other.token_breakdown.input);
+        self.token_breakdown.output = self
+            .token_breakdown
+            .output
+            .saturating_add(other.token_breakdown.output);
+        self.token_breakdown.cache_read = self
+            .token_breakdown
+            .cache_read
+            .saturating_add(other.token_breakdown.cache_read);
+        self.token_breakdown.cache_write = self
+            .token_breakdown
+            .cache_write
+            .saturating_add(other.token_breakdown.cache_write);
+        self.token_breakdown.reasoning = self
+            .token_breakdown
+            .reasoning
+            .saturating_add(other.token_breakdown.reasoning);
 
         for (key, source) in other.sources {
             let entry = self
@@ -231,6 +1000,7 @@ impl DayAccumulator {
                     source: source.source.clone(),
                     model_id: source.model_id.clone(),
                     provider_id: source.provider_id.clone(),
+                    agent: source.agent.clone(),
                     tokens: TokenBreakdown::default(),
                     cost: 0.0,
                     messages: 0,
@@ -238,25 +1008,67 @@ impl DayAccumulator {
 
             entry.tokens.input = entry.tokens.input.saturating_add(source.tokens.input);
             entry.tokens.output = entry.tokens.output.saturating_add(source.tokens.output);
-            entry.tokens.cache_read = entry.tokens.cache_read.saturating_add(source.tokens.cache_read);
-            entry.tokens.cache_write = entry.tokens.cache_write.saturating_add(source.tokens.cache_write);
-            entry.tokens.reasoning = entry.tokens.reasoning.saturating_add(source.tokens.reasoning);
+            entry.tokens.cache_read = entry
+                .tokens
+                .cache_read
+                .saturating_add(source.tokens.cache_read);
+            entry.tokens.cache_write = entry
+                .tokens
+                .cache_write
+                .saturating_add(source.tokens.cache_write);
+            entry.tokens.reasoning = entry
+                .tokens
+                .reasoning
+                .saturating_add(source.tokens.reasoning);
             entry.cost += source.cost;
             entry.messages = entry.messages.saturating_add(source.messages);
         }
     }
 
     fn into_contribution(self, date: String) -> DailyContribution {
+        let active_span_ms = self
+            .last_timestamp
+            .saturating_sub(self.first_timestamp)
+            .max(MIN_ACTIVE_SPAN_MS);
+        let active_hours = active_span_ms as f64 / MIN_ACTIVE_SPAN_MS as f64;
+        let tokens_per_active_hour = self.totals.tokens as f64 / active_hours;
+        let sources: Vec<SourceContribution> = self.sources.into_values().collect();
+        let (top_model, top_model_cost) = top_model(&sources);
+
         DailyContribution {
             date,
             totals: self.totals,
             intensity: 0, // Will be calculated later
             token_breakdown: self.token_breakdown,
-            sources: self.sources.into_values().collect(),
+            sources,
+            tokens_per_active_hour,
+            provider_breakdown: Vec::new(),
+            top_model,
+            top_model_cost,
         }
     }
 }
 
+/// The costliest model across `sources` with its summed cost, the first by
+/// name on a tie; None when no model had a cost
+fn top_model(sources: &[SourceContribution]) -> (Option<String>, f64) {
+    let mut per_model: BTreeMap<&str, f64> = BTreeMap::new();
+    for source in sources {
+        *per_model.entry(source.model_id.as_str()).or_default() += source.cost;
+    }
+
+    let mut top: Option<(&str, f64)> = None;
+    for (model, cost) in per_model {
+        if cost > top.map_or(0.0, |(_, top_cost)| top_cost) {
+            top = Some((model, cost));
+        }
+    }
+    match top {
+        Some((model, cost)) => (Some(model.to_string()), cost),
+        None => (None, 0.0),
+    }
+}
+
 #[derive(Default)]
 struct YearAccumulator {
     tokens: i64,
@@ -265,28 +1077,1103 @@ struct YearAccumulator {
     end: String,
 }
 
-fn calculate_intensities(contributions: &mut [DailyContribution]) {
-    let max_cost = contributions
+/// Fill each day's `provider_breakdown` by summing its source contributions
+/// per provider
+pub fn add_provider_breakdown(contributions: &mut [DailyContribution]) {
+    for day in contributions.iter_mut() {
+        let mut providers: BTreeMap<&str, ProviderDayContribution> = BTreeMap::new();
+        for source in &day.sources {
+            let entry = providers
+                .entry(source.provider_id.as_str())
+                .or_insert_with(|| ProviderDayContribution {
+                    provider_id: source.provider_id.clone(),
+                    tokens: TokenBreakdown::default(),
+                    cost: 0.0,
+                    messages: 0,
+                });
+            entry.tokens.input = entry.tokens.input.saturating_add(source.tokens.input);
+            entry.tokens.output = entry.tokens.output.saturating_add(source.tokens.output);
+            entry.tokens.cache_read = entry
+                .tokens
+                .cache_read
+                .saturating_add(source.tokens.cache_read);
+            entry.tokens.cache_write = entry
+                .tokens
+                .cache_write
+                .saturating_add(source.tokens.cache_write);
+            entry.tokens.reasoning = entry
+                .tokens
+                .reasoning
+                .saturating_add(source.tokens.reasoning);
+            entry.cost += source.cost;
+            entry.messages = entry.messages.saturating_add(source.messages);
+        }
+        day.provider_breakdown = providers.into_values().collect();
+    }
+}
+
+/// Heatmap levels (intensities `0..5`) unless a caller asks for others
+pub const DEFAULT_INTENSITY_LEVELS: u8 = 5;
+
+/// Set each day's `intensity` in `0..levels` (clamped to 2–10) from its
+/// `metric` relative to the day with the most. Any amount at all is at least
+/// 1, and the remaining levels split the ratio into equal bands.
+pub fn calculate_intensities(
+    contributions: &mut [DailyContribution],
+    levels: u8,
+    metric: ActiveMetric,
+) {
+    let top = levels.clamp(2, 10) - 1;
+    let max = contributions
         .iter()
-        .map(|c| c.totals.cost)
+        .map(|c| metric.measure(c))
         .fold(0.0, f64::max);
 
-    if max_cost == 0.0 {
+    if max == 0.0 {
         return;
     }
 
     for c in contributions.iter_mut() {
-        let ratio = c.totals.cost / max_cost;
-        c.intensity = if ratio >= 0.75 {
-            4
-        } else if ratio >= 0.5 {
-            3
-        } else if ratio >= 0.25 {
-            2
-        } else if ratio > 0.0 {
-            1
+        let ratio = metric.measure(c) / max;
+        c.intensity = if ratio > 0.0 {
+            (1 + (ratio * top as f64) as u8).min(top)
         } else {
             0
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message_on(model: &str, date: &str, cost: f64) -> UnifiedMessage {
+        message_in_session(model, "session-1", date, cost)
+    }
+
+    fn message_in_session(model: &str, session_id: &str, date: &str, cost: f64) -> UnifiedMessage {
+        let mut msg = UnifiedMessage::new(
+            "claude",
+            model,
+            "anthropic",
+            session_id,
+            1733011200000,
+            TokenBreakdown {
+                input: 100,
+                output: 50,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+            },
+            cost,
+        );
+        msg.date = date.to_string();
+        msg
+    }
+
+    #[test]
+    fn test_aggregate_by_model_first_and_last_date() {
+        let messages = vec![
+            message_on("claude-sonnet-4", "2025-03-10", 0.1),
+            message_on("claude-sonnet-4", "2025-01-05", 0.1),
+            message_on("claude-sonnet-4", "2025-02-20", 0.1),
+            message_on("claude-sonnet-4", "", 0.1),
+        ];
+
+        let entries = aggregate_by_model(&messages);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message_count, 4);
+        assert_eq!(entries[0].first_date, "2025-01-05");
+        assert_eq!(entries[0].last_date, "2025-03-10");
+    }
+
+    #[test]
+    fn test_aggregate_by_model_sorted_by_cost() {
+        let messages = vec![
+            message_on("claude-haiku-4-5", "2025-01-01", 0.01),
+            message_on("claude-opus-4-5", "2025-01-01", 1.0),
+        ];
+
+        let entries = aggregate_by_model(&messages);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].model, "claude-opus-4-5");
+        assert_eq!(entries[1].model, "claude-haiku-4-5");
+    }
+
+    #[test]
+    fn test_aggregate_by_model_breaks_cost_ties_by_source_provider_model() {
+        let mut codex = message_on("gpt-5-mini", "2025-01-01", 0.0);
+        codex.source = "codex".to_string();
+        codex.provider_id = "openai".to_string();
+        let messages = vec![
+            message_on("claude-sonnet-4", "2025-01-01", 0.0),
+            codex,
+            message_on("claude-haiku-4-5", "2025-01-01", 0.0),
+            message_on("claude-opus-4-5", "2025-01-01", 1.0),
+        ];
+
+        for _ in 0..20 {
+            let order: Vec<String> = aggregate_by_model(&messages)
+                .into_iter()
+                .map(|e| format!("{}/{}", e.source, e.model))
+                .collect();
+            assert_eq!(
+                order,
+                [
+                    "claude/claude-opus-4-5",
+                    "claude/claude-haiku-4-5",
+                    "claude/claude-sonnet-4",
+                    "codex/gpt-5-mini"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_aggregate_by_model_session_count() {
+        let mut messages = Vec::new();
+        for i in 0..10 {
+            let session = if i < 6 { "session-a" } else { "session-b" };
+            messages.push(message_in_session(
+                "claude-opus-4-5",
+                session,
+                "2025-01-01",
+                0.5,
+            ));
+        }
+        for session in ["session-x", "session-y", "session-z"] {
+            messages.push(message_in_session(
+                "claude-haiku-4-5",
+                session,
+                "2025-01-01",
+                0.01,
+            ));
+        }
+
+        let entries = aggregate_by_model(&messages);
+
+        let opus = entries
+            .iter()
+            .find(|e| e.model == "claude-opus-4-5")
+            .unwrap();
+        assert_eq!(opus.message_count, 10);
+        assert_eq!(opus.session_count, 2);
+
+        let haiku = entries
+            .iter()
+            .find(|e| e.model == "claude-haiku-4-5")
+            .unwrap();
+        assert_eq!(haiku.message_count, 3);
+        assert_eq!(haiku.session_count, 3);
+    }
+
+    #[test]
+    fn test_aggregate_by_model_sums_cost_breakdown() {
+        let priced = |cost: f64| {
+            let mut msg = message_on("claude-sonnet-4", "2025-01-01", cost);
+            msg.cost_breakdown = TokenCostBreakdown {
+                input_cost: cost * 0.2,
+                output_cost: cost * 0.5,
+                cache_read_cost: cost * 0.1,
+                cache_write_cost: cost * 0.15,
+                reasoning_cost: cost * 0.05,
+                unattributed_cost: 0.0,
+            };
+            msg
+        };
+        let mut recorded = message_on("claude-sonnet-4", "2025-01-02", 0.7);
+        recorded.cost_breakdown.unattributed_cost = 0.7;
+        let messages = vec![priced(0.3), priced(1.1), recorded];
+
+        let entries = aggregate_by_model(&messages);
+
+        assert_eq!(entries.len(), 1);
+        let breakdown = &entries[0].cost_breakdown;
+        assert!((breakdown.total() - entries[0].cost).abs() < 1e-9);
+        assert!((breakdown.output_cost - 0.7).abs() < 1e-9);
+        assert!((breakdown.unattributed_cost - 0.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tokens_per_active_hour_over_the_day_span() {
+        let hour = 60 * 60 * 1000;
+        let start = 1735725600000_i64; // 2025-01-01T10:00:00Z
+        let at = |offset: i64| {
+            let mut msg = message_on("claude-sonnet-4", "2025-01-01", 0.1);
+            msg.timestamp = start + offset;
+            msg
+        };
+        // 150 tokens each, spanning 4 hours
+        let messages = vec![at(0), at(hour), at(2 * hour + 30 * 60 * 1000), at(4 * hour)];
+        let lone = vec![message_on("claude-sonnet-4", "2025-01-02", 0.1)];
+
+        let days = aggregate_by_date(&[messages, lone].concat(), false);
+
+        assert_eq!(days[0].totals.tokens, 600);
+        assert!((days[0].tokens_per_active_hour - 150.0).abs() < 1e-9);
+        // A single message is measured over the one-hour floor
+        assert!((days[1].tokens_per_active_hour - 150.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_add_totals_on_sums_only_that_day() {
+        let messages = [
+            message_on("claude-sonnet-4", "2025-03-09", 4.0),
+            message_on("claude-sonnet-4", "2025-03-10", 0.5),
+            message_on("gpt-5", "2025-03-10", 0.25),
+            message_on("claude-sonnet-4", "2025-03-11", 8.0),
+        ];
+
+        let mut totals = DailyTotals::default();
+        add_totals_on(&mut totals, &messages[..2], "2025-03-10");
+        add_totals_on(&mut totals, &messages[2..], "2025-03-10");
+
+        assert_eq!(totals.messages, 2);
+        assert_eq!(totals.cost, 0.75);
+        assert_eq!(totals.tokens, 300);
+    }
+
+    #[test]
+    fn test_summary_sources_and_models_are_sorted() {
+        let mut messages = Vec::new();
+        for (i, (source, model)) in [
+            ("opencode", "gpt-5"),
+            ("claude", "claude-sonnet-4"),
+            ("warp", "gpt-4.1"),
+            ("amp", "claude-opus-4-5"),
+            ("codex", "gpt-5-codex"),
+            ("gemini", "gemini-2.5-pro"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut msg = message_on(model, &format!("2025-01-{:02}", i + 1), 0.5);
+            msg.source = source.to_string();
+            messages.push(msg);
+        }
+
+        let summary =
+            generate_graph_result(aggregate_by_date(&messages, false), 0, ActiveMetric::Cost)
+                .summary;
+
+        assert_eq!(
+            summary.sources,
+            ["amp", "claude", "codex", "gemini", "opencode", "warp"]
+        );
+        assert_eq!(
+            summary.models,
+            [
+                "claude-opus-4-5",
+                "claude-sonnet-4",
+                "gemini-2.5-pro",
+                "gpt-4.1",
+                "gpt-5",
+                "gpt-5-codex"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_agent_splits_source_contributions() {
+        let with_agent = |agent: &str, cost: f64| {
+            let mut msg = message_on("claude-sonnet-4", "2025-01-01", cost);
+            msg.source = "opencode".to_string();
+            msg.agent = Some(agent.to_string());
+            msg
+        };
+        let messages = vec![
+            with_agent("build", 1.0),
+            with_agent("plan", 0.5),
+            with_agent("build", 0.25),
+        ];
+
+        let merged = aggregate_by_date(&messages, false);
+        assert_eq!(merged[0].sources.len(), 1);
+        assert_eq!(merged[0].sources[0].agent, None);
+
+        let grouped = aggregate_by_date(&messages, true);
+        assert_eq!(grouped.len(), 1);
+        let mut sources = grouped[0].sources.clone();
+        sources.sort_by(|a, b| a.agent.cmp(&b.agent));
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].agent.as_deref(), Some("build"));
+        assert_eq!(sources[0].messages, 2);
+        assert_eq!(sources[0].cost, 1.25);
+        assert_eq!(sources[1].agent.as_deref(), Some("plan"));
+        assert_eq!(sources[1].messages, 1);
+        assert_eq!(grouped[0].totals.cost, merged[0].totals.cost);
+    }
+
+    #[test]
+    fn test_report_and_graph_share_messages() {
+        let messages = vec![
+            message_on("claude-opus-4-5", "2025-01-01", 1.5),
+            message_on("claude-sonnet-4", "2025-01-01", 0.25),
+            message_on("claude-sonnet-4", "2025-01-02", 0.25),
+            message_in_session("claude-haiku-4-5", "session-b", "2025-01-03", 0.125),
+        ];
+
+        let entries = aggregate_by_model(&messages);
+        let contributions = aggregate_by_date(&messages, false);
+
+        // Borrowing leaves the dataset intact and repeatable
+        assert_eq!(messages.len(), 4);
+        assert_eq!(aggregate_by_model(&messages).len(), entries.len());
+
+        let report_cost: f64 = entries.iter().map(|e| e.cost).sum();
+        let report_messages: i32 = entries.iter().map(|e| e.message_count).sum();
+        let report_tokens: i64 = entries.iter().map(|e| e.input + e.output).sum();
+
+        let summary = calculate_summary(&contributions, ActiveMetric::Cost);
+        assert_eq!(report_cost, 2.125);
+        assert_eq!(summary.total_cost, report_cost);
+        assert_eq!(report_messages, 4);
+        assert_eq!(
+            contributions.iter().map(|c| c.totals.messages).sum::<i32>(),
+            report_messages
+        );
+        assert_eq!(summary.total_tokens, report_tokens);
+        assert_eq!(contributions.len(), 3);
+    }
+
+    #[test]
+    fn test_provider_breakdown() {
+        let mut openai = message_on("gpt-5", "2025-01-01", 0.5);
+        openai.provider_id = "openai".to_string();
+        let messages = vec![
+            message_on("claude-sonnet-4", "2025-01-01", 1.0),
+            message_on("claude-haiku-4-5", "2025-01-01", 0.25),
+            openai,
+        ];
+
+        let mut contributions = aggregate_by_date(&messages, false);
+        assert!(contributions[0].provider_breakdown.is_empty());
+
+        add_provider_breakdown(&mut contributions);
+        let providers = &contributions[0].provider_breakdown;
+        assert_eq!(providers.len(), 2);
+        assert_eq!(providers[0].provider_id, "anthropic");
+        assert_eq!(providers[0].cost, 1.25);
+        assert_eq!(providers[0].messages, 2);
+        assert_eq!(providers[0].tokens.input, 2 * messages[0].tokens.input);
+        assert_eq!(providers[1].provider_id, "openai");
+        assert_eq!(providers[1].cost, 0.5);
+        assert_eq!(providers[1].messages, 1);
+    }
+
+    #[test]
+    fn test_intensity_levels() {
+        let messages = vec![
+            message_on("claude-sonnet-4", "2025-01-01", 10.0),
+            message_on("claude-sonnet-4", "2025-01-02", 5.0),
+            message_on("claude-sonnet-4", "2025-01-03", 1.0),
+            message_on("claude-sonnet-4", "2025-01-04", 0.0),
+        ];
+        let mut contributions = aggregate_by_date(&messages, false);
+        let intensities =
+            |c: &[DailyContribution]| c.iter().map(|d| d.intensity).collect::<Vec<_>>();
+
+        // The default five levels
+        assert_eq!(intensities(&contributions), [4, 3, 1, 0]);
+
+        calculate_intensities(&mut contributions, 10, ActiveMetric::Cost);
+        assert_eq!(intensities(&contributions), [9, 5, 1, 0]);
+
+        // Out-of-range counts are clamped
+        calculate_intensities(&mut contributions, 1, ActiveMetric::Cost);
+        assert_eq!(intensities(&contributions), [1, 1, 1, 0]);
+        calculate_intensities(&mut contributions, 50, ActiveMetric::Cost);
+        assert_eq!(contributions[0].intensity, 9);
+    }
+
+    #[test]
+    fn test_accumulators_match_batch_aggregation() {
+        let mut messages = Vec::new();
+        for (i, source) in ["opencode", "claude", "codex"].iter().enumerate() {
+            for day in 1..=5 {
+                let cost = 0.1 * (i + 1) as f64 + 0.01 * day as f64;
+                let mut msg = message_in_session(
+                    &format!("model-{}", i),
+                    &format!("session-{}", day % 2),
+                    &format!("2025-01-{:02}", day),
+                    cost,
+                );
+                msg.source = source.to_string();
+                messages.push(msg);
+            }
+        }
+
+        let batch_models = aggregate_by_model(&messages);
+        let batch_days = aggregate_by_date(&messages, false);
+
+        // Low-memory mode feeds one source at a time
+        let mut models = ModelAccumulator::default();
+        let mut days = DateAccumulator::default();
+        for chunk in messages.chunks(5) {
+            let batch = chunk.to_vec();
+            models.add(&batch);
+            days.add(&batch);
+        }
+        let streamed_models = models.finish();
+        let streamed_days = days.finish();
+
+        assert_eq!(
+            format!("{:?}", streamed_models),
+            format!("{:?}", batch_models)
+        );
+
+        assert_eq!(streamed_days.len(), batch_days.len());
+        for (streamed, batch) in streamed_days.iter().zip(&batch_days) {
+            assert_eq!(streamed.date, batch.date);
+            assert_eq!(streamed.totals.tokens, batch.totals.tokens);
+            assert_eq!(streamed.totals.messages, batch.totals.messages);
+            assert!((streamed.totals.cost - batch.totals.cost).abs() < 1e-12);
+            assert_eq!(streamed.intensity, batch.intensity);
+            assert_eq!(streamed.sources.len(), batch.sources.len());
+        }
+    }
+
+    #[test]
+    fn test_far_future_timestamp_does_not_extend_graph() {
+        // 9999-12-31T00:00:00Z
+        let corrupt = UnifiedMessage::new(
+            "claude",
+            "claude-sonnet-4",
+            "anthropic",
+            "session-1",
+            253402214400000,
+            TokenBreakdown {
+                input: 100,
+                output: 50,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+            },
+            5.0,
+        );
+        let messages = vec![
+            message_on("claude-sonnet-4", "2025-01-05", 1.0),
+            message_on("claude-sonnet-4", "2025-02-20", 2.0),
+            corrupt,
+        ];
+
+        let result =
+            generate_graph_result(aggregate_by_date(&messages, false), 0, ActiveMetric::Cost);
+
+        assert_eq!(result.meta.date_range_start, "2025-01-05");
+        assert_eq!(result.meta.date_range_end, "2025-02-20");
+        assert_eq!(result.contributions.len(), 2);
+        assert_eq!(result.years.len(), 1);
+    }
+
+    #[test]
+    fn test_compare_model_usage() {
+        let first = aggregate_by_model(&[
+            message_on("claude-sonnet-4", "2025-01-01", 2.0),
+            message_on("claude-sonnet-4", "2025-01-02", 2.0),
+        ]);
+        let second = aggregate_by_model(&[
+            message_on("claude-sonnet-4", "2025-02-01", 1.0),
+            message_on("claude-haiku-4-5", "2025-02-01", 0.5),
+        ]);
+
+        let deltas = compare_model_usage(&first, &second);
+        assert_eq!(deltas.len(), 2);
+
+        let cheaper = deltas
+            .iter()
+            .find(|d| d.model == "claude-sonnet-4")
+            .unwrap();
+        assert_eq!(cheaper.first_cost, 4.0);
+        assert_eq!(cheaper.second_cost, 1.0);
+        assert_eq!(cheaper.cost_delta, -3.0);
+        assert_eq!(cheaper.first_tokens, 300);
+        assert_eq!(cheaper.tokens_delta, -150);
+        assert_eq!(cheaper.pct_change, Some(-75.0));
+
+        let new_model = deltas
+            .iter()
+            .find(|d| d.model == "claude-haiku-4-5")
+            .unwrap();
+        assert_eq!(new_model.first_cost, 0.0);
+        assert_eq!(new_model.first_tokens, 0);
+        assert_eq!(new_model.cost_delta, 0.5);
+        assert_eq!(new_model.tokens_delta, 150);
+        assert_eq!(new_model.pct_change, None);
+
+        // Largest change first
+        assert_eq!(deltas[0].model, "claude-sonnet-4");
+    }
+
+    #[test]
+    fn test_range_totals() {
+        let entries = aggregate_by_model(&[
+            message_on("claude-sonnet-4", "2025-01-01", 2.0),
+            message_on("claude-haiku-4-5", "2025-01-01", 0.5),
+        ]);
+        let totals = range_totals(&entries);
+        assert_eq!(totals.cost, 2.5);
+        assert_eq!(totals.tokens, 300);
+        assert_eq!(totals.messages, 2);
+    }
+
+    fn contribution_on(date: &str, cost: f64) -> DailyContribution {
+        DailyContribution {
+            date: date.to_string(),
+            totals: DailyTotals {
+                tokens: 0,
+                cost,
+                messages: 1,
+            },
+            intensity: 0,
+            token_breakdown: TokenBreakdown::default(),
+            sources: Vec::new(),
+            tokens_per_active_hour: 0.0,
+            provider_breakdown: Vec::new(),
+            top_model: None,
+            top_model_cost: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_rolling_cost_sparse_days() {
+        let contributions = vec![
+            contribution_on("2025-01-01", 1.0),
+            contribution_on("2025-01-15", 2.0),
+            // 30 days ending here start on 2025-01-01
+            contribution_on("2025-01-30", 4.0),
+            // 2025-01-01 has left the window
+            contribution_on("2025-01-31", 8.0),
+            // Only 2025-03-05 itself is in range after the gap
+            contribution_on("2025-03-05", 16.0),
+        ];
+
+        let rolling = calculate_rolling_cost(&contributions);
+
+        assert_eq!(rolling, vec![1.0, 3.0, 7.0, 14.0, 16.0]);
+    }
+
+    #[test]
+    fn test_active_metric_counts_unpriced_days() {
+        let mut unpriced = message_on("custom-model", "2025-03-02", 0.0);
+        unpriced.tokens.input = 2_000_000;
+        let messages = vec![message_on("claude-sonnet-4", "2025-03-01", 1.0), unpriced];
+
+        let by_cost =
+            generate_graph_result(aggregate_by_date(&messages, false), 0, ActiveMetric::Cost);
+        assert_eq!(by_cost.summary.active_days, 1);
+        assert_eq!(by_cost.summary.average_per_day, 1.0);
+        assert_eq!(by_cost.contributions[1].intensity, 0);
+
+        let mut contributions = aggregate_by_date(&messages, false);
+        calculate_intensities(
+            &mut contributions,
+            DEFAULT_INTENSITY_LEVELS,
+            ActiveMetric::Tokens,
+        );
+        let by_tokens = generate_graph_result(contributions, 0, ActiveMetric::Tokens);
+        assert_eq!(by_tokens.summary.active_days, 2);
+        assert_eq!(by_tokens.summary.average_per_day, 0.5);
+        assert_eq!(by_tokens.contributions[1].intensity, 4);
+        assert_eq!(by_tokens.contributions[0].intensity, 1);
+
+        assert_eq!(ActiveMetric::parse(None), Ok(ActiveMetric::Cost));
+        assert_eq!(
+            ActiveMetric::parse(Some("tokens")),
+            Ok(ActiveMetric::Tokens)
+        );
+        assert!(ActiveMetric::parse(Some("messages")).is_err());
+    }
+
+    #[test]
+    fn test_rolling_cost_in_graph_result() {
+        let result = generate_graph_result(
+            vec![
+                contribution_on("2025-01-01", 1.5),
+                contribution_on("2025-01-02", 2.5),
+            ],
+            0,
+            ActiveMetric::Cost,
+        );
+        assert_eq!(result.rolling_cost, vec![1.5, 4.0]);
+        assert_eq!(result.rolling_cost.len(), result.contributions.len());
+    }
+
+    #[test]
+    fn test_fill_date_gaps() {
+        let messages = vec![
+            message_on("claude-sonnet-4", "2025-01-01", 1.0),
+            message_on("claude-sonnet-4", "2025-01-05", 2.0),
+            message_on("claude-sonnet-4", "2025-01-06", 0.5),
+        ];
+        let mut result =
+            generate_graph_result(aggregate_by_date(&messages, false), 0, ActiveMetric::Cost);
+        let summary_days = result.summary.total_days;
+        fill_date_gaps(&mut result);
+
+        let dates: Vec<&str> = result
+            .contributions
+            .iter()
+            .map(|c| c.date.as_str())
+            .collect();
+        assert_eq!(
+            dates,
+            [
+                "2025-01-01",
+                "2025-01-02",
+                "2025-01-03",
+                "2025-01-04",
+                "2025-01-05",
+                "2025-01-06"
+            ]
+        );
+        for gap in &result.contributions[1..4] {
+            assert_eq!(gap.intensity, 0);
+            assert_eq!(gap.totals.cost, 0.0);
+            assert!(gap.sources.is_empty());
+        }
+        assert!(result.contributions[0].intensity > 0);
+        assert!(result.contributions[4].intensity > 0);
+        assert_eq!(result.rolling_cost, vec![1.0, 1.0, 1.0, 1.0, 3.0, 3.5]);
+        assert_eq!(result.summary.total_days, summary_days);
+
+        // Contiguous input is left alone
+        let mut contiguous = generate_graph_result(
+            aggregate_by_date(&messages[1..], false),
+            0,
+            ActiveMetric::Cost,
+        );
+        fill_date_gaps(&mut contiguous);
+        assert_eq!(contiguous.contributions.len(), 2);
+    }
+
+    #[test]
+    fn test_quarter_of() {
+        assert_eq!(quarter_of("2025-01-15").as_deref(), Some("2025-Q1"));
+        assert_eq!(quarter_of("2025-03-31").as_deref(), Some("2025-Q1"));
+        assert_eq!(quarter_of("2025-04-01").as_deref(), Some("2025-Q2"));
+        assert_eq!(quarter_of("2025-12-31").as_deref(), Some("2025-Q4"));
+        assert_eq!(quarter_of("2025-1"), None);
+        assert_eq!(quarter_of("2025-13-01"), None);
+        assert_eq!(quarter_of(""), None);
+    }
+
+    #[test]
+    fn test_aggregate_by_quarter_march_april_boundary() {
+        let entries = aggregate_by_quarter(vec![
+            message_on("claude-sonnet-4", "2025-04-01", 3.0),
+            message_on("claude-sonnet-4", "2025-03-31", 1.0),
+            message_on("claude-haiku-4-5", "2025-03-01", 0.5),
+            message_on("claude-opus-4-5", "2025-04-30", 2.0),
+            message_on("claude-opus-4-5", "bad", 100.0),
+        ]);
+
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].quarter, "2025-Q1");
+        assert_eq!(entries[0].message_count, 2);
+        assert_eq!(entries[0].cost, 1.5);
+        assert_eq!(entries[0].input, 200);
+        assert_eq!(
+            entries[0].models,
+            vec!["claude-haiku-4-5", "claude-sonnet-4"]
+        );
+
+        assert_eq!(entries[1].quarter, "2025-Q2");
+        assert_eq!(entries[1].message_count, 2);
+        assert_eq!(entries[1].cost, 5.0);
+        assert_eq!(
+            entries[1].models,
+            vec!["claude-opus-4-5", "claude-sonnet-4"]
+        );
+    }
+
+    fn message_with_tokens(model: &str, date: &str, tokens: TokenBreakdown) -> UnifiedMessage {
+        let mut msg = message_on(model, date, 0.1);
+        msg.tokens = tokens;
+        msg
+    }
+
+    fn mixed_token_messages() -> Vec<UnifiedMessage> {
+        vec![
+            message_with_tokens(
+                "gpt-5",
+                "2025-01-10",
+                TokenBreakdown {
+                    input: 100,
+                    output: 40,
+                    cache_read: 500,
+                    cache_write: 0,
+                    reasoning: 300,
+                },
+            ),
+            message_with_tokens(
+                "gpt-5",
+                "2025-02-03",
+                TokenBreakdown {
+                    input: 20,
+                    output: 5,
+                    cache_read: 0,
+                    cache_write: 0,
+                    reasoning: 75,
+                },
+            ),
+            message_with_tokens(
+                "claude-sonnet-4",
+                "2025-01-22",
+                TokenBreakdown {
+                    input: 7,
+                    output: 900,
+                    cache_read: 12_000,
+                    cache_write: 2_000,
+                    reasoning: 0,
+                },
+            ),
+            message_with_tokens(
+                "claude-sonnet-4",
+                "2025-02-28",
+                TokenBreakdown {
+                    input: 3,
+                    output: 60,
+                    cache_read: 0,
+                    cache_write: 400,
+                    reasoning: 0,
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_model_total_tokens_includes_every_category() {
+        let entries = aggregate_by_model(&mixed_token_messages());
+
+        assert_eq!(entries.len(), 2);
+        for entry in &entries {
+            assert_eq!(
+                entry.total_tokens,
+                entry.input + entry.output + entry.cache_read + entry.cache_write + entry.reasoning,
+                "{}",
+                entry.model
+            );
+        }
+        let gpt = entries.iter().find(|e| e.model == "gpt-5").unwrap();
+        assert_eq!(gpt.total_tokens, 1_040);
+    }
+
+    #[test]
+    fn test_cost_per_mtok_ranks_models_by_efficiency() {
+        let mut messages = vec![
+            message_with_tokens(
+                "cheap",
+                "2025-01-10",
+                TokenBreakdown {
+                    input: 1_000_000,
+                    output: 500_000,
+                    ..Default::default()
+                },
+            ),
+            message_with_tokens(
+                "pricey",
+                "2025-01-10",
+                TokenBreakdown {
+                    input: 500_000,
+                    output: 250_000,
+                    ..Default::default()
+                },
+            ),
+            message_with_tokens(
+                "silent",
+                "2025-01-10",
+                TokenBreakdown {
+                    input: 1_000,
+                    ..Default::default()
+                },
+            ),
+            message_with_tokens(
+                "free",
+                "2025-01-10",
+                TokenBreakdown {
+                    input: 1_000,
+                    output: 1_000,
+                    ..Default::default()
+                },
+            ),
+        ];
+        messages[0].cost = 1.5;
+        messages[1].cost = 3.0;
+        messages[3].cost = 0.0;
+
+        let entries = aggregate_by_model(&messages);
+        let entry = |model: &str| entries.iter().find(|e| e.model == model).unwrap();
+
+        assert!((entry("cheap").cost_per_mtok_output - 3.0).abs() < 1e-9);
+        assert!((entry("cheap").cost_per_mtok_total - 1.0).abs() < 1e-9);
+        assert!((entry("pricey").cost_per_mtok_output - 12.0).abs() < 1e-9);
+        assert!((entry("pricey").cost_per_mtok_total - 4.0).abs() < 1e-9);
+
+        // No output tokens or no cost comes out as 0, never NaN or inf
+        assert_eq!(entry("silent").cost_per_mtok_output, 0.0);
+        assert!(entry("silent").cost_per_mtok_total > 0.0);
+        assert_eq!(entry("free").cost_per_mtok_output, 0.0);
+        assert_eq!(entry("free").cost_per_mtok_total, 0.0);
+    }
+
+    #[test]
+    fn test_cache_hit_rate() {
+        let messages = vec![
+            message_with_tokens(
+                "cached",
+                "2025-01-10",
+                TokenBreakdown {
+                    input: 100,
+                    output: 50,
+                    cache_read: 900,
+                    ..Default::default()
+                },
+            ),
+            message_with_tokens(
+                "uncached",
+                "2025-01-10",
+                TokenBreakdown {
+                    input: 1_000,
+                    output: 50,
+                    ..Default::default()
+                },
+            ),
+            message_with_tokens(
+                "empty",
+                "2025-01-11",
+                TokenBreakdown {
+                    output: 10,
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        let entries = aggregate_by_model(&messages);
+        let entry = |model: &str| entries.iter().find(|e| e.model == model).unwrap();
+        assert!((entry("cached").cache_hit_rate - 0.9).abs() < 1e-9);
+        assert_eq!(entry("uncached").cache_hit_rate, 0.0);
+        assert_eq!(entry("empty").cache_hit_rate, 0.0);
+
+        let summary = calculate_summary(&aggregate_by_date(&messages, false), ActiveMetric::Cost);
+        assert!((summary.cache_hit_rate - 900.0 / 2_000.0).abs() < 1e-9);
+        assert_eq!(
+            calculate_summary(&[], ActiveMetric::Cost).cache_hit_rate,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_top_model_is_costliest_of_the_day() {
+        let messages = vec![
+            message_on("claude-sonnet-4", "2025-01-10", 0.5),
+            message_on("claude-opus-4", "2025-01-10", 1.25),
+            message_on("claude-sonnet-4", "2025-01-10", 0.5),
+            // A tie goes to the first model by name
+            message_on("gpt-5", "2025-01-11", 0.75),
+            message_on("gpt-4o", "2025-01-11", 0.75),
+            // Nothing priced, so no top model
+            message_on("local-model", "2025-01-12", 0.0),
+        ];
+
+        let contributions = aggregate_by_date(&messages, false);
+        let day = |date: &str| contributions.iter().find(|c| c.date == date).unwrap();
+
+        assert_eq!(
+            day("2025-01-10").top_model.as_deref(),
+            Some("claude-opus-4")
+        );
+        assert!((day("2025-01-10").top_model_cost - 1.25).abs() < 1e-9);
+        assert_eq!(day("2025-01-11").top_model.as_deref(), Some("gpt-4o"));
+        assert_eq!(day("2025-01-12").top_model, None);
+        assert_eq!(day("2025-01-12").top_model_cost, 0.0);
+    }
+
+    #[test]
+    fn test_summary_groups_models_by_provider() {
+        let with_provider = |model: &str, provider: &str| {
+            let mut msg = message_on(model, "2025-01-10", 1.0);
+            msg.provider_id = provider.to_string();
+            msg
+        };
+        let messages = vec![
+            with_provider("gpt-5", "openai"),
+            with_provider("claude-sonnet-4", "anthropic"),
+            with_provider("claude-opus-4", "anthropic"),
+            with_provider("gpt-4o", "openai"),
+            with_provider("claude-sonnet-4", "anthropic"),
+        ];
+
+        let summary = calculate_summary(&aggregate_by_date(&messages, false), ActiveMetric::Cost);
+
+        let grouped: Vec<(&str, Vec<&str>)> = summary
+            .models_by_provider
+            .iter()
+            .map(|p| {
+                (
+                    p.provider.as_str(),
+                    p.models.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            grouped,
+            vec![
+                ("anthropic", vec!["claude-opus-4", "claude-sonnet-4"]),
+                ("openai", vec!["gpt-4o", "gpt-5"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unpriced_models_lists_models_priced_at_zero() {
+        let mut litellm = std::collections::HashMap::new();
+        litellm.insert(
+            "claude-sonnet-4".to_string(),
+            crate::pricing::ModelPricing {
+                input_cost_per_token: Some(0.000003),
+                output_cost_per_token: Some(0.000015),
+                ..Default::default()
+            },
+        );
+        let pricing =
+            crate::pricing::PricingService::new(litellm, std::collections::HashMap::new());
+
+        let tokens = TokenBreakdown {
+            input: 1_000,
+            output: 200,
+            ..Default::default()
+        };
+        let mut messages = vec![
+            message_with_tokens("claude-sonnet-4", "2025-01-10", tokens.clone()),
+            message_with_tokens("mystery-model", "2025-01-10", tokens.clone()),
+            message_with_tokens("mystery-model", "2025-01-11", tokens),
+            // No tokens, so nothing went unpriced
+            message_with_tokens("empty-model", "2025-01-11", TokenBreakdown::default()),
+        ];
+        for msg in &mut messages {
+            pricing.apply(msg);
+        }
+
+        assert!(messages[0].cost > 0.0);
+        assert_eq!(unpriced_models(&messages), ["mystery-model"]);
+    }
+
+    #[test]
+    fn test_aggregate_all_matches_individual_aggregations() {
+        let mut messages = mixed_token_messages();
+        messages.push(message_in_session("gpt-5", "other", "2025-01-10", 0.5));
+        messages.push(message_on("gpt-5", "", 0.25));
+
+        let (models, days, months) = aggregate_all(&messages);
+
+        let sorted_days = |mut days: Vec<DailyContribution>| {
+            for day in &mut days {
+                day.sources
+                    .sort_by(|a, b| a.source.cmp(&b.source).then(a.model_id.cmp(&b.model_id)));
+            }
+            format!("{:?}", days)
+        };
+        assert_eq!(
+            format!("{:?}", models),
+            format!("{:?}", aggregate_by_model(&messages))
+        );
+        assert_eq!(
+            sorted_days(days),
+            sorted_days(aggregate_by_date(&messages, false))
+        );
+        assert_eq!(
+            format!("{:?}", months),
+            format!("{:?}", aggregate_by_month(messages, false))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_by_month_totals_tokens() {
+        let entries = aggregate_by_month(mixed_token_messages(), false);
+
+        let months: Vec<&str> = entries.iter().map(|e| e.month.as_str()).collect();
+        assert_eq!(months, ["2025-01", "2025-02"]);
+        // Reasoning has no column of its own here but still counts toward the total
+        assert_eq!(
+            entries[0].total_tokens,
+            100 + 40 + 500 + 300 + 7 + 900 + 12_000 + 2_000
+        );
+        assert_eq!(entries[1].total_tokens, 20 + 5 + 75 + 3 + 60 + 400);
+        assert_eq!(entries[1].models, vec!["claude-sonnet-4", "gpt-5"]);
+        assert_eq!(entries[1].message_count, 2);
+    }
+
+    #[test]
+    fn test_month_source_breakdown_sums_to_month() {
+        let mut messages = mixed_token_messages();
+        messages[1].source = "cursor".to_string();
+        messages[1].cost = 0.4;
+
+        let entries = aggregate_by_month(messages.clone(), true);
+
+        let february = &entries[1];
+        let sources: Vec<&str> = february
+            .source_breakdown
+            .iter()
+            .map(|s| s.source.as_str())
+            .collect();
+        assert_eq!(sources, ["claude", "cursor"]);
+        let sum = |f: fn(&SourceUsage) -> i64| february.source_breakdown.iter().map(f).sum::<i64>();
+        assert_eq!(sum(|s| s.input), february.input);
+        assert_eq!(sum(|s| s.output), february.output);
+        assert_eq!(sum(|s| s.cache_write), february.cache_write);
+        assert_eq!(sum(|s| s.total_tokens), february.total_tokens);
+        assert_eq!(
+            sum(|s| s.message_count as i64),
+            february.message_count as i64
+        );
+        let cost: f64 = february.source_breakdown.iter().map(|s| s.cost).sum();
+        assert!((cost - february.cost).abs() < 1e-9);
+        assert_eq!(february.source_breakdown[1].cost, 0.4);
+
+        // Off by default
+        assert!(aggregate_by_month(messages, false)
+            .iter()
+            .all(|m| m.source_breakdown.is_empty()));
+    }
+
+    #[test]
+    fn test_aggregate_by_project_splits_claude_projects() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let projects = dir.path().join(".claude").join("projects");
+        let line = |id: &str| {
+            format!(
+                r#"{{"type":"assistant","timestamp":"2025-01-01T10:00:00.000Z","requestId":"req_{id}","message":{{"id":"msg_{id}","model":"claude-sonnet-4","usage":{{"input_tokens":100,"output_tokens":50}}}}}}"#
+            )
+        };
+        for (project, ids) in [
+            ("-Users-me-app", vec!["1", "2"]),
+            ("-Users-me-site", vec!["3"]),
+        ] {
+            let project_dir = projects.join(project);
+            std::fs::create_dir_all(&project_dir).unwrap();
+            let content: Vec<String> = ids.iter().map(|id| line(id)).collect();
+            std::fs::write(project_dir.join("session.jsonl"), content.join("\n")).unwrap();
+        }
+
+        let mut messages = Vec::new();
+        for project in ["-Users-me-app", "-Users-me-site"] {
+            let path = projects.join(project).join("session.jsonl");
+            messages.extend(crate::sessions::claudecode::parse_claude_file(&path));
+        }
+        messages.push(message_on("gemini-2.5-pro", "2025-01-01", 0.0));
+
+        let entries = aggregate_by_project(messages);
+        let by_project = |name: &str| entries.iter().find(|e| e.project == name).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(by_project("-Users-me-app").message_count, 2);
+        assert_eq!(by_project("-Users-me-app").input, 200);
+        assert_eq!(by_project("-Users-me-site").message_count, 1);
+        assert_eq!(by_project(UNKNOWN_PROJECT).models, vec!["gemini-2.5-pro"]);
+    }
+}
@@ -0,0 +1,115 @@
+//! Anonymized identifiers for shareable output (`anonymize`)
+//!
+//! Session ids and project paths are replaced by the first 8 hex digits of
+//! SHA-256 over a salt and the original value. The same value maps to the
+//! same token for one salt; the salt is random per run unless the caller
+//! passes one, so tokens from different runs can't be joined.
+
+use crate::aggregator::UNKNOWN_PROJECT;
+use crate::{ParsedMessage, ProjectUsage};
+use sha2::{Digest, Sha256};
+
+pub struct Anonymizer {
+    salt: String,
+}
+
+impl Anonymizer {
+    /// An anonymizer salted with `salt`, or a fresh random salt. Fails when
+    /// the OS random number generator can't supply one.
+    pub fn new(salt: Option<String>) -> Result<Self, String> {
+        let salt = match salt {
+            Some(salt) => salt,
+            None => {
+                let mut bytes = [0u8; 16];
+                getrandom::fill(&mut bytes)
+                    .map_err(|e| format!("Failed to generate an anonymize salt: {}", e))?;
+                bytes.iter().map(|b| format!("{:02x}", b)).collect()
+            }
+        };
+        Ok(Anonymizer { salt })
+    }
+
+    /// The stand-in for `value`
+    pub fn token(&self, value: &str) -> String {
+        let digest = Sha256::new()
+            .chain_update(self.salt.as_bytes())
+            .chain_update([0])
+            .chain_update(value.as_bytes())
+            .finalize();
+        digest[..4].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn messages(&self, messages: &mut [ParsedMessage]) {
+        for message in messages {
+            message.session_id = self.token(&message.session_id);
+            message.project = message.project.as_deref().map(|p| self.token(p));
+        }
+    }
+
+    pub fn projects(&self, entries: &mut [ProjectUsage]) {
+        for entry in entries.iter_mut().filter(|e| e.project != UNKNOWN_PROJECT) {
+            entry.project = self.token(&entry.project);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(session_id: &str, project: &str) -> ParsedMessage {
+        ParsedMessage {
+            source: "claude".to_string(),
+            model_id: "claude-sonnet-4".to_string(),
+            provider_id: "anthropic".to_string(),
+            session_id: session_id.to_string(),
+            timestamp: 1748772000000,
+            date: "2025-06-01".to_string(),
+            input: 100,
+            output: 50,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+            agent: None,
+            cost: Some(0.1),
+            credits: None,
+            premium_requests: None,
+            project: Some(project.to_string()),
+            batch: None,
+        }
+    }
+
+    #[test]
+    fn test_anonymized_ids_are_stable_and_hide_raw_values() {
+        let anonymizer = Anonymizer::new(None).unwrap();
+        let mut messages = vec![
+            message("ses-secret-1", "-Users-me-code-app"),
+            message("ses-secret-2", "-Users-me-code-app"),
+            message("ses-secret-1", "-Users-me-code-other"),
+        ];
+
+        anonymizer.messages(&mut messages);
+
+        assert_eq!(messages[0].session_id, messages[2].session_id);
+        assert_ne!(messages[0].session_id, messages[1].session_id);
+        assert_eq!(messages[0].project, messages[1].project);
+        assert_eq!(messages[0].session_id.len(), 8);
+        let output = format!("{:?}", messages);
+        assert!(!output.contains("secret") && !output.contains("Users"));
+        // Aggregated numbers are untouched
+        assert_eq!(messages[0].input, 100);
+
+        // A given salt reproduces its tokens; a fresh one does not
+        let salted = Anonymizer::new(Some("salt".to_string())).unwrap();
+        assert_eq!(
+            salted.token("ses-secret-1"),
+            Anonymizer::new(Some("salt".to_string()))
+                .unwrap()
+                .token("ses-secret-1")
+        );
+        assert_ne!(
+            salted.token("ses-secret-1"),
+            Anonymizer::new(None).unwrap().token("ses-secret-1")
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Session data from a zip archive
+//!
+//! Reads an exported copy of the session directories (e.g. a zipped home
+//! backup) without unpacking it. Entries are routed to a parser by their path
+//! inside the archive, using the same layouts the scanner looks for on disk:
+//!
+//! - `.claude/projects/**/*.jsonl` → Claude Code
+//! - `.codex/sessions/**/*.jsonl[.zst]` → Codex
+//!
+//! Everything else in the archive is ignored and never decompressed. Routed
+//! entries are streamed into the source's reader core; one larger than
+//! [`MAX_ENTRY_BYTES`] or failing its CRC check fails the whole archive.
+
+use crate::scanner::SessionType;
+use crate::sessions::{claudecode, codex, UnifiedMessage};
+use std::io::{self, BufReader, Read, Seek};
+use std::path::Path;
+
+/// Largest uncompressed entry read, so a corrupt or hostile size can't run
+/// the scan out of memory or time
+pub const MAX_ENTRY_BYTES: u64 = 1 << 30;
+
+/// Messages from an archive with what was read to get them
+#[derive(Default)]
+pub struct ArchiveScan {
+    pub messages: Vec<UnifiedMessage>,
+    /// Session entries parsed
+    pub files_parsed: i32,
+    /// Uncompressed bytes read from those entries
+    pub bytes_read: i64,
+}
+
+/// The session type an in-archive path belongs to, if any
+pub fn route(name: &str) -> Option<SessionType> {
+    let name = name.replace('\\', "/");
+    let in_dir = |dir: &str| name.starts_with(dir) || name.contains(&format!("/{}", dir));
+    if in_dir(".claude/projects/") && name.ends_with(".jsonl") {
+        return Some(SessionType::Claude);
+    }
+    if in_dir(".codex/sessions/") && (name.ends_with(".jsonl") || name.ends_with(".jsonl.zst")) {
+        return Some(SessionType::Codex);
+    }
+    None
+}
+
+/// Parse every session file in the archive. `fallback_timestamp` stands in
+/// for entries without a timestamp of their own.
+pub fn parse_archive(
+    reader: impl Read + Seek,
+    fallback_timestamp: i64,
+) -> Result<ArchiveScan, String> {
+    let mut zip = zip::ZipArchive::new(reader).map_err(|e| e.to_string())?;
+    let mut scan = ArchiveScan::default();
+    let mut claude_messages = Vec::new();
+    for index in 0..zip.len() {
+        // Route on the central directory's name, before reading the entry
+        let name = match zip.name_for_index(index) {
+            Some(name) => name.map_err(|e| e.to_string())?.into_owned(),
+            None => continue,
+        };
+        let Some(session_type) = route(&name) else {
+            continue;
+        };
+
+        let entry = zip
+            .by_index(index)
+            .map_err(|e| format!("{}: {}", name, e))?;
+        if entry.size() > MAX_ENTRY_BYTES {
+            return Err(format!("{}: larger than {} bytes", name, MAX_ENTRY_BYTES));
+        }
+        let mut entry = EntryReader::new(entry);
+        let path = Path::new(&name);
+        match session_type {
+            SessionType::Claude => claude_messages.extend(claudecode::parse_claude_reader(
+                BufReader::new(&mut entry),
+                path,
+                fallback_timestamp,
+            )),
+            SessionType::Codex => scan.messages.extend(codex::parse_codex_reader(
+                BufReader::new(&mut entry),
+                path,
+                fallback_timestamp,
+            )),
+            _ => {}
+        }
+        scan.bytes_read += entry.finish().map_err(|e| format!("{}: {}", name, e))? as i64;
+        scan.files_parsed += 1;
+    }
+    scan.messages
+        .extend(claudecode::dedup_claude_messages(claude_messages));
+    Ok(scan)
+}
+
+/// An entry as its parser sees it. Parsers skip unreadable lines rather than
+/// fail, so the first error (including the CRC check at the end of the entry
+/// and going past [`MAX_ENTRY_BYTES`]) is kept here and reads end there.
+struct EntryReader<R> {
+    inner: R,
+    read: u64,
+    error: Option<io::Error>,
+}
+
+impl<R: Read> EntryReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            read: 0,
+            error: None,
+        }
+    }
+
+    /// Read whatever the parser left, so the CRC is checked, and return the
+    /// entry's size or its first error
+    fn finish(mut self) -> io::Result<u64> {
+        io::copy(&mut self, &mut io::sink())?;
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.read),
+        }
+    }
+}
+
+impl<R: Read> Read for EntryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.error.is_some() {
+            return Ok(0);
+        }
+        match self.inner.read(buf) {
+            Ok(n) => {
+                self.read += n as u64;
+                if self.read > MAX_ENTRY_BYTES {
+                    self.error = Some(io::Error::other(format!(
+                        "larger than {} bytes",
+                        MAX_ENTRY_BYTES
+                    )));
+                    return Ok(0);
+                }
+                Ok(n)
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => Err(e),
+            Err(e) => {
+                self.error = Some(e);
+                Ok(0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::CompressionMethod;
+
+    /// A zip archive holding `files`, compressed with `method`
+    fn build_zip(files: &[(&str, &[u8])], method: CompressionMethod) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(method);
+        for (name, data) in files {
+            if name.ends_with('/') {
+                writer.add_directory(*name, options).unwrap();
+            } else {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(data).unwrap();
+            }
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    const CLAUDE: &[u8] = br#"{"type":"assistant","timestamp":"2025-06-01T10:00:00Z","requestId":"req_1","message":{"id":"msg_1","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}
+{"type":"assistant","timestamp":"2025-06-01T10:00:00Z","requestId":"req_1","message":{"id":"msg_1","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}
+"#;
+
+    const CODEX: &[u8] = br#"{"type":"turn_context","timestamp":"2025-06-02T09:00:00Z","payload":{"model":"gpt-5"}}
+{"type":"event_msg","timestamp":"2025-06-02T09:00:01Z","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":200,"cached_input_tokens":50,"output_tokens":20}}}}
+"#;
+
+    #[test]
+    fn test_parse_archive_routes_claude_and_codex() {
+        for method in [CompressionMethod::Stored, CompressionMethod::Deflated] {
+            let archive = build_zip(
+                &[
+                    ("home/.claude/", b""),
+                    ("home/.claude/projects/myproject/session-1.jsonl", CLAUDE),
+                    (".codex/sessions/2025/06/02/rollout-1.jsonl", CODEX),
+                    ("home/notes.jsonl", CLAUDE),
+                ],
+                method,
+            );
+
+            let scan = parse_archive(Cursor::new(archive), 0).unwrap();
+
+            assert_eq!(scan.files_parsed, 2);
+            assert_eq!(scan.bytes_read, (CLAUDE.len() + CODEX.len()) as i64);
+            let messages = scan.messages;
+            assert_eq!(messages.len(), 2);
+            let claude = messages.iter().find(|m| m.source == "claude").unwrap();
+            assert_eq!(claude.session_id, "session-1");
+            assert_eq!(claude.project.as_deref(), Some("myproject"));
+            assert_eq!(claude.date, "2025-06-01");
+            assert_eq!(claude.tokens.input, 100);
+            let codex = messages.iter().find(|m| m.source == "codex").unwrap();
+            assert_eq!(codex.model_id, "gpt-5");
+            assert_eq!(codex.tokens.input, 150);
+            assert_eq!(codex.tokens.cache_read, 50);
+        }
+
+        assert!(parse_archive(Cursor::new(b"not a zip".to_vec()), 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_archive_rejects_bad_crc() {
+        let mut archive = build_zip(
+            &[(".codex/sessions/rollout-1.jsonl", CODEX)],
+            CompressionMethod::Stored,
+        );
+        // Flip a byte of the stored entry's data so it no longer matches its CRC
+        let at = archive
+            .windows(CODEX.len())
+            .position(|w| w == CODEX)
+            .unwrap();
+        archive[at + 10] ^= 0x20;
+
+        let err = parse_archive(Cursor::new(archive), 0).err().unwrap();
+        assert!(err.contains("rollout-1.jsonl"), "{}", err);
+    }
+}
@@ -0,0 +1,115 @@
+//! Error codes for failures JS callers may want to tell apart
+//!
+//! The code leads the error message, followed by a colon and the reason, e.g.
+//! `E_NO_HOME: HOME directory not specified ...`. Callers match on the prefix:
+//!
+//! - `E_NO_HOME`: no `homeDir` was given and the home directory could not be
+//!   determined
+//! - `E_PRICING_FETCH`: pricing data could not be loaded from the network or
+//!   the cache
+//! - `E_INVALID_DATE`: `year`, `since`, `until` or `dateSource` is malformed,
+//!   or `since` is after `until`
+//! - `E_INVALID_METRIC`: `activeMetric` is not a known metric
+//! - `E_NO_FILES`: `strict` is set and the scan found no session files
+//! - `E_INVALID_PROVIDER_MAP`: the user's model → provider map (see
+//!   `sessions::providers`) isn't a JSON object of strings
+//! - `E_UNKNOWN_SOURCE`: a single-file parse or headless validation named a
+//!   source it doesn't support
+//! - `E_READ_FAILED`: a file, archive or the watermark directory could not be
+//!   read or removed
+//! - `E_MODEL_NOT_FOUND`: a pricing lookup found no entry for the model
+//!
+//! Errors without a code are everything else (bad options).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NoHome,
+    PricingFetch,
+    InvalidDate,
+    InvalidMetric,
+    NoFiles,
+    InvalidProviderMap,
+    UnknownSource,
+    ReadFailed,
+    ModelNotFound,
+}
+
+impl ErrorCode {
+    const ALL: [ErrorCode; 9] = [
+        ErrorCode::NoHome,
+        ErrorCode::PricingFetch,
+        ErrorCode::InvalidDate,
+        ErrorCode::InvalidMetric,
+        ErrorCode::NoFiles,
+        ErrorCode::InvalidProviderMap,
+        ErrorCode::UnknownSource,
+        ErrorCode::ReadFailed,
+        ErrorCode::ModelNotFound,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::NoHome => "E_NO_HOME",
+            ErrorCode::PricingFetch => "E_PRICING_FETCH",
+            ErrorCode::InvalidDate => "E_INVALID_DATE",
+            ErrorCode::InvalidMetric => "E_INVALID_METRIC",
+            ErrorCode::NoFiles => "E_NO_FILES",
+            ErrorCode::InvalidProviderMap => "E_INVALID_PROVIDER_MAP",
+            ErrorCode::UnknownSource => "E_UNKNOWN_SOURCE",
+            ErrorCode::ReadFailed => "E_READ_FAILED",
+            ErrorCode::ModelNotFound => "E_MODEL_NOT_FOUND",
+        }
+    }
+
+    /// `reason` with this code in front
+    pub fn reason(self, reason: impl std::fmt::Display) -> String {
+        format!("{}: {}", self.as_str(), reason)
+    }
+
+    /// The code `message` starts with, if any
+    pub fn of(message: &str) -> Option<ErrorCode> {
+        ErrorCode::ALL.into_iter().find(|code| {
+            message
+                .strip_prefix(code.as_str())
+                .is_some_and(|rest| rest.starts_with(':'))
+        })
+    }
+
+    fn status(self) -> napi::Status {
+        match self {
+            ErrorCode::InvalidDate | ErrorCode::InvalidMetric | ErrorCode::UnknownSource => {
+                napi::Status::InvalidArg
+            }
+            ErrorCode::NoHome
+            | ErrorCode::PricingFetch
+            | ErrorCode::NoFiles
+            | ErrorCode::InvalidProviderMap
+            | ErrorCode::ReadFailed
+            | ErrorCode::ModelNotFound => napi::Status::GenericFailure,
+        }
+    }
+}
+
+/// A napi error for `reason`, with the status its code calls for
+pub fn to_napi(reason: String) -> napi::Error {
+    match ErrorCode::of(&reason) {
+        Some(code) => napi::Error::new(code.status(), reason),
+        None => napi::Error::from_reason(reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_round_trips_through_message() {
+        for code in ErrorCode::ALL {
+            let message = code.reason("something went wrong");
+            assert!(message.starts_with(code.as_str()));
+            assert_eq!(ErrorCode::of(&message), Some(code));
+        }
+        assert_eq!(ErrorCode::of("E_NO_HOMEWARD: nope"), None);
+        assert_eq!(ErrorCode::of("Failed to read file"), None);
+    }
+}
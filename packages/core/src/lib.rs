@@ -5,18 +5,36 @@
 
 #![deny(clippy::all)]
 
+#[cfg(not(feature = "noop"))]
+use napi::bindgen_prelude::Unknown;
+#[cfg(not(feature = "noop"))]
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
 use napi_derive::napi;
 
 mod aggregator;
+mod anonymize;
+mod archive;
+mod error;
 mod parser;
 mod pricing;
+mod progress;
+mod query;
+mod rounding;
 mod scanner;
 mod sessions;
+mod watermark;
 
 pub use aggregator::*;
 pub use parser::*;
 pub use scanner::*;
 
+/// Internals the benchmarks measure directly
+#[cfg(feature = "bench")]
+pub mod bench {
+    pub use crate::sessions::droid::model_from_jsonl_bytes;
+    pub use crate::sessions::utils::FileBytes;
+}
+
 /// Version of the native module
 #[napi]
 pub fn version() -> String {
@@ -29,10 +47,134 @@ pub fn health_check() -> String {
     "tokscale-core is healthy!".to_string()
 }
 
+/// A directory the scan would walk
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ScanTarget {
+    pub source: String,
+    pub path: String,
+    /// File pattern matched inside `path`, e.g. "*.jsonl"
+    pub pattern: String,
+    pub exists: bool,
+    /// Matching files under `path` right now
+    pub file_count: i32,
+}
+
+impl From<scanner::ScanTarget> for ScanTarget {
+    fn from(target: scanner::ScanTarget) -> Self {
+        Self {
+            source: target.session_type.source().to_string(),
+            path: target.path,
+            pattern: target.pattern.to_string(),
+            exists: target.exists,
+            file_count: target.file_count as i32,
+        }
+    }
+}
+
+/// A supported source and where it is looked for by default
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct SourceInfo {
+    pub name: String,
+    /// First directory scanned for the source, after env overrides
+    pub default_path: String,
+    /// File pattern matched inside `default_path`, e.g. "*.jsonl"
+    pub pattern: String,
+    /// Scanned when no `sources` are given
+    pub enabled_by_default: bool,
+    /// Whether `default_path` exists
+    pub detected: bool,
+}
+
+impl From<scanner::SourceInfo> for SourceInfo {
+    fn from(info: scanner::SourceInfo) -> Self {
+        Self {
+            name: info.session_type.source().to_string(),
+            default_path: info.default_path,
+            pattern: info.pattern.to_string(),
+            enabled_by_default: info.enabled_by_default,
+            detected: info.detected,
+        }
+    }
+}
+
+/// Every source tokscale supports, where it looks for each by default and
+/// whether anything is there under `home_dir` (default: the user's home)
+#[napi]
+pub fn list_sources(home_dir: Option<String>) -> napi::Result<Vec<SourceInfo>> {
+    let home_dir = get_home_dir(&home_dir)?;
+    Ok(scanner::list_sources(&home_dir)
+        .into_iter()
+        .map(SourceInfo::from)
+        .collect())
+}
+
+/// List every directory a report would scan for the given home and sources,
+/// after env overrides and headless roots, without parsing any files
+#[napi]
+pub fn describe_scan(options: ReportOptions) -> napi::Result<Vec<ScanTarget>> {
+    let home_dir = get_home_dir(&options.home_dir)?;
+    // An empty list scans every source
+    let sources = options.sources.unwrap_or_default();
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+
+    Ok(scanner::describe_scan(&home_dir, &sources, &exclude_dirs)
+        .into_iter()
+        .map(ScanTarget::from)
+        .collect())
+}
+
+/// One parser's result in the self-test
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct SelfTestEntry {
+    pub source: String,
+    /// Whether the parser produced messages from its fixture
+    pub ok: bool,
+    pub messages: i32,
+    /// Why the fixture could not be set up or parsed
+    pub error: Option<String>,
+}
+
+/// Parser self-test result
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    pub entries: Vec<SelfTestEntry>,
+    pub processing_time_ms: u32,
+}
+
+/// Run every source parser against a minimal embedded fixture, reporting
+/// which ones still produce messages
+#[napi]
+pub fn self_test() -> napi::Result<SelfTestReport> {
+    let start = Instant::now();
+
+    let entries = sessions::selftest::run_self_test()
+        .into_iter()
+        .map(|result| SelfTestEntry {
+            source: result.source.to_string(),
+            ok: result.ok(),
+            messages: result.messages.as_ref().map_or(0, |&n| n as i32),
+            error: result.messages.err(),
+        })
+        .collect();
+
+    Ok(SelfTestReport {
+        entries,
+        processing_time_ms: start.elapsed().as_millis() as u32,
+    })
+}
+
 /// Token breakdown by type
 #[napi(object)]
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TokenBreakdown {
+    /// Input tokens, including any image/media input tokens that a source reports
+    /// in a separate field. Providers bill image tokens at the input rate, so
+    /// parsers fold them in here rather than tracking them as their own category.
     pub input: i64,
     pub output: i64,
     pub cache_read: i64,
@@ -40,6 +182,68 @@ pub struct TokenBreakdown {
     pub reasoning: i64,
 }
 
+impl TokenBreakdown {
+    /// Every token category, reasoning included
+    pub fn total(&self) -> i64 {
+        self.input + self.output + self.cache_read + self.cache_write + self.reasoning
+    }
+}
+
+/// Cost split by token type. The components sum to the total cost.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct TokenCostBreakdown {
+    pub input_cost: f64,
+    pub output_cost: f64,
+    pub cache_read_cost: f64,
+    pub cache_write_cost: f64,
+    /// Reasoning tokens at the output rate, or the reasoning multiple of it.
+    /// Zero in model reports and cost estimates unless `separate_reasoning_cost`
+    /// is set; reasoning is then part of `output_cost`.
+    pub reasoning_cost: f64,
+    /// Cost a source recorded itself (Cursor CSV cost) and kept
+    /// because the model has no pricing; it can't be split by token type
+    pub unattributed_cost: f64,
+}
+
+impl TokenCostBreakdown {
+    pub fn total(&self) -> f64 {
+        self.input_cost
+            + self.output_cost
+            + self.cache_read_cost
+            + self.cache_write_cost
+            + self.reasoning_cost
+            + self.unattributed_cost
+    }
+
+    /// Multiply every component by `factor`
+    pub fn scale(&mut self, factor: f64) {
+        self.input_cost *= factor;
+        self.output_cost *= factor;
+        self.cache_read_cost *= factor;
+        self.cache_write_cost *= factor;
+        self.reasoning_cost *= factor;
+        self.unattributed_cost *= factor;
+    }
+
+    /// Bill reasoning as output: move `reasoning_cost` into `output_cost`
+    pub fn fold_reasoning_into_output(&mut self) {
+        self.output_cost += self.reasoning_cost;
+        self.reasoning_cost = 0.0;
+    }
+}
+
+impl std::ops::AddAssign<&TokenCostBreakdown> for TokenCostBreakdown {
+    fn add_assign(&mut self, other: &TokenCostBreakdown) {
+        self.input_cost += other.input_cost;
+        self.output_cost += other.output_cost;
+        self.cache_read_cost += other.cache_read_cost;
+        self.cache_write_cost += other.cache_write_cost;
+        self.reasoning_cost += other.reasoning_cost;
+        self.unattributed_cost += other.unattributed_cost;
+    }
+}
+
 // =============================================================================
 // Two-Phase Processing Types (for parallel execution optimization)
 // =============================================================================
@@ -59,6 +263,16 @@ pub struct ParsedMessage {
     pub cache_write: i64,
     pub reasoning: i64,
     pub agent: Option<String>,
+    /// Computed cost; set by `query_messages` and on report `messages`
+    pub cost: Option<f64>,
+    /// Amp credits the message used; never part of `cost`
+    pub credits: Option<f64>,
+    /// Copilot premium requests the message was billed as
+    pub premium_requests: Option<f64>,
+    /// Encoded project directory (Claude, Codex, OpenCode)
+    pub project: Option<String>,
+    /// Sent through Claude's Batch API, so priced at the batch rate
+    pub batch: Option<bool>,
 }
 
 /// Result of parsing local sources (excludes Cursor - it's network-synced)
@@ -73,6 +287,12 @@ pub struct ParsedMessages {
     pub amp_count: i32,
     pub droid_count: i32,
     pub openclaw_count: i32,
+    pub warp_count: i32,
+    pub roo_count: i32,
+    pub kilo_count: i32,
+    pub qwen_count: i32,
+    pub copilot_count: i32,
+    pub jules_count: i32,
     pub processing_time_ms: u32,
 }
 
@@ -85,6 +305,8 @@ pub struct LocalParseOptions {
     pub since: Option<String>,
     pub until: Option<String>,
     pub year: Option<String>,
+    /// Directory names never scanned into. Defaults to `["archive"]`.
+    pub exclude_dirs: Option<Vec<String>>,
 }
 
 /// Options for finalizing report
@@ -97,11 +319,82 @@ pub struct FinalizeReportOptions {
     pub since: Option<String>,
     pub until: Option<String>,
     pub year: Option<String>,
+    /// Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+    /// Defaults to LiteLLM first.
+    pub pricing_priority: Option<Vec<String>>,
+    /// Itemize reasoning cost in the model report instead of folding it into
+    /// output cost. Default false.
+    pub separate_reasoning_cost: Option<bool>,
+    /// Drop messages whose total tokens exceed this, as corrupt. Negative
+    /// token counts are always clamped to 0. Unset keeps every message.
+    pub max_tokens_per_message: Option<i64>,
 }
 
-/// Daily contribution totals
+/// Options for `aggregate_messages` and `report_messages`
 #[napi(object)]
 #[derive(Debug, Clone, Default)]
+pub struct AggregateOptions {
+    /// Recompute each message's cost from pricing data. When false, each
+    /// message's own `cost` is used (0 when unset). Default true.
+    pub apply_pricing: Option<bool>,
+    /// Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+    /// Defaults to LiteLLM first.
+    pub pricing_priority: Option<Vec<String>>,
+    pub year: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Split each graph day's sources by agent
+    pub group_by_agent: Option<bool>,
+    /// Itemize reasoning cost in `report_messages` instead of folding it into
+    /// output cost. Default false.
+    pub separate_reasoning_cost: Option<bool>,
+    /// As in `ReportOptions`, for the pricing applied to the messages
+    pub prefer_cheaper: Option<bool>,
+    pub reasoning_rate_multiplier: Option<f64>,
+    pub batch_rate_multiplier: Option<f64>,
+    pub unknown_model_rate: Option<NativePricing>,
+    pub cost_source: Option<String>,
+    pub fuzzy_min_length: Option<u32>,
+    pub fuzzy_blocklist: Option<Vec<String>>,
+    /// Add a zeroed graph day for every day without activity between the
+    /// first and last active day
+    pub fill_gaps: Option<bool>,
+    /// Round every emitted cost to this many decimal places
+    pub round_costs: Option<u32>,
+    /// Drop messages whose total tokens exceed this, as in `ReportOptions`
+    pub max_tokens_per_message: Option<i64>,
+}
+
+impl AggregateOptions {
+    /// The same settings as report options, so supplied messages are priced,
+    /// filtered and shaped exactly like scanned ones
+    fn report_options(&self) -> ReportOptions {
+        ReportOptions {
+            since: self.since.clone(),
+            until: self.until.clone(),
+            year: self.year.clone(),
+            pricing_priority: self.pricing_priority.clone(),
+            prefer_cheaper: self.prefer_cheaper,
+            reasoning_rate_multiplier: self.reasoning_rate_multiplier,
+            batch_rate_multiplier: self.batch_rate_multiplier,
+            group_by_agent: self.group_by_agent,
+            unknown_model_rate: self.unknown_model_rate.clone(),
+            cost_source: self.cost_source.clone(),
+            fill_gaps: self.fill_gaps,
+            fuzzy_min_length: self.fuzzy_min_length,
+            fuzzy_blocklist: self.fuzzy_blocklist.clone(),
+            round_costs: self.round_costs,
+            separate_reasoning_cost: self.separate_reasoning_cost,
+            max_tokens_per_message: self.max_tokens_per_message,
+            ..Default::default()
+        }
+    }
+}
+
+/// Daily contribution totals
+#[napi(object)]
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DailyTotals {
     pub tokens: i64,
     pub cost: f64,
@@ -110,10 +403,24 @@ pub struct DailyTotals {
 
 /// Source contribution for a specific day
 #[napi(object)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SourceContribution {
     pub source: String,
     pub model_id: String,
+    pub provider_id: String,
+    /// Set only when the graph was grouped by agent and the source records one
+    pub agent: Option<String>,
+    pub tokens: TokenBreakdown,
+    pub cost: f64,
+    pub messages: i32,
+}
+
+/// One provider's share of a day
+#[napi(object)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderDayContribution {
     pub provider_id: String,
     pub tokens: TokenBreakdown,
     pub cost: f64,
@@ -122,18 +429,31 @@ pub struct SourceContribution {
 
 /// Daily contribution data
 #[napi(object)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DailyContribution {
     pub date: String,
     pub totals: DailyTotals,
     pub intensity: u8,
     pub token_breakdown: TokenBreakdown,
     pub sources: Vec<SourceContribution>,
+    /// Tokens per hour between the day's first and last message, measured over
+    /// at least one hour
+    pub tokens_per_active_hour: f64,
+    /// `sources` summed per provider, sorted by provider; empty unless the
+    /// graph was asked for it
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub provider_breakdown: Vec<ProviderDayContribution>,
+    /// The day's costliest model, its cost summed over `sources`; ties go to
+    /// the first model by name. None when nothing that day had a cost.
+    pub top_model: Option<String>,
+    pub top_model_cost: f64,
 }
 
 /// Year summary
 #[napi(object)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct YearSummary {
     pub year: String,
     pub total_tokens: i64,
@@ -144,7 +464,8 @@ pub struct YearSummary {
 
 /// Data summary statistics
 #[napi(object)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DataSummary {
     pub total_tokens: i64,
     pub total_cost: f64,
@@ -152,50 +473,197 @@ pub struct DataSummary {
     pub active_days: i32,
     pub average_per_day: f64,
     pub max_cost_in_single_day: f64,
+    /// `cache_read / (cache_read + input)` over every day; 0 with no prompt tokens
+    pub cache_hit_rate: f64,
     pub sources: Vec<String>,
     pub models: Vec<String>,
+    /// `models` grouped by provider, providers and models each sorted
+    pub models_by_provider: Vec<ProviderModels>,
+}
+
+/// The models seen from one provider
+#[napi(object)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderModels {
+    pub provider: String,
+    pub models: Vec<String>,
 }
 
 /// Metadata about the graph generation
 #[napi(object)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GraphMeta {
     pub generated_at: String,
     pub version: String,
     pub date_range_start: String,
     pub date_range_end: String,
     pub processing_time_ms: u32,
+    /// Models with usage that priced at $0, sorted. Their days look lighter on
+    /// the heatmap than they were.
+    pub unpriced_models: Vec<String>,
+    /// Combined size of the files parsed, counted as each is handed to its
+    /// parser; over `processing_time_ms` this is the scan throughput. 0 when
+    /// the graph was built from messages parsed elsewhere.
+    pub bytes_read: i64,
+    /// Number of files parsed, under the same conditions as `bytes_read`
+    pub files_parsed: i32,
+    /// Messages with negative token counts, clamped to 0
+    pub clamped_messages: i32,
+    /// Messages dropped for exceeding `max_tokens_per_message`
+    pub dropped_messages: i32,
 }
 
 /// Complete graph result
 #[napi(object)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GraphResult {
     pub meta: GraphMeta,
     pub summary: DataSummary,
     pub years: Vec<YearSummary>,
     pub contributions: Vec<DailyContribution>,
+    /// Trailing 30-calendar-day cost ending at each entry of `contributions`
+    pub rolling_cost: Vec<f64>,
+    /// What the scan found; set only when the graph parsed local sessions itself
+    pub scan_stats: Option<ScanStatsDetail>,
+    /// Per-file parse times by source; set only with `profile`
+    pub parse_timing_histogram: Option<Vec<SourceParseTiming>>,
+}
+
+/// Files of one source that took `bucket_ms` up to the next bucket to parse
+#[napi(object)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseTimingBucket {
+    /// Lower bound of the bucket: 0, 1, 5, 25, ... ms
+    pub bucket_ms: u32,
+    pub count: u32,
+}
+
+/// How long each file of `source` took to parse
+#[napi(object)]
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceParseTiming {
+    pub source: String,
+    /// Non-empty buckets, fastest first
+    pub buckets: Vec<ParseTimingBucket>,
+}
+
+/// How far a scan's parsing has got
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ScanProgress {
+    pub files_done: i32,
+    pub files_total: i32,
+    /// Source of the file just parsed
+    pub source: String,
+}
+
+/// Files a report's scan found, per source
+#[napi(object)]
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanStatsDetail {
+    pub opencode_files: i32,
+    pub claude_files: i32,
+    pub codex_files: i32,
+    pub gemini_files: i32,
+    pub cursor_files: i32,
+    pub amp_files: i32,
+    pub droid_files: i32,
+    pub openclaw_files: i32,
+    pub warp_files: i32,
+    pub roo_files: i32,
+    pub kilo_files: i32,
+    pub qwen_files: i32,
+    pub copilot_files: i32,
+    pub jules_files: i32,
+    pub total_files: i32,
+    /// Combined size of the scanned files
+    pub total_bytes: i64,
 }
 
 // =============================================================================
 // Shared Utilities
 // =============================================================================
 
+use error::ErrorCode;
 use rayon::prelude::*;
 use sessions::UnifiedMessage;
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 fn get_home_dir(home_dir_option: &Option<String>) -> napi::Result<String> {
-    home_dir_option
-        .clone()
-        .or_else(|| std::env::var("HOME").ok())
-        .or_else(|| dirs::home_dir().map(|p| p.to_string_lossy().into_owned()))
-        .ok_or_else(|| {
-            napi::Error::from_reason(
-                "HOME directory not specified and could not determine home directory",
-            )
+    home_dir_or_detected(home_dir_option).map_err(error::to_napi)
+}
+
+/// `home_dir_option` if given, otherwise `$HOME` or the platform home directory
+fn home_dir_or_detected(home_dir_option: &Option<String>) -> Result<String, String> {
+    let detected = std::env::var("HOME")
+        .ok()
+        .or_else(|| dirs::home_dir().map(|p| p.to_string_lossy().into_owned()));
+    resolve_home_dir(home_dir_option, detected)
+}
+
+/// `home_dir_option` if given, otherwise the `detected` home directory
+fn resolve_home_dir(
+    home_dir_option: &Option<String>,
+    detected: Option<String>,
+) -> Result<String, String> {
+    home_dir_option.clone().or(detected).ok_or_else(|| {
+        ErrorCode::NoHome
+            .reason("HOME directory not specified and could not determine home directory")
+    })
+}
+
+/// Check a report's options and the model provider map (see
+/// `sessions::providers`), returning the report's date source
+fn check_report_options(options: &ReportOptions) -> Result<sessions::DateSource, String> {
+    let date_source = report_date_source(options)?;
+    sessions::providers::check_overrides()?;
+    Ok(date_source)
+}
+
+/// Check a report's year/since/until filters and parse its date source
+fn report_date_source(options: &ReportOptions) -> Result<sessions::DateSource, String> {
+    let invalid = |reason: String| ErrorCode::InvalidDate.reason(reason);
+    let parse_date = |field: &str, value: &str| {
+        chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+            invalid(format!(
+                "Invalid {}: {} (expected YYYY-MM-DD)",
+                field, value
+            ))
         })
+    };
+
+    if let Some(year) = &options.year {
+        if year.len() != 4 || !year.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid(format!("Invalid year: {} (expected YYYY)", year)));
+        }
+    }
+    let since = options
+        .since
+        .as_deref()
+        .map(|s| parse_date("since", s))
+        .transpose()?;
+    let until = options
+        .until
+        .as_deref()
+        .map(|s| parse_date("until", s))
+        .transpose()?;
+    if let (Some(since), Some(until)) = (since, until) {
+        if since > until {
+            return Err(invalid(format!(
+                "since ({}) is after until ({})",
+                since, until
+            )));
+        }
+    }
+
+    sessions::DateSource::parse(options.date_source.as_deref()).map_err(invalid)
 }
 
 // =============================================================================
@@ -204,13 +672,103 @@ fn get_home_dir(home_dir_option: &Option<String>) -> napi::Result<String> {
 
 /// Options for reports
 #[napi(object)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ReportOptions {
     pub home_dir: Option<String>,
     pub sources: Option<Vec<String>>,
     pub since: Option<String>,
     pub until: Option<String>,
     pub year: Option<String>,
+    /// Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+    /// Defaults to LiteLLM first.
+    pub pricing_priority: Option<Vec<String>>,
+    /// When a model is priced by both LiteLLM and OpenRouter, use whichever
+    /// input + output rate is lower
+    pub prefer_cheaper: Option<bool>,
+    /// Bill reasoning tokens at this multiple of the output rate, separately
+    /// from output. Unset folds reasoning into output at the output rate.
+    pub reasoning_rate_multiplier: Option<f64>,
+    /// Bill Claude Batch API messages (`service_tier: "batch"`) at this
+    /// fraction of the standard rates. Default 0.5.
+    pub batch_rate_multiplier: Option<f64>,
+    /// Aggregate each source as soon as it is parsed and drop its messages,
+    /// bounding memory by the largest source rather than all of them.
+    /// Honored by the graph and the model, monthly, quarterly and project
+    /// reports.
+    pub low_memory: Option<bool>,
+    /// Token counts only: never load pricing (so never touch the network) and
+    /// report every cost as 0. Honored by the model report and graph.
+    pub skip_pricing: Option<bool>,
+    /// Where message dates come from: "timestamp" (default) for what each tool
+    /// recorded, or "mtime" for the session file's modification time
+    pub date_source: Option<String>,
+    /// Split each day's source contributions by agent as well as source and
+    /// model. Honored by the graph.
+    pub group_by_agent: Option<bool>,
+    /// Rates for models no pricing dataset matches. Unset prices them at $0.
+    pub unknown_model_rate: Option<NativePricing>,
+    /// Model ids to leave out of the report: exact ids or globs (`*`, `?`),
+    /// case-insensitive
+    pub exclude_models: Option<Vec<String>>,
+    /// Directory names never scanned into, matched case-insensitively against
+    /// any directory below a source root. Defaults to `["archive"]`.
+    pub exclude_dirs: Option<Vec<String>>,
+    /// Whose cost each message gets: "calculated" from pricing data,
+    /// "source" as the tool recorded it, or "prefer_source" for the recorded
+    /// cost when nonzero. Unset calculates, keeping Cursor's recorded cost
+    /// for unknown models. Honored by the model report and graph.
+    pub cost_source: Option<String>,
+    /// Fail with `E_NO_FILES` instead of returning an empty report when no
+    /// session files are found, listing the directories checked
+    pub strict: Option<bool>,
+    /// Heatmap levels: each day's `intensity` is in `0..levels`. Default 5,
+    /// clamped to 2–10. Honored by the graph.
+    pub intensity_levels: Option<u8>,
+    /// Fill each day's `provider_breakdown`. Honored by the graph.
+    pub include_provider_breakdown: Option<bool>,
+    /// Add a zeroed contribution for every day without activity between the
+    /// first and last active day. Honored by the graph.
+    pub fill_gaps: Option<bool>,
+    /// Shortest model id allowed to fuzzy-match a pricing entry. Default 5.
+    pub fuzzy_min_length: Option<u32>,
+    /// Model ids that never fuzzy-match. Default "auto", "mini", "chat" and
+    /// "base"; a list here replaces them.
+    pub fuzzy_blocklist: Option<Vec<String>>,
+    /// Fill each month's `source_breakdown`. Honored by the monthly report.
+    pub include_source_breakdown: Option<bool>,
+    /// What makes a day active: "cost" (default) or "tokens", which also
+    /// counts unpriced days. Sets `active_days`, `average_per_day` and the
+    /// heatmap intensities. Anything else fails with `E_INVALID_METRIC`.
+    /// Honored by the graph.
+    pub active_metric: Option<String>,
+    /// Attach the filtered messages, with cost, as `messages`. Off by default
+    /// since the list can be huge. Honored by the model report.
+    pub include_messages: Option<bool>,
+    /// Round every emitted cost, per entry and in totals, to this many decimal
+    /// places (halves away from zero). Unset leaves costs unrounded.
+    pub round_costs: Option<u32>,
+    /// Replace session ids and project paths in the output with salted hashes
+    /// (the model report's `messages`, the project report's entries). Totals
+    /// are unaffected.
+    pub anonymize: Option<bool>,
+    /// Salt for `anonymize`, so tokens match across runs. Default: random per run.
+    pub anonymize_salt: Option<String>,
+    /// Parse at most this many files of a source at once, handing each batch
+    /// to the aggregation before reading the next, to bound peak memory.
+    /// Honored by today's summary and, with `low_memory`, the graph and the
+    /// model, monthly, quarterly and project reports; without `low_memory`
+    /// every batch is kept until aggregation, so peak memory is not bounded.
+    pub max_inflight_files: Option<u32>,
+    /// Itemize reasoning tokens (o1/o3/o4 thinking) as `reasoning_cost` in the
+    /// cost breakdowns instead of folding them into `output_cost`. Default
+    /// false. Honored by the model reports.
+    pub separate_reasoning_cost: Option<bool>,
+    /// Time each file's parse and report the times per source as
+    /// `parse_timing_histogram`. Honored by the graph and the model reports.
+    pub profile: Option<bool>,
+    /// Drop messages whose total tokens exceed this, as corrupt. Negative
+    /// token counts are always clamped to 0. Unset keeps every message.
+    pub max_tokens_per_message: Option<i64>,
 }
 
 /// Model usage summary for reports
@@ -225,8 +783,29 @@ pub struct ModelUsage {
     pub cache_read: i64,
     pub cache_write: i64,
     pub reasoning: i64,
+    /// Sum of every token category above, reasoning included
+    pub total_tokens: i64,
     pub message_count: i32,
+    /// Number of distinct sessions that used this model
+    pub session_count: i32,
     pub cost: f64,
+    pub cost_breakdown: TokenCostBreakdown,
+    /// Copilot premium requests billed; 0 for other sources
+    pub premium_requests: f64,
+    /// Amp credits used; 0 for other sources and never part of `cost`
+    pub credits: f64,
+    /// Cost per million output tokens; 0 when there were none
+    pub cost_per_mtok_output: f64,
+    /// Cost per million tokens across every category; 0 when there were none
+    pub cost_per_mtok_total: f64,
+    /// `cache_read / (cache_read + input)`; 0 when there were no prompt tokens
+    pub cache_hit_rate: f64,
+    /// "Estimated" when some of `cost` came from the unknown-model rate
+    pub pricing_source: Option<String>,
+    /// Earliest date (YYYY-MM-DD) this model was used
+    pub first_date: String,
+    /// Latest date (YYYY-MM-DD) this model was used
+    pub last_date: String,
 }
 
 /// Monthly usage summary
@@ -239,10 +818,74 @@ pub struct MonthlyUsage {
     pub output: i64,
     pub cache_read: i64,
     pub cache_write: i64,
+    /// Input, output, cache and reasoning tokens combined
+    pub total_tokens: i64,
+    pub message_count: i32,
+    pub cost: f64,
+    /// Each source's share of the month, by source name. Empty unless
+    /// `include_source_breakdown` was set.
+    pub source_breakdown: Vec<SourceUsage>,
+}
+
+/// One source's usage within a month
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct SourceUsage {
+    pub source: String,
+    pub input: i64,
+    pub output: i64,
+    pub cache_read: i64,
+    pub cache_write: i64,
+    /// Input, output, cache and reasoning tokens combined
+    pub total_tokens: i64,
+    pub message_count: i32,
+    pub cost: f64,
+}
+
+/// Quarterly usage summary
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct QuarterlyUsage {
+    /// Quarter key, e.g. "2025-Q1"
+    pub quarter: String,
+    pub models: Vec<String>,
+    pub input: i64,
+    pub output: i64,
+    pub cache_read: i64,
+    pub cache_write: i64,
+    pub message_count: i32,
+    pub cost: f64,
+}
+
+/// Per-project usage summary
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ProjectUsage {
+    /// Encoded project directory, or "(unknown)" for sources without one
+    pub project: String,
+    pub models: Vec<String>,
+    pub input: i64,
+    pub output: i64,
+    pub cache_read: i64,
+    pub cache_write: i64,
+    pub reasoning: i64,
     pub message_count: i32,
     pub cost: f64,
 }
 
+/// Project report result
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ProjectReport {
+    pub entries: Vec<ProjectUsage>,
+    pub total_cost: f64,
+    pub processing_time_ms: u32,
+    /// Messages with negative token counts, clamped to 0
+    pub clamped_messages: i32,
+    /// Messages dropped for exceeding `max_tokens_per_message`
+    pub dropped_messages: i32,
+}
+
 /// Model report result
 #[napi(object)]
 #[derive(Debug, Clone)]
@@ -254,7 +897,24 @@ pub struct ModelReport {
     pub total_cache_write: i64,
     pub total_messages: i32,
     pub total_cost: f64,
+    /// `total_cost` split by token type
+    pub cost_breakdown: TokenCostBreakdown,
+    /// Amp credits across all entries, kept apart from the dollar `total_cost`
+    pub total_credits: f64,
+    /// What prompt caching saved: cache reads at the input rate minus what
+    /// they cost, summed over models that have a cache-read price
+    pub cache_savings: f64,
     pub processing_time_ms: u32,
+    /// What the scan found; set only when the report parsed local sessions itself
+    pub scan_stats: Option<ScanStatsDetail>,
+    /// The messages behind `entries`; set only with `include_messages`
+    pub messages: Option<Vec<ParsedMessage>>,
+    /// Messages with negative token counts, clamped to 0
+    pub clamped_messages: i32,
+    /// Messages dropped for exceeding `max_tokens_per_message`
+    pub dropped_messages: i32,
+    /// Per-file parse times by source; set only with `profile`
+    pub parse_timing_histogram: Option<Vec<SourceParseTiming>>,
 }
 
 /// Monthly report result
@@ -264,217 +924,676 @@ pub struct MonthlyReport {
     pub entries: Vec<MonthlyUsage>,
     pub total_cost: f64,
     pub processing_time_ms: u32,
+    /// Messages with negative token counts, clamped to 0
+    pub clamped_messages: i32,
+    /// Messages dropped for exceeding `max_tokens_per_message`
+    pub dropped_messages: i32,
+}
+
+/// Quarterly report result
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct QuarterlyReport {
+    pub entries: Vec<QuarterlyUsage>,
+    pub total_cost: f64,
+    pub processing_time_ms: u32,
+    /// Messages with negative token counts, clamped to 0
+    pub clamped_messages: i32,
+    /// Messages dropped for exceeding `max_tokens_per_message`
+    pub dropped_messages: i32,
+}
+
+/// How sources are found, dated and handed over while parsing
+struct ScanOptions<'a> {
+    /// Where message dates come from
+    date_source: sessions::DateSource,
+    /// Directory names never scanned into
+    exclude_dirs: &'a [String],
+    /// Files modified outside this range are dropped before parsing (see
+    /// [`report_mtime_window`])
+    mtime_window: Option<(i64, i64)>,
+    /// Ticked for each parsed file
+    progress: progress::Progress,
+    /// Each source's files are parsed this many at a time
+    max_inflight_files: Option<usize>,
+    /// Messages with more tokens than this are dropped as they are parsed
+    max_tokens_per_message: Option<i64>,
+    /// What sanitizing the parsed messages changed so far
+    sanitized: std::cell::Cell<sessions::TokenSanitizeCounts>,
+}
+
+impl<'a> ScanOptions<'a> {
+    /// Every file not under `exclude_dirs`, parsed at once without progress
+    fn new(date_source: sessions::DateSource, exclude_dirs: &'a [String]) -> Self {
+        Self {
+            date_source,
+            exclude_dirs,
+            mtime_window: None,
+            progress: progress::Progress::silent(),
+            max_inflight_files: None,
+            max_tokens_per_message: None,
+            sanitized: Default::default(),
+        }
+    }
+
+    /// The scan a report asks for: its mtime window and file cap
+    fn for_report(
+        options: &ReportOptions,
+        date_source: sessions::DateSource,
+        exclude_dirs: &'a [String],
+    ) -> Self {
+        Self {
+            mtime_window: report_mtime_window(options, date_source),
+            max_inflight_files: report_max_inflight_files(options),
+            max_tokens_per_message: options.max_tokens_per_message,
+            ..Self::new(date_source, exclude_dirs)
+        }
+    }
+
+    /// Messages clamped and dropped by the scans run with these options
+    fn sanitized(&self) -> sessions::TokenSanitizeCounts {
+        self.sanitized.get()
+    }
+}
+
+/// Parse and price the report's sources, handing the messages that pass its
+/// filters to `add`: one batch at a time under `low_memory`, so each batch can
+/// be dropped once aggregated, otherwise all at once
+fn for_each_report_batch(
+    home_dir: &str,
+    sources: &[String],
+    pricing: &pricing::PricingService,
+    options: &ReportOptions,
+    scan: &ScanOptions,
+    mut add: impl FnMut(Vec<UnifiedMessage>),
+) -> scanner::ScanResult {
+    if options.low_memory.unwrap_or(false) {
+        for_each_source_with_pricing(home_dir, sources, pricing, scan, |batch| {
+            add(filter_messages_for_report(batch, options))
+        })
+    } else {
+        let (all_messages, scan_result) =
+            parse_all_messages_with_pricing(home_dir, sources, pricing, scan);
+        add(filter_messages_for_report(all_messages, options));
+        scan_result
+    }
 }
 
+/// Parse and price every source, returning the messages with the scan that
+/// found them
 fn parse_all_messages_with_pricing(
     home_dir: &str,
     sources: &[String],
     pricing: &pricing::PricingService,
-) -> Vec<UnifiedMessage> {
-    let scan_result = scanner::scan_all_sources(home_dir, sources);
+    scan: &ScanOptions,
+) -> (Vec<UnifiedMessage>, scanner::ScanResult) {
     let mut all_messages: Vec<UnifiedMessage> = Vec::new();
+    let scan_result = for_each_source_with_pricing(home_dir, sources, pricing, scan, |messages| {
+        all_messages.extend(messages)
+    });
+    (all_messages, scan_result)
+}
+
+/// Parse and price each source in turn, handing its messages to `sink` before
+/// the next source is parsed. Batches arrive in the same order
+/// `parse_all_messages_with_pricing` concatenates them. Cursor's usage export
+/// and OpenClaw's session index span many sessions, so the scan's date source
+/// leaves their recorded timestamps alone.
+///
+/// With `max_inflight_files`, each source's files are parsed that many at a
+/// time and each batch reaches `sink` before the next is read. Claude, Roo and
+/// Kilo dedup across batches by key; Claude's headless messages and Cursor's
+/// exports (merged as a whole) are held back and handed over at the end.
+fn for_each_source_with_pricing(
+    home_dir: &str,
+    sources: &[String],
+    pricing: &pricing::PricingService,
+    scan: &ScanOptions,
+    sink: impl FnMut(Vec<UnifiedMessage>),
+) -> scanner::ScanResult {
+    let mut scan_result = scanner::scan_all_sources_excluding(home_dir, sources, scan.exclude_dirs);
+    if let Some(window) = scan.mtime_window {
+        retain_modified_within(&mut scan_result, window);
+    }
+    for_each_scanned_source_with_pricing(scan_result, pricing, scan, sink)
+}
+
+/// `files` in batches of at most `max_inflight_files`, or all at once
+fn file_batches(
+    files: &[PathBuf],
+    max_inflight_files: Option<usize>,
+) -> std::slice::Chunks<'_, PathBuf> {
+    files.chunks(max_inflight_files.unwrap_or(files.len()).max(1))
+}
+
+/// [`for_each_source_with_pricing`] over files the caller has already scanned
+/// (and filtered), so the scan's `exclude_dirs` and `mtime_window` go unused
+fn for_each_scanned_source_with_pricing(
+    scan_result: scanner::ScanResult,
+    pricing: &pricing::PricingService,
+    scan: &ScanOptions,
+    mut sink: impl FnMut(Vec<UnifiedMessage>),
+) -> scanner::ScanResult {
+    let ScanOptions {
+        date_source,
+        ref progress,
+        max_inflight_files,
+        ..
+    } = *scan;
+    progress.start(scan_result.total_files());
+
+    // Every batch has its token counts repaired before anyone sees it
+    let mut sink = |mut messages: Vec<UnifiedMessage>| {
+        let mut sanitized = scan.sanitized.get();
+        sanitized += sessions::sanitize_tokens(&mut messages, scan.max_tokens_per_message);
+        scan.sanitized.set(sanitized);
+        sink(messages);
+    };
 
     // Parse OpenCode files in parallel
-    let opencode_messages: Vec<UnifiedMessage> = scan_result
-        .opencode_files
-        .par_iter()
-        .filter_map(|path| {
-            let mut msg = sessions::opencode::parse_opencode_file(path)?;
-            // Recalculate cost using pricing data
-            msg.cost = pricing.calculate_cost(
-                &msg.model_id,
-                msg.tokens.input,
-                msg.tokens.output,
-                msg.tokens.cache_read,
-                msg.tokens.cache_write,
-                msg.tokens.reasoning,
-            );
-            Some(msg)
-        })
-        .collect();
-    all_messages.extend(opencode_messages);
+    for batch in file_batches(&scan_result.opencode_files, max_inflight_files) {
+        let opencode_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .filter_map(|path| {
+                let msg = progress.parse("opencode", path, || {
+                    sessions::opencode::parse_opencode_file(path)
+                });
+                let mut msg = msg?;
+                date_source.apply(path, std::slice::from_mut(&mut msg));
+                // Recalculate cost using pricing data
+                pricing.apply(&mut msg);
+                Some(msg)
+            })
+            .collect();
+        sink(opencode_messages);
+    }
 
-    // Parse Claude files in parallel
-    let claude_messages: Vec<UnifiedMessage> = scan_result
-        .claude_files
-        .par_iter()
-        .flat_map(|path| {
-            sessions::claudecode::parse_claude_file(path)
-                .into_iter()
-                .map(|mut msg| {
-                    msg.cost = pricing.calculate_cost(
-                        &msg.model_id,
-                        msg.tokens.input,
-                        msg.tokens.output,
-                        msg.tokens.cache_read,
-                        msg.tokens.cache_write,
-                        msg.tokens.reasoning,
-                    );
-                    msg
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
-    all_messages.extend(claude_messages);
+    // Parse Claude files in parallel, deduplicating globally so headless
+    // copies of interactive sessions are counted once
+    let price_claude = |messages: Vec<UnifiedMessage>| -> Vec<UnifiedMessage> {
+        messages
+            .into_iter()
+            .map(|mut msg| {
+                pricing.apply(&mut msg);
+                msg
+            })
+            .collect()
+    };
+    let mut claude_dedup = sessions::claudecode::ClaudeDedup::default();
+    for batch in file_batches(&scan_result.claude_files, max_inflight_files) {
+        // Deduplicated on their recorded timestamps and restamped after, so
+        // a headless copy still matches its interactive exchange under mtime
+        let claude_files: Vec<(Vec<UnifiedMessage>, Option<i64>)> = batch
+            .par_iter()
+            .map(|path| {
+                let messages = progress.parse("claude", path, || {
+                    sessions::claudecode::parse_claude_file(path)
+                });
+                let restamp = if messages.is_empty() {
+                    None
+                } else {
+                    date_source.restamp(path)
+                };
+                (messages, restamp)
+            })
+            .collect();
+        let claude_messages = claude_files
+            .into_iter()
+            .flat_map(|(messages, restamp)| claude_dedup.add_restamped(messages, restamp))
+            .collect();
+        sink(price_claude(claude_messages));
+    }
+    sink(price_claude(claude_dedup.finish()));
 
     // Parse Codex files in parallel
-    let codex_messages: Vec<UnifiedMessage> = scan_result
-        .codex_files
-        .par_iter()
-        .flat_map(|path| {
-            sessions::codex::parse_codex_file(path)
-                .into_iter()
-                .map(|mut msg| {
-                    msg.cost = pricing.calculate_cost(
-                        &msg.model_id,
-                        msg.tokens.input,
-                        msg.tokens.output,
-                        msg.tokens.cache_read,
-                        msg.tokens.cache_write,
-                        msg.tokens.reasoning,
-                    );
-                    msg
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
-    all_messages.extend(codex_messages);
+    for batch in file_batches(&scan_result.codex_files, max_inflight_files) {
+        let codex_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages =
+                    progress.parse("codex", path, || sessions::codex::parse_codex_file(path));
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(codex_messages);
+    }
 
     // Parse Gemini files in parallel
-    let gemini_messages: Vec<UnifiedMessage> = scan_result
-        .gemini_files
-        .par_iter()
-        .flat_map(|path| {
-            sessions::gemini::parse_gemini_file(path)
-                .into_iter()
-                .map(|mut msg| {
-                    // Gemini: thoughts count as output for billing
-                    msg.cost = pricing.calculate_cost(
-                        &msg.model_id,
-                        msg.tokens.input,
-                        msg.tokens.output + msg.tokens.reasoning,
-                        0, // Gemini cached tokens are free
-                        0,
-                        0,
-                    );
-                    msg
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
-    all_messages.extend(gemini_messages);
+    for batch in file_batches(&scan_result.gemini_files, max_inflight_files) {
+        let gemini_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages =
+                    progress.parse("gemini", path, || sessions::gemini::parse_gemini_file(path));
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(gemini_messages);
+    }
 
     // Parse Cursor files in parallel
     // Calculate cost using our pricing data for consistency with other providers
     // Fall back to CSV cost only if no pricing is found
-    let cursor_messages: Vec<UnifiedMessage> = scan_result
-        .cursor_files
-        .par_iter()
-        .flat_map(|path| {
-            sessions::cursor::parse_cursor_file(path)
-                .into_iter()
-                .map(|mut msg| {
-                    let csv_cost = msg.cost; // Store original CSV cost
-                    let calculated_cost = pricing.calculate_cost(
-                        &msg.model_id,
-                        msg.tokens.input,
-                        msg.tokens.output,
-                        msg.tokens.cache_read,
-                        msg.tokens.cache_write,
-                        msg.tokens.reasoning,
-                    );
-                    // Use calculated cost if available, otherwise keep CSV cost
-                    msg.cost = if calculated_cost > 0.0 {
-                        calculated_cost
-                    } else {
-                        csv_cost
-                    };
-                    msg
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
-    all_messages.extend(cursor_messages);
+    // Exports can overlap, so merge them before pricing
+    let mut cursor_exports: Vec<Vec<UnifiedMessage>> = Vec::new();
+    for batch in file_batches(&scan_result.cursor_files, max_inflight_files) {
+        cursor_exports.par_extend(batch.par_iter().map(|path| {
+            progress.parse("cursor", path, || sessions::cursor::parse_cursor_file(path))
+        }));
+    }
+    let cursor_messages: Vec<UnifiedMessage> =
+        sessions::cursor::merge_cursor_exports(cursor_exports)
+            .into_iter()
+            .map(|mut msg| {
+                pricing.apply_or_keep_recorded(&mut msg);
+                msg
+            })
+            .collect();
+    sink(cursor_messages);
 
     // Parse Amp files in parallel
-    // Calculate cost using our pricing data for consistency with other providers
-    // Fall back to credits (original cost) only if no pricing is found
-    let amp_messages: Vec<UnifiedMessage> = scan_result
-        .amp_files
-        .par_iter()
-        .flat_map(|path| {
-            sessions::amp::parse_amp_file(path)
-                .into_iter()
-                .map(|mut msg| {
-                    let credits = msg.cost; // Store original credits value
-                    let calculated_cost = pricing.calculate_cost(
-                        &msg.model_id,
-                        msg.tokens.input,
-                        msg.tokens.output,
-                        msg.tokens.cache_read,
-                        msg.tokens.cache_write,
-                        msg.tokens.reasoning,
-                    );
-                    // Use calculated cost if available, otherwise keep credits
-                    msg.cost = if calculated_cost > 0.0 {
-                        calculated_cost
-                    } else {
-                        credits
-                    };
-                    msg
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
-    all_messages.extend(amp_messages);
+    // Amp records credits, not dollars, so cost always comes from our pricing
+    for batch in file_batches(&scan_result.amp_files, max_inflight_files) {
+        let amp_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages =
+                    progress.parse("amp", path, || sessions::amp::parse_amp_file(path));
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(amp_messages);
+    }
 
     // Parse Droid files in parallel
-    let droid_messages: Vec<UnifiedMessage> = scan_result
-        .droid_files
-        .par_iter()
-        .flat_map(|path| {
-            sessions::droid::parse_droid_file(path)
-                .into_iter()
-                .map(|mut msg| {
-                    msg.cost = pricing.calculate_cost(
-                        &msg.model_id,
-                        msg.tokens.input,
-                        msg.tokens.output,
-                        msg.tokens.cache_read,
-                        msg.tokens.cache_write,
-                        msg.tokens.reasoning,
-                    );
-                    msg
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
-    all_messages.extend(droid_messages);
+    for batch in file_batches(&scan_result.droid_files, max_inflight_files) {
+        let droid_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages =
+                    progress.parse("droid", path, || sessions::droid::parse_droid_file(path));
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(droid_messages);
+    }
 
     // Parse OpenClaw index files
-    let openclaw_messages: Vec<UnifiedMessage> = scan_result
-        .openclaw_files
-        .par_iter()
-        .flat_map(|path| {
-            sessions::openclaw::parse_openclaw_index(path)
-                .into_iter()
-                .map(|mut msg| {
-                    msg.cost = pricing.calculate_cost(
-                        &msg.model_id,
-                        msg.tokens.input,
-                        msg.tokens.output,
-                        msg.tokens.cache_read,
-                        msg.tokens.cache_write,
-                        msg.tokens.reasoning,
-                    );
-                    msg
-                })
-                .collect::<Vec<_>>()
+    for batch in file_batches(&scan_result.openclaw_files, max_inflight_files) {
+        let openclaw_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages = progress.parse("openclaw", path, || {
+                    sessions::openclaw::parse_openclaw_index(path)
+                });
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(openclaw_messages);
+    }
+
+    // Parse Warp conversation files in parallel
+    for batch in file_batches(&scan_result.warp_files, max_inflight_files) {
+        let warp_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages =
+                    progress.parse("warp", path, || sessions::warp::parse_warp_file(path));
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(warp_messages);
+    }
+
+    // Parse Roo Code task histories in parallel, deduplicating resumed tasks
+    let mut roo_seen = std::collections::HashSet::new();
+    for batch in file_batches(&scan_result.roo_files, max_inflight_files) {
+        let roo_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages =
+                    progress.parse("roo", path, || sessions::roo::parse_roo_file(path));
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(sessions::roo::dedup_roo_messages(
+            &mut roo_seen,
+            roo_messages,
+        ));
+    }
+
+    // Parse Kilo Code task histories in parallel, deduplicating resumed tasks
+    let mut kilo_seen = std::collections::HashSet::new();
+    for batch in file_batches(&scan_result.kilo_files, max_inflight_files) {
+        let kilo_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages =
+                    progress.parse("kilo", path, || sessions::kilo::parse_kilo_file(path));
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(sessions::kilo::dedup_kilo_messages(
+            &mut kilo_seen,
+            kilo_messages,
+        ));
+    }
+
+    // Parse Qwen Code files in parallel
+    for batch in file_batches(&scan_result.qwen_files, max_inflight_files) {
+        let qwen_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages =
+                    progress.parse("qwen", path, || sessions::qwen::parse_qwen_file(path));
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(qwen_messages);
+    }
+
+    // Parse Copilot Chat sessions in parallel
+    for batch in file_batches(&scan_result.copilot_files, max_inflight_files) {
+        let copilot_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages = progress.parse("copilot", path, || {
+                    sessions::copilot::parse_copilot_file(path)
+                });
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(copilot_messages);
+    }
+
+    // Parse Jules task usage in parallel
+    for batch in file_batches(&scan_result.jules_files, max_inflight_files) {
+        let jules_messages: Vec<UnifiedMessage> = batch
+            .par_iter()
+            .flat_map(|path| {
+                let mut messages =
+                    progress.parse("jules", path, || sessions::jules::parse_jules_file(path));
+                date_source.apply(path, &mut messages);
+                messages
+                    .into_iter()
+                    .map(|mut msg| {
+                        pricing.apply(&mut msg);
+                        msg
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        sink(jules_messages);
+    }
+
+    scan_result
+}
+
+/// Pricing for a report: the shared service with the report's adjustments, or
+/// the zero-cost stand-in when `skip_pricing` is set
+async fn report_pricing(
+    options: &ReportOptions,
+) -> Result<std::sync::Arc<pricing::PricingService>, String> {
+    if options.skip_pricing.unwrap_or(false) {
+        return Ok(pricing::PricingService::unpriced());
+    }
+    let pricing =
+        pricing::PricingService::get_or_init_with_priority(options.pricing_priority.as_deref())
+            .await
+            .map_err(|e| ErrorCode::PricingFetch.reason(e))?;
+    let pricing = pricing::PricingService::with_fuzzy_config(pricing, report_fuzzy_config(options));
+    let pricing = pricing::PricingService::preferring_cheaper(
+        pricing,
+        options.prefer_cheaper.unwrap_or(false),
+    );
+    let pricing = pricing::PricingService::with_reasoning_multiplier(
+        pricing,
+        options.reasoning_rate_multiplier,
+    )?;
+    let pricing =
+        pricing::PricingService::with_batch_multiplier(pricing, options.batch_rate_multiplier)?;
+    let unknown_model_rate = options
+        .unknown_model_rate
+        .as_ref()
+        .map(NativePricing::to_model_pricing)
+        .transpose()?;
+    let pricing = pricing::PricingService::with_unknown_model_rate(pricing, unknown_model_rate)?;
+    let cost_source = pricing::CostSource::parse(options.cost_source.as_deref())?;
+    Ok(pricing::PricingService::with_cost_source(
+        pricing,
+        cost_source,
+    ))
+}
+
+/// Fuzzy matching rules from the report's options; `None` when neither is set
+fn report_fuzzy_config(options: &ReportOptions) -> Option<pricing::FuzzyMatchConfig> {
+    if options.fuzzy_min_length.is_none() && options.fuzzy_blocklist.is_none() {
+        return None;
+    }
+    let default = pricing::FuzzyMatchConfig::default();
+    Some(pricing::FuzzyMatchConfig {
+        min_len: options
+            .fuzzy_min_length
+            .map_or(default.min_len, |n| n as usize),
+        blocklist: options.fuzzy_blocklist.clone().unwrap_or(default.blocklist),
+    })
+}
+
+/// With mtime dates, the file modification times (Unix ms, end exclusive) that
+/// can land inside the report's year/since/until window. None when dates come
+/// from message timestamps, nothing is bounded, or a bound doesn't parse.
+fn report_mtime_window(
+    options: &ReportOptions,
+    date_source: sessions::DateSource,
+) -> Option<(i64, i64)> {
+    use chrono::NaiveDate;
+
+    if date_source != sessions::DateSource::Mtime {
+        return None;
+    }
+    let parse_date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+
+    let (mut first, mut last) = (None::<NaiveDate>, None::<NaiveDate>);
+    if let Some(year) = &options.year {
+        let year: i32 = year.parse().ok()?;
+        first = NaiveDate::from_ymd_opt(year, 1, 1);
+        last = NaiveDate::from_ymd_opt(year, 12, 31);
+    }
+    if let Some(since) = &options.since {
+        let since = parse_date(since)?;
+        first = Some(first.map_or(since, |d| d.max(since)));
+    }
+    if let Some(until) = &options.until {
+        let until = parse_date(until)?;
+        last = Some(last.map_or(until, |d| d.min(until)));
+    }
+    if first.is_none() && last.is_none() {
+        return None;
+    }
+
+    let local_midnight = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .and_then(|dt| dt.and_local_timezone(chrono::Local).earliest())
+            .map(|dt| dt.timestamp_millis())
+    };
+    let from = match first {
+        Some(date) => local_midnight(date)?,
+        None => i64::MIN,
+    };
+    let to = match last {
+        Some(date) => local_midnight(date.succ_opt()?)?,
+        None => i64::MAX,
+    };
+    Some((from, to))
+}
+
+/// Drop files last modified outside `from..to`. Cursor and OpenClaw keep their
+/// own timestamps under mtime dates, so their files always stay.
+fn retain_modified_within(scan_result: &mut scanner::ScanResult, (from, to): (i64, i64)) {
+    scan_result.retain(|session_type, path| {
+        matches!(session_type, SessionType::Cursor | SessionType::OpenClaw)
+            || (from..to).contains(&sessions::utils::file_modified_timestamp_ms(path))
+    });
+}
+
+/// The report's cap on files parsed at once; 0 counts as no cap
+fn report_max_inflight_files(options: &ReportOptions) -> Option<usize> {
+    options
+        .max_inflight_files
+        .filter(|&n| n > 0)
+        .map(|n| n as usize)
+}
+
+/// The anonymizer for a report, when `anonymize` is set
+fn report_anonymizer(options: &ReportOptions) -> Result<Option<anonymize::Anonymizer>, String> {
+    if !options.anonymize.unwrap_or(false) {
+        return Ok(None);
+    }
+    anonymize::Anonymizer::new(options.anonymize_salt.clone()).map(Some)
+}
+
+/// Anonymize the messages attached to `report`, per `anonymize`
+fn anonymize_model_report(report: &mut ModelReport, options: &ReportOptions) -> Result<(), String> {
+    if let (Some(anonymizer), Some(messages)) =
+        (report_anonymizer(options)?, report.messages.as_mut())
+    {
+        anonymizer.messages(messages);
+    }
+    Ok(())
+}
+
+/// Fold reasoning cost into output cost unless `separate_reasoning_cost` is set
+fn report_reasoning_cost(report: &mut ModelReport, separate_reasoning_cost: Option<bool>) {
+    if separate_reasoning_cost.unwrap_or(false) {
+        return;
+    }
+    for entry in &mut report.entries {
+        entry.cost_breakdown.fold_reasoning_into_output();
+    }
+    report.cost_breakdown.fold_reasoning_into_output();
+}
+
+/// `progress`, timing each file's parse when the report asks for `profile`
+fn report_progress(progress: progress::Progress, options: &ReportOptions) -> progress::Progress {
+    if options.profile.unwrap_or(false) {
+        progress.profiled()
+    } else {
+        progress
+    }
+}
+
+/// `report` with its costs rounded per `round_costs`
+fn round_report<T: rounding::RoundCosts>(mut report: T, options: &ReportOptions) -> T {
+    if let Some(places) = options.round_costs {
+        report.round_costs(places);
+    }
+    report
+}
+
+/// Attach the stats of the files found for `result`, and what `progress`
+/// counted as they were parsed
+fn record_scan(
+    result: &mut GraphResult,
+    scan_result: &scanner::ScanResult,
+    progress: &progress::Progress,
+) {
+    (result.meta.files_parsed, result.meta.bytes_read) = progress.parsed();
+    result.scan_stats = Some(scan_result.stats());
+}
+
+/// In strict mode, fail when the scan found no session files at all, listing
+/// every directory that was checked
+fn ensure_files_found(
+    options: &ReportOptions,
+    home_dir: &str,
+    sources: &[String],
+    scan_result: &scanner::ScanResult,
+) -> Result<(), String> {
+    if !options.strict.unwrap_or(false) || scan_result.total_files() > 0 {
+        return Ok(());
+    }
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let checked: Vec<String> = scanner::describe_scan(home_dir, sources, &exclude_dirs)
+        .into_iter()
+        .map(|target| {
+            let missing = if target.exists { "" } else { ", missing" };
+            format!("  {} ({}{})", target.path, target.pattern, missing)
         })
         .collect();
-    all_messages.extend(openclaw_messages);
-
-    all_messages
+    Err(ErrorCode::NoFiles.reason(format!(
+        "No session files found (strict mode). Checked:\n{}",
+        checked.join("\n")
+    )))
 }
 
 /// Get model usage report with pricing calculation
@@ -484,222 +1603,753 @@ pub async fn get_model_report(options: ReportOptions) -> napi::Result<ModelRepor
 
     let home_dir = get_home_dir(&options.home_dir)?;
 
-    let sources = options.sources.clone().unwrap_or_else(|| {
-        vec![
-            "opencode".to_string(),
-            "claude".to_string(),
-            "codex".to_string(),
-            "gemini".to_string(),
-            "cursor".to_string(),
-            "amp".to_string(),
-            "droid".to_string(),
-            "openclaw".to_string(),
-        ]
-    });
+    let sources = scanner::sources_or_default(options.sources.as_deref());
 
-    let pricing = pricing::PricingService::get_or_init()
+    let date_source = check_report_options(&options).map_err(error::to_napi)?;
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let pricing = report_pricing(&options).await.map_err(error::to_napi)?;
+
+    // Aggregate by model, after date filters
+    let include_messages = options.include_messages.unwrap_or(false);
+    let mut messages = include_messages.then(Vec::new);
+    let scan = ScanOptions {
+        progress: report_progress(progress::Progress::silent(), &options),
+        ..ScanOptions::for_report(&options, date_source, &exclude_dirs)
+    };
+    let mut acc = aggregator::ModelAccumulator::default();
+    let scan_result =
+        for_each_report_batch(&home_dir, &sources, &pricing, &options, &scan, |filtered| {
+            acc.add(&filtered);
+            if let Some(messages) = messages.as_mut() {
+                messages.extend(filtered.iter().map(priced_message));
+            }
+        });
+    let entries = acc.finish();
+    ensure_files_found(&options, &home_dir, &sources, &scan_result).map_err(error::to_napi)?;
+
+    let sanitized = scan.sanitized();
+    let mut report = ModelReport {
+        scan_stats: Some(scan_result.stats()),
+        messages,
+        clamped_messages: sanitized.clamped as i32,
+        dropped_messages: sanitized.dropped as i32,
+        parse_timing_histogram: scan.progress.parse_timing(),
+        ..model_report(
+            entries,
+            &pricing,
+            start.elapsed().as_millis() as u32,
+            options.separate_reasoning_cost,
+        )
+    };
+    anonymize_model_report(&mut report, &options).map_err(error::to_napi)?;
+    Ok(round_report(report, &options))
+}
+
+/// Model report over only the messages newer than the last call's, then
+/// advance the stored watermark to the newest message seen. Each combination
+/// of home dir, sources, date source and filters keeps its own watermark. The
+/// first call for one (or the first after `reset_watermark`) covers everything.
+#[napi]
+pub async fn get_model_report_since_last(options: ReportOptions) -> napi::Result<ModelReport> {
+    model_report_since_last(options, &watermark::watermark_dir())
         .await
-        .map_err(|e| napi::Error::from_reason(e))?;
-    let all_messages = parse_all_messages_with_pricing(&home_dir, &sources, &pricing);
+        .map_err(error::to_napi)
+}
 
-    // Apply date filters
+async fn model_report_since_last(
+    options: ReportOptions,
+    watermark_dir: &Path,
+) -> Result<ModelReport, String> {
+    let start = Instant::now();
+
+    let home_dir = home_dir_or_detected(&options.home_dir)?;
+    let sources = scanner::sources_or_default(options.sources.as_deref());
+    let date_source = check_report_options(&options)?;
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let pricing = report_pricing(&options).await?;
+
+    let scan = ScanOptions {
+        progress: report_progress(progress::Progress::silent(), &options),
+        ..ScanOptions::for_report(&options, date_source, &exclude_dirs)
+    };
+    let (all_messages, scan_result) =
+        parse_all_messages_with_pricing(&home_dir, &sources, &pricing, &scan);
+    ensure_files_found(&options, &home_dir, &sources, &scan_result)?;
     let filtered = filter_messages_for_report(all_messages, &options);
+    let watermark_path = watermark::watermark_path_in(
+        watermark_dir,
+        &watermark_scope(&home_dir, &sources, &options),
+    );
+    let fresh = watermark::take_since_at(&watermark_path, filtered)?;
+
+    let mut report = ModelReport {
+        scan_stats: Some(scan_result.stats()),
+        messages: options
+            .include_messages
+            .unwrap_or(false)
+            .then(|| fresh.iter().map(priced_message).collect()),
+        clamped_messages: scan.sanitized().clamped as i32,
+        dropped_messages: scan.sanitized().dropped as i32,
+        parse_timing_histogram: scan.progress.parse_timing(),
+        ..model_report(
+            aggregator::aggregate_by_model(&fresh),
+            &pricing,
+            start.elapsed().as_millis() as u32,
+            options.separate_reasoning_cost,
+        )
+    };
+    anonymize_model_report(&mut report, &options)?;
+    Ok(round_report(report, &options))
+}
 
-    // Aggregate by model
-    let mut model_map: std::collections::HashMap<String, ModelUsage> =
-        std::collections::HashMap::new();
-
-    for msg in filtered {
-        let key = format!("{}:{}:{}", msg.source, msg.provider_id, msg.model_id);
-        let entry = model_map.entry(key).or_insert_with(|| ModelUsage {
-            source: msg.source.clone(),
-            model: msg.model_id.clone(),
-            provider: msg.provider_id.clone(),
-            input: 0,
-            output: 0,
-            cache_read: 0,
-            cache_write: 0,
-            reasoning: 0,
-            message_count: 0,
-            cost: 0.0,
-        });
+/// What decides which messages a delta report can see: the home dir, the
+/// resolved `sources`, the date source and the filters. Each scope keeps its
+/// own watermark.
+fn watermark_scope(home_dir: &str, sources: &[String], options: &ReportOptions) -> Vec<String> {
+    let sorted = |list: &[String]| {
+        let mut list = list.to_vec();
+        list.sort();
+        list.dedup();
+        list.join(",")
+    };
+    let exclude_models: Vec<String> = options
+        .exclude_models
+        .iter()
+        .flatten()
+        .map(|m| m.to_lowercase())
+        .collect();
+    vec![
+        home_dir.to_string(),
+        sorted(sources),
+        options.date_source.clone().unwrap_or_default(),
+        options.year.clone().unwrap_or_default(),
+        options.since.clone().unwrap_or_default(),
+        options.until.clone().unwrap_or_default(),
+        sorted(&exclude_models),
+    ]
+}
 
-        entry.input += msg.tokens.input;
-        entry.output += msg.tokens.output;
-        entry.cache_read += msg.tokens.cache_read;
-        entry.cache_write += msg.tokens.cache_write;
-        entry.reasoning += msg.tokens.reasoning;
-        entry.message_count += 1;
-        entry.cost += msg.cost;
-    }
-
-    let mut entries: Vec<ModelUsage> = model_map.into_values().collect();
-    // Sort by cost descending (NaN values sorted to the end)
-    entries.sort_by(|a, b| {
-        // Handle NaN: treat as smallest value so they sort to the end in descending order
-        match (a.cost.is_nan(), b.cost.is_nan()) {
-            (true, true) => std::cmp::Ordering::Equal,
-            (true, false) => std::cmp::Ordering::Greater, // NaN sorts after valid values
-            (false, true) => std::cmp::Ordering::Less,
-            (false, false) => b
-                .cost
-                .partial_cmp(&a.cost)
-                .unwrap_or(std::cmp::Ordering::Equal),
+/// Forget every "since last run" watermark
+#[napi]
+pub fn reset_watermark() -> napi::Result<()> {
+    watermark::reset_all_at(&watermark::watermark_dir()).map_err(error::to_napi)
+}
+
+/// Options for the raw message query. All set predicates must match.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    pub home_dir: Option<String>,
+    pub sources: Option<Vec<String>>,
+    /// Model id globs (`*`, `?`), case-insensitive
+    pub models: Option<Vec<String>>,
+    pub providers: Option<Vec<String>>,
+    pub agents: Option<Vec<String>>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Minimum total tokens per message
+    pub min_tokens: Option<i64>,
+    pub session_ids: Option<Vec<String>>,
+    /// As in `ReportOptions`, for the pricing applied to the messages
+    pub pricing_priority: Option<Vec<String>>,
+    pub skip_pricing: Option<bool>,
+    pub prefer_cheaper: Option<bool>,
+    pub reasoning_rate_multiplier: Option<f64>,
+    pub batch_rate_multiplier: Option<f64>,
+    pub unknown_model_rate: Option<NativePricing>,
+    pub cost_source: Option<String>,
+    pub fuzzy_min_length: Option<u32>,
+    pub fuzzy_blocklist: Option<Vec<String>>,
+}
+
+impl QueryOptions {
+    /// The same settings as report options, so the dates are checked and the
+    /// messages priced exactly like a report's
+    fn report_options(&self) -> ReportOptions {
+        ReportOptions {
+            since: self.since.clone(),
+            until: self.until.clone(),
+            pricing_priority: self.pricing_priority.clone(),
+            skip_pricing: self.skip_pricing,
+            prefer_cheaper: self.prefer_cheaper,
+            reasoning_rate_multiplier: self.reasoning_rate_multiplier,
+            batch_rate_multiplier: self.batch_rate_multiplier,
+            unknown_model_rate: self.unknown_model_rate.clone(),
+            cost_source: self.cost_source.clone(),
+            fuzzy_min_length: self.fuzzy_min_length,
+            fuzzy_blocklist: self.fuzzy_blocklist.clone(),
+            ..Default::default()
         }
-    });
+    }
+}
+
+/// Query raw messages with arbitrary predicates, returning each with its computed cost
+#[napi]
+pub async fn query_messages(options: QueryOptions) -> napi::Result<Vec<ParsedMessage>> {
+    queried_messages(options).await.map_err(error::to_napi)
+}
+
+async fn queried_messages(options: QueryOptions) -> Result<Vec<ParsedMessage>, String> {
+    let home_dir = home_dir_or_detected(&options.home_dir)?;
+
+    let sources = scanner::sources_or_default(options.sources.as_deref());
+
+    let report_options = options.report_options();
+    report_date_source(&report_options)?;
+    let pricing = report_pricing(&report_options).await?;
+    let exclude_dirs = scanner::exclude_dirs_or_default(None);
+    let (all_messages, _) = parse_all_messages_with_pricing(
+        &home_dir,
+        &sources,
+        &pricing,
+        &ScanOptions::new(sessions::DateSource::Timestamp, &exclude_dirs),
+    );
+
+    let query = query::MessageQuery {
+        sources: options.sources,
+        models: options.models,
+        providers: options.providers,
+        agents: options.agents,
+        since: options.since,
+        until: options.until,
+        min_tokens: options.min_tokens,
+        session_ids: options.session_ids,
+    };
+
+    let mut matched: Vec<&UnifiedMessage> = all_messages
+        .iter()
+        .filter(|msg| query.matches(msg))
+        .collect();
+    matched.sort_by_key(|msg| msg.timestamp);
+
+    Ok(matched.into_iter().map(priced_message).collect())
+}
+
+/// An inclusive date window (YYYY-MM-DD); missing bounds are open
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct DateWindow {
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+/// Options for comparing two date ranges
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct CompareOptions {
+    pub home_dir: Option<String>,
+    pub sources: Option<Vec<String>>,
+    pub first: DateWindow,
+    pub second: DateWindow,
+    /// As in `ReportOptions`, for the pricing applied to the messages
+    pub pricing_priority: Option<Vec<String>>,
+    pub skip_pricing: Option<bool>,
+    pub prefer_cheaper: Option<bool>,
+    pub reasoning_rate_multiplier: Option<f64>,
+    pub batch_rate_multiplier: Option<f64>,
+    pub unknown_model_rate: Option<NativePricing>,
+    pub cost_source: Option<String>,
+    pub fuzzy_min_length: Option<u32>,
+    pub fuzzy_blocklist: Option<Vec<String>>,
+}
+
+impl CompareOptions {
+    /// Report options for one window, so its dates are checked and its
+    /// messages priced exactly like a report's
+    fn report_options(&self, window: &DateWindow) -> ReportOptions {
+        ReportOptions {
+            since: window.since.clone(),
+            until: window.until.clone(),
+            pricing_priority: self.pricing_priority.clone(),
+            skip_pricing: self.skip_pricing,
+            prefer_cheaper: self.prefer_cheaper,
+            reasoning_rate_multiplier: self.reasoning_rate_multiplier,
+            batch_rate_multiplier: self.batch_rate_multiplier,
+            unknown_model_rate: self.unknown_model_rate.clone(),
+            cost_source: self.cost_source.clone(),
+            fuzzy_min_length: self.fuzzy_min_length,
+            fuzzy_blocklist: self.fuzzy_blocklist.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Per-model change between two date ranges
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ModelRangeDelta {
+    pub source: String,
+    pub model: String,
+    pub provider: String,
+    pub first_cost: f64,
+    pub second_cost: f64,
+    pub first_tokens: i64,
+    pub second_tokens: i64,
+    /// `second_cost - first_cost`
+    pub cost_delta: f64,
+    /// `second_tokens - first_tokens`
+    pub tokens_delta: i64,
+    /// Cost change as a percentage of the first window; unset when the first cost is zero
+    pub pct_change: Option<f64>,
+}
+
+/// Totals for one window of a range comparison
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct RangeTotals {
+    pub cost: f64,
+    pub tokens: i64,
+    pub messages: i32,
+}
+
+/// Result of comparing two date ranges
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct RangeComparison {
+    pub entries: Vec<ModelRangeDelta>,
+    pub first_totals: RangeTotals,
+    pub second_totals: RangeTotals,
+    pub processing_time_ms: u32,
+}
+
+/// Compare per-model usage between two date ranges
+#[napi]
+pub async fn compare_ranges(options: CompareOptions) -> napi::Result<RangeComparison> {
+    range_comparison(options).await.map_err(error::to_napi)
+}
+
+async fn range_comparison(options: CompareOptions) -> Result<RangeComparison, String> {
+    let start = Instant::now();
+
+    let home_dir = home_dir_or_detected(&options.home_dir)?;
+
+    let sources = scanner::sources_or_default(options.sources.as_deref());
+
+    let first_options = options.report_options(&options.first);
+    report_date_source(&first_options)?;
+    report_date_source(&options.report_options(&options.second))?;
+    let pricing = report_pricing(&first_options).await?;
+    let exclude_dirs = scanner::exclude_dirs_or_default(None);
+    let (all_messages, _) = parse_all_messages_with_pricing(
+        &home_dir,
+        &sources,
+        &pricing,
+        &ScanOptions::new(sessions::DateSource::Timestamp, &exclude_dirs),
+    );
+
+    let in_window = |window: &DateWindow| {
+        let query = query::MessageQuery {
+            since: window.since.clone(),
+            until: window.until.clone(),
+            ..Default::default()
+        };
+        let mut acc = aggregator::ModelAccumulator::default();
+        for msg in all_messages.iter().filter(|msg| query.matches(msg)) {
+            acc.add_message(msg);
+        }
+        acc.finish()
+    };
+
+    let first = in_window(&options.first);
+    let second = in_window(&options.second);
+
+    Ok(RangeComparison {
+        entries: aggregator::compare_model_usage(&first, &second),
+        first_totals: aggregator::range_totals(&first),
+        second_totals: aggregator::range_totals(&second),
+        processing_time_ms: start.elapsed().as_millis() as u32,
+    })
+}
+
+/// Get monthly usage report with pricing calculation
+#[napi]
+pub async fn get_monthly_report(options: ReportOptions) -> napi::Result<MonthlyReport> {
+    monthly_report(options).await.map_err(error::to_napi)
+}
+
+async fn monthly_report(options: ReportOptions) -> Result<MonthlyReport, String> {
+    let start = Instant::now();
+
+    let home_dir = home_dir_or_detected(&options.home_dir)?;
+
+    let sources = scanner::sources_or_default(options.sources.as_deref());
+
+    let date_source = check_report_options(&options)?;
+    let pricing = report_pricing(&options).await?;
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let scan = ScanOptions::for_report(&options, date_source, &exclude_dirs);
+    let mut acc =
+        aggregator::MonthAccumulator::new(options.include_source_breakdown.unwrap_or(false));
+    let scan_result =
+        for_each_report_batch(&home_dir, &sources, &pricing, &options, &scan, |filtered| {
+            acc.add(&filtered)
+        });
+    ensure_files_found(&options, &home_dir, &sources, &scan_result)?;
+
+    let entries = acc.finish();
 
-    let total_input: i64 = entries.iter().map(|e| e.input).sum();
-    let total_output: i64 = entries.iter().map(|e| e.output).sum();
-    let total_cache_read: i64 = entries.iter().map(|e| e.cache_read).sum();
-    let total_cache_write: i64 = entries.iter().map(|e| e.cache_write).sum();
-    let total_messages: i32 = entries.iter().map(|e| e.message_count).sum();
     let total_cost: f64 = entries.iter().map(|e| e.cost).sum();
 
-    Ok(ModelReport {
+    let report = MonthlyReport {
         entries,
-        total_input,
-        total_output,
-        total_cache_read,
-        total_cache_write,
-        total_messages,
         total_cost,
         processing_time_ms: start.elapsed().as_millis() as u32,
-    })
+        clamped_messages: scan.sanitized().clamped as i32,
+        dropped_messages: scan.sanitized().dropped as i32,
+    };
+    Ok(round_report(report, &options))
 }
 
-/// Helper struct for aggregating monthly data (avoids clippy::type_complexity)
-#[derive(Default)]
-struct MonthAggregator {
-    models: std::collections::HashSet<String>,
-    input: i64,
-    output: i64,
-    cache_read: i64,
-    cache_write: i64,
-    message_count: i32,
-    cost: f64,
+/// Get quarterly usage report with pricing calculation
+#[napi]
+pub async fn get_quarterly_report(options: ReportOptions) -> napi::Result<QuarterlyReport> {
+    quarterly_report(options).await.map_err(error::to_napi)
 }
 
-/// Get monthly usage report with pricing calculation
+async fn quarterly_report(options: ReportOptions) -> Result<QuarterlyReport, String> {
+    let start = Instant::now();
+
+    let home_dir = home_dir_or_detected(&options.home_dir)?;
+
+    let sources = scanner::sources_or_default(options.sources.as_deref());
+
+    let date_source = check_report_options(&options)?;
+    let pricing = report_pricing(&options).await?;
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let scan = ScanOptions::for_report(&options, date_source, &exclude_dirs);
+    let mut acc = aggregator::QuarterAccumulator::default();
+    let scan_result =
+        for_each_report_batch(&home_dir, &sources, &pricing, &options, &scan, |filtered| {
+            acc.add(&filtered)
+        });
+    ensure_files_found(&options, &home_dir, &sources, &scan_result)?;
+
+    let entries = acc.finish();
+    let total_cost: f64 = entries.iter().map(|e| e.cost).sum();
+
+    let report = QuarterlyReport {
+        entries,
+        total_cost,
+        processing_time_ms: start.elapsed().as_millis() as u32,
+        clamped_messages: scan.sanitized().clamped as i32,
+        dropped_messages: scan.sanitized().dropped as i32,
+    };
+    Ok(round_report(report, &options))
+}
+
+/// Get per-project usage report with pricing calculation
 #[napi]
-pub async fn get_monthly_report(options: ReportOptions) -> napi::Result<MonthlyReport> {
+pub async fn get_project_report(options: ReportOptions) -> napi::Result<ProjectReport> {
+    project_report(options).await.map_err(error::to_napi)
+}
+
+async fn project_report(options: ReportOptions) -> Result<ProjectReport, String> {
     let start = Instant::now();
 
+    let home_dir = home_dir_or_detected(&options.home_dir)?;
+
+    let sources = scanner::sources_or_default(options.sources.as_deref());
+
+    let date_source = check_report_options(&options)?;
+    let pricing = report_pricing(&options).await?;
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let scan = ScanOptions::for_report(&options, date_source, &exclude_dirs);
+    let mut acc = aggregator::ProjectAccumulator::default();
+    let scan_result =
+        for_each_report_batch(&home_dir, &sources, &pricing, &options, &scan, |filtered| {
+            acc.add(&filtered)
+        });
+    ensure_files_found(&options, &home_dir, &sources, &scan_result)?;
+
+    let mut entries = acc.finish();
+    let total_cost: f64 = entries.iter().map(|e| e.cost).sum();
+    if let Some(anonymizer) = report_anonymizer(&options)? {
+        anonymizer.projects(&mut entries);
+    }
+
+    let report = ProjectReport {
+        entries,
+        total_cost,
+        processing_time_ms: start.elapsed().as_millis() as u32,
+        clamped_messages: scan.sanitized().clamped as i32,
+        dropped_messages: scan.sanitized().dropped as i32,
+    };
+    Ok(round_report(report, &options))
+}
+
+/// Generate graph data with pricing calculation
+#[napi]
+pub async fn generate_graph_with_pricing(options: ReportOptions) -> napi::Result<GraphResult> {
+    graph_with_pricing(options, progress::Progress::silent()).await
+}
+
+/// `generate_graph_with_pricing`, calling `on_progress` as files are parsed
+/// (at most every 100 files or 100ms, and once when the last file is done).
+/// Not built under `noop`, which has no JS values to call back with.
+#[cfg(not(feature = "noop"))]
+#[napi]
+pub async fn generate_graph_with_progress(
+    options: ReportOptions,
+    on_progress: ThreadsafeFunction<
+        ScanProgress,
+        Unknown<'static>,
+        ScanProgress,
+        napi::Status,
+        false,
+    >,
+) -> napi::Result<GraphResult> {
+    let progress = progress::Progress::new(Box::new(move |update| {
+        on_progress.call(update, ThreadsafeFunctionCallMode::NonBlocking);
+    }));
+    graph_with_pricing(options, progress).await
+}
+
+async fn graph_with_pricing(
+    options: ReportOptions,
+    progress: progress::Progress,
+) -> napi::Result<GraphResult> {
+    let start = Instant::now();
+    let progress = report_progress(progress, &options);
+
     let home_dir = get_home_dir(&options.home_dir)?;
 
-    let sources = options.sources.clone().unwrap_or_else(|| {
-        vec![
-            "opencode".to_string(),
-            "claude".to_string(),
-            "codex".to_string(),
-            "gemini".to_string(),
-            "cursor".to_string(),
-            "amp".to_string(),
-            "droid".to_string(),
-            "openclaw".to_string(),
-        ]
-    });
+    let sources = scanner::sources_or_default(options.sources.as_deref());
 
-    let pricing = pricing::PricingService::get_or_init()
-        .await
-        .map_err(|e| napi::Error::from_reason(e))?;
-    let all_messages = parse_all_messages_with_pricing(&home_dir, &sources, &pricing);
+    let date_source = check_report_options(&options).map_err(error::to_napi)?;
+    let active_metric = aggregator::ActiveMetric::parse(options.active_metric.as_deref())
+        .map_err(|e| error::to_napi(ErrorCode::InvalidMetric.reason(e)))?;
+    let pricing = report_pricing(&options).await.map_err(error::to_napi)?;
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let scan = ScanOptions {
+        progress,
+        ..ScanOptions::for_report(&options, date_source, &exclude_dirs)
+    };
 
-    // Apply date filters
-    let filtered = filter_messages_for_report(all_messages, &options);
+    // Aggregate by date, after date filters
+    let mut unpriced_models = std::collections::BTreeSet::new();
+    let (contributions, scan_result) = if options.low_memory.unwrap_or(false) {
+        let mut acc = aggregator::DateAccumulator::new(options.group_by_agent.unwrap_or(false));
+        let scan_result =
+            for_each_source_with_pricing(&home_dir, &sources, &pricing, &scan, |messages| {
+                let filtered = filter_messages_for_report(messages, &options);
+                aggregator::collect_unpriced_models(&mut unpriced_models, &filtered);
+                acc.add(&filtered);
+            });
+        (acc.finish(), scan_result)
+    } else {
+        let mut all_messages = Vec::new();
+        let scan_result =
+            for_each_source_with_pricing(&home_dir, &sources, &pricing, &scan, |messages| {
+                all_messages.extend(messages)
+            });
+        let (contributions, unpriced) = graph_contributions(all_messages, &options);
+        unpriced_models = unpriced;
+        (contributions, scan_result)
+    };
+    ensure_files_found(&options, &home_dir, &sources, &scan_result).map_err(error::to_napi)?;
+
+    let mut result = graph_result(
+        contributions,
+        unpriced_models,
+        scan.sanitized(),
+        &pricing,
+        &options,
+        active_metric,
+        start,
+    );
+    record_scan(&mut result, &scan_result, &scan.progress);
+    result.parse_timing_histogram = scan.progress.parse_timing();
+
+    Ok(round_report(result, &options))
+}
+
+/// Daily contributions for priced `messages` after the report's filters, with
+/// the models that had no pricing
+fn graph_contributions(
+    messages: Vec<UnifiedMessage>,
+    options: &ReportOptions,
+) -> (Vec<DailyContribution>, std::collections::BTreeSet<String>) {
+    let filtered = filter_messages_for_report(messages, options);
+    let mut unpriced_models = std::collections::BTreeSet::new();
+    aggregator::collect_unpriced_models(&mut unpriced_models, &filtered);
+    let contributions =
+        aggregator::aggregate_by_date(&filtered, options.group_by_agent.unwrap_or(false));
+    (contributions, unpriced_models)
+}
 
-    // Aggregate by month
-    let mut month_map: std::collections::HashMap<String, MonthAggregator> =
-        std::collections::HashMap::new();
+/// The graph result for filtered daily contributions, shaped by the graph
+/// options. Shared by the graph endpoints; callers add their scan counters
+/// and round it.
+fn graph_result(
+    mut contributions: Vec<DailyContribution>,
+    unpriced_models: std::collections::BTreeSet<String>,
+    sanitized: sessions::TokenSanitizeCounts,
+    pricing: &pricing::PricingService,
+    options: &ReportOptions,
+    active_metric: aggregator::ActiveMetric,
+    start: Instant,
+) -> GraphResult {
+    if options.intensity_levels.is_some() || active_metric != aggregator::ActiveMetric::Cost {
+        let levels = options
+            .intensity_levels
+            .unwrap_or(aggregator::DEFAULT_INTENSITY_LEVELS);
+        aggregator::calculate_intensities(&mut contributions, levels, active_metric);
+    }
+    if options.include_provider_breakdown.unwrap_or(false) {
+        aggregator::add_provider_breakdown(&mut contributions);
+    }
 
-    for msg in filtered {
-        // Extract month from date (YYYY-MM-DD -> YYYY-MM)
-        let month = if msg.date.len() >= 7 {
-            msg.date[..7].to_string()
-        } else {
-            continue;
-        };
+    // Generate result
+    let processing_time_ms = start.elapsed().as_millis() as u32;
+    let mut result =
+        aggregator::generate_graph_result(contributions, processing_time_ms, active_metric);
+    if options.fill_gaps.unwrap_or(false) {
+        aggregator::fill_date_gaps(&mut result);
+    }
+    record_sanitized(&mut result, sanitized);
+    // Token-only mode prices everything at zero on purpose
+    if !pricing.is_unpriced() {
+        result.meta.unpriced_models = unpriced_models.into_iter().collect();
+    }
+    result
+}
 
-        let entry = month_map.entry(month).or_default();
+/// Graph data for the Claude Code and Codex sessions in a zip archive (see
+/// `archive`), priced and filtered like `generate_graph_with_pricing`. Dates
+/// always come from the messages; entries without one fall back to the
+/// archive's modification time. `meta.filesParsed` and `meta.bytesRead`
+/// count the session entries read from the archive, uncompressed.
+#[napi]
+pub async fn generate_graph_from_archive(
+    archive_path: String,
+    options: ReportOptions,
+) -> napi::Result<GraphResult> {
+    let start = Instant::now();
 
-        entry.models.insert(msg.model_id.clone());
-        entry.input += msg.tokens.input;
-        entry.output += msg.tokens.output;
-        entry.cache_read += msg.tokens.cache_read;
-        entry.cache_write += msg.tokens.cache_write;
-        entry.message_count += 1;
-        entry.cost += msg.cost;
-    }
+    check_report_options(&options).map_err(error::to_napi)?;
+    let active_metric = aggregator::ActiveMetric::parse(options.active_metric.as_deref())
+        .map_err(|e| error::to_napi(ErrorCode::InvalidMetric.reason(e)))?;
+    let pricing = report_pricing(&options).await.map_err(error::to_napi)?;
 
-    let mut entries: Vec<MonthlyUsage> = month_map
+    let scan = read_archive(&archive_path).map_err(error::to_napi)?;
+    let mut messages: Vec<UnifiedMessage> = scan
+        .messages
         .into_iter()
-        .map(|(month, agg)| MonthlyUsage {
-            month,
-            models: agg.models.into_iter().collect(),
-            input: agg.input,
-            output: agg.output,
-            cache_read: agg.cache_read,
-            cache_write: agg.cache_write,
-            message_count: agg.message_count,
-            cost: agg.cost,
+        .map(|mut msg| {
+            pricing.apply(&mut msg);
+            msg
         })
         .collect();
+    let sanitized = sessions::sanitize_tokens(&mut messages, options.max_tokens_per_message);
+
+    let (contributions, unpriced_models) = graph_contributions(messages, &options);
+    let mut result = graph_result(
+        contributions,
+        unpriced_models,
+        sanitized,
+        &pricing,
+        &options,
+        active_metric,
+        start,
+    );
+    result.meta.bytes_read = scan.bytes_read;
+    result.meta.files_parsed = scan.files_parsed;
+
+    Ok(round_report(result, &options))
+}
 
-    // Sort by month ascending
-    entries.sort_by(|a, b| a.month.cmp(&b.month));
-
-    let total_cost: f64 = entries.iter().map(|e| e.cost).sum();
-
-    Ok(MonthlyReport {
-        entries,
-        total_cost,
-        processing_time_ms: start.elapsed().as_millis() as u32,
-    })
+/// The sessions in the zip archive at `archive_path`
+fn read_archive(archive_path: &str) -> Result<archive::ArchiveScan, String> {
+    let path = std::path::Path::new(archive_path);
+    let file = std::fs::File::open(path).map_err(|e| {
+        ErrorCode::ReadFailed.reason(format!("Failed to read {}: {}", archive_path, e))
+    })?;
+    archive::parse_archive(
+        std::io::BufReader::new(file),
+        sessions::utils::file_modified_timestamp_ms(path),
+    )
+    .map_err(|e| ErrorCode::ReadFailed.reason(format!("Failed to read {}: {}", archive_path, e)))
 }
 
-/// Generate graph data with pricing calculation
+/// Today's totals (local time) for status-bar style widgets. Honors `sources`,
+/// `date_source` and the pricing options; the date filters are ignored.
+/// Files last modified before midnight are skipped without parsing.
 #[napi]
-pub async fn generate_graph_with_pricing(options: ReportOptions) -> napi::Result<GraphResult> {
-    let start = Instant::now();
-
-    let home_dir = get_home_dir(&options.home_dir)?;
-
-    let sources = options.sources.clone().unwrap_or_else(|| {
-        vec![
-            "opencode".to_string(),
-            "claude".to_string(),
-            "codex".to_string(),
-            "gemini".to_string(),
-            "cursor".to_string(),
-            "amp".to_string(),
-            "droid".to_string(),
-            "openclaw".to_string(),
-        ]
-    });
-
-    let pricing = pricing::PricingService::get_or_init()
-        .await
-        .map_err(|e| napi::Error::from_reason(e))?;
-    let all_messages = parse_all_messages_with_pricing(&home_dir, &sources, &pricing);
+pub async fn today_summary(options: ReportOptions) -> napi::Result<DailyTotals> {
+    today_totals(options).await.map_err(error::to_napi)
+}
 
-    // Apply date filters
-    let filtered = filter_messages_for_report(all_messages, &options);
+async fn today_totals(options: ReportOptions) -> Result<DailyTotals, String> {
+    let home_dir = home_dir_or_detected(&options.home_dir)?;
+    let sources = scanner::sources_or_default(options.sources.as_deref());
+    let date_source = check_report_options(&options)?;
+    let pricing = report_pricing(&options).await?;
+
+    let now = chrono::Local::now();
+    let today = now.format("%Y-%m-%d").to_string();
+
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let mut scan_result = scanner::scan_all_sources_excluding(&home_dir, &sources, &exclude_dirs);
+    ensure_files_found(&options, &home_dir, &sources, &scan_result)?;
+    // A file last modified before midnight holds nothing from today: with
+    // mtime dates by definition, and with timestamp dates because messages
+    // are written as they happen
+    let midnight = now
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|dt| dt.and_local_timezone(chrono::Local).earliest())
+        .map_or(i64::MIN, |dt| dt.timestamp_millis());
+    retain_modified_within(&mut scan_result, (midnight, i64::MAX));
+
+    let mut totals = DailyTotals::default();
+    for_each_scanned_source_with_pricing(
+        scan_result,
+        &pricing,
+        &ScanOptions::for_report(&options, date_source, &exclude_dirs),
+        |messages| {
+            aggregator::add_totals_on(&mut totals, &messages, &today);
+        },
+    );
 
-    // Aggregate by date
-    let contributions = aggregator::aggregate_by_date(filtered);
+    Ok(round_report(totals, &options))
+}
 
-    // Generate result
-    let processing_time_ms = start.elapsed().as_millis() as u32;
-    let result = aggregator::generate_graph_result(contributions, processing_time_ms);
+/// Serialize a graph as canonical JSON for file export: object keys sorted,
+/// and each day's sources plus the summary's source and model lists in a fixed
+/// order, so two runs over the same data differ only where the data does
+#[napi]
+pub fn graph_result_to_json(mut result: GraphResult, pretty: bool) -> String {
+    // Summary lists are already sorted by `calculate_summary`
+    for day in &mut result.contributions {
+        day.sources.sort_by(|a, b| {
+            (&a.source, &a.model_id, &a.provider_id, &a.agent).cmp(&(
+                &b.source,
+                &b.model_id,
+                &b.provider_id,
+                &b.agent,
+            ))
+        });
+    }
 
-    Ok(result)
+    // Going through `Value` sorts object keys (serde_json's map is a BTreeMap)
+    let value = serde_json::to_value(&result).unwrap_or_default();
+    let json = if pretty {
+        serde_json::to_string_pretty(&value)
+    } else {
+        serde_json::to_string(&value)
+    };
+    json.unwrap_or_default()
 }
 
-/// Filter messages by date range (for reports)
+/// Filter messages by date range and excluded models (for reports). Undated
+/// messages (implausible timestamps) are always dropped, so every report
+/// agrees on totals whatever the date filters.
 fn filter_messages_for_report(
     messages: Vec<UnifiedMessage>,
     options: &ReportOptions,
 ) -> Vec<UnifiedMessage> {
     let mut filtered = messages;
+    filtered.retain(|m| !m.date.is_empty());
 
     // Filter by year
     if let Some(year) = &options.year {
@@ -717,9 +2367,24 @@ fn filter_messages_for_report(
         filtered.retain(|m| m.date.as_str() <= until.as_str());
     }
 
+    // Drop excluded models
+    if let Some(exclude) = &options.exclude_models {
+        let patterns: Vec<String> = exclude.iter().map(|p| p.to_lowercase()).collect();
+        filtered.retain(|m| {
+            let model = m.model_id.to_lowercase();
+            !patterns.iter().any(|p| query::glob_matches(p, &model))
+        });
+    }
+
     filtered
 }
 
+/// Record what sanitizing token counts changed in the graph's meta
+fn record_sanitized(result: &mut GraphResult, counts: sessions::TokenSanitizeCounts) {
+    result.meta.clamped_messages = counts.clamped as i32;
+    result.meta.dropped_messages = counts.dropped as i32;
+}
+
 fn is_headless_path(path: &Path, headless_roots: &[PathBuf]) -> bool {
     headless_roots.iter().any(|root| path.starts_with(root))
 }
@@ -743,22 +2408,13 @@ pub fn parse_local_sources(options: LocalParseOptions) -> napi::Result<ParsedMes
     let home_dir = get_home_dir(&options.home_dir)?;
 
     // Default to local sources only (no cursor)
-    let sources = options.sources.clone().unwrap_or_else(|| {
-        vec![
-            "opencode".to_string(),
-            "claude".to_string(),
-            "codex".to_string(),
-            "gemini".to_string(),
-            "amp".to_string(),
-            "droid".to_string(),
-            "openclaw".to_string(),
-        ]
-    });
+    let sources = scanner::sources_or_default(options.sources.as_deref());
 
     // Filter out cursor if somehow included
     let local_sources: Vec<String> = sources.into_iter().filter(|s| s != "cursor").collect();
 
-    let scan_result = scanner::scan_all_sources(&home_dir, &local_sources);
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let scan_result = scanner::scan_all_sources_excluding(&home_dir, &local_sources, &exclude_dirs);
     let headless_roots = scanner::headless_roots(&home_dir);
 
     let mut messages: Vec<ParsedMessage> = Vec::new();
@@ -776,27 +2432,16 @@ pub fn parse_local_sources(options: LocalParseOptions) -> napi::Result<ParsedMes
     messages.extend(opencode_msgs);
 
     // Parse Claude files in parallel, then deduplicate globally
-    let claude_msgs_raw: Vec<(String, ParsedMessage)> = scan_result
+    let claude_msgs_raw: Vec<UnifiedMessage> = scan_result
         .claude_files
         .par_iter()
-        .flat_map(|path| {
-            sessions::claudecode::parse_claude_file(path)
-                .into_iter()
-                .map(|msg| {
-                    let dedup_key = msg.dedup_key.clone().unwrap_or_default();
-                    (dedup_key, unified_to_parsed(&msg))
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect();
-
-    // Global deduplication across all Claude files
-    let mut seen_keys: std::collections::HashSet<String> = std::collections::HashSet::new();
-    let claude_msgs: Vec<ParsedMessage> = claude_msgs_raw
-        .into_iter()
-        .filter(|(key, _)| key.is_empty() || seen_keys.insert(key.clone()))
-        .map(|(_, msg)| msg)
+        .flat_map(|path| sessions::claudecode::parse_claude_file(path))
         .collect();
+    let claude_msgs: Vec<ParsedMessage> =
+        sessions::claudecode::dedup_claude_messages(claude_msgs_raw)
+            .iter()
+            .map(unified_to_parsed)
+            .collect();
     let claude_count = claude_msgs.len() as i32;
     messages.extend(claude_msgs);
 
@@ -874,6 +2519,90 @@ pub fn parse_local_sources(options: LocalParseOptions) -> napi::Result<ParsedMes
     let openclaw_count = openclaw_msgs.len() as i32;
     messages.extend(openclaw_msgs);
 
+    // Parse Warp conversation files in parallel
+    let warp_msgs: Vec<ParsedMessage> = scan_result
+        .warp_files
+        .par_iter()
+        .flat_map(|path| {
+            sessions::warp::parse_warp_file(path)
+                .into_iter()
+                .map(|msg| unified_to_parsed(&msg))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let warp_count = warp_msgs.len() as i32;
+    messages.extend(warp_msgs);
+
+    // Parse Roo Code task histories in parallel, then deduplicate resumed tasks
+    let roo_raw: Vec<UnifiedMessage> = scan_result
+        .roo_files
+        .par_iter()
+        .flat_map(|path| sessions::roo::parse_roo_file(path))
+        .collect();
+    let roo_msgs: Vec<ParsedMessage> =
+        sessions::roo::dedup_roo_messages(&mut std::collections::HashSet::new(), roo_raw)
+            .iter()
+            .map(unified_to_parsed)
+            .collect();
+    let roo_count = roo_msgs.len() as i32;
+    messages.extend(roo_msgs);
+
+    // Parse Kilo Code task histories in parallel, then deduplicate resumed tasks
+    let kilo_raw: Vec<UnifiedMessage> = scan_result
+        .kilo_files
+        .par_iter()
+        .flat_map(|path| sessions::kilo::parse_kilo_file(path))
+        .collect();
+    let kilo_msgs: Vec<ParsedMessage> =
+        sessions::kilo::dedup_kilo_messages(&mut std::collections::HashSet::new(), kilo_raw)
+            .iter()
+            .map(unified_to_parsed)
+            .collect();
+    let kilo_count = kilo_msgs.len() as i32;
+    messages.extend(kilo_msgs);
+
+    // Parse Qwen Code files in parallel
+    let qwen_msgs: Vec<ParsedMessage> = scan_result
+        .qwen_files
+        .par_iter()
+        .flat_map(|path| {
+            sessions::qwen::parse_qwen_file(path)
+                .into_iter()
+                .map(|msg| unified_to_parsed(&msg))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let qwen_count = qwen_msgs.len() as i32;
+    messages.extend(qwen_msgs);
+
+    // Parse Copilot Chat sessions in parallel
+    let copilot_msgs: Vec<ParsedMessage> = scan_result
+        .copilot_files
+        .par_iter()
+        .flat_map(|path| {
+            sessions::copilot::parse_copilot_file(path)
+                .into_iter()
+                .map(|msg| unified_to_parsed(&msg))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let copilot_count = copilot_msgs.len() as i32;
+    messages.extend(copilot_msgs);
+
+    // Parse Jules task usage in parallel
+    let jules_msgs: Vec<ParsedMessage> = scan_result
+        .jules_files
+        .par_iter()
+        .flat_map(|path| {
+            sessions::jules::parse_jules_file(path)
+                .into_iter()
+                .map(|msg| unified_to_parsed(&msg))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let jules_count = jules_msgs.len() as i32;
+    messages.extend(jules_msgs);
+
     // Apply date filters
     let filtered = filter_parsed_messages(messages, &options);
 
@@ -886,10 +2615,98 @@ pub fn parse_local_sources(options: LocalParseOptions) -> napi::Result<ParsedMes
         amp_count,
         droid_count,
         openclaw_count,
+        warp_count,
+        roo_count,
+        kilo_count,
+        qwen_count,
+        copilot_count,
+        jules_count,
         processing_time_ms: start.elapsed().as_millis() as u32,
     })
 }
 
+/// A headless input line that could not be parsed
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct HeadlessLineError {
+    /// 1-based line number
+    pub line_no: i32,
+    pub reason: String,
+}
+
+/// Line-by-line outcome of validating a headless input file
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct HeadlessValidation {
+    pub lines_total: i32,
+    pub lines_parsed: i32,
+    pub messages_emitted: i32,
+    pub errors: Vec<HeadlessLineError>,
+}
+
+/// Validate a headless output file for `source` (claude, codex, gemini or qwen),
+/// reporting which lines the parser would skip
+#[napi]
+pub fn validate_headless_file(path: String, source: String) -> napi::Result<HeadlessValidation> {
+    let validation = sessions::headless::validate_headless_file(Path::new(&path), &source)
+        .map_err(error::to_napi)?;
+
+    Ok(HeadlessValidation {
+        lines_total: validation.lines_total as i32,
+        lines_parsed: validation.lines_parsed as i32,
+        messages_emitted: validation.messages_emitted as i32,
+        errors: validation
+            .errors
+            .into_iter()
+            .map(|e| HeadlessLineError {
+                line_no: e.line_no as i32,
+                reason: e.reason,
+            })
+            .collect(),
+    })
+}
+
+/// Parse a single session file as `source` (e.g. "claude", "codex"), skipping
+/// the directory scan, and return its messages with computed cost
+#[napi]
+pub async fn parse_file(path: String, source: String) -> napi::Result<Vec<ParsedMessage>> {
+    let messages =
+        sessions::parse_source_file(Path::new(&path), &source).map_err(error::to_napi)?;
+
+    let pricing = pricing::PricingService::get_or_init()
+        .await
+        .map_err(|e| error::to_napi(ErrorCode::PricingFetch.reason(e)))?;
+
+    Ok(priced_file_messages(messages, &source, &pricing))
+}
+
+/// One file's `messages` priced and sanitized the way the scan does it, so
+/// `parse_file` agrees with the reports. Cursor keeps its recorded cost when
+/// the model is unpriced.
+fn priced_file_messages(
+    mut messages: Vec<UnifiedMessage>,
+    source: &str,
+    pricing: &pricing::PricingService,
+) -> Vec<ParsedMessage> {
+    for msg in &mut messages {
+        if source == "cursor" {
+            pricing.apply_or_keep_recorded(msg);
+        } else {
+            pricing.apply(msg);
+        }
+    }
+    sessions::sanitize_tokens(&mut messages, None);
+    messages.iter().map(priced_message).collect()
+}
+
+/// `msg` as a `ParsedMessage` carrying its computed cost
+fn priced_message(msg: &UnifiedMessage) -> ParsedMessage {
+    ParsedMessage {
+        cost: Some(msg.cost),
+        ..unified_to_parsed(msg)
+    }
+}
+
 fn unified_to_parsed(msg: &UnifiedMessage) -> ParsedMessage {
     ParsedMessage {
         source: msg.source.clone(),
@@ -904,6 +2721,11 @@ fn unified_to_parsed(msg: &UnifiedMessage) -> ParsedMessage {
         cache_write: msg.tokens.cache_write,
         reasoning: msg.tokens.reasoning,
         agent: msg.agent.clone(),
+        cost: None,
+        credits: msg.credits,
+        premium_requests: (msg.premium_requests != 0.0).then_some(msg.premium_requests),
+        project: msg.project.clone(),
+        batch: msg.batch.then_some(true),
     }
 }
 
@@ -913,6 +2735,8 @@ fn filter_parsed_messages(
     options: &LocalParseOptions,
 ) -> Vec<ParsedMessage> {
     let mut filtered = messages;
+    // Undated messages are dropped, as in `filter_messages_for_report`
+    filtered.retain(|m| !m.date.is_empty());
 
     if let Some(year) = &options.year {
         let year_prefix = format!("{}-", year);
@@ -946,8 +2770,14 @@ fn parsed_to_unified(msg: &ParsedMessage, cost: f64) -> UnifiedMessage {
             reasoning: msg.reasoning,
         },
         cost,
+        cost_breakdown: TokenCostBreakdown::default(),
+        cost_estimated: false,
+        premium_requests: msg.premium_requests.unwrap_or(0.0),
+        credits: msg.credits,
         agent: msg.agent.clone(),
         dedup_key: None,
+        project: msg.project.clone(),
+        batch: msg.batch.unwrap_or(false),
     }
 }
 
@@ -958,25 +2788,299 @@ pub async fn finalize_report(options: FinalizeReportOptions) -> napi::Result<Mod
 
     let home_dir = get_home_dir(&options.home_dir)?;
 
-    let pricing = pricing::PricingService::get_or_init()
-        .await
-        .map_err(|e| napi::Error::from_reason(e))?;
+    let pricing =
+        pricing::PricingService::get_or_init_with_priority(options.pricing_priority.as_deref())
+            .await
+            .map_err(|e| error::to_napi(ErrorCode::PricingFetch.reason(e)))?;
+
+    let (all_messages, sanitized) = finalized_messages(&options.scope(), &home_dir, &pricing);
+
+    // Aggregate by model
+    let entries = aggregator::aggregate_by_model(&all_messages);
+
+    Ok(ModelReport {
+        clamped_messages: sanitized.clamped as i32,
+        dropped_messages: sanitized.dropped as i32,
+        ..model_report(
+            entries,
+            &pricing,
+            start.elapsed().as_millis() as u32,
+            options.separate_reasoning_cost,
+        )
+    })
+}
+
+/// Options for finalizing monthly report
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct FinalizeMonthlyOptions {
+    pub home_dir: Option<String>,
+    pub local_messages: ParsedMessages,
+    pub include_cursor: bool,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub year: Option<String>,
+    /// Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+    /// Defaults to LiteLLM first.
+    pub pricing_priority: Option<Vec<String>>,
+    /// Drop messages whose total tokens exceed this, as corrupt. Negative
+    /// token counts are always clamped to 0. Unset keeps every message.
+    pub max_tokens_per_message: Option<i64>,
+}
+
+/// Finalize monthly report
+#[napi]
+pub async fn finalize_monthly_report(
+    options: FinalizeMonthlyOptions,
+) -> napi::Result<MonthlyReport> {
+    let start = Instant::now();
+
+    let home_dir = get_home_dir(&options.home_dir)?;
+
+    let pricing =
+        pricing::PricingService::get_or_init_with_priority(options.pricing_priority.as_deref())
+            .await
+            .map_err(|e| error::to_napi(ErrorCode::PricingFetch.reason(e)))?;
+
+    let (all_messages, sanitized) = finalized_messages(&options.scope(), &home_dir, &pricing);
+
+    // Aggregate by month
+    let entries = aggregator::aggregate_by_month(all_messages, false);
+    let total_cost: f64 = entries.iter().map(|e| e.cost).sum();
+
+    Ok(MonthlyReport {
+        entries,
+        total_cost,
+        processing_time_ms: start.elapsed().as_millis() as u32,
+        clamped_messages: sanitized.clamped as i32,
+        dropped_messages: sanitized.dropped as i32,
+    })
+}
+
+/// Options for finalizing graph
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct FinalizeGraphOptions {
+    pub home_dir: Option<String>,
+    pub local_messages: ParsedMessages,
+    pub include_cursor: bool,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub year: Option<String>,
+    /// Pricing source order for auto lookup, e.g. `["openrouter", "litellm"]`.
+    /// Defaults to LiteLLM first.
+    pub pricing_priority: Option<Vec<String>>,
+    /// Drop messages whose total tokens exceed this, as corrupt. Negative
+    /// token counts are always clamped to 0. Unset keeps every message.
+    pub max_tokens_per_message: Option<i64>,
+}
+
+/// Finalize graph
+#[napi]
+pub async fn finalize_graph(options: FinalizeGraphOptions) -> napi::Result<GraphResult> {
+    let start = Instant::now();
+
+    let home_dir = get_home_dir(&options.home_dir)?;
+
+    let pricing =
+        pricing::PricingService::get_or_init_with_priority(options.pricing_priority.as_deref())
+            .await
+            .map_err(|e| error::to_napi(ErrorCode::PricingFetch.reason(e)))?;
+
+    let (all_messages, sanitized) = finalized_messages(&options.scope(), &home_dir, &pricing);
+
+    // Aggregate by date
+    let contributions = aggregator::aggregate_by_date(&all_messages, false);
+
+    // Generate result
+    let processing_time_ms = start.elapsed().as_millis() as u32;
+    let mut result = aggregator::generate_graph_result(
+        contributions,
+        processing_time_ms,
+        aggregator::ActiveMetric::Cost,
+    );
+    result.meta.unpriced_models = aggregator::unpriced_models(&all_messages);
+    record_sanitized(&mut result, sanitized);
+
+    Ok(result)
+}
+
+/// Combined result for report and graph (single pricing lookup)
+#[napi(object)]
+pub struct ReportAndGraph {
+    pub report: ModelReport,
+    pub graph: GraphResult,
+}
+
+/// Finalize both report and graph in a single call with shared pricing
+/// This ensures consistent costs between report and graph data
+#[napi]
+pub async fn finalize_report_and_graph(
+    options: FinalizeReportOptions,
+) -> napi::Result<ReportAndGraph> {
+    let start = Instant::now();
+
+    let home_dir = get_home_dir(&options.home_dir)?;
+
+    // Single pricing lookup - shared by both report and graph
+    let pricing =
+        pricing::PricingService::get_or_init_with_priority(options.pricing_priority.as_deref())
+            .await
+            .map_err(|e| error::to_napi(ErrorCode::PricingFetch.reason(e)))?;
+
+    let (all_messages, sanitized) = finalized_messages(&options.scope(), &home_dir, &pricing);
+
+    // Both aggregations borrow the same messages; no copy is made for the graph
+    // --- Generate Report ---
+    let entries = aggregator::aggregate_by_model(&all_messages);
+    let report = ModelReport {
+        clamped_messages: sanitized.clamped as i32,
+        dropped_messages: sanitized.dropped as i32,
+        ..model_report(
+            entries,
+            &pricing,
+            start.elapsed().as_millis() as u32,
+            options.separate_reasoning_cost,
+        )
+    };
+
+    // --- Generate Graph ---
+    let contributions = aggregator::aggregate_by_date(&all_messages, false);
+    let mut graph = aggregator::generate_graph_result(
+        contributions,
+        start.elapsed().as_millis() as u32,
+        aggregator::ActiveMetric::Cost,
+    );
+    graph.meta.unpriced_models = aggregator::unpriced_models(&all_messages);
+    record_sanitized(&mut graph, sanitized);
+
+    Ok(ReportAndGraph { report, graph })
+}
+
+/// Combined report, graph and monthly result (single pricing lookup and pass)
+#[napi(object)]
+pub struct AllReports {
+    pub report: ModelReport,
+    pub graph: GraphResult,
+    pub monthly: MonthlyReport,
+}
+
+/// Finalize the model report, graph and monthly report together, pricing the
+/// messages once and aggregating them in a single pass
+#[napi]
+pub async fn finalize_all(options: FinalizeReportOptions) -> napi::Result<AllReports> {
+    let start = Instant::now();
+
+    let home_dir = get_home_dir(&options.home_dir)?;
+
+    let pricing =
+        pricing::PricingService::get_or_init_with_priority(options.pricing_priority.as_deref())
+            .await
+            .map_err(|e| error::to_napi(ErrorCode::PricingFetch.reason(e)))?;
+
+    let (all_messages, sanitized) = finalized_messages(&options.scope(), &home_dir, &pricing);
+    let (entries, contributions, months) = aggregator::aggregate_all(&all_messages);
+
+    let processing_time_ms = start.elapsed().as_millis() as u32;
+    let monthly_total_cost: f64 = months.iter().map(|e| e.cost).sum();
+
+    let mut graph = aggregator::generate_graph_result(
+        contributions,
+        processing_time_ms,
+        aggregator::ActiveMetric::Cost,
+    );
+    graph.meta.unpriced_models = aggregator::unpriced_models(&all_messages);
+    record_sanitized(&mut graph, sanitized);
+
+    Ok(AllReports {
+        report: ModelReport {
+            clamped_messages: sanitized.clamped as i32,
+            dropped_messages: sanitized.dropped as i32,
+            ..model_report(
+                entries,
+                &pricing,
+                processing_time_ms,
+                options.separate_reasoning_cost,
+            )
+        },
+        graph,
+        monthly: MonthlyReport {
+            entries: months,
+            total_cost: monthly_total_cost,
+            processing_time_ms,
+            clamped_messages: sanitized.clamped as i32,
+            dropped_messages: sanitized.dropped as i32,
+        },
+    })
+}
+
+/// What the finalize endpoints share: the local messages, whether to add
+/// Cursor, and the date range and token limit applied to both
+struct FinalizeScope<'a> {
+    local_messages: &'a ParsedMessages,
+    include_cursor: bool,
+    since: Option<&'a str>,
+    until: Option<&'a str>,
+    year: Option<&'a str>,
+    max_tokens_per_message: Option<i64>,
+}
+
+impl FinalizeReportOptions {
+    fn scope(&self) -> FinalizeScope<'_> {
+        FinalizeScope {
+            local_messages: &self.local_messages,
+            include_cursor: self.include_cursor,
+            since: self.since.as_deref(),
+            until: self.until.as_deref(),
+            year: self.year.as_deref(),
+            max_tokens_per_message: self.max_tokens_per_message,
+        }
+    }
+}
+
+impl FinalizeMonthlyOptions {
+    fn scope(&self) -> FinalizeScope<'_> {
+        FinalizeScope {
+            local_messages: &self.local_messages,
+            include_cursor: self.include_cursor,
+            since: self.since.as_deref(),
+            until: self.until.as_deref(),
+            year: self.year.as_deref(),
+            max_tokens_per_message: self.max_tokens_per_message,
+        }
+    }
+}
+
+impl FinalizeGraphOptions {
+    fn scope(&self) -> FinalizeScope<'_> {
+        FinalizeScope {
+            local_messages: &self.local_messages,
+            include_cursor: self.include_cursor,
+            since: self.since.as_deref(),
+            until: self.until.as_deref(),
+            year: self.year.as_deref(),
+            max_tokens_per_message: self.max_tokens_per_message,
+        }
+    }
+}
 
-    // Convert local messages and apply pricing
+/// Local messages priced with `pricing`, plus Cursor when enabled, within the
+/// options' date range, with negative token counts clamped and messages over
+/// `max_tokens_per_message` dropped
+fn finalized_messages(
+    options: &FinalizeScope,
+    home_dir: &str,
+    pricing: &pricing::PricingService,
+) -> (Vec<UnifiedMessage>, sessions::TokenSanitizeCounts) {
+    // Convert local messages and apply pricing (once)
     let mut all_messages: Vec<UnifiedMessage> = options
         .local_messages
         .messages
         .iter()
         .map(|msg| {
-            let cost = pricing.calculate_cost(
-                &msg.model_id,
-                msg.input,
-                msg.output,
-                msg.cache_read,
-                msg.cache_write,
-                msg.reasoning,
-            );
-            parsed_to_unified(msg, cost)
+            let mut unified = parsed_to_unified(msg, 0.0);
+            pricing.apply(&mut unified);
+            unified
         })
         .collect();
 
@@ -985,96 +3089,183 @@ pub async fn finalize_report(options: FinalizeReportOptions) -> napi::Result<Mod
         let cursor_cache_dir = format!("{}/.config/tokscale/cursor-cache", home_dir);
         let cursor_files = scanner::scan_directory(&cursor_cache_dir, "usage*.csv");
 
-        let cursor_messages: Vec<UnifiedMessage> = cursor_files
+        let cursor_exports: Vec<Vec<UnifiedMessage>> = cursor_files
             .par_iter()
-            .flat_map(|path| {
-                sessions::cursor::parse_cursor_file(path)
-                    .into_iter()
-                    .map(|mut msg| {
-                        let csv_cost = msg.cost;
-                        let calculated_cost = pricing.calculate_cost(
-                            &msg.model_id,
-                            msg.tokens.input,
-                            msg.tokens.output,
-                            msg.tokens.cache_read,
-                            msg.tokens.cache_write,
-                            msg.tokens.reasoning,
-                        );
-                        msg.cost = if calculated_cost > 0.0 {
-                            calculated_cost
-                        } else {
-                            csv_cost
-                        };
-                        msg
-                    })
-                    .collect::<Vec<_>>()
-            })
+            .map(|path| sessions::cursor::parse_cursor_file(path))
             .collect();
+        let cursor_messages: Vec<UnifiedMessage> =
+            sessions::cursor::merge_cursor_exports(cursor_exports)
+                .into_iter()
+                .map(|mut msg| {
+                    pricing.apply_or_keep_recorded(&mut msg);
+                    msg
+                })
+                .collect();
 
         all_messages.extend(cursor_messages);
     }
 
-    // Apply date filters to cursor messages (local already filtered)
-    if options.include_cursor {
-        if let Some(year) = &options.year {
-            let year_prefix = format!("{}-", year);
-            all_messages.retain(|m| m.date.starts_with(&year_prefix));
-        }
-        if let Some(since) = &options.since {
-            all_messages.retain(|m| m.date.as_str() >= since.as_str());
-        }
-        if let Some(until) = &options.until {
-            all_messages.retain(|m| m.date.as_str() <= until.as_str());
-        }
+    // Apply date filters, dropping undated messages
+    all_messages.retain(|m| !m.date.is_empty());
+    if let Some(year) = options.year {
+        let year_prefix = format!("{}-", year);
+        all_messages.retain(|m| m.date.starts_with(&year_prefix));
+    }
+    if let Some(since) = options.since {
+        all_messages.retain(|m| m.date.as_str() >= since);
+    }
+    if let Some(until) = options.until {
+        all_messages.retain(|m| m.date.as_str() <= until);
     }
 
-    // Aggregate by model
-    let mut model_map: std::collections::HashMap<String, ModelUsage> =
-        std::collections::HashMap::new();
-
-    for msg in all_messages {
-        let key = format!("{}:{}:{}", msg.source, msg.provider_id, msg.model_id);
-        let entry = model_map.entry(key).or_insert_with(|| ModelUsage {
-            source: msg.source.clone(),
-            model: msg.model_id.clone(),
-            provider: msg.provider_id.clone(),
-            input: 0,
-            output: 0,
-            cache_read: 0,
-            cache_write: 0,
-            reasoning: 0,
-            message_count: 0,
-            cost: 0.0,
-        });
+    let sanitized = sessions::sanitize_tokens(&mut all_messages, options.max_tokens_per_message);
+    (all_messages, sanitized)
+}
 
-        entry.input += msg.tokens.input;
-        entry.output += msg.tokens.output;
-        entry.cache_read += msg.tokens.cache_read;
-        entry.cache_write += msg.tokens.cache_write;
-        entry.reasoning += msg.tokens.reasoning;
-        entry.message_count += 1;
-        entry.cost += msg.cost;
-    }
-
-    let mut entries: Vec<ModelUsage> = model_map.into_values().collect();
-    entries.sort_by(|a, b| match (a.cost.is_nan(), b.cost.is_nan()) {
-        (true, true) => std::cmp::Ordering::Equal,
-        (true, false) => std::cmp::Ordering::Greater,
-        (false, true) => std::cmp::Ordering::Less,
-        (false, false) => b
-            .cost
-            .partial_cmp(&a.cost)
-            .unwrap_or(std::cmp::Ordering::Equal),
-    });
+/// Graph over caller-supplied `messages`, with no scanning: priced (unless
+/// `apply_pricing` is false), date-filtered and aggregated like a scanned graph
+#[napi]
+pub async fn aggregate_messages(
+    messages: Vec<ParsedMessage>,
+    options: AggregateOptions,
+) -> napi::Result<GraphResult> {
+    let start = Instant::now();
+    let pricing = aggregate_pricing(&options).await?;
+    Ok(graph_from_messages(&messages, &options, &pricing, start))
+}
+
+/// Model report over caller-supplied `messages`, with no scanning; see
+/// `aggregate_messages`
+#[napi]
+pub async fn report_messages(
+    messages: Vec<ParsedMessage>,
+    options: AggregateOptions,
+) -> napi::Result<ModelReport> {
+    let start = Instant::now();
+    let pricing = aggregate_pricing(&options).await?;
+    Ok(report_from_messages(&messages, &options, &pricing, start))
+}
+
+/// Pricing for `aggregate_messages`; the unpriced stand-in when messages keep
+/// their own costs
+async fn aggregate_pricing(
+    options: &AggregateOptions,
+) -> napi::Result<std::sync::Arc<pricing::PricingService>> {
+    if !options.apply_pricing.unwrap_or(true) {
+        return Ok(pricing::PricingService::unpriced());
+    }
+    report_pricing(&options.report_options())
+        .await
+        .map_err(error::to_napi)
+}
+
+/// `messages` priced with `pricing`, with negative token counts clamped and
+/// messages over `max_tokens_per_message` dropped. A supplied cost is kept for
+/// models the pricing doesn't know. Messages without a date get one from
+/// their timestamp.
+fn supplied_messages(
+    messages: &[ParsedMessage],
+    pricing: &pricing::PricingService,
+    max_tokens_per_message: Option<i64>,
+) -> (Vec<UnifiedMessage>, sessions::TokenSanitizeCounts) {
+    let mut unified = messages
+        .iter()
+        .map(|msg| {
+            let mut unified = parsed_to_unified(msg, msg.cost.unwrap_or(0.0));
+            if unified.date.is_empty() {
+                unified.set_timestamp(unified.timestamp);
+            }
+            if pricing.is_unpriced() {
+                // The supplied cost can't be split by token type
+                unified.cost_breakdown.unattributed_cost = unified.cost;
+            } else {
+                pricing.apply_or_keep_recorded(&mut unified);
+            }
+            unified
+        })
+        .collect();
+    let sanitized = sessions::sanitize_tokens(&mut unified, max_tokens_per_message);
+    (unified, sanitized)
+}
+
+fn graph_from_messages(
+    messages: &[ParsedMessage],
+    options: &AggregateOptions,
+    pricing: &pricing::PricingService,
+    start: Instant,
+) -> GraphResult {
+    let options = options.report_options();
+    let (messages, sanitized) =
+        supplied_messages(messages, pricing, options.max_tokens_per_message);
+    let (contributions, unpriced_models) = graph_contributions(messages, &options);
+    let result = graph_result(
+        contributions,
+        unpriced_models,
+        sanitized,
+        pricing,
+        &options,
+        aggregator::ActiveMetric::Cost,
+        start,
+    );
+    round_report(result, &options)
+}
+
+fn report_from_messages(
+    messages: &[ParsedMessage],
+    options: &AggregateOptions,
+    pricing: &pricing::PricingService,
+    start: Instant,
+) -> ModelReport {
+    let options = options.report_options();
+    let (messages, sanitized) =
+        supplied_messages(messages, pricing, options.max_tokens_per_message);
+    let filtered = filter_messages_for_report(messages, &options);
+    let report = ModelReport {
+        clamped_messages: sanitized.clamped as i32,
+        dropped_messages: sanitized.dropped as i32,
+        ..model_report(
+            aggregator::aggregate_by_model(&filtered),
+            pricing,
+            start.elapsed().as_millis() as u32,
+            options.separate_reasoning_cost,
+        )
+    };
+    round_report(report, &options)
+}
+
+/// Dollar value of prompt caching across `entries`; see [`ModelReport::cache_savings`]
+fn cache_savings(entries: &[ModelUsage], pricing: &pricing::PricingService) -> f64 {
+    entries
+        .iter()
+        .filter(|e| e.cache_read > 0)
+        .map(|e| pricing.cache_savings(&e.model, e.cache_read))
+        .sum()
+}
 
+/// Model report over finalized `entries`, with totals summed from them.
+/// Reasoning cost is folded into output unless `separate_reasoning_cost` is set.
+fn model_report(
+    entries: Vec<ModelUsage>,
+    pricing: &pricing::PricingService,
+    processing_time_ms: u32,
+    separate_reasoning_cost: Option<bool>,
+) -> ModelReport {
     let total_input: i64 = entries.iter().map(|e| e.input).sum();
     let total_output: i64 = entries.iter().map(|e| e.output).sum();
     let total_cache_read: i64 = entries.iter().map(|e| e.cache_read).sum();
     let total_cache_write: i64 = entries.iter().map(|e| e.cache_write).sum();
     let total_messages: i32 = entries.iter().map(|e| e.message_count).sum();
     let total_cost: f64 = entries.iter().map(|e| e.cost).sum();
+    let total_credits: f64 = entries.iter().map(|e| e.credits).sum();
+    let cost_breakdown = entries
+        .iter()
+        .fold(TokenCostBreakdown::default(), |mut acc, e| {
+            acc += &e.cost_breakdown;
+            acc
+        });
+    let cache_savings = cache_savings(&entries, pricing);
 
-    Ok(ModelReport {
+    let mut report = ModelReport {
         entries,
         total_input,
         total_output,
@@ -1082,434 +3273,1951 @@ pub async fn finalize_report(options: FinalizeReportOptions) -> napi::Result<Mod
         total_cache_write,
         total_messages,
         total_cost,
-        processing_time_ms: start.elapsed().as_millis() as u32,
-    })
+        cost_breakdown,
+        total_credits,
+        cache_savings,
+        processing_time_ms,
+        scan_stats: None,
+        messages: None,
+        clamped_messages: 0,
+        dropped_messages: 0,
+        parse_timing_histogram: None,
+    };
+    report_reasoning_cost(&mut report, separate_reasoning_cost);
+    report
 }
 
-/// Options for finalizing monthly report
+// =============================================================================
+// New Pricing API (Rust-native pricing fetching)
+// =============================================================================
+
 #[napi(object)]
 #[derive(Debug, Clone)]
-pub struct FinalizeMonthlyOptions {
-    pub home_dir: Option<String>,
-    pub local_messages: ParsedMessages,
-    pub include_cursor: bool,
-    pub since: Option<String>,
-    pub until: Option<String>,
-    pub year: Option<String>,
+pub struct NativePricing {
+    pub input_cost_per_token: f64,
+    pub output_cost_per_token: f64,
+    pub cache_read_input_token_cost: Option<f64>,
+    pub cache_creation_input_token_cost: Option<f64>,
+    /// What the costs are per: "per_token" or "per_million_tokens". Unset
+    /// means per token.
+    pub unit: Option<String>,
 }
 
-/// Finalize monthly report
-#[napi]
-pub async fn finalize_monthly_report(options: FinalizeMonthlyOptions) -> napi::Result<MonthlyReport> {
-    let start = Instant::now();
+const UNIT_PER_TOKEN: &str = "per_token";
+const UNIT_PER_MILLION_TOKENS: &str = "per_million_tokens";
+
+impl NativePricing {
+    /// `pricing`'s rates, scaled to per million tokens when `per_million`
+    fn from_model_pricing(pricing: &pricing::ModelPricing, per_million: bool) -> Self {
+        let scale = if per_million { 1_000_000.0 } else { 1.0 };
+        Self {
+            input_cost_per_token: pricing.input_cost_per_token.unwrap_or(0.0) * scale,
+            output_cost_per_token: pricing.output_cost_per_token.unwrap_or(0.0) * scale,
+            cache_read_input_token_cost: pricing.cache_read_input_token_cost.map(|c| c * scale),
+            cache_creation_input_token_cost: pricing
+                .cache_creation_input_token_cost
+                .map(|c| c * scale),
+            unit: Some(
+                if per_million {
+                    UNIT_PER_MILLION_TOKENS
+                } else {
+                    UNIT_PER_TOKEN
+                }
+                .to_string(),
+            ),
+        }
+    }
+
+    /// Per-token rates, whatever `unit` they were given in
+    fn to_model_pricing(&self) -> Result<pricing::ModelPricing, String> {
+        let scale = match self.unit.as_deref() {
+            None | Some(UNIT_PER_TOKEN) => 1.0,
+            Some(UNIT_PER_MILLION_TOKENS) => 1.0 / 1_000_000.0,
+            Some(other) => {
+                return Err(format!(
+                    "Unknown pricing unit: {} (expected \"{}\" or \"{}\")",
+                    other, UNIT_PER_TOKEN, UNIT_PER_MILLION_TOKENS
+                ))
+            }
+        };
+        Ok(pricing::ModelPricing {
+            input_cost_per_token: Some(self.input_cost_per_token * scale),
+            output_cost_per_token: Some(self.output_cost_per_token * scale),
+            cache_read_input_token_cost: self.cache_read_input_token_cost.map(|c| c * scale),
+            cache_creation_input_token_cost: self
+                .cache_creation_input_token_cost
+                .map(|c| c * scale),
+            ..Default::default()
+        })
+    }
+}
+
+#[napi(object)]
+pub struct PricingLookupResult {
+    pub model_id: String,
+    pub matched_key: String,
+    pub source: String,
+    pub pricing: NativePricing,
+}
+
+/// Pricing resolved for one model id
+#[napi(object)]
+pub struct ResolvedModelPricing {
+    pub model_id: String,
+    pub matched_key: String,
+    /// "LiteLLM", "OpenRouter", or "none" when no pricing matched
+    pub source: String,
+    pub input_cost_per_token: Option<f64>,
+    pub output_cost_per_token: Option<f64>,
+    pub cache_read: Option<f64>,
+    pub cache_write: Option<f64>,
+}
+
+impl From<pricing::ResolvedModelPricing> for ResolvedModelPricing {
+    fn from(resolved: pricing::ResolvedModelPricing) -> Self {
+        Self {
+            model_id: resolved.model_id,
+            matched_key: resolved.matched_key,
+            source: resolved.source,
+            input_cost_per_token: resolved.input_cost_per_token,
+            output_cost_per_token: resolved.output_cost_per_token,
+            cache_read: resolved.cache_read,
+            cache_write: resolved.cache_write,
+        }
+    }
+}
 
+/// Pricing resolved for every distinct model in the parsed messages, for auditing costs
+#[napi]
+pub async fn dump_resolved_pricing(
+    options: ReportOptions,
+) -> napi::Result<Vec<ResolvedModelPricing>> {
     let home_dir = get_home_dir(&options.home_dir)?;
 
-    let pricing = pricing::PricingService::get_or_init()
+    let sources = scanner::sources_or_default(options.sources.as_deref());
+
+    let date_source = check_report_options(&options).map_err(error::to_napi)?;
+    let pricing = report_pricing(&options).await.map_err(error::to_napi)?;
+    let exclude_dirs = scanner::exclude_dirs_or_default(options.exclude_dirs.as_deref());
+    let scan = ScanOptions::for_report(&options, date_source, &exclude_dirs);
+    let (all_messages, _) = parse_all_messages_with_pricing(&home_dir, &sources, &pricing, &scan);
+    let filtered = filter_messages_for_report(all_messages, &options);
+
+    Ok(pricing
+        .resolve_models(filtered.iter().map(|msg| msg.model_id.as_str()))
+        .into_iter()
+        .map(ResolvedModelPricing::from)
+        .collect())
+}
+
+/// Loaded pricing data summary
+#[napi(object)]
+pub struct PricingStatus {
+    pub litellm_models: i32,
+    pub openrouter_models: i32,
+    /// RFC 3339 time the pricing data was loaded
+    pub fetched_at: String,
+    /// Whether the data came from the on-disk cache rather than a full download
+    pub from_cache: bool,
+}
+
+impl From<pricing::PricingStatus> for PricingStatus {
+    fn from(status: pricing::PricingStatus) -> Self {
+        Self {
+            litellm_models: status.litellm_models as i32,
+            openrouter_models: status.openrouter_models as i32,
+            fetched_at: status.fetched_at,
+            from_cache: status.from_cache,
+        }
+    }
+}
+
+/// Refetch pricing, bypassing the cache TTL, and return the new status
+#[napi]
+pub async fn refresh_pricing() -> napi::Result<PricingStatus> {
+    let service = pricing::PricingService::refresh()
         .await
-        .map_err(|e| napi::Error::from_reason(e))?;
+        .map_err(|e| error::to_napi(ErrorCode::PricingFetch.reason(e)))?;
 
-    // Convert local messages and apply pricing
-    let mut all_messages: Vec<UnifiedMessage> = options
-        .local_messages
-        .messages
-        .iter()
-        .map(|msg| {
-            let cost = pricing.calculate_cost(
-                &msg.model_id,
-                msg.input,
-                msg.output,
-                msg.cache_read,
-                msg.cache_write,
-                msg.reasoning,
-            );
-            parsed_to_unified(msg, cost)
-        })
-        .collect();
+    Ok(service.status().into())
+}
 
-    // Add Cursor messages if enabled
-    if options.include_cursor {
-        let cursor_cache_dir = format!("{}/.config/tokscale/cursor-cache", home_dir);
-        let cursor_files = scanner::scan_directory(&cursor_cache_dir, "usage*.csv");
+/// Status of the loaded pricing data, or `null` if pricing has not been fetched yet
+#[napi]
+pub async fn pricing_status() -> Option<PricingStatus> {
+    pricing::PricingService::current()
+        .await
+        .map(|service| service.status().into())
+}
 
-        let cursor_messages: Vec<UnifiedMessage> = cursor_files
-            .par_iter()
-            .flat_map(|path| {
-                sessions::cursor::parse_cursor_file(path)
-                    .into_iter()
-                    .map(|mut msg| {
-                        let csv_cost = msg.cost;
-                        let calculated_cost = pricing.calculate_cost(
-                            &msg.model_id,
-                            msg.tokens.input,
-                            msg.tokens.output,
-                            msg.tokens.cache_read,
-                            msg.tokens.cache_write,
-                            msg.tokens.reasoning,
-                        );
-                        msg.cost = if calculated_cost > 0.0 {
-                            calculated_cost
-                        } else {
-                            csv_cost
-                        };
-                        msg
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect();
+/// What a hypothetical usage would cost on one model
+#[napi(object)]
+pub struct CostEstimate {
+    pub model_id: String,
+    /// Whether any pricing applied; when not, every cost is 0
+    pub matched: bool,
+    /// Pricing entry used; empty when unmatched or estimated
+    pub matched_key: String,
+    /// "LiteLLM", "OpenRouter", "Estimated", or "none" when unmatched
+    pub source: String,
+    pub cost_breakdown: TokenCostBreakdown,
+    pub total_cost: f64,
+}
 
-        all_messages.extend(cursor_messages);
+fn cost_estimate(
+    pricing: &pricing::PricingService,
+    model_id: String,
+    tokens: &TokenBreakdown,
+    separate_reasoning_cost: Option<bool>,
+) -> CostEstimate {
+    match pricing.estimate_cost(&model_id, tokens) {
+        Some((resolved, mut cost_breakdown)) => {
+            if !separate_reasoning_cost.unwrap_or(false) {
+                cost_breakdown.fold_reasoning_into_output();
+            }
+            CostEstimate {
+                model_id,
+                matched: true,
+                matched_key: resolved.matched_key,
+                source: resolved.source,
+                total_cost: cost_breakdown.total(),
+                cost_breakdown,
+            }
+        }
+        None => CostEstimate {
+            model_id,
+            matched: false,
+            matched_key: String::new(),
+            source: "none".to_string(),
+            cost_breakdown: TokenCostBreakdown::default(),
+            total_cost: 0.0,
+        },
     }
+}
 
-    // Apply date filters
-    if let Some(year) = &options.year {
-        let year_prefix = format!("{}-", year);
-        all_messages.retain(|m| m.date.starts_with(&year_prefix));
+/// Cost of `tokens` on `model_id` under the loaded pricing, e.g. to see what
+/// last month's usage would have cost on another model. An unknown model
+/// comes back with `matched: false` and zero cost. Reasoning cost is folded
+/// into output unless `separate_reasoning_cost` is set.
+#[napi]
+pub async fn estimate_cost(
+    model_id: String,
+    tokens: TokenBreakdown,
+    separate_reasoning_cost: Option<bool>,
+) -> napi::Result<CostEstimate> {
+    let service = pricing::PricingService::get_or_init()
+        .await
+        .map_err(|e| error::to_napi(ErrorCode::PricingFetch.reason(e)))?;
+
+    Ok(cost_estimate(
+        &service,
+        model_id,
+        &tokens,
+        separate_reasoning_cost,
+    ))
+}
+
+/// Pricing for `model_id`, optionally forced to one source ("litellm" or
+/// "openrouter"). With `per_million`, rates are per million tokens.
+#[napi]
+pub async fn lookup_pricing(
+    model_id: String,
+    provider: Option<String>,
+    per_million: Option<bool>,
+) -> napi::Result<PricingLookupResult> {
+    let service = pricing::PricingService::get_or_init()
+        .await
+        .map_err(|e| error::to_napi(ErrorCode::PricingFetch.reason(e)))?;
+
+    pricing_lookup(
+        &service,
+        model_id,
+        provider.as_deref(),
+        per_million.unwrap_or(false),
+    )
+    .map_err(error::to_napi)
+}
+
+fn pricing_lookup(
+    service: &pricing::PricingService,
+    model_id: String,
+    force_source: Option<&str>,
+    per_million: bool,
+) -> Result<PricingLookupResult, String> {
+    match service.lookup_with_source(&model_id, force_source) {
+        Some(result) => Ok(PricingLookupResult {
+            model_id,
+            matched_key: result.matched_key,
+            source: result.source,
+            pricing: NativePricing::from_model_pricing(&result.pricing, per_million),
+        }),
+        None => Err(ErrorCode::ModelNotFound.reason(format!(
+            "Model not found: {}{}",
+            model_id,
+            force_source
+                .map(|s| format!(" (forced source: {})", s))
+                .unwrap_or_default()
+        ))),
     }
-    if let Some(since) = &options.since {
-        all_messages.retain(|m| m.date.as_str() >= since.as_str());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_native_pricing_units() {
+        let model = pricing::ModelPricing {
+            input_cost_per_token: Some(0.000003),
+            output_cost_per_token: Some(0.000015),
+            cache_read_input_token_cost: Some(3e-7),
+            cache_creation_input_token_cost: None,
+            ..Default::default()
+        };
+
+        let per_token = NativePricing::from_model_pricing(&model, false);
+        assert_eq!(per_token.unit.as_deref(), Some("per_token"));
+        assert_eq!(per_token.input_cost_per_token, 0.000003);
+        assert_eq!(per_token.cache_creation_input_token_cost, None);
+
+        let per_million = NativePricing::from_model_pricing(&model, true);
+        assert_eq!(per_million.unit.as_deref(), Some("per_million_tokens"));
+        assert!((per_million.input_cost_per_token - 3.0).abs() < 1e-9);
+        assert!((per_million.output_cost_per_token - 15.0).abs() < 1e-9);
+        assert!((per_million.cache_read_input_token_cost.unwrap() - 0.3).abs() < 1e-9);
+
+        // Either unit reads back as the same per-token rates
+        let round_trip = per_million.to_model_pricing().unwrap();
+        assert!((round_trip.output_cost_per_token.unwrap() - 0.000015).abs() < 1e-15);
+        let unlabeled = NativePricing {
+            unit: None,
+            ..per_token
+        };
+        assert_eq!(
+            unlabeled.to_model_pricing().unwrap().input_cost_per_token,
+            Some(0.000003)
+        );
+        let bogus = NativePricing {
+            unit: Some("per_kilo".to_string()),
+            ..per_million
+        };
+        assert!(bogus.to_model_pricing().is_err());
     }
-    if let Some(until) = &options.until {
-        all_messages.retain(|m| m.date.as_str() <= until.as_str());
+
+    #[test]
+    fn test_error_codes_for_home_and_dates() {
+        let err = resolve_home_dir(&None, None).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::NoHome));
+        assert!(err.starts_with("E_NO_HOME: "));
+        assert_eq!(
+            resolve_home_dir(&None, Some("/home/me".to_string())).unwrap(),
+            "/home/me"
+        );
+        assert_eq!(
+            resolve_home_dir(&Some("/custom".to_string()), Some("/home/me".to_string())).unwrap(),
+            "/custom"
+        );
+
+        let invalid = [
+            ReportOptions {
+                year: Some("25".to_string()),
+                ..Default::default()
+            },
+            ReportOptions {
+                since: Some("2025-13-01".to_string()),
+                ..Default::default()
+            },
+            ReportOptions {
+                until: Some("last week".to_string()),
+                ..Default::default()
+            },
+            ReportOptions {
+                since: Some("2025-06-02".to_string()),
+                until: Some("2025-06-01".to_string()),
+                ..Default::default()
+            },
+            ReportOptions {
+                date_source: Some("ctime".to_string()),
+                ..Default::default()
+            },
+        ];
+        for options in &invalid {
+            let err = report_date_source(options).unwrap_err();
+            assert!(err.starts_with("E_INVALID_DATE: "), "{}", err);
+        }
+
+        let valid = ReportOptions {
+            year: Some("2025".to_string()),
+            since: Some("2025-06-01".to_string()),
+            until: Some("2025-06-01".to_string()),
+            date_source: Some("mtime".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(report_date_source(&valid), Ok(sessions::DateSource::Mtime));
+    }
+
+    #[test]
+    fn test_error_codes_for_files_and_lookups() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("session.jsonl");
+        std::fs::write(&file, "not a zip").unwrap();
+        let missing = dir.path().join("missing.jsonl");
+
+        let cases = [
+            (
+                sessions::parse_source_file(&file, "chatgpt").map(|_| ()),
+                ErrorCode::UnknownSource,
+            ),
+            (
+                sessions::parse_source_file(&missing, "claude").map(|_| ()),
+                ErrorCode::ReadFailed,
+            ),
+            (
+                sessions::headless::validate_headless_file(&file, "cursor").map(|_| ()),
+                ErrorCode::UnknownSource,
+            ),
+            (
+                sessions::headless::validate_headless_file(&missing, "claude").map(|_| ()),
+                ErrorCode::ReadFailed,
+            ),
+            (
+                read_archive(&missing.to_string_lossy()).map(|_| ()),
+                ErrorCode::ReadFailed,
+            ),
+            (
+                read_archive(&file.to_string_lossy()).map(|_| ()),
+                ErrorCode::ReadFailed,
+            ),
+            // Not a directory, so it can't be removed as one
+            (watermark::reset_all_at(&file), ErrorCode::ReadFailed),
+            (
+                pricing_lookup(
+                    &pricing::PricingService::unpriced(),
+                    "gpt-nonexistent".to_string(),
+                    None,
+                    false,
+                )
+                .map(|_| ()),
+                ErrorCode::ModelNotFound,
+            ),
+        ];
+        for (result, code) in cases {
+            let err = result.unwrap_err();
+            assert_eq!(ErrorCode::of(&err), Some(code), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_graph_meta_bytes_read_matches_fixtures() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        let codex = home.path().join(".codex/sessions/2025/06/01");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&codex).unwrap();
+        let fixtures = [
+            (
+                project.join("a.jsonl"),
+                r#"{"type":"assistant","timestamp":"2025-06-01T10:00:00.000Z","message":{"model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}"#,
+            ),
+            (
+                codex.join("rollout-1.jsonl"),
+                r#"{"type":"event_msg","timestamp":"2025-06-01T11:00:00Z","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":200,"output_tokens":20}}}}"#,
+            ),
+        ];
+        for (path, content) in &fixtures {
+            std::fs::write(path, content).unwrap();
+        }
+
+        let home_dir = home.path().to_string_lossy().into_owned();
+        let sources = ["claude".to_string(), "codex".to_string()];
+        let pricing = pricing::PricingService::unpriced();
+        let scan = ScanOptions::new(sessions::DateSource::Timestamp, &[]);
+        let mut messages = Vec::new();
+        let scan_result =
+            for_each_source_with_pricing(&home_dir, &sources, &pricing, &scan, |batch| {
+                messages.extend(batch)
+            });
+        let mut result = aggregator::generate_graph_result(
+            aggregator::aggregate_by_date(&messages, false),
+            0,
+            aggregator::ActiveMetric::Cost,
+        );
+        record_scan(&mut result, &scan_result, &scan.progress);
+
+        let expected: usize = fixtures.iter().map(|(_, content)| content.len()).sum();
+        assert_eq!(result.meta.bytes_read, expected as i64);
+        assert_eq!(result.meta.files_parsed, 2);
+    }
+
+    #[test]
+    fn test_progress_reaches_files_total() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        std::fs::create_dir_all(&project).unwrap();
+        let line = r#"{"type":"assistant","timestamp":"2025-06-01T10:00:00.000Z","message":{"model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}"#;
+        for i in 0..3 {
+            std::fs::write(project.join(format!("{}.jsonl", i)), line).unwrap();
+        }
+
+        let updates = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = updates.clone();
+        let progress = progress::Progress::new(Box::new(move |update: ScanProgress| {
+            recorded.lock().unwrap().push(update);
+        }));
+        let home_dir = home.path().to_string_lossy().into_owned();
+        let scan = ScanOptions {
+            progress,
+            ..ScanOptions::new(sessions::DateSource::Timestamp, &[])
+        };
+        let scan_result = for_each_source_with_pricing(
+            &home_dir,
+            &["claude".to_string()],
+            &pricing::PricingService::unpriced(),
+            &scan,
+            |_| {},
+        );
+
+        let updates = updates.lock().unwrap();
+        let last = updates.iter().max_by_key(|u| u.files_done).unwrap();
+        assert_eq!(scan_result.total_files(), 3);
+        assert_eq!((last.files_done, last.files_total), (3, 3));
+        assert_eq!(last.source, "claude");
+    }
+
+    #[test]
+    fn test_parse_timing_histogram_counts_every_file() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        let codex = home.path().join(".codex/sessions/2025/06/01");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&codex).unwrap();
+        let line = r#"{"type":"assistant","timestamp":"2025-06-01T10:00:00.000Z","message":{"model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}"#;
+        for i in 0..4 {
+            std::fs::write(project.join(format!("{}.jsonl", i)), line).unwrap();
+        }
+        std::fs::write(codex.join("rollout-1.jsonl"), "").unwrap();
+
+        let home_dir = home.path().to_string_lossy().into_owned();
+        let sources = ["claude".to_string(), "codex".to_string()];
+        let parse_with = |scan: &ScanOptions| {
+            for_each_source_with_pricing(
+                &home_dir,
+                &sources,
+                &pricing::PricingService::unpriced(),
+                scan,
+                |_| {},
+            )
+        };
+
+        assert!(progress::Progress::silent().parse_timing().is_none());
+
+        let scan = ScanOptions {
+            progress: progress::Progress::silent().profiled(),
+            ..ScanOptions::new(sessions::DateSource::Timestamp, &[])
+        };
+        let stats = parse_with(&scan).stats();
+        let timing = scan.progress.parse_timing().unwrap();
+        let files = |source: &str| -> u32 {
+            let histogram = timing.iter().find(|t| t.source == source).unwrap();
+            histogram.buckets.iter().map(|b| b.count).sum()
+        };
+
+        assert_eq!(files("claude"), stats.claude_files as u32);
+        assert_eq!(files("codex"), stats.codex_files as u32);
+        assert_eq!((stats.claude_files, stats.codex_files), (4, 1));
+        assert!(timing.iter().flat_map(|t| &t.buckets).all(|b| b.count > 0));
+    }
+
+    #[test]
+    fn test_max_inflight_files_matches_unbounded() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        let codex = home.path().join(".codex/sessions/2025/06/01");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&codex).unwrap();
+        for i in 0..5 {
+            let claude = format!(
+                r#"{{"type":"assistant","timestamp":"2025-06-0{}T10:00:00.000Z","requestId":"req_{}","message":{{"id":"msg_{}","model":"claude-sonnet-4","usage":{{"input_tokens":{},"output_tokens":50}}}}}}"#,
+                i + 1,
+                i,
+                i,
+                100 * (i + 1)
+            );
+            std::fs::write(project.join(format!("{}.jsonl", i)), claude).unwrap();
+            let rollout = format!(
+                r#"{{"type":"event_msg","timestamp":"2025-06-0{}T11:00:00Z","payload":{{"type":"token_count","info":{{"last_token_usage":{{"input_tokens":200,"output_tokens":{}}}}}}}}}"#,
+                i + 1,
+                10 * (i + 1)
+            );
+            std::fs::write(codex.join(format!("rollout-{}.jsonl", i)), rollout).unwrap();
+        }
+        // A copy of one session in another project must still dedup across batches
+        let copy = home.path().join(".claude/projects/-Users-me-copy");
+        std::fs::create_dir_all(&copy).unwrap();
+        std::fs::copy(project.join("0.jsonl"), copy.join("0.jsonl")).unwrap();
+
+        let home_dir = home.path().to_string_lossy().into_owned();
+        let sources = ["claude".to_string(), "codex".to_string()];
+        let pricing = pricing::PricingService::unpriced();
+        let graph_with = |max_inflight_files: Option<usize>| {
+            let mut acc = aggregator::DateAccumulator::new(false);
+            let mut batches = 0;
+            let scan = ScanOptions {
+                max_inflight_files,
+                ..ScanOptions::new(sessions::DateSource::Timestamp, &[])
+            };
+            for_each_source_with_pricing(&home_dir, &sources, &pricing, &scan, |messages| {
+                batches += 1;
+                acc.add(&messages);
+            });
+            let mut result =
+                aggregator::generate_graph_result(acc.finish(), 0, aggregator::ActiveMetric::Cost);
+            result.meta.generated_at.clear();
+            (graph_result_to_json(result, false), batches)
+        };
+
+        let (unbounded, unbounded_batches) = graph_with(None);
+        let (bounded, bounded_batches) = graph_with(Some(2));
+        assert_eq!(bounded, unbounded);
+        assert!(bounded_batches > unbounded_batches);
+        // Claude 1500 input + 250 output once, despite the copy; Codex 1000 + 150
+        let graph: serde_json::Value = serde_json::from_str(&bounded).unwrap();
+        assert_eq!(graph["summary"]["totalTokens"], 2900);
+    }
+
+    #[tokio::test]
+    async fn test_since_last_keeps_a_watermark_per_source_scope() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        let codex = home.path().join(".codex/sessions");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&codex).unwrap();
+        std::fs::write(
+            project.join("session.jsonl"),
+            r#"{"type":"assistant","timestamp":"2025-06-02T10:00:00.000Z","requestId":"req_1","message":{"id":"msg_1","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}"#,
+        )
+        .unwrap();
+        // Older than the Claude message, so a shared watermark would skip it
+        std::fs::write(
+            codex.join("rollout-1.jsonl"),
+            r#"{"type":"event_msg","timestamp":"2025-06-01T11:00:00Z","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":200,"output_tokens":10}}}}"#,
+        )
+        .unwrap();
+
+        let watermarks = TempDir::new().unwrap();
+        let since_last = |sources: Option<&[&str]>| {
+            let options = ReportOptions {
+                home_dir: Some(home.path().to_string_lossy().into_owned()),
+                sources: sources.map(|s| s.iter().map(|s| s.to_string()).collect()),
+                skip_pricing: Some(true),
+                ..Default::default()
+            };
+            async {
+                model_report_since_last(options, watermarks.path())
+                    .await
+                    .unwrap()
+                    .total_messages
+            }
+        };
+
+        assert_eq!(since_last(Some(&["claude"])).await, 1);
+        assert_eq!(since_last(Some(&["codex"])).await, 1);
+        assert_eq!(since_last(Some(&["claude"])).await, 0);
+        assert_eq!(since_last(Some(&["codex"])).await, 0);
+        // Every source together is a scope of its own, however it's spelled
+        assert_eq!(since_last(None).await, 2);
+        assert_eq!(since_last(None).await, 0);
+        let every_source = scanner::sources_or_default(None);
+        let mut every_source: Vec<&str> = every_source.iter().map(String::as_str).rev().collect();
+        assert_eq!(since_last(Some(&every_source)).await, 0);
+        every_source.push("claude");
+        assert_eq!(since_last(Some(&every_source)).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_model_report_times_parses_with_profile() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("session.jsonl"),
+            r#"{"type":"assistant","timestamp":"2025-06-02T10:00:00.000Z","requestId":"req_1","message":{"id":"msg_1","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}"#,
+        )
+        .unwrap();
+
+        let watermarks = TempDir::new().unwrap();
+        let report = |profile: Option<bool>| {
+            let options = ReportOptions {
+                home_dir: Some(home.path().to_string_lossy().into_owned()),
+                sources: Some(vec!["claude".to_string()]),
+                skip_pricing: Some(true),
+                profile,
+                ..Default::default()
+            };
+            async {
+                model_report_since_last(options, watermarks.path())
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert!(report(None).await.parse_timing_histogram.is_none());
+        let timing = report(Some(true)).await.parse_timing_histogram.unwrap();
+        assert_eq!(timing.len(), 1);
+        assert_eq!(timing[0].source, "claude");
+    }
+
+    #[tokio::test]
+    async fn test_today_summary_sums_only_today() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        std::fs::create_dir_all(&project).unwrap();
+        let line = |id: usize, at: chrono::DateTime<chrono::Local>, input: i64| {
+            format!(
+                r#"{{"type":"assistant","timestamp":"{}","requestId":"req_{id}","message":{{"id":"msg_{id}","model":"claude-sonnet-4","usage":{{"input_tokens":{input},"output_tokens":0}}}}}}"#,
+                at.to_utc()
+                    .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+            )
+        };
+        let now = chrono::Local::now();
+        let day = chrono::Duration::days(1);
+        // Three days in one live session file
+        let lines = [
+            line(1, now - day * 2, 1),
+            line(2, now - day, 10),
+            line(3, now, 100),
+            line(4, now, 1_000),
+        ];
+        std::fs::write(project.join("live.jsonl"), lines.join("\n")).unwrap();
+        // Untouched since yesterday, so never parsed even though it claims today
+        let stale = project.join("stale.jsonl");
+        std::fs::write(&stale, line(5, now, 10_000)).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified((now - day * 2).into())
+            .unwrap();
+
+        let options = ReportOptions {
+            home_dir: Some(home.path().to_string_lossy().into_owned()),
+            sources: Some(vec!["claude".to_string()]),
+            skip_pricing: Some(true),
+            ..Default::default()
+        };
+        let totals = today_totals(options).await.unwrap();
+        assert_eq!(totals.tokens, 1_100);
+        assert_eq!(totals.messages, 2);
+        assert_eq!(totals.cost, 0.0);
     }
 
-    // Aggregate by month
-    let mut month_map: std::collections::HashMap<String, MonthAggregator> =
-        std::collections::HashMap::new();
+    #[tokio::test]
+    async fn test_low_memory_reports_match_collected_ones() {
+        let home = TempDir::new().unwrap();
+        for (project, month) in [
+            ("-Users-me-app", "05"),
+            ("-Users-me-api", "06"),
+            ("-Users-me-web", "07"),
+        ] {
+            let dir = home.path().join(".claude/projects").join(project);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(
+                dir.join("session.jsonl"),
+                format!(
+                    r#"{{"type":"assistant","timestamp":"2025-{month}-01T10:00:00.000Z","requestId":"req_{project}","message":{{"id":"msg_{project}","model":"claude-sonnet-4","usage":{{"input_tokens":100,"output_tokens":50}}}}}}"#
+                ),
+            )
+            .unwrap();
+        }
 
-    for msg in all_messages {
-        let month = if msg.date.len() >= 7 {
-            msg.date[..7].to_string()
-        } else {
-            continue;
+        let options = |low_memory: bool| ReportOptions {
+            home_dir: Some(home.path().to_string_lossy().into_owned()),
+            sources: Some(vec!["claude".to_string()]),
+            skip_pricing: Some(true),
+            low_memory: Some(low_memory),
+            max_inflight_files: Some(1),
+            since: Some("2025-06-01".to_string()),
+            ..Default::default()
         };
 
-        let entry = month_map.entry(month).or_default();
-        entry.models.insert(msg.model_id.clone());
-        entry.input += msg.tokens.input;
-        entry.output += msg.tokens.output;
-        entry.cache_read += msg.tokens.cache_read;
-        entry.cache_write += msg.tokens.cache_write;
-        entry.message_count += 1;
-        entry.cost += msg.cost;
+        let months = |report: MonthlyReport| {
+            report
+                .entries
+                .into_iter()
+                .map(|e| (e.month, e.input))
+                .collect::<Vec<_>>()
+        };
+        let collected = months(monthly_report(options(false)).await.unwrap());
+        assert_eq!(
+            collected,
+            [("2025-06".to_string(), 100), ("2025-07".to_string(), 100)]
+        );
+        assert_eq!(
+            months(monthly_report(options(true)).await.unwrap()),
+            collected
+        );
+
+        let quarters = |report: QuarterlyReport| {
+            report
+                .entries
+                .into_iter()
+                .map(|e| (e.quarter, e.message_count))
+                .collect::<Vec<_>>()
+        };
+        let collected = quarters(quarterly_report(options(false)).await.unwrap());
+        assert_eq!(
+            collected,
+            [("2025-Q2".to_string(), 1), ("2025-Q3".to_string(), 1)]
+        );
+        assert_eq!(
+            quarters(quarterly_report(options(true)).await.unwrap()),
+            collected
+        );
+
+        let projects = |report: ProjectReport| {
+            let mut projects: Vec<_> = report
+                .entries
+                .into_iter()
+                .map(|e| (e.project, e.output))
+                .collect();
+            projects.sort();
+            projects
+        };
+        let collected = projects(project_report(options(false)).await.unwrap());
+        assert_eq!(collected.len(), 2);
+        assert_eq!(
+            projects(project_report(options(true)).await.unwrap()),
+            collected
+        );
     }
 
-    let mut entries: Vec<MonthlyUsage> = month_map
-        .into_iter()
-        .map(|(month, agg)| MonthlyUsage {
-            month,
-            models: agg.models.into_iter().collect(),
-            input: agg.input,
-            output: agg.output,
-            cache_read: agg.cache_read,
-            cache_write: agg.cache_write,
-            message_count: agg.message_count,
-            cost: agg.cost,
-        })
-        .collect();
+    #[test]
+    fn test_mtime_window_skips_files_outside_year() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        std::fs::create_dir_all(&project).unwrap();
+        for (name, modified) in [
+            ("old.jsonl", "2024-06-01T12:00:00Z"),
+            ("this.jsonl", "2025-06-01T12:00:00Z"),
+            ("new.jsonl", "2026-02-01T12:00:00Z"),
+        ] {
+            let path = project.join(name);
+            std::fs::write(
+                &path,
+                r#"{"type":"assistant","timestamp":"2025-06-01T10:00:00.000Z","message":{"model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50}}}"#,
+            )
+            .unwrap();
+            let modified = chrono::DateTime::parse_from_rfc3339(modified).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified.into())
+                .unwrap();
+        }
 
-    entries.sort_by(|a, b| a.month.cmp(&b.month));
-    let total_cost: f64 = entries.iter().map(|e| e.cost).sum();
+        let options = ReportOptions {
+            year: Some("2025".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            report_mtime_window(&options, sessions::DateSource::Timestamp),
+            None
+        );
+        assert_eq!(
+            report_mtime_window(&ReportOptions::default(), sessions::DateSource::Mtime),
+            None
+        );
+        let window = report_mtime_window(&options, sessions::DateSource::Mtime);
+        assert!(window.is_some());
+
+        let home_dir = home.path().to_string_lossy().into_owned();
+        let sources = ["claude".to_string()];
+        let pricing = pricing::PricingService::unpriced();
+        let (messages, scan_result) = parse_all_messages_with_pricing(
+            &home_dir,
+            &sources,
+            &pricing,
+            &ScanOptions {
+                mtime_window: window,
+                ..ScanOptions::new(sessions::DateSource::Mtime, &[])
+            },
+        );
+        assert_eq!(scan_result.claude_files.len(), 1);
+        assert!(scan_result.claude_files[0].ends_with("this.jsonl"));
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].date.starts_with("2025-"));
+
+        // The report is the same as parsing everything and filtering afterwards
+        let (all, _) = parse_all_messages_with_pricing(
+            &home_dir,
+            &sources,
+            &pricing,
+            &ScanOptions::new(sessions::DateSource::Mtime, &[]),
+        );
+        assert_eq!(all.len(), 3);
+        assert_eq!(filter_messages_for_report(all, &options).len(), 1);
+
+        // since/until narrow the year further
+        let narrowed = ReportOptions {
+            since: Some("2025-07-01".to_string()),
+            ..options
+        };
+        let window = report_mtime_window(&narrowed, sessions::DateSource::Mtime);
+        let (messages, _) = parse_all_messages_with_pricing(
+            &home_dir,
+            &sources,
+            &pricing,
+            &ScanOptions {
+                mtime_window: window,
+                ..ScanOptions::new(sessions::DateSource::Mtime, &[])
+            },
+        );
+        assert!(messages.is_empty());
+    }
 
-    Ok(MonthlyReport {
-        entries,
-        total_cost,
-        processing_time_ms: start.elapsed().as_millis() as u32,
-    })
-}
+    #[test]
+    #[serial_test::serial]
+    fn test_mtime_date_source_still_dedups_headless_copies() {
+        let home = TempDir::new().unwrap();
+        let interactive = home.path().join(".claude/projects/-Users-me-app/abc.jsonl");
+        let headless = home
+            .path()
+            .join(".config/tokscale/headless/claude/abc.json");
+        for (path, content, modified) in [
+            (
+                &interactive,
+                r#"{"type":"assistant","timestamp":"2024-12-01T10:00:00.412Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50}}}"#,
+                "2025-03-15T12:00:00Z",
+            ),
+            (
+                &headless,
+                r#"{"type":"result","timestamp":"2024-12-01T10:00:00Z","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50}}"#,
+                "2025-03-16T12:00:00Z",
+            ),
+        ] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+            let modified = chrono::DateTime::parse_from_rfc3339(modified).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(modified.into())
+                .unwrap();
+        }
 
-/// Options for finalizing graph
-#[napi(object)]
-#[derive(Debug, Clone)]
-pub struct FinalizeGraphOptions {
-    pub home_dir: Option<String>,
-    pub local_messages: ParsedMessages,
-    pub include_cursor: bool,
-    pub since: Option<String>,
-    pub until: Option<String>,
-    pub year: Option<String>,
-}
+        let previous = std::env::var("TOKSCALE_HEADLESS_DIR").ok();
+        std::env::remove_var("TOKSCALE_HEADLESS_DIR");
+        let home_dir = home.path().to_string_lossy().into_owned();
+        let pricing = pricing::PricingService::unpriced();
+        let (messages, _) = parse_all_messages_with_pricing(
+            &home_dir,
+            &["claude".to_string()],
+            &pricing,
+            &ScanOptions::new(sessions::DateSource::Mtime, &[]),
+        );
+        if let Some(previous) = previous {
+            std::env::set_var("TOKSCALE_HEADLESS_DIR", previous);
+        }
 
-/// Finalize graph
-#[napi]
-pub async fn finalize_graph(options: FinalizeGraphOptions) -> napi::Result<GraphResult> {
-    let start = Instant::now();
+        // The interactive copy wins and takes its own file's date
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].project.as_deref(), Some("-Users-me-app"));
+        assert_eq!(messages[0].date, "2025-03-15");
+    }
 
-    let home_dir = get_home_dir(&options.home_dir)?;
+    #[test]
+    fn test_mtime_date_source_restamps_openclaw() {
+        let home = TempDir::new().unwrap();
+        let agent = home.path().join(".openclaw/agents/main");
+        std::fs::create_dir_all(&agent).unwrap();
+        let session = agent.join("session-abc.jsonl");
+        std::fs::write(
+            &session,
+            r#"{"type":"model_change","provider":"anthropic","modelId":"claude-3.5-sonnet"}
+{"type":"message","id":"msg1","message":{"role":"assistant","content":[],"usage":{"input":100,"output":50},"timestamp":1700000000000}}"#,
+        )
+        .unwrap();
+        let index = agent.join("sessions.json");
+        std::fs::write(
+            &index,
+            format!(
+                r#"{{"agent:main:main": {{"sessionId": "abc-123", "sessionFile": "{}"}}}}"#,
+                session.to_string_lossy().replace('\\', "\\\\")
+            ),
+        )
+        .unwrap();
+        let modified = chrono::DateTime::parse_from_rfc3339("2025-03-15T12:00:00Z").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&index)
+            .unwrap()
+            .set_modified(modified.into())
+            .unwrap();
+
+        let home_dir = home.path().to_string_lossy().into_owned();
+        let pricing = pricing::PricingService::unpriced();
+        let parse = |date_source| {
+            parse_all_messages_with_pricing(
+                &home_dir,
+                &["openclaw".to_string()],
+                &pricing,
+                &ScanOptions::new(date_source, &[]),
+            )
+            .0
+        };
+        assert_eq!(parse(sessions::DateSource::Timestamp)[0].date, "2023-11-14");
+        assert_eq!(parse(sessions::DateSource::Mtime)[0].date, "2025-03-15");
+    }
 
-    let pricing = pricing::PricingService::get_or_init()
-        .await
-        .map_err(|e| napi::Error::from_reason(e))?;
+    #[tokio::test]
+    async fn test_skip_pricing_reports_tokens_without_loading_pricing() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("session.jsonl"),
+            r#"{"type":"assistant","timestamp":"2024-12-01T10:00:00.000Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50}}}
+{"type":"assistant","timestamp":"2024-12-01T10:05:00.000Z","requestId":"req_002","message":{"id":"msg_002","model":"claude-3-5-sonnet","usage":{"input_tokens":200,"output_tokens":100}}}"#,
+        )
+        .unwrap();
+
+        let options = ReportOptions {
+            home_dir: Some(home.path().to_string_lossy().into_owned()),
+            sources: Some(vec!["claude".to_string()]),
+            // Would force a fetch if pricing were loaded
+            pricing_priority: Some(vec!["openrouter".to_string()]),
+            skip_pricing: Some(true),
+            ..Default::default()
+        };
 
-    // Convert local messages and apply pricing
-    let mut all_messages: Vec<UnifiedMessage> = options
-        .local_messages
-        .messages
-        .iter()
-        .map(|msg| {
-            let cost = pricing.calculate_cost(
-                &msg.model_id,
-                msg.input,
-                msg.output,
-                msg.cache_read,
-                msg.cache_write,
-                msg.reasoning,
-            );
-            parsed_to_unified(msg, cost)
+        let pricing = report_pricing(&options).await.unwrap();
+        assert!(pricing.is_unpriced());
+
+        let home_dir = options.home_dir.clone().unwrap();
+        let (messages, _) = parse_all_messages_with_pricing(
+            &home_dir,
+            &["claude".to_string()],
+            &pricing,
+            &ScanOptions::new(sessions::DateSource::Timestamp, &[]),
+        );
+        let entries =
+            aggregator::aggregate_by_model(&filter_messages_for_report(messages, &options));
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].input, 300);
+        assert_eq!(entries[0].output, 150);
+        assert_eq!(entries[0].message_count, 2);
+        assert_eq!(entries[0].cost, 0.0);
+
+        // The monthly, quarterly and project endpoints honor it too
+        let monthly = monthly_report(options.clone()).await.unwrap();
+        assert_eq!(monthly.entries.len(), 1);
+        assert_eq!(monthly.entries[0].input, 300);
+        assert_eq!(monthly.total_cost, 0.0);
+        let quarterly = quarterly_report(options.clone()).await.unwrap();
+        assert_eq!(quarterly.entries[0].message_count, 2);
+        assert_eq!(quarterly.total_cost, 0.0);
+        let project = project_report(options.clone()).await.unwrap();
+        assert_eq!(project.entries[0].output, 150);
+        assert_eq!(project.total_cost, 0.0);
+
+        // So does the raw message query, which checks its dates like a report
+        let query = QueryOptions {
+            home_dir: options.home_dir.clone(),
+            pricing_priority: options.pricing_priority.clone(),
+            skip_pricing: Some(true),
+            ..Default::default()
+        };
+        let queried = queried_messages(query.clone()).await.unwrap();
+        assert_eq!(queried.len(), 2);
+        assert!(queried.iter().all(|msg| msg.cost == Some(0.0)));
+        let err = queried_messages(QueryOptions {
+            since: Some("2024-12".to_string()),
+            ..query
         })
-        .collect();
+        .await
+        .unwrap_err();
+        assert!(err.starts_with("E_INVALID_DATE: "), "{}", err);
+
+        // And the range comparison, which checks both windows' dates
+        let compare = CompareOptions {
+            home_dir: options.home_dir.clone(),
+            sources: None,
+            first: DateWindow {
+                since: None,
+                until: Some("2024-11-30".to_string()),
+            },
+            second: DateWindow {
+                since: Some("2024-12-01".to_string()),
+                until: None,
+            },
+            pricing_priority: options.pricing_priority.clone(),
+            skip_pricing: Some(true),
+            prefer_cheaper: None,
+            reasoning_rate_multiplier: None,
+            batch_rate_multiplier: None,
+            unknown_model_rate: None,
+            cost_source: None,
+            fuzzy_min_length: None,
+            fuzzy_blocklist: None,
+        };
+        let comparison = range_comparison(compare.clone()).await.unwrap();
+        assert_eq!(comparison.first_totals.messages, 0);
+        assert_eq!(comparison.second_totals.messages, 2);
+        assert_eq!(comparison.second_totals.tokens, 450);
+        assert_eq!(comparison.second_totals.cost, 0.0);
+        let bad_second = DateWindow {
+            since: Some("2024-12-02".to_string()),
+            until: Some("2024-12-01".to_string()),
+        };
+        let err = range_comparison(CompareOptions {
+            second: bad_second,
+            ..compare
+        })
+        .await
+        .unwrap_err();
+        assert!(err.starts_with("E_INVALID_DATE: "), "{}", err);
 
-    // Add Cursor messages if enabled
-    if options.include_cursor {
-        let cursor_cache_dir = format!("{}/.config/tokscale/cursor-cache", home_dir);
-        let cursor_files = scanner::scan_directory(&cursor_cache_dir, "usage*.csv");
+        // The shared service was never fetched
+        assert!(pricing::PricingService::current().await.is_none());
+    }
 
-        let cursor_messages: Vec<UnifiedMessage> = cursor_files
-            .par_iter()
-            .flat_map(|path| {
-                sessions::cursor::parse_cursor_file(path)
-                    .into_iter()
-                    .map(|mut msg| {
-                        let csv_cost = msg.cost;
-                        let calculated_cost = pricing.calculate_cost(
-                            &msg.model_id,
-                            msg.tokens.input,
-                            msg.tokens.output,
-                            msg.tokens.cache_read,
-                            msg.tokens.cache_write,
-                            msg.tokens.reasoning,
-                        );
-                        msg.cost = if calculated_cost > 0.0 {
-                            calculated_cost
-                        } else {
-                            csv_cost
-                        };
-                        msg
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect();
+    #[tokio::test]
+    async fn test_reports_count_sanitized_messages() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("session.jsonl"),
+            r#"{"type":"assistant","timestamp":"2024-12-01T10:00:00.000Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50}}}
+{"type":"assistant","timestamp":"2024-12-01T10:05:00.000Z","requestId":"req_002","message":{"id":"msg_002","model":"claude-3-5-sonnet","usage":{"input_tokens":200,"output_tokens":-100}}}
+{"type":"assistant","timestamp":"2024-12-01T10:10:00.000Z","requestId":"req_003","message":{"id":"msg_003","model":"claude-3-5-sonnet","usage":{"input_tokens":50000000,"output_tokens":10}}}"#,
+        )
+        .unwrap();
+
+        let options = ReportOptions {
+            home_dir: Some(home.path().to_string_lossy().into_owned()),
+            sources: Some(vec!["claude".to_string()]),
+            skip_pricing: Some(true),
+            max_tokens_per_message: Some(10_000_000),
+            ..Default::default()
+        };
 
-        all_messages.extend(cursor_messages);
+        let monthly = monthly_report(options.clone()).await.unwrap();
+        assert_eq!((monthly.clamped_messages, monthly.dropped_messages), (1, 1));
+        assert_eq!(monthly.entries[0].input, 300);
+        assert_eq!(monthly.entries[0].output, 50);
+        let quarterly = quarterly_report(options.clone()).await.unwrap();
+        assert_eq!(
+            (quarterly.clamped_messages, quarterly.dropped_messages),
+            (1, 1)
+        );
+        let project = project_report(options.clone()).await.unwrap();
+        assert_eq!((project.clamped_messages, project.dropped_messages), (1, 1));
+        assert_eq!(project.entries[0].message_count, 2);
+
+        // The scan sanitizes each batch as it is parsed, so every consumer
+        // sees the repaired counts
+        let pricing = pricing::PricingService::unpriced();
+        let scan = ScanOptions::for_report(&options, sessions::DateSource::Timestamp, &[]);
+        let mut outputs = Vec::new();
+        for_each_source_with_pricing(
+            options.home_dir.as_deref().unwrap(),
+            &["claude".to_string()],
+            &pricing,
+            &scan,
+            |messages| {
+                outputs.extend(messages.iter().map(|m| m.tokens.output));
+            },
+        );
+        outputs.sort();
+        assert_eq!(outputs, vec![0, 50]);
+        assert_eq!(
+            scan.sanitized(),
+            sessions::TokenSanitizeCounts {
+                clamped: 1,
+                dropped: 1
+            }
+        );
     }
 
-    // Apply date filters
-    if let Some(year) = &options.year {
-        let year_prefix = format!("{}-", year);
-        all_messages.retain(|m| m.date.starts_with(&year_prefix));
-    }
-    if let Some(since) = &options.since {
-        all_messages.retain(|m| m.date.as_str() >= since.as_str());
-    }
-    if let Some(until) = &options.until {
-        all_messages.retain(|m| m.date.as_str() <= until.as_str());
+    #[tokio::test]
+    async fn test_reports_agree_on_totals_with_undated_messages() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("session.jsonl"),
+            r#"{"type":"assistant","timestamp":"2024-12-01T10:00:00.000Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50}}}
+{"type":"assistant","timestamp":"9999-01-01T00:00:00.000Z","requestId":"req_002","message":{"id":"msg_002","model":"claude-3-5-sonnet","usage":{"input_tokens":200,"output_tokens":100}}}"#,
+        )
+        .unwrap();
+
+        for until in [None, Some("2025-01-01".to_string())] {
+            let options = ReportOptions {
+                home_dir: Some(home.path().to_string_lossy().into_owned()),
+                sources: Some(vec!["claude".to_string()]),
+                skip_pricing: Some(true),
+                until,
+                ..Default::default()
+            };
+
+            let pricing = pricing::PricingService::unpriced();
+            let scan = ScanOptions::for_report(&options, sessions::DateSource::Timestamp, &[]);
+            let (messages, _) = parse_all_messages_with_pricing(
+                options.home_dir.as_deref().unwrap(),
+                &["claude".to_string()],
+                &pricing,
+                &scan,
+            );
+            assert_eq!(messages.len(), 2);
+
+            let models = aggregator::aggregate_by_model(&filter_messages_for_report(
+                messages.clone(),
+                &options,
+            ));
+            assert_eq!(aggregator::range_totals(&models).tokens, 150);
+            let (days, _) = graph_contributions(messages, &options);
+            assert_eq!(days.iter().map(|d| d.totals.tokens).sum::<i64>(), 150);
+            let monthly = monthly_report(options).await.unwrap();
+            assert_eq!(
+                monthly.entries.iter().map(|e| e.total_tokens).sum::<i64>(),
+                150
+            );
+        }
     }
 
-    // Aggregate by date
-    let contributions = aggregator::aggregate_by_date(all_messages);
+    #[test]
+    fn test_exclude_models_drops_entries_and_totals() {
+        let messages: Vec<UnifiedMessage> = [
+            ("claude-sonnet-4", 100, 1.0),
+            ("text-embedding-3-small", 5_000, 0.1),
+            ("Claude-Haiku-Title", 40, 0.01),
+            ("gpt-5", 200, 2.0),
+        ]
+        .into_iter()
+        .map(|(model, input, cost)| {
+            let tokens = TokenBreakdown {
+                input,
+                ..Default::default()
+            };
+            UnifiedMessage::new("opencode", model, "x", "s", 1748772000000, tokens, cost)
+        })
+        .collect();
 
-    // Generate result
-    let processing_time_ms = start.elapsed().as_millis() as u32;
-    let result = aggregator::generate_graph_result(contributions, processing_time_ms);
+        let options = ReportOptions {
+            exclude_models: Some(vec![
+                "TEXT-EMBEDDING-3-SMALL".to_string(),
+                "*haiku*".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let entries =
+            aggregator::aggregate_by_model(&filter_messages_for_report(messages.clone(), &options));
+
+        let mut models: Vec<&str> = entries.iter().map(|e| e.model.as_str()).collect();
+        models.sort_unstable();
+        assert_eq!(models, ["claude-sonnet-4", "gpt-5"]);
+        let totals = aggregator::range_totals(&entries);
+        assert_eq!(totals.tokens, 300);
+        assert!((totals.cost - 3.0).abs() < 1e-9);
+
+        // Unset keeps everything
+        let all = filter_messages_for_report(messages, &ReportOptions::default());
+        assert_eq!(all.len(), 4);
+    }
 
-    Ok(result)
-}
+    #[test]
+    #[serial_test::serial]
+    fn test_strict_mode_errors_when_no_files_found() {
+        let dir = TempDir::new().unwrap();
+        let home = dir.path().to_str().unwrap();
+        let sources = vec!["claude".to_string()];
+        let options = ReportOptions {
+            strict: Some(true),
+            ..Default::default()
+        };
 
-/// Combined result for report and graph (single pricing lookup)
-#[napi(object)]
-pub struct ReportAndGraph {
-    pub report: ModelReport,
-    pub graph: GraphResult,
-}
+        let empty = scanner::scan_all_sources(home, &sources);
+        let err = ensure_files_found(&options, home, &sources, &empty).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), Some(ErrorCode::NoFiles), "{}", err);
+        assert!(err.contains("No session files found"), "{}", err);
+        assert!(
+            err.contains(&format!("{}/.claude/projects", home)),
+            "{}",
+            err
+        );
+        // Off by default
+        assert!(ensure_files_found(&ReportOptions::default(), home, &sources, &empty).is_ok());
+
+        let project = dir.path().join(".claude/projects/app");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join("session.jsonl"), "").unwrap();
+        let found = scanner::scan_all_sources(home, &sources);
+        assert!(ensure_files_found(&options, home, &sources, &found).is_ok());
+    }
 
-/// Finalize both report and graph in a single call with shared pricing
-/// This ensures consistent costs between report and graph data
-#[napi]
-pub async fn finalize_report_and_graph(options: FinalizeReportOptions) -> napi::Result<ReportAndGraph> {
-    let start = Instant::now();
+    #[test]
+    fn test_cost_estimate() {
+        let pricing = pricing::PricingService::new(
+            [(
+                "claude-sonnet-4".to_string(),
+                pricing::ModelPricing {
+                    input_cost_per_token: Some(0.000003),
+                    output_cost_per_token: Some(0.000015),
+                    cache_read_input_token_cost: Some(0.0000003),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            Default::default(),
+        );
+        let tokens = TokenBreakdown {
+            input: 1_000_000,
+            output: 100_000,
+            cache_read: 2_000_000,
+            ..Default::default()
+        };
 
-    let home_dir = get_home_dir(&options.home_dir)?;
+        let known = cost_estimate(&pricing, "claude-sonnet-4".to_string(), &tokens, None);
+        assert!(known.matched);
+        assert_eq!(known.matched_key, "claude-sonnet-4");
+        assert_eq!(known.source, "LiteLLM");
+        assert!((known.cost_breakdown.input_cost - 3.0).abs() < 1e-9);
+        assert!((known.cost_breakdown.output_cost - 1.5).abs() < 1e-9);
+        assert!((known.cost_breakdown.cache_read_cost - 0.6).abs() < 1e-9);
+        assert!((known.total_cost - 5.1).abs() < 1e-9);
+
+        let unknown = cost_estimate(&pricing, "totally-unknown-model".to_string(), &tokens, None);
+        assert!(!unknown.matched);
+        assert_eq!(unknown.source, "none");
+        assert_eq!(unknown.total_cost, 0.0);
+    }
 
-    // Single pricing lookup - shared by both report and graph
-    let pricing = pricing::PricingService::get_or_init()
-        .await
-        .map_err(|e| napi::Error::from_reason(e))?;
+    #[test]
+    fn test_attached_messages_reaggregate_to_entries() {
+        let tokens = |input: i64, output: i64| TokenBreakdown {
+            input,
+            output,
+            ..Default::default()
+        };
+        let filtered = vec![
+            UnifiedMessage::new(
+                "claude",
+                "claude-sonnet-4",
+                "anthropic",
+                "s1",
+                1748772000000,
+                tokens(100, 50),
+                0.25,
+            ),
+            UnifiedMessage::new(
+                "claude",
+                "claude-sonnet-4",
+                "anthropic",
+                "s2",
+                1748775600000,
+                tokens(300, 10),
+                0.5,
+            ),
+            UnifiedMessage::new(
+                "codex",
+                "gpt-5",
+                "openai",
+                "s3",
+                1748779200000,
+                tokens(200, 20),
+                0.125,
+            ),
+        ];
+        let entries = aggregator::aggregate_by_model(&filtered);
+
+        let attached: Vec<ParsedMessage> = filtered.iter().map(priced_message).collect();
+        let replayed: Vec<UnifiedMessage> = attached
+            .iter()
+            .map(|msg| parsed_to_unified(msg, msg.cost.unwrap()))
+            .collect();
+        let reaggregated = aggregator::aggregate_by_model(&replayed);
 
-    // Convert local messages and apply pricing (once)
-    let mut all_messages: Vec<UnifiedMessage> = options
-        .local_messages
-        .messages
-        .iter()
-        .map(|msg| {
-            let cost = pricing.calculate_cost(
-                &msg.model_id,
-                msg.input,
-                msg.output,
-                msg.cache_read,
-                msg.cache_write,
-                msg.reasoning,
+        assert_eq!(reaggregated.len(), entries.len());
+        for (a, b) in reaggregated.iter().zip(&entries) {
+            assert_eq!(
+                (&a.source, &a.model, &a.provider),
+                (&b.source, &b.model, &b.provider)
             );
-            parsed_to_unified(msg, cost)
-        })
-        .collect();
-
-    // Add Cursor messages if enabled
-    if options.include_cursor {
-        let cursor_cache_dir = format!("{}/.config/tokscale/cursor-cache", home_dir);
-        let cursor_files = scanner::scan_directory(&cursor_cache_dir, "usage*.csv");
+            assert_eq!(
+                (a.input, a.output, a.total_tokens),
+                (b.input, b.output, b.total_tokens)
+            );
+            assert_eq!(
+                (a.message_count, a.session_count),
+                (b.message_count, b.session_count)
+            );
+            assert!((a.cost - b.cost).abs() < 1e-9);
+        }
+    }
 
-        let cursor_messages: Vec<UnifiedMessage> = cursor_files
-            .par_iter()
-            .flat_map(|path| {
-                sessions::cursor::parse_cursor_file(path)
-                    .into_iter()
-                    .map(|mut msg| {
-                        let csv_cost = msg.cost;
-                        let calculated_cost = pricing.calculate_cost(
-                            &msg.model_id,
-                            msg.tokens.input,
-                            msg.tokens.output,
-                            msg.tokens.cache_read,
-                            msg.tokens.cache_write,
-                            msg.tokens.reasoning,
-                        );
-                        msg.cost = if calculated_cost > 0.0 {
-                            calculated_cost
-                        } else {
-                            csv_cost
-                        };
-                        msg
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect();
+    #[test]
+    fn test_supplied_messages_aggregate_to_hand_computed_totals() {
+        let message =
+            |model: &str, session: &str, timestamp: i64, input: i64, output: i64, cost: f64| {
+                ParsedMessage {
+                    source: "embedded".to_string(),
+                    model_id: model.to_string(),
+                    provider_id: "anthropic".to_string(),
+                    session_id: session.to_string(),
+                    timestamp,
+                    date: String::new(),
+                    input,
+                    output,
+                    cache_read: 0,
+                    cache_write: 0,
+                    reasoning: 0,
+                    agent: None,
+                    cost: Some(cost),
+                    credits: None,
+                    premium_requests: None,
+                    project: None,
+                    batch: None,
+                }
+            };
+        // 2025-06-01, 2025-06-01, 2025-06-02, and one in May the filter drops
+        let messages = vec![
+            message(
+                "claude-sonnet-4",
+                "s1",
+                1748772000000,
+                1_000_000,
+                100_000,
+                1.0,
+            ),
+            message("claude-sonnet-4", "s2", 1748775600000, 2_000_000, 0, 2.0),
+            message("claude-haiku-4", "s2", 1748858400000, 0, 1_000_000, 4.0),
+            message("claude-haiku-4", "s3", 1746000000000, 5_000_000, 0, 8.0),
+        ];
+        let options = AggregateOptions {
+            since: Some("2025-06-01".to_string()),
+            ..Default::default()
+        };
 
-        all_messages.extend(cursor_messages);
+        // Supplied costs, no pricing
+        let unpriced = pricing::PricingService::unpriced();
+        let graph = graph_from_messages(&messages, &options, &unpriced, Instant::now());
+        assert_eq!(graph.contributions.len(), 2);
+        assert_eq!(graph.contributions[0].date, "2025-06-01");
+        assert_eq!(graph.contributions[0].totals.tokens, 3_100_000);
+        assert!((graph.contributions[0].totals.cost - 3.0).abs() < 1e-9);
+        assert_eq!(graph.contributions[1].totals.messages, 1);
+        assert_eq!(graph.summary.total_tokens, 4_100_000);
+        assert!((graph.summary.total_cost - 7.0).abs() < 1e-9);
+
+        // Priced: sonnet $3/M in, $15/M out; haiku $1/M in, $5/M out
+        let rates = |input: f64, output: f64| pricing::ModelPricing {
+            input_cost_per_token: Some(input),
+            output_cost_per_token: Some(output),
+            ..Default::default()
+        };
+        let priced = pricing::PricingService::new(
+            [
+                ("claude-sonnet-4".to_string(), rates(0.000003, 0.000015)),
+                ("claude-haiku-4".to_string(), rates(0.000001, 0.000005)),
+            ]
+            .into(),
+            Default::default(),
+        );
+        let report = report_from_messages(&messages, &options, &priced, Instant::now());
+        assert_eq!(report.total_messages, 3);
+        assert_eq!(report.total_input, 3_000_000);
+        assert_eq!(report.total_output, 1_100_000);
+        let sonnet = report
+            .entries
+            .iter()
+            .find(|e| e.model == "claude-sonnet-4")
+            .unwrap();
+        // 3M input at $3/M + 100K output at $15/M
+        assert!((sonnet.cost - 10.5).abs() < 1e-9);
+        assert_eq!(sonnet.session_count, 2);
+        assert!((report.total_cost - 15.5).abs() < 1e-9);
+        assert!((report.cost_breakdown.total() - report.total_cost).abs() < 1e-9);
     }
 
-    // Apply date filters
-    if let Some(year) = &options.year {
-        let year_prefix = format!("{}-", year);
-        all_messages.retain(|m| m.date.starts_with(&year_prefix));
-    }
-    if let Some(since) = &options.since {
-        all_messages.retain(|m| m.date.as_str() >= since.as_str());
+    #[test]
+    fn test_supplied_messages_match_scanned_graph_shaping() {
+        let message = |model: &str, timestamp: i64, cost: f64| ParsedMessage {
+            source: "embedded".to_string(),
+            model_id: model.to_string(),
+            provider_id: "anthropic".to_string(),
+            session_id: "s1".to_string(),
+            timestamp,
+            date: String::new(),
+            input: 1_000_000,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+            agent: None,
+            cost: Some(cost),
+            credits: None,
+            premium_requests: None,
+            project: None,
+            batch: None,
+        };
+        // 2025-06-01 and 2025-06-03, with a model the pricing doesn't know
+        let messages = vec![
+            message("claude-sonnet-4", 1748772000000, 0.0),
+            message("in-house-model", 1748944800000, 1.23456),
+        ];
+        let priced = pricing::PricingService::new(
+            [(
+                "claude-sonnet-4".to_string(),
+                pricing::ModelPricing {
+                    input_cost_per_token: Some(0.000003),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            Default::default(),
+        );
+        let options = AggregateOptions {
+            fill_gaps: Some(true),
+            round_costs: Some(2),
+            ..Default::default()
+        };
+
+        let graph = graph_from_messages(&messages, &options, &priced, Instant::now());
+        let dates: Vec<&str> = graph
+            .contributions
+            .iter()
+            .map(|d| d.date.as_str())
+            .collect();
+        assert_eq!(dates, ["2025-06-01", "2025-06-02", "2025-06-03"]);
+        // The supplied cost survives for the unknown model, rounded
+        assert_eq!(graph.contributions[2].totals.cost, 1.23);
+        assert_eq!(graph.summary.total_cost, 4.23);
+
+        let report = report_from_messages(&messages, &options, &priced, Instant::now());
+        let in_house = report
+            .entries
+            .iter()
+            .find(|e| e.model == "in-house-model")
+            .unwrap();
+        assert_eq!(in_house.cost, 1.23);
+        assert_eq!(report.total_cost, 4.23);
     }
-    if let Some(until) = &options.until {
-        all_messages.retain(|m| m.date.as_str() <= until.as_str());
+
+    #[test]
+    fn test_parse_file_prices_like_the_scan() {
+        let home = TempDir::new().unwrap();
+        let project = home.path().join(".claude/projects/-Users-me-app");
+        std::fs::create_dir_all(&project).unwrap();
+        let path = project.join("session.jsonl");
+        std::fs::write(
+            &path,
+            r#"{"type":"assistant","timestamp":"2025-06-01T10:00:00.000Z","requestId":"req_1","message":{"id":"msg_1","model":"claude-sonnet-4","usage":{"input_tokens":1000000,"output_tokens":-5,"cache_read_input_tokens":1000000}}}
+{"type":"assistant","timestamp":"2025-06-01T11:00:00.000Z","requestId":"req_2","message":{"id":"msg_2","model":"claude-sonnet-4","service_tier":"batch","usage":{"input_tokens":1000000,"output_tokens":100000}}}"#,
+        )
+        .unwrap();
+        let pricing = pricing::PricingService::new(
+            [(
+                "claude-sonnet-4".to_string(),
+                pricing::ModelPricing {
+                    input_cost_per_token: Some(0.000003),
+                    output_cost_per_token: Some(0.000015),
+                    cache_read_input_token_cost: Some(3e-7),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            Default::default(),
+        );
+
+        let single = priced_file_messages(
+            sessions::parse_source_file(&path, "claude").unwrap(),
+            "claude",
+            &pricing,
+        );
+        let (scanned, _) = parse_all_messages_with_pricing(
+            &home.path().to_string_lossy(),
+            &["claude".to_string()],
+            &pricing,
+            &ScanOptions::new(sessions::DateSource::Timestamp, &[]),
+        );
+
+        assert_eq!(single.len(), scanned.len());
+        for (single, scanned) in single.iter().zip(&scanned) {
+            assert_eq!(
+                (single.input, single.output, single.cache_read),
+                (
+                    scanned.tokens.input,
+                    scanned.tokens.output,
+                    scanned.tokens.cache_read
+                )
+            );
+            assert!((single.cost.unwrap() - scanned.cost).abs() < 1e-9);
+        }
+        // The negative output count was clamped, not priced
+        assert_eq!(single[0].output, 0);
+        assert!((single[0].cost.unwrap() - 3.3).abs() < 1e-9);
     }
 
-    // Clone messages for graph aggregation (report consumes for model aggregation)
-    let messages_for_graph = all_messages.clone();
+    fn finalize_options(messages: Vec<ParsedMessage>) -> FinalizeReportOptions {
+        FinalizeReportOptions {
+            home_dir: None,
+            local_messages: ParsedMessages {
+                messages,
+                opencode_count: 0,
+                claude_count: 0,
+                codex_count: 0,
+                gemini_count: 0,
+                amp_count: 0,
+                droid_count: 0,
+                openclaw_count: 0,
+                warp_count: 0,
+                roo_count: 0,
+                kilo_count: 0,
+                qwen_count: 0,
+                copilot_count: 0,
+                jules_count: 0,
+                processing_time_ms: 0,
+            },
+            include_cursor: false,
+            since: None,
+            until: None,
+            year: None,
+            pricing_priority: None,
+            separate_reasoning_cost: None,
+            max_tokens_per_message: None,
+        }
+    }
 
-    // --- Generate Report ---
-    let mut model_map: std::collections::HashMap<String, ModelUsage> =
-        std::collections::HashMap::new();
-
-    for msg in all_messages {
-        let key = format!("{}:{}:{}", msg.source, msg.provider_id, msg.model_id);
-        let entry = model_map.entry(key).or_insert_with(|| ModelUsage {
-            source: msg.source.clone(),
-            model: msg.model_id.clone(),
-            provider: msg.provider_id.clone(),
-            input: 0,
-            output: 0,
+    #[test]
+    fn test_finalize_and_supplied_paths_honor_max_tokens_per_message() {
+        let message = |session: &str, input: i64, output: i64| ParsedMessage {
+            source: "claude".to_string(),
+            model_id: "claude-sonnet-4".to_string(),
+            provider_id: "anthropic".to_string(),
+            session_id: session.to_string(),
+            timestamp: 1748772000000,
+            date: "2025-06-01".to_string(),
+            input,
+            output,
             cache_read: 0,
             cache_write: 0,
             reasoning: 0,
-            message_count: 0,
-            cost: 0.0,
-        });
-
-        entry.input += msg.tokens.input;
-        entry.output += msg.tokens.output;
-        entry.cache_read += msg.tokens.cache_read;
-        entry.cache_write += msg.tokens.cache_write;
-        entry.reasoning += msg.tokens.reasoning;
-        entry.message_count += 1;
-        entry.cost += msg.cost;
-    }
-
-    let mut entries: Vec<ModelUsage> = model_map.into_values().collect();
-    entries.sort_by(|a, b| match (a.cost.is_nan(), b.cost.is_nan()) {
-        (true, true) => std::cmp::Ordering::Equal,
-        (true, false) => std::cmp::Ordering::Greater,
-        (false, true) => std::cmp::Ordering::Less,
-        (false, false) => b
-            .cost
-            .partial_cmp(&a.cost)
-            .unwrap_or(std::cmp::Ordering::Equal),
-    });
-
-    let total_input: i64 = entries.iter().map(|e| e.input).sum();
-    let total_output: i64 = entries.iter().map(|e| e.output).sum();
-    let total_cache_read: i64 = entries.iter().map(|e| e.cache_read).sum();
-    let total_cache_write: i64 = entries.iter().map(|e| e.cache_write).sum();
-    let total_messages: i32 = entries.iter().map(|e| e.message_count).sum();
-    let total_cost: f64 = entries.iter().map(|e| e.cost).sum();
+            agent: None,
+            cost: None,
+            credits: None,
+            premium_requests: None,
+            project: None,
+            batch: None,
+        };
+        let messages = vec![
+            message("s1", 100, -5),
+            message("s2", i64::MAX - 10, 50),
+            message("s3", 200, 20),
+        ];
+        let pricing = pricing::PricingService::unpriced();
+
+        let options = FinalizeReportOptions {
+            max_tokens_per_message: Some(1_000_000),
+            ..finalize_options(messages.clone())
+        };
+        let (finalized, sanitized) = finalized_messages(&options.scope(), "/nonexistent", &pricing);
+        assert_eq!(finalized.len(), 2);
+        assert_eq!(
+            sanitized,
+            sessions::TokenSanitizeCounts {
+                clamped: 1,
+                dropped: 1
+            }
+        );
+
+        // Without a ceiling only the clamp applies
+        let (finalized, sanitized) = finalized_messages(
+            &finalize_options(messages.clone()).scope(),
+            "/nonexistent",
+            &pricing,
+        );
+        assert_eq!(finalized.len(), 3);
+        assert_eq!(sanitized.dropped, 0);
+
+        let options = AggregateOptions {
+            max_tokens_per_message: Some(1_000_000),
+            ..Default::default()
+        };
+        let report = report_from_messages(&messages, &options, &pricing, Instant::now());
+        assert_eq!(report.total_messages, 2);
+        assert_eq!((report.clamped_messages, report.dropped_messages), (1, 1));
+        let graph = graph_from_messages(&messages, &options, &pricing, Instant::now());
+        assert_eq!(graph.summary.total_tokens, 320);
+    }
 
-    let report = ModelReport {
-        entries,
-        total_input,
-        total_output,
-        total_cache_read,
-        total_cache_write,
-        total_messages,
-        total_cost,
-        processing_time_ms: start.elapsed().as_millis() as u32,
-    };
+    #[test]
+    fn test_premium_requests_survive_finalize_and_supplied_paths() {
+        let copilot = ParsedMessage {
+            source: "copilot".to_string(),
+            model_id: "gpt-4.1".to_string(),
+            provider_id: "openai".to_string(),
+            session_id: "chat-1".to_string(),
+            timestamp: 1748772000000,
+            date: "2025-06-01".to_string(),
+            input: 100,
+            output: 20,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+            agent: None,
+            cost: None,
+            credits: None,
+            premium_requests: Some(1.5),
+            project: None,
+            batch: None,
+        };
+        let messages = vec![
+            copilot.clone(),
+            ParsedMessage {
+                session_id: "chat-2".to_string(),
+                ..copilot
+            },
+        ];
+        let pricing = pricing::PricingService::unpriced();
+
+        let options = finalize_options(messages.clone());
+        let (finalized, _) = finalized_messages(&options.scope(), "/nonexistent", &pricing);
+        let entries = aggregator::aggregate_by_model(&finalized);
+        assert_eq!(entries[0].premium_requests, 3.0);
+
+        let report = report_from_messages(
+            &messages,
+            &AggregateOptions::default(),
+            &pricing,
+            Instant::now(),
+        );
+        assert_eq!(report.entries[0].premium_requests, 3.0);
+
+        // And back out again
+        assert_eq!(unified_to_parsed(&finalized[0]).premium_requests, Some(1.5));
+    }
 
-    // --- Generate Graph ---
-    let contributions = aggregator::aggregate_by_date(messages_for_graph);
-    let graph = aggregator::generate_graph_result(contributions, start.elapsed().as_millis() as u32);
+    #[test]
+    fn test_cache_savings() {
+        let rates = |input: f64, cache_read: Option<f64>| pricing::ModelPricing {
+            input_cost_per_token: Some(input),
+            output_cost_per_token: Some(input * 5.0),
+            cache_read_input_token_cost: cache_read,
+            ..Default::default()
+        };
+        let pricing = pricing::PricingService::new(
+            [
+                (
+                    "claude-sonnet-4".to_string(),
+                    rates(0.000003, Some(0.0000003)),
+                ),
+                ("gpt-4o".to_string(), rates(0.0000025, None)),
+            ]
+            .into(),
+            Default::default(),
+        );
+        let cached = TokenBreakdown {
+            input: 10_000,
+            output: 1_000,
+            cache_read: 1_000_000,
+            ..Default::default()
+        };
+        let mut messages = vec![
+            UnifiedMessage::new(
+                "claude",
+                "claude-sonnet-4",
+                "anthropic",
+                "s",
+                1748772000000,
+                cached.clone(),
+                0.0,
+            ),
+            // No cache-read price: contributes nothing
+            UnifiedMessage::new("codex", "gpt-4o", "openai", "s", 1748772000000, cached, 0.0),
+        ];
+        for msg in &mut messages {
+            pricing.apply(msg);
+        }
 
-    Ok(ReportAndGraph { report, graph })
-}
+        let report = model_report(aggregator::aggregate_by_model(&messages), &pricing, 0, None);
 
-// =============================================================================
-// New Pricing API (Rust-native pricing fetching)
-// =============================================================================
+        // 1M cache reads at $3/M instead of $0.30/M
+        assert!(
+            (report.cache_savings - 2.7).abs() < 1e-9,
+            "{}",
+            report.cache_savings
+        );
+        assert_eq!(pricing.cache_savings("gpt-4o", 1_000_000), 0.0);
+        assert_eq!(pricing.cache_savings("unknown-model", 1_000_000), 0.0);
+    }
 
-#[napi(object)]
-pub struct NativePricing {
-    pub input_cost_per_token: f64,
-    pub output_cost_per_token: f64,
-    pub cache_read_input_token_cost: Option<f64>,
-    pub cache_creation_input_token_cost: Option<f64>,
-}
+    #[test]
+    fn test_reasoning_cost_itemized_only_when_separate() {
+        let pricing = pricing::PricingService::new(
+            [(
+                "o3".to_string(),
+                pricing::ModelPricing {
+                    input_cost_per_token: Some(0.000002),
+                    output_cost_per_token: Some(0.000008),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            Default::default(),
+        );
+        let tokens = TokenBreakdown {
+            input: 1_000_000,
+            output: 500_000,
+            reasoning: 250_000,
+            ..Default::default()
+        };
+        let mut message = UnifiedMessage::new(
+            "codex",
+            "o3",
+            "openai",
+            "s",
+            1748772000000,
+            tokens.clone(),
+            0.0,
+        );
+        pricing.apply(&mut message);
+        let entries = aggregator::aggregate_by_model(&[message]);
+
+        let report_with =
+            |separate: Option<bool>| model_report(entries.clone(), &pricing, 0, separate);
+
+        let separate = report_with(Some(true));
+        assert!((separate.cost_breakdown.output_cost - 4.0).abs() < 1e-9);
+        assert!((separate.cost_breakdown.reasoning_cost - 2.0).abs() < 1e-9);
+        assert!((separate.entries[0].cost_breakdown.reasoning_cost - 2.0).abs() < 1e-9);
+
+        let folded = report_with(None);
+        assert!((folded.cost_breakdown.output_cost - 6.0).abs() < 1e-9);
+        assert_eq!(folded.cost_breakdown.reasoning_cost, 0.0);
+        assert_eq!(folded.entries[0].cost_breakdown.reasoning_cost, 0.0);
+
+        // Folding moves cost between lines; the totals don't change
+        assert!((folded.total_cost - separate.total_cost).abs() < 1e-9);
+        assert!((folded.cost_breakdown.total() - separate.cost_breakdown.total()).abs() < 1e-9);
+
+        // Cost estimates follow the same default
+        let estimate =
+            |separate: Option<bool>| cost_estimate(&pricing, "o3".to_string(), &tokens, separate);
+        assert_eq!(estimate(None).cost_breakdown.reasoning_cost, 0.0);
+        assert!((estimate(None).cost_breakdown.output_cost - 6.0).abs() < 1e-9);
+        assert!((estimate(Some(true)).cost_breakdown.reasoning_cost - 2.0).abs() < 1e-9);
+        assert!((estimate(None).total_cost - estimate(Some(true)).total_cost).abs() < 1e-9);
+    }
 
-#[napi(object)]
-pub struct PricingLookupResult {
-    pub model_id: String,
-    pub matched_key: String,
-    pub source: String,
-    pub pricing: NativePricing,
-}
+    #[test]
+    fn test_amp_credits_reported_apart_from_cost() {
+        let tokens = TokenBreakdown {
+            input: 1_000,
+            output: 500,
+            ..Default::default()
+        };
+        let mut amp = UnifiedMessage::new(
+            "amp",
+            "claude-sonnet-4",
+            "anthropic",
+            "T-1",
+            1748772000000,
+            tokens.clone(),
+            0.0,
+        );
+        amp.credits = Some(1.5);
+        let mut claude = UnifiedMessage::new(
+            "claude",
+            "claude-sonnet-4",
+            "anthropic",
+            "s",
+            1748772000000,
+            tokens,
+            0.0,
+        );
+        let pricing = pricing::PricingService::new(
+            [(
+                "claude-sonnet-4".to_string(),
+                pricing::ModelPricing {
+                    input_cost_per_token: Some(0.000003),
+                    output_cost_per_token: Some(0.000015),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            Default::default(),
+        );
+        pricing.apply(&mut amp);
+        pricing.apply(&mut claude);
+
+        let report = model_report(
+            aggregator::aggregate_by_model(&[amp, claude]),
+            &pricing,
+            0,
+            None,
+        );
+
+        assert!((report.total_cost - 0.021).abs() < 1e-9);
+        assert_eq!(report.total_credits, 1.5);
+        let amp_entry = report.entries.iter().find(|e| e.source == "amp").unwrap();
+        assert_eq!(amp_entry.credits, 1.5);
+        assert!((amp_entry.cost - 0.0105).abs() < 1e-9);
+    }
 
-#[napi]
-pub async fn lookup_pricing(model_id: String, provider: Option<String>) -> napi::Result<PricingLookupResult> {
-    let service = pricing::PricingService::get_or_init()
-        .await
-        .map_err(|e| napi::Error::from_reason(e))?;
+    #[test]
+    fn test_graph_json_is_byte_identical_across_runs() {
+        let messages: Vec<UnifiedMessage> = [
+            ("claude", "claude-sonnet-4", 1748772000000_i64, 0.5),
+            ("codex", "gpt-5", 1748775600000, 0.25),
+            ("gemini", "gemini-2.5-pro", 1748779200000, 0.125),
+            ("amp", "claude-sonnet-4", 1748858400000, 1.0),
+            ("claude", "claude-opus-4-5", 1748862000000, 2.0),
+        ]
+        .into_iter()
+        .map(|(source, model, timestamp, cost)| {
+            let tokens = TokenBreakdown {
+                input: 100,
+                output: 50,
+                ..Default::default()
+            };
+            UnifiedMessage::new(source, model, "provider", "s", timestamp, tokens, cost)
+        })
+        .collect();
+        let reversed: Vec<UnifiedMessage> = messages.iter().rev().cloned().collect();
+
+        let first = aggregator::generate_graph_result(
+            aggregator::aggregate_by_date(&messages, false),
+            7,
+            aggregator::ActiveMetric::Cost,
+        );
+        let mut second = aggregator::generate_graph_result(
+            aggregator::aggregate_by_date(&reversed, false),
+            7,
+            aggregator::ActiveMetric::Cost,
+        );
+        // The generation time is wall-clock, not data
+        second.meta.generated_at = first.meta.generated_at.clone();
+
+        for pretty in [false, true] {
+            let json = graph_result_to_json(first.clone(), pretty);
+            assert_eq!(json, graph_result_to_json(second.clone(), pretty));
+        }
 
-    let force_source = provider.as_deref();
-    
-    match service.lookup_with_source(&model_id, force_source) {
-        Some(result) => Ok(PricingLookupResult {
-            model_id,
-            matched_key: result.matched_key,
-            source: result.source,
-            pricing: NativePricing {
-                input_cost_per_token: result.pricing.input_cost_per_token.unwrap_or(0.0),
-                output_cost_per_token: result.pricing.output_cost_per_token.unwrap_or(0.0),
-                cache_read_input_token_cost: result.pricing.cache_read_input_token_cost,
-                cache_creation_input_token_cost: result.pricing.cache_creation_input_token_cost,
-            },
-        }),
-        None => Err(napi::Error::from_reason(format!(
-            "Model not found: {}{}",
-            model_id,
-            force_source.map(|s| format!(" (forced source: {})", s)).unwrap_or_default()
-        ))),
+        let json = graph_result_to_json(first, false);
+        assert!(
+            json.starts_with(r#"{"contributions":[{"date":"2025-06-01","intensity":"#),
+            "{}",
+            json
+        );
+        assert!(json.contains(
+            r#""models":["claude-opus-4-5","claude-sonnet-4","gemini-2.5-pro","gpt-5"]"#
+        ));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["contributions"][0]["sources"][0]["source"], "claude");
+        assert_eq!(value["contributions"][0]["sources"][1]["source"], "codex");
     }
 }
@@ -0,0 +1,49 @@
+//! Retry delays shared by the pricing fetchers
+//!
+//! Exponential backoff with jitter, so concurrent requests that fail together
+//! do not all retry at the same instant.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+const INITIAL_BACKOFF_MS: u64 = 200;
+const MAX_BACKOFF_MS: u64 = 5_000;
+
+/// Delay before retrying after failed attempt `attempt` (0-based): the
+/// doubling base delay plus up to half again at random, capped at 5s
+pub fn delay(attempt: u32) -> Duration {
+    let base = INITIAL_BACKOFF_MS.saturating_mul(1 << attempt.min(16));
+    let jitter = random_u64() % (base / 2 + 1);
+    Duration::from_millis(base.saturating_add(jitter).min(MAX_BACKOFF_MS))
+}
+
+/// Sleep for `delay(attempt)`
+pub async fn sleep(attempt: u32) {
+    tokio::time::sleep(delay(attempt)).await;
+}
+
+fn random_u64() -> u64 {
+    // Each RandomState is freshly seeded, which is all the randomness jitter needs
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_grows_with_jitter_and_cap() {
+        for attempt in 0..3 {
+            let base = INITIAL_BACKOFF_MS << attempt;
+            let d = delay(attempt).as_millis() as u64;
+            assert!(
+                d >= base && d <= base + base / 2,
+                "attempt {}: {}",
+                attempt,
+                d
+            );
+        }
+        assert_eq!(delay(30).as_millis() as u64, MAX_BACKOFF_MS);
+    }
+}
@@ -0,0 +1,83 @@
+//! Per-source billing rules
+//!
+//! The single place that decides how a source's recorded token counts map onto
+//! the tokens that are actually priced.
+
+use crate::TokenBreakdown;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BillingRule {
+    /// Every category at its own rate; reasoning at the output rate
+    Standard,
+    /// Thoughts at the output rate and cached tokens free, since the recorded
+    /// input already includes them (Gemini CLI format: Gemini, Qwen, Jules)
+    CacheFree,
+}
+
+/// Billing rule for messages from `source`
+pub fn provider_billing_rule(source: &str) -> BillingRule {
+    match source {
+        "gemini" | "qwen" | "jules" => BillingRule::CacheFree,
+        _ => BillingRule::Standard,
+    }
+}
+
+impl BillingRule {
+    /// Tokens to price under this rule. Reasoning stays separate so a reasoning
+    /// rate multiplier still applies; without one it is billed as output.
+    pub fn billable(self, tokens: &TokenBreakdown) -> TokenBreakdown {
+        match self {
+            BillingRule::Standard => tokens.clone(),
+            BillingRule::CacheFree => TokenBreakdown {
+                cache_read: 0,
+                cache_write: 0,
+                ..tokens.clone()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_gemini_format_sources_are_cache_free() {
+        assert_eq!(provider_billing_rule("gemini"), BillingRule::CacheFree);
+        assert_eq!(provider_billing_rule("qwen"), BillingRule::CacheFree);
+        assert_eq!(provider_billing_rule("jules"), BillingRule::CacheFree);
+        for source in [
+            "opencode", "claude", "codex", "cursor", "amp", "droid", "openclaw", "warp", "roo",
+            "kilo", "copilot",
+        ] {
+            assert_eq!(
+                provider_billing_rule(source),
+                BillingRule::Standard,
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_cache_free_zeroes_cache_only() {
+        let tokens = TokenBreakdown {
+            input: 100,
+            output: 50,
+            cache_read: 400,
+            cache_write: 30,
+            reasoning: 20,
+        };
+
+        let billable = BillingRule::CacheFree.billable(&tokens);
+        assert_eq!(billable.input, 100);
+        assert_eq!(billable.output, 50);
+        assert_eq!(billable.reasoning, 20);
+        assert_eq!(billable.cache_read, 0);
+        assert_eq!(billable.cache_write, 0);
+
+        let standard = BillingRule::Standard.billable(&tokens);
+        assert_eq!(standard.cache_read, 400);
+        assert_eq!(standard.cache_write, 30);
+    }
+}
@@ -1,11 +1,15 @@
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
-use serde::{Serialize, Deserialize};
 
 const CACHE_TTL_SECS: u64 = 3600;
 
+/// `TOKSCALE_CACHE_DIR` if set, otherwise `tokscale` under the OS cache dir
 pub fn get_cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("TOKSCALE_CACHE_DIR").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
     dirs::cache_dir()
         .unwrap_or_else(|| PathBuf::from("/tmp"))
         .join("tokscale")
@@ -18,57 +22,123 @@ pub fn get_cache_path(filename: &str) -> PathBuf {
 #[derive(Serialize, Deserialize)]
 pub struct CachedData<T> {
     pub timestamp: u64,
+    /// `ETag` response header, replayed as `If-None-Match` on refetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// `Last-Modified` response header, replayed as `If-Modified-Since` on refetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    pub data: T,
+}
+
+/// Pricing data returned by a fetcher, noting whether the on-disk cache supplied it
+/// (either still fresh or revalidated upstream) instead of a full download.
+pub struct Fetched<T> {
     pub data: T,
+    pub from_cache: bool,
 }
 
-pub fn load_cache<T: for<'de> Deserialize<'de>>(filename: &str) -> Option<T> {
-    let path = get_cache_path(filename);
-    let content = fs::read_to_string(&path).ok()?;
-    let cached: CachedData<T> = serde_json::from_str(&content).ok()?;
-    
-    let now = SystemTime::now()
+pub fn now_secs() -> u64 {
+    SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
-        .as_secs();
-    
-    if cached.timestamp > now || now.saturating_sub(cached.timestamp) > CACHE_TTL_SECS {
+        .as_secs()
+}
+
+/// Whether a cache entry written at `timestamp` is still within the TTL
+pub fn is_fresh(timestamp: u64) -> bool {
+    let now = now_secs();
+    timestamp <= now && now.saturating_sub(timestamp) <= CACHE_TTL_SECS
+}
+
+/// Load the data at `path` if it was cached within the TTL
+pub fn load_cache_at<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<T> {
+    let cached: CachedData<T> = load_cache_entry_at(path)?;
+
+    if !is_fresh(cached.timestamp) {
         return None;
     }
-    
+
     Some(cached.data)
 }
 
-pub fn save_cache<T: Serialize>(filename: &str, data: &T) -> Result<(), std::io::Error> {
-    let dir = get_cache_dir();
+/// Load a cache entry regardless of its age (used for conditional refetches)
+pub fn load_cache_entry_at<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<CachedData<T>> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn save_cache_at<T: Serialize>(
+    final_path: &Path,
+    data: &T,
+    etag: Option<String>,
+    last_modified: Option<String>,
+) -> Result<(), std::io::Error> {
+    let dir = final_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(get_cache_dir);
     fs::create_dir_all(&dir)?;
-    
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
-    let cached = CachedData { timestamp: now, data };
+
+    let cached = CachedData {
+        timestamp: now_secs(),
+        etag,
+        last_modified,
+        data,
+    };
     let content = serde_json::to_string(&cached)?;
-    
-    let final_path = get_cache_path(filename);
+
+    let filename = final_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
     let nanos = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map(|d| d.as_nanos() as u64)
         .unwrap_or(0);
     let tmp_filename = format!(".{}.{}.{:x}.tmp", filename, std::process::id(), nanos);
     let tmp_path = dir.join(&tmp_filename);
-    
+
     use std::io::Write;
     let write_result = (|| {
         let mut file = fs::File::create(&tmp_path)?;
         file.write_all(content.as_bytes())?;
         file.sync_all()?;
-        fs::rename(&tmp_path, &final_path)
+        fs::rename(&tmp_path, final_path)
     })();
-    
+
     if write_result.is_err() {
         let _ = fs::remove_file(&tmp_path);
     }
-    
+
     write_result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+    use tempfile::TempDir;
+
+    #[test]
+    #[serial]
+    fn test_cache_dir_env_override_round_trips() {
+        let previous = std::env::var("TOKSCALE_CACHE_DIR").ok();
+        let dir = TempDir::new().unwrap();
+        let base = dir.path().join("nested");
+        std::env::set_var("TOKSCALE_CACHE_DIR", &base);
+
+        let path = get_cache_path("pricing-test.json");
+        assert_eq!(path, base.join("pricing-test.json"));
+        save_cache_at(&path, &vec![1, 2, 3], None, None).unwrap();
+        assert_eq!(load_cache_at::<Vec<i32>>(&path), Some(vec![1, 2, 3]));
+        // Only the cache file is left behind, no temp files
+        assert_eq!(fs::read_dir(&base).unwrap().count(), 1);
+
+        match previous {
+            Some(value) => std::env::set_var("TOKSCALE_CACHE_DIR", value),
+            None => std::env::remove_var("TOKSCALE_CACHE_DIR"),
+        }
+        assert_ne!(get_cache_dir(), base);
+    }
+}
@@ -1,11 +1,12 @@
-use super::cache;
+use super::{backoff, cache};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use serde::{Serialize, Deserialize};
+use std::path::Path;
 
 const CACHE_FILENAME: &str = "pricing-litellm.json";
-const PRICING_URL: &str = "https://raw.githubusercontent.com/BerriAI/litellm/main/model_prices_and_context_window.json";
+const PRICING_URL: &str =
+    "https://raw.githubusercontent.com/BerriAI/litellm/main/model_prices_and_context_window.json";
 const MAX_RETRIES: u32 = 3;
-const INITIAL_BACKOFF_MS: u64 = 200;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ModelPricing {
@@ -13,69 +14,260 @@ pub struct ModelPricing {
     pub output_cost_per_token: Option<f64>,
     pub cache_creation_input_token_cost: Option<f64>,
     pub cache_read_input_token_cost: Option<f64>,
+    /// Long-context tier rates (e.g. Claude's 1M-context beta), applied to every
+    /// token of a request whose prompt exceeds 200k tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_cost_per_token_above_200k_tokens: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_cost_per_token_above_200k_tokens: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_token_cost_above_200k_tokens: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_token_cost_above_200k_tokens: Option<f64>,
 }
 
 pub type PricingDataset = HashMap<String, ModelPricing>;
 
-pub fn load_cached() -> Option<PricingDataset> {
-    cache::load_cache(CACHE_FILENAME)
+pub async fn fetch(force_refresh: bool) -> Result<cache::Fetched<PricingDataset>, String> {
+    fetch_from(
+        PRICING_URL,
+        &cache::get_cache_path(CACHE_FILENAME),
+        force_refresh,
+    )
+    .await
 }
 
-pub async fn fetch() -> Result<PricingDataset, reqwest::Error> {
-    if let Some(cached) = load_cached() {
-        return Ok(cached);
-    }
-    
+/// Fetch pricing from `url`, revalidating an expired cache entry at `cache_path`
+/// with `If-None-Match`/`If-Modified-Since` so an unchanged upstream file costs a 304
+/// instead of a full download. `force_refresh` revalidates even a fresh entry.
+async fn fetch_from(
+    url: &str,
+    cache_path: &Path,
+    force_refresh: bool,
+) -> Result<cache::Fetched<PricingDataset>, String> {
+    let stale = match cache::load_cache_entry_at::<PricingDataset>(cache_path) {
+        Some(entry) if !force_refresh && cache::is_fresh(entry.timestamp) => {
+            return Ok(cache::Fetched {
+                data: entry.data,
+                from_cache: true,
+            });
+        }
+        other => other,
+    };
+
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(30))
         .connect_timeout(std::time::Duration::from_secs(10))
-        .build()?;
-    
-    let mut last_error: Option<reqwest::Error> = None;
-    
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut last_error: Option<String> = None;
+
     for attempt in 0..MAX_RETRIES {
-        match client.get(PRICING_URL).send().await {
+        let mut request = client.get(url);
+        if let Some(entry) = &stale {
+            if let Some(etag) = &entry.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        match request.send().await {
             Ok(response) => {
                 let status = response.status();
-                
+
+                if status == reqwest::StatusCode::NOT_MODIFIED {
+                    if let Some(entry) = stale {
+                        // Upstream unchanged: bump the timestamp and keep the stored data
+                        let _ = cache::save_cache_at(
+                            cache_path,
+                            &entry.data,
+                            entry.etag,
+                            entry.last_modified,
+                        );
+                        return Ok(cache::Fetched {
+                            data: entry.data,
+                            from_cache: true,
+                        });
+                    }
+                }
+
                 if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
-                    eprintln!("[tokscale] LiteLLM HTTP {} (attempt {}/{})", status, attempt + 1, MAX_RETRIES);
+                    eprintln!(
+                        "[tokscale] LiteLLM HTTP {} (attempt {}/{})",
+                        status,
+                        attempt + 1,
+                        MAX_RETRIES
+                    );
                     let _ = response.bytes().await;
                     if attempt < MAX_RETRIES - 1 {
-                        tokio::time::sleep(std::time::Duration::from_millis(
-                            INITIAL_BACKOFF_MS * (1 << attempt)
-                        )).await;
+                        backoff::sleep(attempt).await;
                     }
                     continue;
                 }
-                
+
                 if !status.is_success() {
                     eprintln!("[tokscale] LiteLLM HTTP {}", status);
-                    return Err(response.error_for_status().unwrap_err());
+                    return Err(format!("LiteLLM HTTP {}", status));
                 }
-                
+
+                let header_value = |name: reqwest::header::HeaderName| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .map(|v| v.to_string())
+                };
+                let etag = header_value(reqwest::header::ETAG);
+                let last_modified = header_value(reqwest::header::LAST_MODIFIED);
+
                 match response.json::<PricingDataset>().await {
                     Ok(data) => {
-                        let _ = cache::save_cache(CACHE_FILENAME, &data);
-                        return Ok(data);
+                        let _ = cache::save_cache_at(cache_path, &data, etag, last_modified);
+                        return Ok(cache::Fetched {
+                            data,
+                            from_cache: false,
+                        });
                     }
                     Err(e) => {
                         eprintln!("[tokscale] LiteLLM JSON parse failed: {}", e);
-                        return Err(e);
+                        return Err(e.to_string());
                     }
                 }
             }
             Err(e) => {
-                eprintln!("[tokscale] LiteLLM network error (attempt {}/{}): {}", attempt + 1, MAX_RETRIES, e);
-                last_error = Some(e);
+                eprintln!(
+                    "[tokscale] LiteLLM network error (attempt {}/{}): {}",
+                    attempt + 1,
+                    MAX_RETRIES,
+                    e
+                );
+                last_error = Some(e.to_string());
                 if attempt < MAX_RETRIES - 1 {
-                    tokio::time::sleep(std::time::Duration::from_millis(
-                        INITIAL_BACKOFF_MS * (1 << attempt)
-                    )).await;
+                    backoff::sleep(attempt).await;
                 }
             }
         }
     }
-    
-    Err(last_error.expect("should have error after retries"))
+
+    Err(last_error.unwrap_or_else(|| "LiteLLM fetch failed after retries".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pricing::test_server::serve;
+    use tempfile::TempDir;
+
+    fn expire(cache_path: &Path) {
+        let mut entry: cache::CachedData<PricingDataset> =
+            cache::load_cache_entry_at(cache_path).unwrap();
+        entry.timestamp = 1;
+        std::fs::write(cache_path, serde_json::to_string(&entry).unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_revalidates_with_etag_and_reuses_on_304() {
+        let dir = TempDir::new().unwrap();
+        let cache_path = dir.path().join(CACHE_FILENAME);
+
+        let body =
+            r#"{"gpt-4o":{"input_cost_per_token":0.0000025,"output_cost_per_token":0.00001}}"#;
+        let ok = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let not_modified =
+            "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string();
+        let (url, server) = serve("/prices.json", vec![ok, not_modified]);
+
+        let first = fetch_from(&url, &cache_path, false).await.unwrap();
+        assert!(first.data.contains_key("gpt-4o"));
+        assert!(!first.from_cache);
+        let entry: cache::CachedData<PricingDataset> =
+            cache::load_cache_entry_at(&cache_path).unwrap();
+        assert_eq!(entry.etag.as_deref(), Some("\"v1\""));
+
+        expire(&cache_path);
+
+        let second = fetch_from(&url, &cache_path, false).await.unwrap();
+        assert!(second.from_cache);
+        assert_eq!(
+            second.data.get("gpt-4o").unwrap().input_cost_per_token,
+            Some(0.0000025)
+        );
+
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+
+        let refreshed: cache::CachedData<PricingDataset> =
+            cache::load_cache_entry_at(&cache_path).unwrap();
+        assert!(cache::is_fresh(refreshed.timestamp));
+        assert_eq!(refreshed.etag.as_deref(), Some("\"v1\""));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_uses_fresh_cache_without_network() {
+        let dir = TempDir::new().unwrap();
+        let cache_path = dir.path().join(CACHE_FILENAME);
+
+        let mut data = PricingDataset::new();
+        data.insert("gpt-4o".into(), ModelPricing::default());
+        cache::save_cache_at(&cache_path, &data, Some("\"v1\"".into()), None).unwrap();
+
+        // Nothing listens on this port; a network attempt would fail
+        let result = fetch_from("http://127.0.0.1:9/prices.json", &cache_path, false)
+            .await
+            .unwrap();
+        assert!(result.data.contains_key("gpt-4o"));
+        assert!(result.from_cache);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_force_refresh_revalidates_fresh_cache() {
+        let dir = TempDir::new().unwrap();
+        let cache_path = dir.path().join(CACHE_FILENAME);
+
+        let mut data = PricingDataset::new();
+        data.insert("gpt-4o".into(), ModelPricing::default());
+        cache::save_cache_at(&cache_path, &data, Some("\"v1\"".into()), None).unwrap();
+
+        let body = r#"{"gpt-5":{"input_cost_per_token":0.00000125}}"#;
+        let ok = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nETag: \"v2\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let (url, server) = serve("/prices.json", vec![ok]);
+
+        let result = fetch_from(&url, &cache_path, true).await.unwrap();
+        assert!(!result.from_cache);
+        assert!(result.data.contains_key("gpt-5"));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("if-none-match: \"v1\""));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_unexpected_status_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let cache_path = dir.path().join(CACHE_FILENAME);
+
+        // A 304 with nothing cached to revalidate is neither success nor 4xx/5xx
+        let not_modified =
+            "HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string();
+        let (url, server) = serve("/prices.json", vec![not_modified]);
+        let Err(err) = fetch_from(&url, &cache_path, false).await else {
+            panic!("a 304 without a cached entry should fail");
+        };
+        assert!(err.contains("304"), "{err}");
+        server.join().unwrap();
+        assert!(cache::load_cache_entry_at::<PricingDataset>(&cache_path).is_none());
+    }
 }
@@ -1,4 +1,5 @@
 use super::{aliases, litellm::ModelPricing};
+use crate::TokenCostBreakdown;
 use std::collections::HashMap;
 use std::sync::RwLock;
 
@@ -47,6 +48,37 @@ const FUZZY_BLOCKLIST: &[&str] = &["auto", "mini", "chat", "base"];
 
 const MIN_FUZZY_MATCH_LEN: usize = 5;
 
+/// Which model ids are allowed to fall through to fuzzy matching. Defaults to
+/// ids of at least five characters outside a short blocklist of generic words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatchConfig {
+    /// Shortest model id that may fuzzy-match
+    pub min_len: usize,
+    /// Model ids that never fuzzy-match, however long
+    pub blocklist: Vec<String>,
+}
+
+impl Default for FuzzyMatchConfig {
+    fn default() -> Self {
+        Self {
+            min_len: MIN_FUZZY_MATCH_LEN,
+            blocklist: FUZZY_BLOCKLIST.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl FuzzyMatchConfig {
+    fn allows(&self, model_id: &str) -> bool {
+        let model_id = model_id.to_lowercase();
+        model_id.len() >= self.min_len
+            && !self.blocklist.iter().any(|b| b.to_lowercase() == model_id)
+    }
+}
+
+/// Prompt size (input + cache read + cache write) above which long-context
+/// tier rates apply
+const LONG_CONTEXT_THRESHOLD_TOKENS: i64 = 200_000;
+
 /// Minimum length for a model name candidate after prefix/suffix stripping.
 /// Prevents false positives like "pro" or "flash" being matched alone.
 const MIN_MODEL_NAME_LEN: usize = 5;
@@ -59,6 +91,37 @@ const MAX_PREFIX_STRIP_SEGMENTS: usize = 2;
 /// Handles tier suffixes (-high, -low) and variant suffixes (-thinking, -codex, -codex-max-xhigh).
 const MAX_SUFFIX_STRIP_SEGMENTS: usize = 4;
 
+/// Pricing tiers tools append to a model id, after `-` or `:` (e.g. "glm-4.7:free")
+const KNOWN_TIER_SUFFIXES: &[&str] = &["free", "minimal", "low", "medium", "high", "xhigh"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    LiteLLM,
+    OpenRouter,
+}
+
+const DEFAULT_PRIORITY: [Source; 2] = [Source::LiteLLM, Source::OpenRouter];
+
+/// Resolve a user-supplied source order (e.g. `["openrouter", "litellm"]`).
+/// Unknown names are ignored and any source left out keeps its default position
+/// after the listed ones, so a partial or empty list still covers both maps.
+fn resolve_priority(priority: Option<&[String]>) -> [Source; 2] {
+    let first =
+        priority
+            .unwrap_or_default()
+            .iter()
+            .find_map(|name| match name.to_lowercase().as_str() {
+                "litellm" => Some(Source::LiteLLM),
+                "openrouter" => Some(Source::OpenRouter),
+                _ => None,
+            });
+
+    match first {
+        Some(Source::OpenRouter) => [Source::OpenRouter, Source::LiteLLM],
+        _ => DEFAULT_PRIORITY,
+    }
+}
+
 #[derive(Clone)]
 struct CachedResult {
     pricing: ModelPricing,
@@ -74,7 +137,10 @@ pub struct PricingLookup {
     litellm_lower: HashMap<String, String>,
     openrouter_lower: HashMap<String, String>,
     openrouter_model_part: HashMap<String, String>,
+    priority: [Source; 2],
+    fuzzy: FuzzyMatchConfig,
     lookup_cache: RwLock<HashMap<String, Option<CachedResult>>>,
+    cheapest_cache: RwLock<HashMap<String, Option<CachedResult>>>,
 }
 
 pub struct LookupResult {
@@ -87,12 +153,22 @@ impl PricingLookup {
     pub fn new(
         litellm: HashMap<String, ModelPricing>,
         openrouter: HashMap<String, ModelPricing>,
+    ) -> Self {
+        Self::with_priority(litellm, openrouter, None)
+    }
+
+    /// Like [`PricingLookup::new`], but tries the sources in `priority` order
+    /// during auto lookup. `None` keeps LiteLLM first.
+    pub fn with_priority(
+        litellm: HashMap<String, ModelPricing>,
+        openrouter: HashMap<String, ModelPricing>,
+        priority: Option<&[String]>,
     ) -> Self {
         let mut litellm_keys: Vec<String> = litellm.keys().cloned().collect();
-        litellm_keys.sort_by(|a, b| b.len().cmp(&a.len()));
+        litellm_keys.sort_by_key(|k| std::cmp::Reverse(k.len()));
 
         let mut openrouter_keys: Vec<String> = openrouter.keys().cloned().collect();
-        openrouter_keys.sort_by(|a, b| b.len().cmp(&a.len()));
+        openrouter_keys.sort_by_key(|k| std::cmp::Reverse(k.len()));
 
         let mut litellm_lower = HashMap::with_capacity(litellm.len());
         for key in &litellm_keys {
@@ -104,7 +180,7 @@ impl PricingLookup {
         for key in &openrouter_keys {
             let lower = key.to_lowercase();
             openrouter_lower.insert(lower.clone(), key.clone());
-            if let Some(model_part) = lower.split('/').last() {
+            if let Some(model_part) = lower.split('/').next_back() {
                 if model_part != lower {
                     openrouter_model_part.insert(model_part.to_string(), key.clone());
                 }
@@ -119,38 +195,78 @@ impl PricingLookup {
             litellm_lower,
             openrouter_lower,
             openrouter_model_part,
+            priority: resolve_priority(priority),
+            fuzzy: FuzzyMatchConfig::default(),
             lookup_cache: RwLock::new(HashMap::with_capacity(64)),
+            cheapest_cache: RwLock::new(HashMap::with_capacity(64)),
         }
     }
 
-    pub fn lookup(&self, model_id: &str) -> Option<LookupResult> {
-        if let Some(cached) = self
-            .lookup_cache
-            .read()
-            .ok()
-            .and_then(|c| c.get(model_id).cloned())
-        {
-            return cached.map(|c| LookupResult {
-                pricing: c.pricing,
-                source: c.source,
-                matched_key: c.matched_key,
-            });
+    /// Number of models in the LiteLLM and OpenRouter maps
+    pub fn model_counts(&self) -> (usize, usize) {
+        (self.litellm.len(), self.openrouter.len())
+    }
+
+    /// Whether auto lookup would already try the sources in `priority` order.
+    pub fn has_priority(&self, priority: Option<&[String]>) -> bool {
+        self.priority == resolve_priority(priority)
+    }
+
+    /// Build a copy of this lookup that tries the sources in `priority` order.
+    pub fn with_reordered_sources(&self, priority: Option<&[String]>) -> Self {
+        Self {
+            fuzzy: self.fuzzy.clone(),
+            ..Self::with_priority(self.litellm.clone(), self.openrouter.clone(), priority)
         }
+    }
+
+    /// The fuzzy matching rules this lookup applies
+    pub fn fuzzy_config(&self) -> &FuzzyMatchConfig {
+        &self.fuzzy
+    }
 
-        let result = self.lookup_with_source(model_id, None);
-
-        if let Ok(mut cache) = self.lookup_cache.write() {
-            cache.insert(
-                model_id.to_string(),
-                result.as_ref().map(|r| CachedResult {
-                    pricing: r.pricing.clone(),
-                    source: r.source.clone(),
-                    matched_key: r.matched_key.clone(),
-                }),
-            );
+    /// Build a copy of this lookup that fuzzy-matches under `fuzzy`
+    pub fn with_fuzzy_config(&self, fuzzy: FuzzyMatchConfig) -> Self {
+        Self {
+            fuzzy,
+            priority: self.priority,
+            ..Self::new(self.litellm.clone(), self.openrouter.clone())
         }
+    }
 
-        result
+    pub fn lookup(&self, model_id: &str) -> Option<LookupResult> {
+        cached_lookup(&self.lookup_cache, model_id, || {
+            self.lookup_with_source(model_id, None)
+        })
+    }
+
+    /// The LiteLLM and OpenRouter matches for `model_id`, each resolved on its own
+    /// rather than stopping at the first source that matches. Ordered by priority.
+    pub fn lookup_candidates(&self, model_id: &str) -> Vec<LookupResult> {
+        self.priority
+            .iter()
+            .filter_map(|source| {
+                let force = match source {
+                    Source::LiteLLM => "litellm",
+                    Source::OpenRouter => "openrouter",
+                };
+                self.lookup_with_source(model_id, Some(force))
+            })
+            .collect()
+    }
+
+    /// Whichever candidate has the lower input + output rate. Candidates without
+    /// any input or output price are skipped; ties keep the priority order.
+    pub fn lookup_cheapest(&self, model_id: &str) -> Option<LookupResult> {
+        cached_lookup(&self.cheapest_cache, model_id, || {
+            self.lookup_candidates(model_id)
+                .into_iter()
+                .filter(|c| effective_rate(&c.pricing) > 0.0)
+                .fold(None, |best: Option<LookupResult>, c| match best {
+                    Some(b) if effective_rate(&b.pricing) <= effective_rate(&c.pricing) => Some(b),
+                    _ => Some(c),
+                })
+        })
     }
 
     pub fn lookup_with_source(
@@ -178,7 +294,16 @@ impl PricingLookup {
             return Some(result);
         }
 
-        // 3. Try stripping unknown prefixes (e.g., antigravity-, myplugin-)
+        // 3. Try stripping stacked tier suffixes (e.g., -high-free, :free:low)
+        if let Some(base) = strip_tier_suffixes(&lower) {
+            if let Some(result) =
+                do_lookup(&base).or_else(|| try_strip_unknown_suffix(&base, do_lookup))
+            {
+                return Some(result);
+            }
+        }
+
+        // 4. Try stripping unknown prefixes (e.g., antigravity-, myplugin-)
         //    For each prefix candidate, also try suffix stripping
         if let Some(result) = try_strip_unknown_prefix(&lower, do_lookup) {
             return Some(result);
@@ -188,82 +313,89 @@ impl PricingLookup {
     }
 
     fn lookup_auto(&self, model_id: &str) -> Option<LookupResult> {
-        if let Some(result) = self.exact_match_litellm(model_id) {
-            return Some(result);
-        }
-
-        if let Some(result) = self.exact_match_openrouter(model_id) {
-            return Some(result);
-        }
-
-        if let Some(version_normalized) = normalize_version_separator(model_id) {
-            if let Some(result) = self.exact_match_litellm(&version_normalized) {
-                return Some(result);
-            }
-            if let Some(result) = self.exact_match_openrouter(&version_normalized) {
-                return Some(result);
-            }
-        }
-
-        if let Some(normalized) = normalize_model_name(model_id) {
-            if let Some(result) = self.exact_match_litellm(&normalized) {
-                return Some(result);
-            }
-            if let Some(result) = self.exact_match_openrouter(&normalized) {
-                return Some(result);
+        let version_normalized = normalize_version_separator(model_id);
+        let normalized = normalize_model_name(model_id);
+
+        let exact_candidates = std::iter::once(Some(model_id))
+            .chain([version_normalized.as_deref(), normalized.as_deref()])
+            .flatten();
+        for candidate in exact_candidates {
+            for source in &self.priority {
+                if let Some(result) = self.exact_match(*source, candidate) {
+                    return Some(result);
+                }
             }
         }
 
-        if let Some(result) = self.prefix_match_litellm(model_id) {
-            return Some(result);
-        }
-        if let Some(result) = self.prefix_match_openrouter(model_id) {
-            return Some(result);
-        }
-
-        if let Some(version_normalized) = normalize_version_separator(model_id) {
-            if let Some(result) = self.prefix_match_litellm(&version_normalized) {
-                return Some(result);
-            }
-            if let Some(result) = self.prefix_match_openrouter(&version_normalized) {
-                return Some(result);
+        let prefix_candidates = std::iter::once(Some(model_id))
+            .chain([version_normalized.as_deref()])
+            .flatten();
+        for candidate in prefix_candidates {
+            for source in &self.priority {
+                if let Some(result) = self.prefix_match(*source, candidate) {
+                    return Some(result);
+                }
             }
         }
 
-        if !is_fuzzy_eligible(model_id) {
+        if !self.fuzzy.allows(model_id) {
             return None;
         }
 
-        let litellm_result = self.fuzzy_match_litellm(model_id);
-        let openrouter_result = self.fuzzy_match_openrouter(model_id);
-
-        match (&litellm_result, &openrouter_result) {
-            (Some(l), Some(o)) => {
-                let l_is_original = is_original_provider(&l.matched_key);
-                let o_is_original = is_original_provider(&o.matched_key);
-                let l_is_reseller = is_reseller_provider(&l.matched_key);
-                let o_is_reseller = is_reseller_provider(&o.matched_key);
-
-                if o_is_original && !l_is_original {
-                    return openrouter_result;
+        // Provider quality decides between two fuzzy hits first; the configured
+        // priority only breaks the remaining ties.
+        let [first, second] = self.priority;
+        let first_result = self.fuzzy_match(first, model_id);
+        let second_result = self.fuzzy_match(second, model_id);
+
+        match (&first_result, &second_result) {
+            (Some(f), Some(s)) => {
+                let f_is_original = is_original_provider(&f.matched_key);
+                let s_is_original = is_original_provider(&s.matched_key);
+                let f_is_reseller = is_reseller_provider(&f.matched_key);
+                let s_is_reseller = is_reseller_provider(&s.matched_key);
+
+                if s_is_original && !f_is_original {
+                    return second_result;
                 }
-                if l_is_original && !o_is_original {
-                    return litellm_result;
+                if f_is_original && !s_is_original {
+                    return first_result;
                 }
-                if !l_is_reseller && o_is_reseller {
-                    return litellm_result;
+                if !f_is_reseller && s_is_reseller {
+                    return first_result;
                 }
-                if !o_is_reseller && l_is_reseller {
-                    return openrouter_result;
+                if !s_is_reseller && f_is_reseller {
+                    return second_result;
                 }
-                litellm_result
+                first_result
             }
-            (Some(_), None) => litellm_result,
-            (None, Some(_)) => openrouter_result,
+            (Some(_), None) => first_result,
+            (None, Some(_)) => second_result,
             (None, None) => None,
         }
     }
 
+    fn exact_match(&self, source: Source, model_id: &str) -> Option<LookupResult> {
+        match source {
+            Source::LiteLLM => self.exact_match_litellm(model_id),
+            Source::OpenRouter => self.exact_match_openrouter(model_id),
+        }
+    }
+
+    fn prefix_match(&self, source: Source, model_id: &str) -> Option<LookupResult> {
+        match source {
+            Source::LiteLLM => self.prefix_match_litellm(model_id),
+            Source::OpenRouter => self.prefix_match_openrouter(model_id),
+        }
+    }
+
+    fn fuzzy_match(&self, source: Source, model_id: &str) -> Option<LookupResult> {
+        match source {
+            Source::LiteLLM => self.fuzzy_match_litellm(model_id),
+            Source::OpenRouter => self.fuzzy_match_openrouter(model_id),
+        }
+    }
+
     fn lookup_litellm_only(&self, model_id: &str) -> Option<LookupResult> {
         if let Some(result) = self.exact_match_litellm(model_id) {
             return Some(result);
@@ -286,7 +418,7 @@ impl PricingLookup {
                 return Some(result);
             }
         }
-        if is_fuzzy_eligible(model_id) {
+        if self.fuzzy.allows(model_id) {
             if let Some(result) = self.fuzzy_match_litellm(model_id) {
                 return Some(result);
             }
@@ -316,7 +448,7 @@ impl PricingLookup {
                 return Some(result);
             }
         }
-        if is_fuzzy_eligible(model_id) {
+        if self.fuzzy.allows(model_id) {
             if let Some(result) = self.fuzzy_match_openrouter(model_id) {
                 return Some(result);
             }
@@ -413,7 +545,7 @@ impl PricingLookup {
 
         for key in &self.openrouter_keys {
             let lower_key = key.to_lowercase();
-            let model_part = lower_key.split('/').last().unwrap_or(&lower_key);
+            let model_part = lower_key.split('/').next_back().unwrap_or(&lower_key);
             if family_matches(model_part, &family) && contains_model_id(model_part, model_id) {
                 family_matches_list.push(key);
             }
@@ -428,7 +560,7 @@ impl PricingLookup {
         let mut all_matches: Vec<&String> = Vec::new();
         for key in &self.openrouter_keys {
             let lower_key = key.to_lowercase();
-            let model_part = lower_key.split('/').last().unwrap_or(&lower_key);
+            let model_part = lower_key.split('/').next_back().unwrap_or(&lower_key);
             if contains_model_id(model_part, model_id) {
                 all_matches.push(key);
             }
@@ -437,6 +569,9 @@ impl PricingLookup {
         select_best_match(&all_matches, &self.openrouter, "OpenRouter")
     }
 
+    /// Total cost at the normally matched rates; unknown models cost nothing.
+    /// The service prices through `cost_breakdown_with` directly.
+    #[allow(dead_code)]
     pub fn calculate_cost(
         &self,
         model_id: &str,
@@ -446,21 +581,106 @@ impl PricingLookup {
         cache_write: i64,
         reasoning: i64,
     ) -> f64 {
-        let result = match self.lookup(model_id) {
-            Some(r) => r,
-            None => return 0.0,
-        };
+        self.lookup(model_id).map_or(0.0, |r| {
+            cost_breakdown_with(
+                &r.pricing,
+                input,
+                output,
+                cache_read,
+                cache_write,
+                reasoning,
+                None,
+            )
+            .total()
+        })
+    }
+}
+
+fn cached_lookup(
+    cache: &RwLock<HashMap<String, Option<CachedResult>>>,
+    model_id: &str,
+    resolve: impl FnOnce() -> Option<LookupResult>,
+) -> Option<LookupResult> {
+    if let Some(cached) = cache.read().ok().and_then(|c| c.get(model_id).cloned()) {
+        return cached.map(|c| LookupResult {
+            pricing: c.pricing,
+            source: c.source,
+            matched_key: c.matched_key,
+        });
+    }
 
-        let p = &result.pricing;
-        let safe_price =
-            |opt: Option<f64>| opt.filter(|v| v.is_finite() && *v >= 0.0).unwrap_or(0.0);
+    let result = resolve();
 
-        let input_cost = input as f64 * safe_price(p.input_cost_per_token);
-        let output_cost = (output + reasoning) as f64 * safe_price(p.output_cost_per_token);
-        let cache_read_cost = cache_read as f64 * safe_price(p.cache_read_input_token_cost);
-        let cache_write_cost = cache_write as f64 * safe_price(p.cache_creation_input_token_cost);
+    if let Ok(mut cache) = cache.write() {
+        cache.insert(
+            model_id.to_string(),
+            result.as_ref().map(|r| CachedResult {
+                pricing: r.pricing.clone(),
+                source: r.source.clone(),
+                matched_key: r.matched_key.clone(),
+            }),
+        );
+    }
 
-        input_cost + output_cost + cache_read_cost + cache_write_cost
+    result
+}
+
+fn safe_price(opt: Option<f64>) -> f64 {
+    opt.filter(|v| v.is_finite() && *v >= 0.0).unwrap_or(0.0)
+}
+
+fn effective_rate(p: &ModelPricing) -> f64 {
+    safe_price(p.input_cost_per_token) + safe_price(p.output_cost_per_token)
+}
+
+/// Cost of a request at `p`'s rates, split by token type. Reasoning tokens bill
+/// at the output rate unless `reasoning_multiplier` is set, in which case they
+/// cost that multiple of it.
+pub(super) fn cost_breakdown_with(
+    p: &ModelPricing,
+    input: i64,
+    output: i64,
+    cache_read: i64,
+    cache_write: i64,
+    reasoning: i64,
+    reasoning_multiplier: Option<f64>,
+) -> TokenCostBreakdown {
+    // The long-context tier is decided by the whole prompt, cached tokens included,
+    // and then applies to every token of the request. Missing tier rates fall back
+    // to the base rate.
+    let long_context = input + cache_read + cache_write > LONG_CONTEXT_THRESHOLD_TOKENS;
+    let rate = |base: Option<f64>, above: Option<f64>| {
+        if long_context {
+            safe_price(above.or(base))
+        } else {
+            safe_price(base)
+        }
+    };
+
+    let output_rate = rate(
+        p.output_cost_per_token,
+        p.output_cost_per_token_above_200k_tokens,
+    );
+
+    TokenCostBreakdown {
+        input_cost: input as f64
+            * rate(
+                p.input_cost_per_token,
+                p.input_cost_per_token_above_200k_tokens,
+            ),
+        output_cost: output as f64 * output_rate,
+        cache_read_cost: cache_read as f64
+            * rate(
+                p.cache_read_input_token_cost,
+                p.cache_read_input_token_cost_above_200k_tokens,
+            ),
+        cache_write_cost: cache_write as f64
+            * rate(
+                p.cache_creation_input_token_cost,
+                p.cache_creation_input_token_cost_above_200k_tokens,
+            ),
+        reasoning_cost: reasoning as f64 * output_rate * reasoning_multiplier.unwrap_or(1.0),
+        unattributed_cost: 0.0,
     }
 }
 
@@ -526,7 +746,7 @@ fn extract_model_family(model_id: &str) -> String {
     }
 
     lower
-        .split(|c: char| c == '-' || c == '_' || c == '.')
+        .split(['-', '_', '.'])
         .next()
         .unwrap_or(&lower)
         .to_string()
@@ -579,10 +799,80 @@ fn normalize_model_name(model_id: &str) -> Option<String> {
             return Some("claude-3.5-haiku".into());
         }
     }
+    if lower.starts_with("gemini-") {
+        return strip_gemini_version_suffix(&lower);
+    }
+    if let Some(gpt5) = normalize_gpt5_name(&lower) {
+        return Some(gpt5);
+    }
 
     None
 }
 
+/// GPT-5 family variants in the order they are checked; longer names first so
+/// "codex-mini" is not read as "mini"
+const GPT5_VARIANTS: &[&str] = &["codex-max", "codex-mini", "codex", "mini", "nano", "pro"];
+
+/// Canonical LiteLLM key for a GPT-5 family id, e.g. "gpt5-codex" →
+/// "gpt-5-codex", "gpt-5-1-codex-high" → "gpt-5.1-codex", "gpt-5.0-mini" →
+/// "gpt-5-mini". Tier and date suffixes are dropped.
+fn normalize_gpt5_name(lower: &str) -> Option<String> {
+    let rest = ["gpt-5", "gpt5", "gpt 5"]
+        .iter()
+        .find_map(|prefix| lower.strip_prefix(prefix))?;
+    if rest.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    // A single-digit minor version: "5.1", "5-1" (but not "5-2025-08-07")
+    let bytes = rest.as_bytes();
+    let (version, rest) = match bytes {
+        [b'.' | b'-', minor, tail @ ..]
+            if minor.is_ascii_digit() && !tail.first().is_some_and(u8::is_ascii_digit) =>
+        {
+            match minor {
+                b'0' => ("5".to_string(), &rest[2..]),
+                _ => (format!("5.{}", *minor as char), &rest[2..]),
+            }
+        }
+        _ => ("5".to_string(), rest),
+    };
+
+    let variant = GPT5_VARIANTS.iter().find(|v| {
+        rest.split([' ', ':'])
+            .any(|part| contains_model_id(part, v))
+    });
+    Some(match variant {
+        Some(v) => format!("gpt-{}-{}", version, v),
+        None => format!("gpt-{}", version),
+    })
+}
+
+/// Gemini ids with release suffixes (`-preview`, `-MM-DD`, `-latest`) trimmed,
+/// e.g. "gemini-2.5-pro-preview-05-06" → "gemini-2.5-pro"; `None` if there
+/// were none
+fn strip_gemini_version_suffix(model_id: &str) -> Option<String> {
+    let is_two_digits = |s: &str| s.len() == 2 && s.bytes().all(|b| b.is_ascii_digit());
+    let mut stripped = model_id;
+    loop {
+        if let Some(rest) = stripped
+            .strip_suffix("-preview")
+            .or_else(|| stripped.strip_suffix("-latest"))
+        {
+            stripped = rest;
+            continue;
+        }
+        let mut parts = stripped.rsplitn(3, '-');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(day), Some(month), Some(rest)) if is_two_digits(day) && is_two_digits(month) => {
+                stripped = rest
+            }
+            _ => break,
+        }
+    }
+    (stripped != model_id).then(|| stripped.to_string())
+}
+
 fn normalize_version_separator(model_id: &str) -> Option<String> {
     let mut result = String::with_capacity(model_id.len());
     let chars: Vec<char> = model_id.chars().collect();
@@ -617,13 +907,6 @@ fn normalize_version_separator(model_id: &str) -> Option<String> {
     }
 }
 
-fn is_fuzzy_eligible(model_id: &str) -> bool {
-    if model_id.len() < MIN_FUZZY_MATCH_LEN {
-        return false;
-    }
-    !FUZZY_BLOCKLIST.iter().any(|blocked| model_id == *blocked)
-}
-
 /// Attempts to find a model by progressively stripping trailing segments.
 /// Handles arbitrary suffixes (e.g., "claude-sonnet-4-5-thinking" → "claude-sonnet-4-5").
 /// This replaces the hardcoded TIER_SUFFIXES and FALLBACK_SUFFIXES approach.
@@ -652,6 +935,21 @@ where
     None
 }
 
+/// `model_id` without one trailing known tier suffix, if it has one
+fn strip_tier_suffix(model_id: &str) -> Option<&str> {
+    let (base, tier) = model_id.rsplit_once(['-', ':'])?;
+    (KNOWN_TIER_SUFFIXES.contains(&tier) && base.len() >= MIN_MODEL_NAME_LEN).then_some(base)
+}
+
+/// `model_id` with every trailing known tier suffix stripped, if it had any
+fn strip_tier_suffixes(model_id: &str) -> Option<String> {
+    let mut base = strip_tier_suffix(model_id)?;
+    while let Some(shorter) = strip_tier_suffix(base) {
+        base = shorter;
+    }
+    Some(base.to_string())
+}
+
 /// Attempts to find a model by progressively stripping leading segments.
 /// Handles arbitrary routing prefixes (e.g., "myplugin-claude-3.5-sonnet" → "claude-3.5-sonnet").
 /// This replaces the hardcoded STRIPPED_PREFIXES approach.
@@ -700,8 +998,8 @@ fn is_reseller_provider(key: &str) -> bool {
         .any(|prefix| lower.starts_with(prefix))
 }
 
-fn select_best_match<'a>(
-    matches: &[&'a String],
+fn select_best_match(
+    matches: &[&String],
     dataset: &HashMap<String, ModelPricing>,
     source: &str,
 ) -> Option<LookupResult> {
@@ -749,6 +1047,7 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: Some(0.00000125),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -758,6 +1057,7 @@ mod tests {
                 output_cost_per_token: Some(0.0000006),
                 cache_read_input_token_cost: Some(0.000000075),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -767,6 +1067,7 @@ mod tests {
                 output_cost_per_token: Some(0.00003),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -778,6 +1079,7 @@ mod tests {
                 output_cost_per_token: Some(0.000014),
                 cache_read_input_token_cost: Some(1.75e-7),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -787,6 +1089,7 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: Some(1.25e-7),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -796,6 +1099,7 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: Some(1.25e-7),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -805,6 +1109,7 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: Some(1.25e-7),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -814,6 +1119,7 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: Some(1.25e-7),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -823,6 +1129,17 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: Some(1.25e-7),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
+            },
+        );
+        m.insert(
+            "gpt-5-mini".into(),
+            ModelPricing {
+                input_cost_per_token: Some(2.5e-7),
+                output_cost_per_token: Some(0.000002),
+                cache_read_input_token_cost: Some(2.5e-8),
+                cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -832,6 +1149,7 @@ mod tests {
                 output_cost_per_token: Some(4e-7),
                 cache_read_input_token_cost: Some(5e-9),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -843,6 +1161,7 @@ mod tests {
                 output_cost_per_token: Some(0.000015),
                 cache_read_input_token_cost: Some(0.0000003),
                 cache_creation_input_token_cost: Some(0.00000375),
+                ..Default::default()
             },
         );
         m.insert(
@@ -852,6 +1171,7 @@ mod tests {
                 output_cost_per_token: Some(0.000015),
                 cache_read_input_token_cost: Some(3e-7),
                 cache_creation_input_token_cost: Some(0.00000375),
+                ..Default::default()
             },
         );
         m.insert(
@@ -861,6 +1181,7 @@ mod tests {
                 output_cost_per_token: Some(0.000005),
                 cache_read_input_token_cost: Some(1e-7),
                 cache_creation_input_token_cost: Some(0.00000125),
+                ..Default::default()
             },
         );
         m.insert(
@@ -870,6 +1191,7 @@ mod tests {
                 output_cost_per_token: Some(0.000004),
                 cache_read_input_token_cost: Some(8e-8),
                 cache_creation_input_token_cost: Some(0.000001),
+                ..Default::default()
             },
         );
         m.insert(
@@ -879,6 +1201,7 @@ mod tests {
                 output_cost_per_token: Some(0.000025),
                 cache_read_input_token_cost: Some(5e-7),
                 cache_creation_input_token_cost: Some(0.00000625),
+                ..Default::default()
             },
         );
         m.insert(
@@ -888,6 +1211,7 @@ mod tests {
                 output_cost_per_token: Some(0.000075),
                 cache_read_input_token_cost: Some(0.0000015),
                 cache_creation_input_token_cost: Some(0.00001875),
+                ..Default::default()
             },
         );
 
@@ -899,6 +1223,7 @@ mod tests {
                 output_cost_per_token: Some(0.000012),
                 cache_read_input_token_cost: Some(2e-7),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -908,6 +1233,7 @@ mod tests {
                 output_cost_per_token: Some(0.000003),
                 cache_read_input_token_cost: Some(5e-8),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -919,6 +1245,7 @@ mod tests {
                 output_cost_per_token: Some(0.0000015),
                 cache_read_input_token_cost: Some(2e-8),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -929,6 +1256,7 @@ mod tests {
                 output_cost_per_token: Some(0.0000175),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -938,6 +1266,7 @@ mod tests {
                 output_cost_per_token: Some(0.000015),
                 cache_read_input_token_cost: Some(3e-7),
                 cache_creation_input_token_cost: Some(0.00000375),
+                ..Default::default()
             },
         );
         m.insert(
@@ -947,6 +1276,7 @@ mod tests {
                 output_cost_per_token: Some(0.000005),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -956,6 +1286,7 @@ mod tests {
                 output_cost_per_token: Some(0.000005),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -974,6 +1305,7 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: Some(0.00000125),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -985,6 +1317,7 @@ mod tests {
                 output_cost_per_token: Some(0.000015),
                 cache_read_input_token_cost: Some(3e-7),
                 cache_creation_input_token_cost: Some(0.00000375),
+                ..Default::default()
             },
         );
         m.insert(
@@ -994,6 +1327,7 @@ mod tests {
                 output_cost_per_token: Some(0.000025),
                 cache_read_input_token_cost: Some(0.0000005),
                 cache_creation_input_token_cost: Some(0.00000625),
+                ..Default::default()
             },
         );
         m.insert(
@@ -1003,6 +1337,7 @@ mod tests {
                 output_cost_per_token: Some(0.000004),
                 cache_read_input_token_cost: Some(8e-8),
                 cache_creation_input_token_cost: Some(0.000001),
+                ..Default::default()
             },
         );
 
@@ -1014,6 +1349,7 @@ mod tests {
                 output_cost_per_token: Some(0.0000015),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -1023,6 +1359,7 @@ mod tests {
                 output_cost_per_token: Some(0.0000019),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -1034,6 +1371,7 @@ mod tests {
                 output_cost_per_token: Some(0.00000184),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         m.insert(
@@ -1043,6 +1381,7 @@ mod tests {
                 output_cost_per_token: Some(0.00000175),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -1054,6 +1393,7 @@ mod tests {
                 output_cost_per_token: Some(9.5e-7),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -1117,6 +1457,32 @@ mod tests {
         assert_eq!(result.source, "LiteLLM");
     }
 
+    #[test]
+    fn test_gpt_5_family_normalized() {
+        let lookup = create_lookup();
+        for (model, key) in [
+            ("gpt-5-codex", "gpt-5-codex"),
+            ("gpt-5.1", "gpt-5.1"),
+            ("GPT5-Codex", "gpt-5-codex"),
+            ("gpt-5-1-codex-high", "gpt-5.1-codex"),
+            ("gpt-5.0-mini", "gpt-5-mini"),
+            ("gpt-5-2025-08-07", "gpt-5"),
+            ("gpt-4o", "gpt-4o"),
+        ] {
+            assert_eq!(lookup.lookup(model).unwrap().matched_key, key, "{}", model);
+        }
+        assert_eq!(
+            normalize_model_name("gpt-5-codex-mini").as_deref(),
+            Some("gpt-5-codex-mini")
+        );
+        assert_eq!(
+            normalize_model_name("gpt-5.1-codex-max-xhigh").as_deref(),
+            Some("gpt-5.1-codex-max")
+        );
+        assert_eq!(normalize_model_name("gpt-4.1"), None);
+        assert_eq!(normalize_model_name("gpt-50"), None);
+    }
+
     #[test]
     fn test_opencode_zen_gpt_5_nano() {
         let lookup = create_lookup();
@@ -1361,6 +1727,65 @@ mod tests {
         assert_eq!(result.source, "LiteLLM");
     }
 
+    #[test]
+    fn test_gemini_preview_and_date_suffixes_stripped() {
+        let lookup = create_lookup();
+        for model in [
+            "gemini-2.5-pro-preview-05-06",
+            "gemini-2.5-pro-preview",
+            "gemini-2.5-pro-latest",
+            "gemini-2.5-pro-06-05",
+        ] {
+            let result = lookup.lookup(model).unwrap();
+            assert_eq!(result.matched_key, "google/gemini-2.5-pro", "{}", model);
+        }
+        assert_eq!(
+            normalize_model_name("gemini-2.5-pro-preview-05-06").as_deref(),
+            Some("gemini-2.5-pro")
+        );
+        assert_eq!(normalize_model_name("gemini-2.5-pro"), None);
+    }
+
+    #[test]
+    fn test_stacked_tier_suffixes() {
+        let lookup = create_lookup();
+        let result = lookup.lookup("gpt-5.1-codex-high-free").unwrap();
+        assert_eq!(result.matched_key, "gpt-5.1-codex");
+        let result = lookup.lookup("glm-4.7:free:low").unwrap();
+        assert_eq!(result.matched_key, "z-ai/glm-4.7");
+
+        assert_eq!(
+            strip_tier_suffixes("gpt-5.1-codex-high-free").as_deref(),
+            Some("gpt-5.1-codex")
+        );
+        assert_eq!(
+            strip_tier_suffixes("glm-4.7:free:low").as_deref(),
+            Some("glm-4.7")
+        );
+        assert_eq!(strip_tier_suffixes("glm-4.7"), None);
+    }
+
+    #[test]
+    fn test_fuzzy_min_length_configurable() {
+        // Too short to fuzzy-match by default
+        assert!(create_lookup().lookup("glm").is_none());
+
+        let lowered = FuzzyMatchConfig {
+            min_len: 3,
+            ..Default::default()
+        };
+        let lookup = create_lookup().with_fuzzy_config(lowered);
+        let result = lookup.lookup("glm").unwrap();
+        assert!(result.matched_key.contains("glm"), "{}", result.matched_key);
+
+        let blocked = create_lookup().with_fuzzy_config(FuzzyMatchConfig {
+            min_len: 3,
+            blocklist: vec!["GLM".to_string()],
+        });
+        assert!(blocked.lookup("glm").is_none());
+        assert!(blocked.lookup("Glm").is_none());
+    }
+
     #[test]
     fn test_normalize_opus_4_5() {
         let lookup = create_lookup();
@@ -1440,6 +1865,7 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: Some(1.25e-7),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         // Note: gpt-5-codex is NOT in the pricing data
@@ -1468,6 +1894,7 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: Some(1.25e-7),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -1495,6 +1922,7 @@ mod tests {
                 output_cost_per_token: Some(0.00001),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
         litellm.insert(
@@ -1504,6 +1932,7 @@ mod tests {
                 output_cost_per_token: Some(0.000015),
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -1552,13 +1981,14 @@ mod tests {
 
     #[test]
     fn test_is_fuzzy_eligible() {
-        assert!(!is_fuzzy_eligible("auto"));
-        assert!(!is_fuzzy_eligible("mini"));
-        assert!(!is_fuzzy_eligible("chat"));
-        assert!(!is_fuzzy_eligible("base"));
-        assert!(!is_fuzzy_eligible("abc"));
-        assert!(is_fuzzy_eligible("gpt-4o"));
-        assert!(is_fuzzy_eligible("claude"));
+        let fuzzy = FuzzyMatchConfig::default();
+        assert!(!fuzzy.allows("auto"));
+        assert!(!fuzzy.allows("mini"));
+        assert!(!fuzzy.allows("chat"));
+        assert!(!fuzzy.allows("base"));
+        assert!(!fuzzy.allows("abc"));
+        assert!(fuzzy.allows("gpt-4o"));
+        assert!(fuzzy.allows("claude"));
     }
 
     // =========================================================================
@@ -1597,6 +2027,7 @@ mod tests {
                 output_cost_per_token: Some(0.0000175), // $17.50/1M tokens
                 cache_read_input_token_cost: None,
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -1608,6 +2039,7 @@ mod tests {
                 output_cost_per_token: Some(0.0000015), // $1.50/1M tokens
                 cache_read_input_token_cost: Some(0.00000002),
                 cache_creation_input_token_cost: None,
+                ..Default::default()
             },
         );
 
@@ -1795,14 +2227,18 @@ mod tests {
     #[test]
     fn test_prefix_and_suffix_combined() {
         let lookup = create_lookup();
-        let result = lookup.lookup("antigravity-claude-opus-4-5-thinking").unwrap();
+        let result = lookup
+            .lookup("antigravity-claude-opus-4-5-thinking")
+            .unwrap();
         assert_eq!(result.matched_key, "claude-opus-4-5");
     }
 
     #[test]
     fn test_prefix_and_suffix_with_tier() {
         let lookup = create_lookup();
-        let result = lookup.lookup("antigravity-claude-opus-4-5-thinking-high").unwrap();
+        let result = lookup
+            .lookup("antigravity-claude-opus-4-5-thinking-high")
+            .unwrap();
         assert_eq!(result.matched_key, "claude-opus-4-5");
     }
 
@@ -1841,4 +2277,158 @@ mod tests {
         let result = lookup.lookup("gpt-5.2-codex").unwrap();
         assert_eq!(result.matched_key, "gpt-5.2");
     }
+
+    fn priced(input: f64) -> ModelPricing {
+        ModelPricing {
+            input_cost_per_token: Some(input),
+            output_cost_per_token: Some(input * 4.0),
+            cache_read_input_token_cost: None,
+            cache_creation_input_token_cost: None,
+            ..Default::default()
+        }
+    }
+
+    /// Same models in both maps at different prices
+    fn create_overlapping_lookup(priority: Option<&[String]>) -> PricingLookup {
+        let mut litellm = HashMap::new();
+        litellm.insert("kimi-k2".into(), priced(0.000001));
+        litellm.insert("kimi-k2-0905-preview".into(), priced(0.000001));
+
+        let mut openrouter = HashMap::new();
+        openrouter.insert("moonshotai/kimi-k2".into(), priced(0.000002));
+        openrouter.insert("moonshot/kimi-k2-0905-preview".into(), priced(0.000002));
+
+        PricingLookup::with_priority(litellm, openrouter, priority)
+    }
+
+    #[test]
+    fn test_priority_default_prefers_litellm() {
+        let lookup = create_overlapping_lookup(None);
+        let result = lookup.lookup("kimi-k2").unwrap();
+        assert_eq!(result.source, "LiteLLM");
+        assert_eq!(result.pricing.input_cost_per_token, Some(0.000001));
+    }
+
+    #[test]
+    fn test_priority_openrouter_first_exact_match() {
+        let priority = vec!["openrouter".to_string(), "litellm".to_string()];
+        let lookup = create_overlapping_lookup(Some(&priority));
+        let result = lookup.lookup("kimi-k2").unwrap();
+        assert_eq!(result.source, "OpenRouter");
+        assert_eq!(result.matched_key, "moonshotai/kimi-k2");
+        assert_eq!(result.pricing.input_cost_per_token, Some(0.000002));
+    }
+
+    #[test]
+    fn test_priority_openrouter_first_fuzzy_match() {
+        let priority = vec!["openrouter".to_string(), "litellm".to_string()];
+        let lookup = create_overlapping_lookup(Some(&priority));
+        let result = lookup.lookup("kimi-k2-0905").unwrap();
+        assert_eq!(result.source, "OpenRouter");
+        assert_eq!(result.matched_key, "moonshot/kimi-k2-0905-preview");
+
+        let default = create_overlapping_lookup(None);
+        let result = default.lookup("kimi-k2-0905").unwrap();
+        assert_eq!(result.source, "LiteLLM");
+        assert_eq!(result.matched_key, "kimi-k2-0905-preview");
+    }
+
+    #[test]
+    fn test_priority_unknown_names_keep_default() {
+        let priority = vec!["bogus".to_string()];
+        let lookup = create_overlapping_lookup(Some(&priority));
+        assert!(lookup.has_priority(None));
+        assert_eq!(lookup.lookup("kimi-k2").unwrap().source, "LiteLLM");
+    }
+
+    #[test]
+    fn test_lookup_cheapest_picks_lower_rate() {
+        let lookup = create_overlapping_lookup(None);
+        // LiteLLM wins the normal lookup but OpenRouter is cheaper here
+        let mut litellm = HashMap::new();
+        litellm.insert("kimi-k2".into(), priced(0.000003));
+        let mut openrouter = HashMap::new();
+        openrouter.insert("moonshotai/kimi-k2".into(), priced(0.000002));
+        let pricier_litellm = PricingLookup::new(litellm, openrouter);
+
+        assert_eq!(pricier_litellm.lookup("kimi-k2").unwrap().source, "LiteLLM");
+        let cheapest = pricier_litellm.lookup_cheapest("kimi-k2").unwrap();
+        assert_eq!(cheapest.source, "OpenRouter");
+        assert_eq!(cheapest.pricing.input_cost_per_token, Some(0.000002));
+
+        // Already-cheaper LiteLLM entry is kept
+        assert_eq!(lookup.lookup_cheapest("kimi-k2").unwrap().source, "LiteLLM");
+    }
+
+    #[test]
+    fn test_calculate_cost_preferring_cheaper() {
+        let lookup = create_overlapping_lookup(Some(&["openrouter".to_string()]));
+        // 1M input at 2e-6 and 1M output at 8e-6 from OpenRouter vs 1e-6/4e-6 from LiteLLM
+        let normal = lookup.calculate_cost("kimi-k2", 1_000_000, 1_000_000, 0, 0, 0);
+        let cheaper = lookup.lookup_cheapest("kimi-k2").map_or(0.0, |r| {
+            cost_breakdown_with(&r.pricing, 1_000_000, 1_000_000, 0, 0, 0, None).total()
+        });
+        assert!((normal - 10.0).abs() < 1e-9);
+        assert!((cheaper - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lookup_candidates_returns_both_sources() {
+        let lookup = create_overlapping_lookup(None);
+        let candidates = lookup.lookup_candidates("kimi-k2");
+        let sources: Vec<&str> = candidates.iter().map(|c| c.source.as_str()).collect();
+        assert_eq!(sources, vec!["LiteLLM", "OpenRouter"]);
+    }
+
+    fn create_long_context_lookup() -> PricingLookup {
+        let mut litellm = HashMap::new();
+        litellm.insert(
+            "claude-sonnet-4-5".into(),
+            ModelPricing {
+                input_cost_per_token: Some(0.000003),
+                output_cost_per_token: Some(0.000015),
+                cache_read_input_token_cost: Some(3e-7),
+                cache_creation_input_token_cost: Some(0.00000375),
+                input_cost_per_token_above_200k_tokens: Some(0.000006),
+                output_cost_per_token_above_200k_tokens: Some(0.0000225),
+                cache_read_input_token_cost_above_200k_tokens: Some(6e-7),
+                cache_creation_input_token_cost_above_200k_tokens: Some(0.0000075),
+            },
+        );
+        PricingLookup::new(litellm, HashMap::new())
+    }
+
+    #[test]
+    fn test_long_context_tier_below_threshold_uses_base_rate() {
+        let lookup = create_long_context_lookup();
+        // 150k input + 50k cache read = exactly 200k, still the base tier
+        let cost = lookup.calculate_cost("claude-sonnet-4-5", 150_000, 1_000, 50_000, 0, 0);
+        let expected = 150_000.0 * 0.000003 + 1_000.0 * 0.000015 + 50_000.0 * 3e-7;
+        assert!((cost - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_long_context_tier_above_threshold_uses_tier_rate() {
+        let lookup = create_long_context_lookup();
+        // Cached tokens count toward the threshold: 10k input + 250k cache read
+        let cost = lookup.calculate_cost("claude-sonnet-4-5", 10_000, 2_000, 250_000, 1_000, 0);
+        let expected =
+            10_000.0 * 0.000006 + 2_000.0 * 0.0000225 + 250_000.0 * 6e-7 + 1_000.0 * 0.0000075;
+        assert!((cost - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_long_context_without_tier_rates_keeps_base_rate() {
+        let lookup = create_lookup();
+        let cost = lookup.calculate_cost("gpt-4o", 300_000, 0, 0, 0, 0);
+        assert!((cost - 300_000.0 * 0.0000025).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_long_context_rates_deserialize_from_litellm() {
+        let json = r#"{"input_cost_per_token":3e-6,"input_cost_per_token_above_200k_tokens":6e-6}"#;
+        let pricing: ModelPricing = serde_json::from_str(json).unwrap();
+        assert_eq!(pricing.input_cost_per_token_above_200k_tokens, Some(6e-6));
+        assert_eq!(pricing.output_cost_per_token_above_200k_tokens, None);
+    }
 }
@@ -1,51 +1,977 @@
 pub mod aliases;
+pub mod backoff;
+pub mod billing;
 pub mod cache;
 pub mod litellm;
 pub mod lookup;
 pub mod openrouter;
+#[cfg(test)]
+mod test_server;
 
-use lookup::{PricingLookup, LookupResult};
+use crate::sessions::UnifiedMessage;
+use crate::TokenCostBreakdown;
+use lookup::{LookupResult, PricingLookup};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::OnceCell;
+use tokio::sync::RwLock;
 
 pub use litellm::ModelPricing;
+pub use lookup::FuzzyMatchConfig;
 
-static PRICING_SERVICE: OnceCell<Arc<PricingService>> = OnceCell::const_new();
+static PRICING_SERVICE: RwLock<Option<Arc<PricingService>>> = RwLock::const_new(None);
 
+/// Anthropic's Batch API bills half the standard rates
+const BATCH_DISCOUNT: f64 = 0.5;
+
+/// Snapshot of the loaded pricing data
+#[derive(Debug, Clone)]
+pub struct PricingStatus {
+    pub litellm_models: usize,
+    pub openrouter_models: usize,
+    /// RFC 3339 time the service was built
+    pub fetched_at: String,
+    /// Whether both datasets were served from the on-disk cache
+    pub from_cache: bool,
+}
+
+/// Whose cost a priced message ends up with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CostSource {
+    /// Calculate from pricing data, keeping Cursor's recorded cost for
+    /// models pricing data does not know
+    #[default]
+    Auto,
+    /// Always calculate from pricing data
+    Calculated,
+    /// Use the cost the source recorded, even when it is zero
+    Source,
+    /// Use the recorded cost when nonzero, otherwise calculate
+    PreferSource,
+}
+
+impl CostSource {
+    /// Parse a `cost_source` option; unset means [`CostSource::Auto`]
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None => Ok(CostSource::Auto),
+            Some("calculated") => Ok(CostSource::Calculated),
+            Some("source") => Ok(CostSource::Source),
+            Some("prefer_source") => Ok(CostSource::PreferSource),
+            Some(other) => Err(format!(
+                "Unknown cost source: {} (expected \"calculated\", \"source\" or \"prefer_source\")",
+                other
+            )),
+        }
+    }
+}
+
+/// Pricing a model id resolved to. Unmatched models have `source` "none",
+/// an empty `matched_key` and no prices.
+#[derive(Debug, Clone)]
+pub struct ResolvedModelPricing {
+    pub model_id: String,
+    pub matched_key: String,
+    pub source: String,
+    pub input_cost_per_token: Option<f64>,
+    pub output_cost_per_token: Option<f64>,
+    pub cache_read: Option<f64>,
+    pub cache_write: Option<f64>,
+}
+
+#[derive(Clone)]
 pub struct PricingService {
-    lookup: PricingLookup,
+    lookup: Arc<PricingLookup>,
+    fetched_at: String,
+    from_cache: bool,
+    /// Price models found in both datasets at the cheaper of the two rates
+    prefer_cheaper: bool,
+    /// Bill reasoning tokens at this multiple of the output rate instead of as output
+    reasoning_multiplier: Option<f64>,
+    /// Fraction of the standard rates batch messages are billed at
+    batch_multiplier: f64,
+    /// Token-only stand-in with no pricing data; see `unpriced`
+    unpriced: bool,
+    /// Rates for models no dataset matches; unset prices them at zero
+    unknown_model_rate: Option<ModelPricing>,
+    /// Whether recorded costs win over calculated ones
+    cost_source: CostSource,
 }
 
 impl PricingService {
-    pub fn new(litellm_data: HashMap<String, ModelPricing>, openrouter_data: HashMap<String, ModelPricing>) -> Self {
+    pub fn new(
+        litellm_data: HashMap<String, ModelPricing>,
+        openrouter_data: HashMap<String, ModelPricing>,
+    ) -> Self {
         Self {
-            lookup: PricingLookup::new(litellm_data, openrouter_data),
+            lookup: Arc::new(PricingLookup::new(litellm_data, openrouter_data)),
+            fetched_at: chrono::Utc::now().to_rfc3339(),
+            from_cache: false,
+            prefer_cheaper: false,
+            reasoning_multiplier: None,
+            batch_multiplier: BATCH_DISCOUNT,
+            unpriced: false,
+            unknown_model_rate: None,
+            cost_source: CostSource::Auto,
         }
     }
-    
-    async fn fetch_inner() -> Result<Self, String> {
-        let (litellm_result, openrouter_data) = tokio::join!(
-            litellm::fetch(),
-            openrouter::fetch_all_mapped()
+
+    /// A service with no pricing data that costs everything at zero, for
+    /// token-only reports. Built locally: never fetches and never touches the
+    /// shared instance.
+    pub fn unpriced() -> Arc<PricingService> {
+        Arc::new(Self {
+            unpriced: true,
+            ..Self::new(HashMap::new(), HashMap::new())
+        })
+    }
+
+    /// Whether this is the `unpriced` stand-in, so source-reported costs
+    /// should be dropped too
+    pub fn is_unpriced(&self) -> bool {
+        self.unpriced
+    }
+
+    async fn fetch_inner(force_refresh: bool) -> Result<Self, String> {
+        let (litellm_result, openrouter) = tokio::join!(
+            litellm::fetch(force_refresh),
+            openrouter::fetch_all_mapped(force_refresh)
         );
-        
-        let litellm_data = litellm_result.map_err(|e| e.to_string())?;
-        
-        Ok(Self::new(litellm_data, openrouter_data))
+
+        let litellm = litellm_result?;
+
+        let mut service = Self::new(litellm.data, openrouter.data);
+        service.from_cache = litellm.from_cache && openrouter.from_cache;
+        Ok(service)
     }
-    
+
     pub async fn get_or_init() -> Result<Arc<PricingService>, String> {
-        PRICING_SERVICE.get_or_try_init(|| async {
-            Self::fetch_inner().await.map(Arc::new)
-        }).await.map(Arc::clone)
+        if let Some(service) = PRICING_SERVICE.read().await.as_ref() {
+            return Ok(Arc::clone(service));
+        }
+
+        let mut slot = PRICING_SERVICE.write().await;
+        if let Some(service) = slot.as_ref() {
+            return Ok(Arc::clone(service));
+        }
+
+        let service = Arc::new(Self::fetch_inner(false).await?);
+        *slot = Some(Arc::clone(&service));
+        Ok(service)
+    }
+
+    /// Refetch pricing past the cache TTL and replace the shared service.
+    /// On failure the previously loaded service stays in place. The fetch runs
+    /// without the lock, so reports keep using the old service meanwhile.
+    pub async fn refresh() -> Result<Arc<PricingService>, String> {
+        let service = Arc::new(Self::fetch_inner(true).await?);
+        *PRICING_SERVICE.write().await = Some(Arc::clone(&service));
+        Ok(service)
+    }
+
+    /// The shared service if pricing has already been loaded, without fetching
+    pub async fn current() -> Option<Arc<PricingService>> {
+        PRICING_SERVICE.read().await.clone()
+    }
+
+    /// Shared service, re-keyed to try pricing sources in `priority` order.
+    /// Returns the shared instance untouched when the order already matches.
+    pub async fn get_or_init_with_priority(
+        priority: Option<&[String]>,
+    ) -> Result<Arc<PricingService>, String> {
+        let service = Self::get_or_init().await?;
+        if service.lookup.has_priority(priority) {
+            return Ok(service);
+        }
+        Ok(Arc::new(Self {
+            lookup: Arc::new(service.lookup.with_reordered_sources(priority)),
+            ..(*service).clone()
+        }))
+    }
+
+    /// Variant of `service` that prices with the cheaper of the LiteLLM
+    /// and OpenRouter rates when a model is in both. Shares the underlying lookup.
+    pub fn preferring_cheaper(
+        service: Arc<PricingService>,
+        prefer_cheaper: bool,
+    ) -> Arc<PricingService> {
+        if service.prefer_cheaper == prefer_cheaper {
+            return service;
+        }
+        Arc::new(Self {
+            prefer_cheaper,
+            ..(*service).clone()
+        })
+    }
+
+    /// Variant of `service` that bills reasoning tokens at `multiplier` times the
+    /// output rate. `None` keeps reasoning folded into output.
+    pub fn with_reasoning_multiplier(
+        service: Arc<PricingService>,
+        multiplier: Option<f64>,
+    ) -> Result<Arc<PricingService>, String> {
+        if let Some(m) = multiplier {
+            if !m.is_finite() || m < 0.0 {
+                return Err(format!("Invalid reasoning rate multiplier: {}", m));
+            }
+        }
+        if service.reasoning_multiplier == multiplier {
+            return Ok(service);
+        }
+        Ok(Arc::new(Self {
+            reasoning_multiplier: multiplier,
+            ..(*service).clone()
+        }))
+    }
+
+    /// Variant of `service` that bills batch messages at `multiplier` times the
+    /// standard rates. `None` keeps the Batch API's 50% discount.
+    pub fn with_batch_multiplier(
+        service: Arc<PricingService>,
+        multiplier: Option<f64>,
+    ) -> Result<Arc<PricingService>, String> {
+        let multiplier = multiplier.unwrap_or(BATCH_DISCOUNT);
+        if !multiplier.is_finite() || multiplier < 0.0 {
+            return Err(format!("Invalid batch rate multiplier: {}", multiplier));
+        }
+        if service.batch_multiplier == multiplier {
+            return Ok(service);
+        }
+        Ok(Arc::new(Self {
+            batch_multiplier: multiplier,
+            ..(*service).clone()
+        }))
     }
 
-    pub fn lookup_with_source(&self, model_id: &str, force_source: Option<&str>) -> Option<LookupResult> {
+    /// Variant of `service` that prices models no dataset matches at `rate`
+    /// instead of zero. `None` keeps them at zero.
+    pub fn with_unknown_model_rate(
+        service: Arc<PricingService>,
+        rate: Option<ModelPricing>,
+    ) -> Result<Arc<PricingService>, String> {
+        if let Some(r) = &rate {
+            let rates = [
+                r.input_cost_per_token,
+                r.output_cost_per_token,
+                r.cache_read_input_token_cost,
+                r.cache_creation_input_token_cost,
+            ];
+            if let Some(bad) = rates
+                .into_iter()
+                .flatten()
+                .find(|v| !v.is_finite() || *v < 0.0)
+            {
+                return Err(format!("Invalid unknown model rate: {}", bad));
+            }
+        }
+        if rate.is_none() && service.unknown_model_rate.is_none() {
+            return Ok(service);
+        }
+        Ok(Arc::new(Self {
+            unknown_model_rate: rate,
+            ..(*service).clone()
+        }))
+    }
+
+    /// Variant of `service` that takes message costs from `cost_source`
+    pub fn with_cost_source(
+        service: Arc<PricingService>,
+        cost_source: CostSource,
+    ) -> Arc<PricingService> {
+        if service.cost_source == cost_source {
+            return service;
+        }
+        Arc::new(Self {
+            cost_source,
+            ..(*service).clone()
+        })
+    }
+
+    /// Variant of `service` that fuzzy-matches under `fuzzy`. `None` keeps the
+    /// service's current rules.
+    pub fn with_fuzzy_config(
+        service: Arc<PricingService>,
+        fuzzy: Option<FuzzyMatchConfig>,
+    ) -> Arc<PricingService> {
+        let fuzzy = match fuzzy {
+            Some(fuzzy) if fuzzy != *service.lookup.fuzzy_config() => fuzzy,
+            _ => return service,
+        };
+        Arc::new(Self {
+            lookup: Arc::new(service.lookup.with_fuzzy_config(fuzzy)),
+            ..(*service).clone()
+        })
+    }
+
+    pub fn status(&self) -> PricingStatus {
+        let (litellm_models, openrouter_models) = self.lookup.model_counts();
+        PricingStatus {
+            litellm_models,
+            openrouter_models,
+            fetched_at: self.fetched_at.clone(),
+            from_cache: self.from_cache,
+        }
+    }
+
+    /// The pricing `apply` uses for each distinct model id, sorted by id
+    pub fn resolve_models<'a>(
+        &self,
+        model_ids: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<ResolvedModelPricing> {
+        let mut ids: Vec<&str> = model_ids.into_iter().collect();
+        ids.sort_unstable();
+        ids.dedup();
+
+        ids.into_iter()
+            .map(|model_id| match self.resolve(model_id) {
+                Some(r) => ResolvedModelPricing {
+                    model_id: model_id.to_string(),
+                    matched_key: r.matched_key,
+                    source: r.source,
+                    input_cost_per_token: r.pricing.input_cost_per_token,
+                    output_cost_per_token: r.pricing.output_cost_per_token,
+                    cache_read: r.pricing.cache_read_input_token_cost,
+                    cache_write: r.pricing.cache_creation_input_token_cost,
+                },
+                None => ResolvedModelPricing {
+                    model_id: model_id.to_string(),
+                    matched_key: String::new(),
+                    source: "none".to_string(),
+                    input_cost_per_token: None,
+                    output_cost_per_token: None,
+                    cache_read: None,
+                    cache_write: None,
+                },
+            })
+            .collect()
+    }
+
+    pub fn lookup_with_source(
+        &self,
+        model_id: &str,
+        force_source: Option<&str>,
+    ) -> Option<LookupResult> {
         self.lookup.lookup_with_source(model_id, force_source)
     }
-    
-    pub fn calculate_cost(&self, model_id: &str, input: i64, output: i64, cache_read: i64, cache_write: i64, reasoning: i64) -> f64 {
-        self.lookup.calculate_cost(model_id, input, output, cache_read, cache_write, reasoning)
+
+    /// What caching saved on `cache_read` tokens of `model_id`: the gap between
+    /// its input and cache-read rates. 0 when the model has no cache-read price.
+    pub fn cache_savings(&self, model_id: &str, cache_read: i64) -> f64 {
+        self.resolve(model_id).map_or(0.0, |r| {
+            match (
+                r.pricing.input_cost_per_token,
+                r.pricing.cache_read_input_token_cost,
+            ) {
+                (Some(input), Some(cached)) => cache_read as f64 * (input - cached),
+                _ => 0.0,
+            }
+        })
+    }
+
+    /// What `tokens` of `model_id` would cost, split by token type, with the
+    /// pricing entry used. `None` when no pricing applies.
+    pub fn estimate_cost(
+        &self,
+        model_id: &str,
+        tokens: &crate::TokenBreakdown,
+    ) -> Option<(LookupResult, TokenCostBreakdown)> {
+        let resolved = self.resolve(model_id)?;
+        let breakdown = lookup::cost_breakdown_with(
+            &resolved.pricing,
+            tokens.input,
+            tokens.output,
+            tokens.cache_read,
+            tokens.cache_write,
+            tokens.reasoning,
+            self.reasoning_multiplier,
+        );
+        Some((resolved, breakdown))
+    }
+
+    /// Whether `model_id` is priced at the unknown-model rate
+    pub fn is_estimated(&self, model_id: &str) -> bool {
+        self.unknown_model_rate.is_some() && self.resolve_known(model_id).is_none()
+    }
+
+    /// What the given token counts of `model_id` cost, split by token type
+    pub fn calculate_cost_breakdown(
+        &self,
+        model_id: &str,
+        input: i64,
+        output: i64,
+        cache_read: i64,
+        cache_write: i64,
+        reasoning: i64,
+    ) -> TokenCostBreakdown {
+        self.resolve(model_id)
+            .map_or_else(TokenCostBreakdown::default, |r| {
+                lookup::cost_breakdown_with(
+                    &r.pricing,
+                    input,
+                    output,
+                    cache_read,
+                    cache_write,
+                    reasoning,
+                    self.reasoning_multiplier,
+                )
+            })
+    }
+
+    /// Cost of a message from `source`, after applying that source's billing
+    /// rule, split by token type
+    pub fn calculate_source_cost_breakdown(
+        &self,
+        source: &str,
+        model_id: &str,
+        tokens: &crate::TokenBreakdown,
+    ) -> TokenCostBreakdown {
+        let t = billing::provider_billing_rule(source).billable(tokens);
+        self.calculate_cost_breakdown(
+            model_id,
+            t.input,
+            t.output,
+            t.cache_read,
+            t.cache_write,
+            t.reasoning,
+        )
+    }
+
+    /// Fraction of the standard rates `msg` is billed at: the batch rate for
+    /// batch messages, else 1
+    pub fn batch_rate(&self, msg: &UnifiedMessage) -> f64 {
+        if msg.batch {
+            self.batch_multiplier
+        } else {
+            1.0
+        }
+    }
+
+    /// What `msg` costs under its source's billing rule and batch rate, split
+    /// by token type
+    pub fn message_cost_breakdown(&self, msg: &UnifiedMessage) -> TokenCostBreakdown {
+        let mut breakdown =
+            self.calculate_source_cost_breakdown(&msg.source, &msg.model_id, &msg.tokens);
+        breakdown.scale(self.batch_rate(msg));
+        breakdown
+    }
+
+    /// Price `msg` under its source's billing rule, replacing its cost unless
+    /// the cost source says to keep the recorded one
+    pub fn apply(&self, msg: &mut UnifiedMessage) {
+        let keep_recorded = match self.cost_source {
+            CostSource::Source => true,
+            CostSource::PreferSource => msg.cost > 0.0,
+            CostSource::Auto | CostSource::Calculated => false,
+        };
+        if keep_recorded && !self.is_unpriced() {
+            Self::keep_recorded(msg, msg.cost);
+            return;
+        }
+        msg.cost_breakdown = self.message_cost_breakdown(msg);
+        msg.cost = msg.cost_breakdown.total();
+        msg.cost_estimated = self.is_estimated(&msg.model_id);
+    }
+
+    /// Like [`PricingService::apply`], but keeps the cost the source recorded
+    /// (Cursor CSV cost) when the model has no pricing, in
+    /// preference to an unknown-model estimate. Token-only mode still zeroes it.
+    pub fn apply_or_keep_recorded(&self, msg: &mut UnifiedMessage) {
+        let recorded = msg.cost;
+        self.apply(msg);
+        if self.cost_source == CostSource::Calculated {
+            return;
+        }
+        let estimate_only = msg.cost_estimated && recorded > 0.0;
+        if (msg.cost <= 0.0 || estimate_only) && !self.is_unpriced() {
+            Self::keep_recorded(msg, recorded);
+        }
+    }
+
+    /// Set `msg`'s cost to `recorded`, unattributed to any token type
+    fn keep_recorded(msg: &mut UnifiedMessage, recorded: f64) {
+        msg.cost = recorded;
+        msg.cost_estimated = false;
+        msg.cost_breakdown = TokenCostBreakdown {
+            unattributed_cost: recorded,
+            ..Default::default()
+        };
+    }
+
+    /// The pricing `apply` uses for `model_id`, falling back to the
+    /// unknown-model rate
+    fn resolve(&self, model_id: &str) -> Option<LookupResult> {
+        self.resolve_known(model_id).or_else(|| {
+            self.unknown_model_rate.clone().map(|pricing| LookupResult {
+                pricing,
+                source: "Estimated".to_string(),
+                matched_key: String::new(),
+            })
+        })
+    }
+
+    /// The dataset pricing `model_id` matched, if any
+    fn resolve_known(&self, model_id: &str) -> Option<LookupResult> {
+        if self.prefer_cheaper {
+            self.lookup.lookup_cheapest(model_id)
+        } else {
+            self.lookup.lookup(model_id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn priced(input: f64, output: f64) -> ModelPricing {
+        ModelPricing {
+            input_cost_per_token: Some(input),
+            output_cost_per_token: Some(output),
+            ..Default::default()
+        }
+    }
+
+    /// What `apply` charges an OpenCode message (standard billing) with these
+    /// token counts
+    fn cost(
+        service: &PricingService,
+        model_id: &str,
+        input: i64,
+        output: i64,
+        cache_read: i64,
+        cache_write: i64,
+        reasoning: i64,
+    ) -> f64 {
+        let tokens = crate::TokenBreakdown {
+            input,
+            output,
+            cache_read,
+            cache_write,
+            reasoning,
+        };
+        service
+            .calculate_source_cost_breakdown("opencode", model_id, &tokens)
+            .total()
+    }
+
+    fn dataset(keys: &[&str]) -> HashMap<String, ModelPricing> {
+        keys.iter()
+            .map(|k| (k.to_string(), ModelPricing::default()))
+            .collect()
+    }
+
+    #[test]
+    fn test_status_counts_reflect_maps() {
+        let service = PricingService::new(
+            dataset(&["gpt-4o", "gpt-4o-mini", "claude-sonnet-4"]),
+            dataset(&["openai/gpt-4o", "anthropic/claude-sonnet-4"]),
+        );
+
+        let status = service.status();
+        assert_eq!(status.litellm_models, 3);
+        assert_eq!(status.openrouter_models, 2);
+        assert!(!status.from_cache);
+        assert!(chrono::DateTime::parse_from_rfc3339(&status.fetched_at).is_ok());
+    }
+
+    #[test]
+    fn test_status_empty_maps() {
+        let service = PricingService::new(HashMap::new(), dataset(&["openai/gpt-4o"]));
+
+        let status = service.status();
+        assert_eq!(status.litellm_models, 0);
+        assert_eq!(status.openrouter_models, 1);
+    }
+
+    #[test]
+    fn test_prefer_cheaper_only_when_flag_set() {
+        let mut litellm = HashMap::new();
+        litellm.insert("gpt-4o".to_string(), priced(0.0000025, 0.00001));
+        let mut openrouter = HashMap::new();
+        openrouter.insert("openai/gpt-4o".to_string(), priced(0.000002, 0.000008));
+
+        let service = Arc::new(PricingService::new(litellm, openrouter));
+        let default_cost = cost(&service, "gpt-4o", 1_000_000, 1_000_000, 0, 0, 0);
+        assert!((default_cost - 12.5).abs() < 1e-9);
+
+        let cheaper = PricingService::preferring_cheaper(Arc::clone(&service), true);
+        let cheaper_cost = cost(&cheaper, "gpt-4o", 1_000_000, 1_000_000, 0, 0, 0);
+        assert!((cheaper_cost - 10.0).abs() < 1e-9);
+
+        let unchanged = PricingService::preferring_cheaper(service, false);
+        assert_eq!(
+            cost(&unchanged, "gpt-4o", 1_000_000, 1_000_000, 0, 0, 0),
+            default_cost
+        );
+    }
+
+    #[test]
+    fn test_resolve_models_includes_unmatched() {
+        let mut litellm = HashMap::new();
+        litellm.insert("gpt-4o".to_string(), priced(0.0000025, 0.00001));
+        let service = PricingService::new(litellm, HashMap::new());
+
+        let resolved = service.resolve_models(["gpt-4o", "totally-unknown-model", "gpt-4o"]);
+        assert_eq!(resolved.len(), 2);
+
+        let matched = resolved.iter().find(|r| r.model_id == "gpt-4o").unwrap();
+        assert_eq!(matched.source, "LiteLLM");
+        assert_eq!(matched.matched_key, "gpt-4o");
+        assert_eq!(matched.input_cost_per_token, Some(0.0000025));
+        assert_eq!(matched.output_cost_per_token, Some(0.00001));
+
+        let unmatched = resolved
+            .iter()
+            .find(|r| r.model_id == "totally-unknown-model")
+            .unwrap();
+        assert_eq!(unmatched.source, "none");
+        assert!(unmatched.matched_key.is_empty());
+        assert_eq!(unmatched.input_cost_per_token, None);
+    }
+
+    #[test]
+    fn test_reasoning_multiplier_prices_reasoning_separately() {
+        let mut litellm = HashMap::new();
+        litellm.insert("o3".to_string(), priced(0.000002, 0.000008));
+        let service = Arc::new(PricingService::new(litellm, HashMap::new()));
+
+        // 1M input, 1M output, 1M reasoning
+        let folded = cost(&service, "o3", 1_000_000, 1_000_000, 0, 0, 1_000_000);
+        assert!((folded - 18.0).abs() < 1e-9);
+
+        let unit =
+            PricingService::with_reasoning_multiplier(Arc::clone(&service), Some(1.0)).unwrap();
+        let unit_cost = cost(&unit, "o3", 1_000_000, 1_000_000, 0, 0, 1_000_000);
+        assert!((unit_cost - folded).abs() < 1e-9);
+
+        let doubled =
+            PricingService::with_reasoning_multiplier(Arc::clone(&service), Some(2.0)).unwrap();
+        let doubled_cost = cost(&doubled, "o3", 1_000_000, 1_000_000, 0, 0, 1_000_000);
+        assert!((doubled_cost - 26.0).abs() < 1e-9);
+
+        // Without reasoning tokens the multiplier changes nothing
+        assert_eq!(
+            cost(&doubled, "o3", 1_000, 1_000, 0, 0, 0),
+            cost(&service, "o3", 1_000, 1_000, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_reasoning_multiplier_rejects_invalid() {
+        let service = Arc::new(PricingService::new(HashMap::new(), HashMap::new()));
+        assert!(
+            PricingService::with_reasoning_multiplier(Arc::clone(&service), Some(-1.0)).is_err()
+        );
+        assert!(
+            PricingService::with_reasoning_multiplier(Arc::clone(&service), Some(f64::NAN))
+                .is_err()
+        );
+        assert!(PricingService::with_reasoning_multiplier(service, None).is_ok());
+    }
+
+    #[test]
+    fn test_gemini_source_cost_bills_thoughts_as_output_and_cache_free() {
+        let mut litellm = HashMap::new();
+        litellm.insert(
+            "gemini-2.5-pro".to_string(),
+            ModelPricing {
+                input_cost_per_token: Some(0.00000125),
+                output_cost_per_token: Some(0.00001),
+                cache_read_input_token_cost: Some(0.0000003),
+                ..Default::default()
+            },
+        );
+        let service = PricingService::new(litellm, HashMap::new());
+        let tokens = crate::TokenBreakdown {
+            input: 0,
+            output: 1_000,
+            cache_read: 50_000,
+            cache_write: 0,
+            reasoning: 4_000,
+        };
+
+        let gemini = service
+            .calculate_source_cost_breakdown("gemini", "gemini-2.5-pro", &tokens)
+            .total();
+        assert!((gemini - (1_000.0 + 4_000.0) * 0.00001).abs() < 1e-12);
+
+        // The same tokens from any other source pay for the cache reads
+        let other = service
+            .calculate_source_cost_breakdown("opencode", "gemini-2.5-pro", &tokens)
+            .total();
+        assert!((other - gemini - 50_000.0 * 0.0000003).abs() < 1e-12);
+        assert_eq!(
+            other,
+            cost(&service, "gemini-2.5-pro", 0, 1_000, 50_000, 0, 4_000)
+        );
+    }
+
+    #[test]
+    fn test_cost_breakdown_sums_to_calculated_cost() {
+        let mut litellm = HashMap::new();
+        litellm.insert(
+            "claude-sonnet-4".to_string(),
+            ModelPricing {
+                input_cost_per_token: Some(0.000003),
+                output_cost_per_token: Some(0.000015),
+                cache_read_input_token_cost: Some(0.0000003),
+                cache_creation_input_token_cost: Some(0.00000375),
+                input_cost_per_token_above_200k_tokens: Some(0.000006),
+                output_cost_per_token_above_200k_tokens: Some(0.0000225),
+                ..Default::default()
+            },
+        );
+        let service = Arc::new(PricingService::new(litellm, HashMap::new()));
+        let doubled =
+            PricingService::with_reasoning_multiplier(Arc::clone(&service), Some(2.0)).unwrap();
+
+        // Below and above the long-context threshold, with and without a multiplier
+        for pricing in [&service, &doubled] {
+            for (input, output, cache_read, cache_write, reasoning) in [
+                (1_234, 567, 8_900, 1_000, 321),
+                (150_000, 4_000, 60_000, 0, 2_500),
+            ] {
+                let breakdown = pricing.calculate_cost_breakdown(
+                    "claude-sonnet-4",
+                    input,
+                    output,
+                    cache_read,
+                    cache_write,
+                    reasoning,
+                );
+                let total = cost(
+                    pricing,
+                    "claude-sonnet-4",
+                    input,
+                    output,
+                    cache_read,
+                    cache_write,
+                    reasoning,
+                );
+                assert!(total > 0.0);
+                assert!((breakdown.total() - total).abs() < 1e-9);
+                assert!((breakdown.cache_read_cost - cache_read as f64 * 0.0000003).abs() < 1e-12);
+                assert_eq!(breakdown.unattributed_cost, 0.0);
+            }
+        }
+
+        let breakdown = doubled.calculate_cost_breakdown("claude-sonnet-4", 0, 1_000, 0, 0, 1_000);
+        assert!((breakdown.output_cost - 0.015).abs() < 1e-12);
+        assert!((breakdown.reasoning_cost - 0.03).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_apply_keeps_recorded_cost_as_unattributed() {
+        let mut litellm = HashMap::new();
+        litellm.insert("gpt-4o".to_string(), priced(0.0000025, 0.00001));
+        let service = PricingService::new(litellm, HashMap::new());
+        let tokens = crate::TokenBreakdown {
+            input: 1_000,
+            output: 500,
+            ..Default::default()
+        };
+
+        let mut priced_msg = UnifiedMessage::new(
+            "cursor",
+            "gpt-4o",
+            "openai",
+            "s",
+            1748772000000,
+            tokens.clone(),
+            9.0,
+        );
+        service.apply_or_keep_recorded(&mut priced_msg);
+        assert!((priced_msg.cost - 0.0075).abs() < 1e-12);
+        assert!((priced_msg.cost_breakdown.total() - priced_msg.cost).abs() < 1e-12);
+
+        let mut unknown = UnifiedMessage::new(
+            "cursor",
+            "mystery-model",
+            "cursor",
+            "s",
+            1748772000000,
+            tokens.clone(),
+            0.42,
+        );
+        service.apply_or_keep_recorded(&mut unknown);
+        assert_eq!(unknown.cost, 0.42);
+        assert_eq!(unknown.cost_breakdown.unattributed_cost, 0.42);
+        assert!((unknown.cost_breakdown.total() - unknown.cost).abs() < 1e-12);
+
+        let mut token_only = UnifiedMessage::new(
+            "cursor",
+            "mystery-model",
+            "cursor",
+            "s",
+            1748772000000,
+            tokens,
+            0.42,
+        );
+        PricingService::unpriced().apply_or_keep_recorded(&mut token_only);
+        assert_eq!(token_only.cost, 0.0);
+        assert_eq!(token_only.cost_breakdown.total(), 0.0);
+    }
+
+    #[test]
+    fn test_batch_multiplier_is_configurable() {
+        let mut litellm = HashMap::new();
+        litellm.insert("claude-sonnet-4".to_string(), priced(0.000003, 0.000015));
+        let service = Arc::new(PricingService::new(litellm, HashMap::new()));
+        let tokens = crate::TokenBreakdown {
+            input: 1_000_000,
+            ..Default::default()
+        };
+        let mut batch = UnifiedMessage::new(
+            "claude",
+            "claude-sonnet-4",
+            "anthropic",
+            "s",
+            1748772000000,
+            tokens,
+            0.0,
+        );
+        batch.batch = true;
+
+        let cost_with = |multiplier: Option<f64>| {
+            let mut msg = batch.clone();
+            PricingService::with_batch_multiplier(Arc::clone(&service), multiplier)
+                .unwrap()
+                .apply(&mut msg);
+            msg.cost
+        };
+        assert!((cost_with(None) - 1.5).abs() < 1e-12);
+        assert!((cost_with(Some(1.0)) - 3.0).abs() < 1e-12);
+        assert!((cost_with(Some(0.25)) - 0.75).abs() < 1e-12);
+        assert!(PricingService::with_batch_multiplier(Arc::clone(&service), Some(-1.0)).is_err());
+        assert!(PricingService::with_batch_multiplier(service, Some(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn test_unknown_model_rate_applies_only_when_set() {
+        let mut litellm = HashMap::new();
+        litellm.insert("gpt-4o".to_string(), priced(0.0000025, 0.00001));
+        let service = Arc::new(PricingService::new(litellm, HashMap::new()));
+        let tokens = crate::TokenBreakdown {
+            input: 1_000_000,
+            output: 100_000,
+            ..Default::default()
+        };
+        let message = |model: &str| {
+            UnifiedMessage::new(
+                "opencode",
+                model,
+                "x",
+                "s",
+                1748772000000,
+                tokens.clone(),
+                0.0,
+            )
+        };
+
+        let mut unknown = message("mystery-model");
+        service.apply(&mut unknown);
+        assert_eq!(unknown.cost, 0.0);
+        assert!(!unknown.cost_estimated);
+
+        let estimating = PricingService::with_unknown_model_rate(
+            Arc::clone(&service),
+            Some(priced(0.00001, 0.00005)),
+        )
+        .unwrap();
+        let mut unknown = message("mystery-model");
+        estimating.apply(&mut unknown);
+        assert!((unknown.cost - 15.0).abs() < 1e-9);
+        assert!(unknown.cost_estimated);
+
+        // Known models keep their own pricing
+        let mut known = message("gpt-4o");
+        estimating.apply(&mut known);
+        assert!((known.cost - 3.5).abs() < 1e-9);
+        assert!(!known.cost_estimated);
+
+        let models = crate::aggregator::aggregate_by_model(&[unknown, known]);
+        let source_of = |model: &str| {
+            models
+                .iter()
+                .find(|m| m.model == model)
+                .unwrap()
+                .pricing_source
+                .clone()
+        };
+        assert_eq!(source_of("mystery-model").as_deref(), Some("Estimated"));
+        assert_eq!(source_of("gpt-4o"), None);
+
+        // A recorded cost still beats the estimate
+        let mut recorded = UnifiedMessage::new(
+            "cursor",
+            "mystery-model",
+            "cursor",
+            "s",
+            1748772000000,
+            tokens,
+            0.42,
+        );
+        estimating.apply_or_keep_recorded(&mut recorded);
+        assert_eq!(recorded.cost, 0.42);
+        assert!(!recorded.cost_estimated);
+
+        assert!(PricingService::with_unknown_model_rate(service, Some(priced(-1.0, 0.0))).is_err());
+    }
+
+    #[test]
+    fn test_cost_source_modes() {
+        let mut litellm = HashMap::new();
+        litellm.insert("gpt-4o".to_string(), priced(0.0000025, 0.00001));
+        let service = Arc::new(PricingService::new(litellm, HashMap::new()));
+        let tokens = crate::TokenBreakdown {
+            input: 1_000,
+            output: 500,
+            ..Default::default()
+        };
+        let priced_with = |cost_source: &str, recorded: f64| {
+            let service = PricingService::with_cost_source(
+                Arc::clone(&service),
+                CostSource::parse(Some(cost_source)).unwrap(),
+            );
+            let mut msg = UnifiedMessage::new(
+                "opencode",
+                "gpt-4o",
+                "openai",
+                "s",
+                1748772000000,
+                tokens.clone(),
+                recorded,
+            );
+            service.apply(&mut msg);
+            msg
+        };
+
+        let calculated = priced_with("calculated", 0.5);
+        assert!((calculated.cost - 0.0075).abs() < 1e-12);
+
+        let source = priced_with("source", 0.5);
+        assert_eq!(source.cost, 0.5);
+        assert_eq!(source.cost_breakdown.unattributed_cost, 0.5);
+        assert_eq!(priced_with("source", 0.0).cost, 0.0);
+
+        assert_eq!(priced_with("prefer_source", 0.5).cost, 0.5);
+        assert!((priced_with("prefer_source", 0.0).cost - 0.0075).abs() < 1e-12);
+
+        // Calculated drops Cursor's recorded cost even for unknown models
+        let calculating =
+            PricingService::with_cost_source(Arc::clone(&service), CostSource::Calculated);
+        let mut unknown = UnifiedMessage::new(
+            "cursor",
+            "mystery-model",
+            "cursor",
+            "s",
+            1748772000000,
+            tokens,
+            0.42,
+        );
+        calculating.apply_or_keep_recorded(&mut unknown);
+        assert_eq!(unknown.cost, 0.0);
+
+        assert_eq!(CostSource::parse(None), Ok(CostSource::Auto));
+        assert!(CostSource::parse(Some("billing")).is_err());
     }
 }
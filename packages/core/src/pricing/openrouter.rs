@@ -1,15 +1,20 @@
-use super::cache;
 use super::litellm::ModelPricing;
+use super::{backoff, cache};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
-use serde::Deserialize;
 use tokio::sync::Semaphore;
 
 const CACHE_FILENAME: &str = "pricing-openrouter.json";
+/// Per-model endpoint pricing, so a run only refetches models that failed before
+const MODEL_CACHE_FILENAME: &str = "pricing-openrouter-models.json";
 const MODELS_URL: &str = "https://openrouter.ai/api/v1/models";
 const MAX_RETRIES: u32 = 3;
-const INITIAL_BACKOFF_MS: u64 = 200;
 const MAX_CONCURRENT_REQUESTS: usize = 10;
+/// Share of author-provider models that must resolve before the merged map is
+/// cached; a patchier run would otherwise pin missing prices for the whole TTL
+const MIN_CACHE_COVERAGE: f64 = 0.95;
 
 /// Structs for `/api/v1/models` endpoint (list all models).
 
@@ -59,7 +64,7 @@ struct EndpointsResponse {
 /// provider names in the endpoints API, such as `Z.AI`.
 fn get_author_provider_name(model_id: &str) -> Option<&'static str> {
     let prefix = model_id.split('/').next()?;
-    
+
     match prefix.to_lowercase().as_str() {
         "z-ai" => Some("Z.AI"),
         "x-ai" => Some("xAI"),
@@ -77,127 +82,205 @@ fn get_author_provider_name(model_id: &str) -> Option<&'static str> {
     }
 }
 
-pub fn load_cached() -> Option<HashMap<String, ModelPricing>> {
-    cache::load_cache(CACHE_FILENAME)
+/// One model's pricing in the per-model cache, aged individually
+#[derive(Serialize, Deserialize)]
+struct CachedModelPricing {
+    timestamp: u64,
+    pricing: ModelPricing,
 }
 
+type ModelCache = HashMap<String, CachedModelPricing>;
+
 fn parse_price(s: &str) -> Option<f64> {
-    s.trim().parse::<f64>().ok().filter(|v| v.is_finite() && *v >= 0.0)
+    s.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite() && *v >= 0.0)
 }
 
-/// Fetch author pricing for a specific model using the /endpoints API
+/// Fetch author pricing for a specific model using the /endpoints API under
+/// `models_url`, retrying network errors, 429s and 5xx up to `MAX_RETRIES` times
 async fn fetch_author_pricing(
-    client: Arc<reqwest::Client>, 
+    client: Arc<reqwest::Client>,
+    models_url: Arc<str>,
     model_id: String,
     semaphore: Arc<Semaphore>,
 ) -> Option<(String, ModelPricing)> {
     let _permit = semaphore.acquire().await.ok()?;
-    
+
     let author_name = get_author_provider_name(&model_id)?;
-    
-    let url = format!("https://openrouter.ai/api/v1/models/{}/endpoints", model_id);
-    
-    let response = match client.get(&url)
-        .header("Content-Type", "application/json")
-        .send()
-        .await {
-            Ok(r) => r,
-            Err(e) => {
-                eprintln!("[tokscale] endpoints fetch failed for {}: {}", model_id, e);
+
+    let url = format!("{}/{}/endpoints", models_url, model_id);
+
+    let mut last_error: Option<String> = None;
+
+    let data: EndpointsResponse = 'retry: {
+        for attempt in 0..MAX_RETRIES {
+            let response = match client
+                .get(&url)
+                .header("Content-Type", "application/json")
+                .send()
+                .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    last_error = Some(format!("network error: {}", e));
+                    if attempt < MAX_RETRIES - 1 {
+                        backoff::sleep(attempt).await;
+                    }
+                    continue;
+                }
+            };
+
+            let status = response.status();
+            if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                last_error = Some(format!("HTTP {}", status));
+                let _ = response.bytes().await;
+                if attempt < MAX_RETRIES - 1 {
+                    backoff::sleep(attempt).await;
+                }
+                continue;
+            }
+
+            if !status.is_success() {
+                eprintln!(
+                    "[tokscale] endpoints API returned {} for {}",
+                    status, model_id
+                );
                 return None;
             }
-        };
-    
-    if !response.status().is_success() {
-        eprintln!("[tokscale] endpoints API returned {} for {}", response.status(), model_id);
+
+            match response.json().await {
+                Ok(d) => break 'retry d,
+                Err(e) => {
+                    eprintln!(
+                        "[tokscale] endpoints JSON parse failed for {}: {}",
+                        model_id, e
+                    );
+                    return None;
+                }
+            }
+        }
+
+        if let Some(err) = &last_error {
+            eprintln!(
+                "[tokscale] endpoints fetch failed for {} after {} retries: {}",
+                model_id, MAX_RETRIES, err
+            );
+        }
         return None;
-    }
-    
-    let data: EndpointsResponse = match response.json().await {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("[tokscale] endpoints JSON parse failed for {}: {}", model_id, e);
+    };
+
+    // Find the endpoint from the author provider
+    let author_endpoint = match data
+        .data
+        .endpoints
+        .iter()
+        .find(|e| e.provider_name == author_name)
+    {
+        Some(ep) => ep,
+        None => {
+            eprintln!(
+                "[tokscale] author provider '{}' not found for {}",
+                author_name, model_id
+            );
             return None;
         }
     };
-    
-    // Find the endpoint from the author provider
-    let author_endpoint = match data.data.endpoints.iter()
-        .find(|e| e.provider_name == author_name) {
-            Some(ep) => ep,
-            None => {
-                eprintln!("[tokscale] author provider '{}' not found for {}", author_name, model_id);
-                return None;
-            }
-        };
-    
+
     let input_cost = parse_price(&author_endpoint.pricing.prompt)?;
     let output_cost = parse_price(&author_endpoint.pricing.completion)?;
-    
+
     let pricing = ModelPricing {
         input_cost_per_token: Some(input_cost),
         output_cost_per_token: Some(output_cost),
-        cache_read_input_token_cost: author_endpoint.pricing.input_cache_read
+        cache_read_input_token_cost: author_endpoint
+            .pricing
+            .input_cache_read
             .as_ref()
             .and_then(|s| parse_price(s)),
-        cache_creation_input_token_cost: author_endpoint.pricing.input_cache_write
+        cache_creation_input_token_cost: author_endpoint
+            .pricing
+            .input_cache_write
             .as_ref()
             .and_then(|s| parse_price(s)),
+        ..Default::default()
     };
-    
+
     Some((model_id, pricing))
 }
 
-/// Fetch all models and get author pricing for each
-pub async fn fetch_all_models() -> HashMap<String, ModelPricing> {
-    if let Some(cached) = load_cached() {
-        return cached;
+/// Fetch all models and get author pricing for each.
+/// `force_refresh` skips a still-fresh cache and always hits the API.
+pub async fn fetch_all_models(
+    force_refresh: bool,
+) -> cache::Fetched<HashMap<String, ModelPricing>> {
+    fetch_all_models_from(MODELS_URL, &cache::get_cache_dir(), force_refresh).await
+}
+
+/// `fetch_all_models` against `models_url`, caching under `cache_dir`. Models
+/// with a fresh per-model cache entry are not refetched.
+async fn fetch_all_models_from(
+    models_url: &str,
+    cache_dir: &Path,
+    force_refresh: bool,
+) -> cache::Fetched<HashMap<String, ModelPricing>> {
+    let cache_path = cache_dir.join(CACHE_FILENAME);
+    let model_cache_path = cache_dir.join(MODEL_CACHE_FILENAME);
+
+    if !force_refresh {
+        if let Some(cached) = cache::load_cache_at(&cache_path) {
+            return cache::Fetched {
+                data: cached,
+                from_cache: true,
+            };
+        }
     }
-    
-    let client = Arc::new(reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .connect_timeout(std::time::Duration::from_secs(10))
-        .build()
-        .unwrap_or_default());
-    
+
+    let client = Arc::new(
+        reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap_or_default(),
+    );
+
     let mut last_error: Option<String> = None;
-    
+
     // First, get the list of all models
     let model_ids: Vec<String> = 'retry: {
         for attempt in 0..MAX_RETRIES {
-            let response = match client.get(MODELS_URL)
+            let response = match client
+                .get(models_url)
                 .header("Content-Type", "application/json")
                 .send()
-                .await {
-                    Ok(r) => r,
-                    Err(e) => {
-                        last_error = Some(format!("network error: {}", e));
-                        if attempt < MAX_RETRIES - 1 {
-                            tokio::time::sleep(std::time::Duration::from_millis(
-                                INITIAL_BACKOFF_MS * (1 << attempt)
-                            )).await;
-                        }
-                        continue;
+                .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    last_error = Some(format!("network error: {}", e));
+                    if attempt < MAX_RETRIES - 1 {
+                        backoff::sleep(attempt).await;
                     }
-                };
-            
+                    continue;
+                }
+            };
+
             let status = response.status();
             if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
                 last_error = Some(format!("HTTP {}", status));
                 let _ = response.bytes().await;
                 if attempt < MAX_RETRIES - 1 {
-                    tokio::time::sleep(std::time::Duration::from_millis(
-                        INITIAL_BACKOFF_MS * (1 << attempt)
-                    )).await;
+                    backoff::sleep(attempt).await;
                 }
                 continue;
             }
-            
+
             if !status.is_success() {
                 eprintln!("[tokscale] OpenRouter models API returned {}", status);
                 break 'retry Vec::new();
             }
-            
+
             let data: ModelsListResponse = match response.json().await {
                 Ok(d) => d,
                 Err(e) => {
@@ -205,57 +288,226 @@ pub async fn fetch_all_models() -> HashMap<String, ModelPricing> {
                     break 'retry Vec::new();
                 }
             };
-            
+
             break 'retry data.data.into_iter().map(|m| m.id).collect();
         }
-        
+
         if let Some(err) = &last_error {
-            eprintln!("[tokscale] OpenRouter fetch failed after {} retries: {}", MAX_RETRIES, err);
+            eprintln!(
+                "[tokscale] OpenRouter fetch failed after {} retries: {}",
+                MAX_RETRIES, err
+            );
         }
         Vec::new()
     };
-    
+
     if model_ids.is_empty() {
-        return HashMap::new();
+        return cache::Fetched {
+            data: HashMap::new(),
+            from_cache: false,
+        };
     }
-    
+
     // Filter to only models with known author providers
-    let models_with_authors: Vec<String> = model_ids.into_iter()
+    let models_with_authors: Vec<String> = model_ids
+        .into_iter()
         .filter(|id| get_author_provider_name(id).is_some())
         .collect();
-    
+
+    // Reuse still-fresh per-model entries; fetch only the rest
+    let mut model_cache: ModelCache = if force_refresh {
+        HashMap::new()
+    } else {
+        cache::load_cache_entry_at::<ModelCache>(&model_cache_path)
+            .map(|entry| entry.data)
+            .unwrap_or_default()
+    };
+    model_cache.retain(|_, entry| cache::is_fresh(entry.timestamp));
+
+    let mut result = HashMap::new();
+    let mut to_fetch = Vec::new();
+    for model_id in &models_with_authors {
+        match model_cache.get(model_id) {
+            Some(entry) => {
+                result.insert(model_id.clone(), entry.pricing.clone());
+            }
+            None => to_fetch.push(model_id.clone()),
+        }
+    }
+
     let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
-    
+    let models_url: Arc<str> = Arc::from(models_url);
+
     // Spawn tasks for parallel fetching
-    let mut handles = Vec::with_capacity(models_with_authors.len());
-    
-    for model_id in models_with_authors {
+    let mut handles = Vec::with_capacity(to_fetch.len());
+
+    for model_id in to_fetch {
         let client = Arc::clone(&client);
+        let models_url = Arc::clone(&models_url);
         let sem = Arc::clone(&semaphore);
-        
-        let handle = tokio::spawn(async move {
-            fetch_author_pricing(client, model_id, sem).await
-        });
-        
+
+        let handle =
+            tokio::spawn(
+                async move { fetch_author_pricing(client, models_url, model_id, sem).await },
+            );
+
         handles.push(handle);
     }
-    
+
     // Collect results
-    let mut result = HashMap::new();
-    
+    let now = cache::now_secs();
+
     for handle in handles {
         if let Ok(Some((model_id, pricing))) = handle.await {
+            model_cache.insert(
+                model_id.clone(),
+                CachedModelPricing {
+                    timestamp: now,
+                    pricing: pricing.clone(),
+                },
+            );
             result.insert(model_id, pricing);
         }
     }
-    
-    if !result.is_empty() {
-        let _ = cache::save_cache(CACHE_FILENAME, &result);
+
+    if !model_cache.is_empty() {
+        let _ = cache::save_cache_at(&model_cache_path, &model_cache, None, None);
+    }
+
+    let coverage = result.len() as f64 / models_with_authors.len().max(1) as f64;
+    if coverage >= MIN_CACHE_COVERAGE {
+        let _ = cache::save_cache_at(&cache_path, &result, None, None);
+    } else {
+        eprintln!(
+            "[tokscale] OpenRouter priced {}/{} models; not caching the merged map",
+            result.len(),
+            models_with_authors.len()
+        );
+    }
+
+    cache::Fetched {
+        data: result,
+        from_cache: false,
     }
-    
-    result
 }
 
-pub async fn fetch_all_mapped() -> HashMap<String, ModelPricing> {
-    fetch_all_models().await
+pub async fn fetch_all_mapped(
+    force_refresh: bool,
+) -> cache::Fetched<HashMap<String, ModelPricing>> {
+    fetch_all_models(force_refresh).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pricing::test_server::{response, serve, serve_routes};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_author_pricing_retries_transient_failures() {
+        let body = r#"{"data":{"id":"anthropic/claude-sonnet-4","endpoints":[
+            {"provider_name":"Google","pricing":{"prompt":"0.000004","completion":"0.00002"}},
+            {"provider_name":"Anthropic","pricing":{"prompt":"0.000003","completion":"0.000015","input_cache_read":"0.0000003"}}
+        ]}}"#;
+        let (url, server) = serve(
+            "/api/v1/models",
+            vec![
+                response("503 Service Unavailable", ""),
+                response("429 Too Many Requests", ""),
+                response("200 OK", body),
+            ],
+        );
+
+        let result = fetch_author_pricing(
+            Arc::new(reqwest::Client::new()),
+            Arc::from(url.as_str()),
+            "anthropic/claude-sonnet-4".to_string(),
+            Arc::new(Semaphore::new(1)),
+        )
+        .await;
+
+        let (model_id, pricing) = result.expect("pricing after retries");
+        assert_eq!(model_id, "anthropic/claude-sonnet-4");
+        assert_eq!(pricing.input_cost_per_token, Some(0.000003));
+        assert_eq!(pricing.output_cost_per_token, Some(0.000015));
+        assert_eq!(pricing.cache_read_input_token_cost, Some(0.0000003));
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_author_pricing_gives_up_after_max_retries() {
+        let failures = (0..MAX_RETRIES)
+            .map(|_| response("500 Internal Server Error", ""))
+            .collect();
+        let (url, server) = serve("/api/v1/models", failures);
+
+        let result = fetch_author_pricing(
+            Arc::new(reqwest::Client::new()),
+            Arc::from(url.as_str()),
+            "openai/gpt-4.1".to_string(),
+            Arc::new(Semaphore::new(1)),
+        )
+        .await;
+
+        assert!(result.is_none());
+        assert_eq!(server.join().unwrap().len(), MAX_RETRIES as usize);
+    }
+
+    fn endpoints_body(model_id: &str, provider: &str) -> String {
+        format!(
+            r#"{{"data":{{"id":"{}","endpoints":[{{"provider_name":"{}","pricing":{{"prompt":"0.000001","completion":"0.000002"}}}}]}}}}"#,
+            model_id, provider
+        )
+    }
+
+    #[tokio::test]
+    async fn test_partial_run_refetches_only_missing_models() {
+        let dir = TempDir::new().unwrap();
+        let list = r#"{"data":[{"id":"anthropic/claude-sonnet-4"},{"id":"openai/gpt-4.1"},{"id":"unknown/model"}]}"#;
+
+        // First run: gpt-4.1 is not found
+        let (url, requested) = serve_routes("/api/v1/models", move |path| match path {
+            "/api/v1/models" => response("200 OK", list),
+            "/api/v1/models/anthropic/claude-sonnet-4/endpoints" => response(
+                "200 OK",
+                &endpoints_body("anthropic/claude-sonnet-4", "Anthropic"),
+            ),
+            _ => response("404 Not Found", ""),
+        });
+
+        let first = fetch_all_models_from(&url, dir.path(), false).await;
+        assert!(!first.from_cache);
+        assert_eq!(first.data.len(), 1);
+        assert_eq!(requested.lock().unwrap().len(), 3);
+        // 1 of 2 author models is below the coverage bar
+        assert!(!dir.path().join(CACHE_FILENAME).exists());
+        let model_cache: ModelCache =
+            cache::load_cache_at(&dir.path().join(MODEL_CACHE_FILENAME)).unwrap();
+        assert!(model_cache.contains_key("anthropic/claude-sonnet-4"));
+
+        // Second run: gpt-4.1 recovers; claude-sonnet-4 comes from the model cache
+        let (url, requested) = serve_routes("/api/v1/models", move |path| match path {
+            "/api/v1/models" => response("200 OK", list),
+            "/api/v1/models/openai/gpt-4.1/endpoints" => {
+                response("200 OK", &endpoints_body("openai/gpt-4.1", "OpenAI"))
+            }
+            _ => response("404 Not Found", ""),
+        });
+
+        let second = fetch_all_models_from(&url, dir.path(), false).await;
+        assert_eq!(second.data.len(), 2);
+        assert_eq!(
+            *requested.lock().unwrap(),
+            vec!["/api/v1/models", "/api/v1/models/openai/gpt-4.1/endpoints"]
+        );
+        let merged: HashMap<String, ModelPricing> =
+            cache::load_cache_at(&dir.path().join(CACHE_FILENAME)).unwrap();
+        assert_eq!(merged.len(), 2);
+
+        // Third run is served from the merged cache without the network
+        let third =
+            fetch_all_models_from("http://127.0.0.1:9/api/v1/models", dir.path(), false).await;
+        assert!(third.from_cache);
+        assert_eq!(third.data.len(), 2);
+    }
 }
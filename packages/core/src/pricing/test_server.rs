@@ -0,0 +1,82 @@
+//! Local HTTP server for the pricing fetchers' tests
+//!
+//! Listens on an ephemeral 127.0.0.1 port and answers with canned raw HTTP
+//! responses, so fetch, retry and revalidation paths run without the network.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// A raw HTTP response with a JSON `body`
+pub fn response(status: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// Serve the given raw HTTP responses, one per connection, and return the URL
+/// of `path` on the server. Joining the handle gives the request heads
+/// received, lowercased.
+pub fn serve(path: &str, responses: Vec<String>) -> (String, JoinHandle<Vec<String>>) {
+    let (listener, url) = bind(path);
+
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            requests.push(read_head(&mut stream).to_lowercase());
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+        requests
+    });
+
+    (url, handle)
+}
+
+/// Serve every connection by request path until the test ends, recording the
+/// paths requested, and return the URL of `path` on the server
+pub fn serve_routes(
+    path: &str,
+    route: impl Fn(&str) -> String + Send + 'static,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    let (listener, url) = bind(path);
+    let requested = Arc::new(Mutex::new(Vec::new()));
+
+    let log = Arc::clone(&requested);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let head = read_head(&mut stream);
+            let path = head.split_whitespace().nth(1).unwrap_or("").to_string();
+            let body = route(&path);
+            log.lock().unwrap().push(path);
+            stream.write_all(body.as_bytes()).unwrap();
+        }
+    });
+
+    (url, requested)
+}
+
+fn bind(path: &str) -> (TcpListener, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}{}", listener.local_addr().unwrap(), path);
+    (listener, url)
+}
+
+/// Read up to the end of the request head
+fn read_head(stream: &mut TcpStream) -> String {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).unwrap();
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8_lossy(&head).into_owned()
+}
@@ -0,0 +1,151 @@
+//! Scan progress for long parses (`generate_graph_with_progress`)
+//!
+//! Parsers tick once per file, from any rayon worker. Updates reach the
+//! callback at most once per 100 files or 100ms, so a big scan doesn't flood
+//! the JS event loop, and the last file always sends one with
+//! `files_done == files_total`.
+//!
+//! When profiling (`profile`), each file's parse time also lands in a per-source
+//! histogram with log-scale buckets: 0-1ms, 1-5ms, 5-25ms and so on by fives.
+//!
+//! Every parsed file also adds its size to the bytes read, whether or not
+//! anyone listens, for the graph's throughput counters.
+
+use crate::{ParseTimingBucket, ScanProgress, SourceParseTiming};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const EMIT_EVERY_FILES: usize = 100;
+const EMIT_EVERY: Duration = Duration::from_millis(100);
+
+/// Lower bound of each timing bucket, in ms; the last is open-ended
+const BUCKET_MS: [u32; 8] = [0, 1, 5, 25, 125, 625, 3125, 15625];
+
+type Callback = Box<dyn Fn(ScanProgress) + Send + Sync>;
+type Histograms = BTreeMap<&'static str, [u32; BUCKET_MS.len()]>;
+
+pub struct Progress {
+    callback: Option<Callback>,
+    total: AtomicUsize,
+    done: AtomicUsize,
+    bytes_read: AtomicI64,
+    last_emit: Mutex<Instant>,
+    timings: Option<Mutex<Histograms>>,
+}
+
+impl Progress {
+    /// Progress that calls `callback` with each update
+    #[cfg_attr(feature = "noop", allow(dead_code))]
+    pub fn new(callback: Callback) -> Self {
+        Progress {
+            callback: Some(callback),
+            ..Progress::silent()
+        }
+    }
+
+    /// Progress nobody listens to
+    pub fn silent() -> Self {
+        Progress {
+            callback: None,
+            total: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+            bytes_read: AtomicI64::new(0),
+            last_emit: Mutex::new(Instant::now()),
+            timings: None,
+        }
+    }
+
+    /// This progress, also timing each file's parse
+    pub fn profiled(self) -> Self {
+        Progress {
+            timings: Some(Mutex::new(BTreeMap::new())),
+            ..self
+        }
+    }
+
+    /// Set the number of files the scan found, before parsing starts
+    pub fn start(&self, files_total: usize) {
+        self.total.store(files_total, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+        self.bytes_read.store(0, Ordering::Relaxed);
+    }
+
+    /// Run `parse` on `path`, one file of `source`, and count it done
+    pub fn parse<T>(&self, source: &'static str, path: &Path, parse: impl FnOnce() -> T) -> T {
+        let started = self.timings.as_ref().map(|_| Instant::now());
+        let parsed = parse();
+        if let (Some(timings), Some(started)) = (&self.timings, started) {
+            let ms = started.elapsed().as_secs_f64() * 1000.0;
+            let bucket = BUCKET_MS[1..]
+                .iter()
+                .take_while(|&&bound| ms >= bound as f64)
+                .count();
+            let mut timings = timings.lock().unwrap_or_else(|e| e.into_inner());
+            timings.entry(source).or_default()[bucket] += 1;
+        }
+        let size = std::fs::metadata(path).map_or(0, |meta| meta.len() as i64);
+        self.bytes_read.fetch_add(size, Ordering::Relaxed);
+        self.file_done(source);
+        parsed
+    }
+
+    /// Files parsed and their combined size since [`start`](Self::start)
+    pub fn parsed(&self) -> (i32, i64) {
+        (
+            self.done.load(Ordering::Relaxed) as i32,
+            self.bytes_read.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Parse-time histograms per source, non-empty buckets only; `None`
+    /// unless profiling
+    pub fn parse_timing(&self) -> Option<Vec<SourceParseTiming>> {
+        let timings = self
+            .timings
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        Some(
+            timings
+                .iter()
+                .map(|(source, counts)| SourceParseTiming {
+                    source: source.to_string(),
+                    buckets: BUCKET_MS
+                        .iter()
+                        .zip(counts)
+                        .filter(|(_, &count)| count > 0)
+                        .map(|(&bucket_ms, &count)| ParseTimingBucket { bucket_ms, count })
+                        .collect(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Count one parsed file of `source`
+    fn file_done(&self, source: &str) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let Some(callback) = &self.callback else {
+            return;
+        };
+        let total = self.total.load(Ordering::Relaxed);
+
+        let due = done >= total || done.is_multiple_of(EMIT_EVERY_FILES) || {
+            let mut last_emit = self.last_emit.lock().unwrap_or_else(|e| e.into_inner());
+            let due = last_emit.elapsed() >= EMIT_EVERY;
+            if due {
+                *last_emit = Instant::now();
+            }
+            due
+        };
+        if due {
+            callback(ScanProgress {
+                files_done: done as i32,
+                files_total: total as i32,
+                source: source.to_string(),
+            });
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Predicate filtering for the raw message query API
+//!
+//! Every predicate that is set must match (they are ANDed); unset predicates
+//! match everything.
+
+use crate::sessions::UnifiedMessage;
+
+#[derive(Debug, Clone, Default)]
+pub struct MessageQuery {
+    pub sources: Option<Vec<String>>,
+    /// Model id globs (`*` and `?`), matched case-insensitively
+    pub models: Option<Vec<String>>,
+    pub providers: Option<Vec<String>>,
+    pub agents: Option<Vec<String>>,
+    /// Inclusive YYYY-MM-DD bounds
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Minimum total tokens (input + output + cache + reasoning)
+    pub min_tokens: Option<i64>,
+    pub session_ids: Option<Vec<String>>,
+}
+
+impl MessageQuery {
+    pub fn matches(&self, msg: &UnifiedMessage) -> bool {
+        if let Some(sources) = &self.sources {
+            if !sources.iter().any(|s| s == &msg.source) {
+                return false;
+            }
+        }
+
+        if let Some(models) = &self.models {
+            let model = msg.model_id.to_lowercase();
+            if !models
+                .iter()
+                .any(|pattern| glob_matches(&pattern.to_lowercase(), &model))
+            {
+                return false;
+            }
+        }
+
+        if let Some(providers) = &self.providers {
+            if !providers
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(&msg.provider_id))
+            {
+                return false;
+            }
+        }
+
+        if let Some(agents) = &self.agents {
+            let agent = match &msg.agent {
+                Some(a) => a,
+                None => return false,
+            };
+            if !agents.iter().any(|a| a.eq_ignore_ascii_case(agent)) {
+                return false;
+            }
+        }
+
+        if let Some(since) = &self.since {
+            if msg.date.as_str() < since.as_str() {
+                return false;
+            }
+        }
+
+        if let Some(until) = &self.until {
+            if msg.date.as_str() > until.as_str() {
+                return false;
+            }
+        }
+
+        if let Some(min_tokens) = self.min_tokens {
+            let t = &msg.tokens;
+            let total = t.input + t.output + t.cache_read + t.cache_write + t.reasoning;
+            if total < min_tokens {
+                return false;
+            }
+        }
+
+        if let Some(session_ids) = &self.session_ids {
+            if !session_ids.iter().any(|id| id == &msg.session_id) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Glob match supporting `*` (any run of characters) and `?` (one character)
+pub(crate) fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenBreakdown;
+
+    fn message(
+        source: &str,
+        model: &str,
+        session_id: &str,
+        date: &str,
+        input: i64,
+    ) -> UnifiedMessage {
+        let mut msg = UnifiedMessage::new(
+            source,
+            model,
+            "anthropic",
+            session_id,
+            1733011200000,
+            TokenBreakdown {
+                input,
+                output: 50,
+                cache_read: 0,
+                cache_write: 0,
+                reasoning: 0,
+            },
+            0.0,
+        );
+        msg.date = date.to_string();
+        msg
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("claude-*", "claude-sonnet-4"));
+        assert!(glob_matches("*sonnet*", "claude-sonnet-4"));
+        assert!(glob_matches("gpt-4?", "gpt-4o"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("claude-*", "gpt-4o"));
+        assert!(!glob_matches("gpt-4?", "gpt-4"));
+        assert!(glob_matches("a*b*c", "axxbyyc"));
+        assert!(!glob_matches("a*b*c", "axxbyy"));
+    }
+
+    #[test]
+    fn test_empty_query_matches_everything() {
+        let msg = message("claude", "claude-sonnet-4", "s1", "2025-01-01", 100);
+        assert!(MessageQuery::default().matches(&msg));
+    }
+
+    #[test]
+    fn test_combined_predicates_are_anded() {
+        let query = MessageQuery {
+            models: Some(vec!["Claude-*".to_string()]),
+            since: Some("2025-01-10".to_string()),
+            min_tokens: Some(1_000),
+            ..Default::default()
+        };
+
+        let hit = message("claude", "claude-sonnet-4", "s1", "2025-01-15", 5_000);
+        let wrong_model = message("codex", "gpt-5", "s1", "2025-01-15", 5_000);
+        let too_early = message("claude", "claude-sonnet-4", "s1", "2025-01-05", 5_000);
+        let too_small = message("claude", "claude-sonnet-4", "s1", "2025-01-15", 100);
+
+        assert!(query.matches(&hit));
+        assert!(!query.matches(&wrong_model));
+        assert!(!query.matches(&too_early));
+        assert!(!query.matches(&too_small));
+    }
+
+    #[test]
+    fn test_agents_and_sessions() {
+        let mut with_agent = message("opencode", "claude-sonnet-4", "s1", "2025-01-15", 100);
+        with_agent.agent = Some("Sisyphus".to_string());
+        let without_agent = message("opencode", "claude-sonnet-4", "s1", "2025-01-15", 100);
+        let other_session = {
+            let mut m = message("opencode", "claude-sonnet-4", "s2", "2025-01-15", 100);
+            m.agent = Some("sisyphus".to_string());
+            m
+        };
+
+        let query = MessageQuery {
+            agents: Some(vec!["sisyphus".to_string()]),
+            session_ids: Some(vec!["s1".to_string()]),
+            sources: Some(vec!["opencode".to_string()]),
+            ..Default::default()
+        };
+
+        assert!(query.matches(&with_agent));
+        assert!(!query.matches(&without_agent));
+        assert!(!query.matches(&other_session));
+    }
+}
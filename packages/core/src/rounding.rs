@@ -0,0 +1,209 @@
+//! Rounding of emitted costs (`round_costs`)
+//!
+//! Sums of float costs pick up noise like `12.340000000000002`. Reports round
+//! every cost they emit, per entry and in totals, as their last step. Halves
+//! round away from zero (`f64::round`), not to even. Rates such as
+//! `cost_per_mtok_output` are left as they are.
+
+use crate::{
+    DailyContribution, DailyTotals, DataSummary, GraphResult, ModelReport, ModelUsage,
+    MonthlyReport, MonthlyUsage, ProjectReport, ProviderDayContribution, QuarterlyReport,
+    SourceContribution, SourceUsage, TokenCostBreakdown, YearSummary,
+};
+
+/// Past this many places rounding can no longer change an f64 cost
+const MAX_PLACES: u32 = 15;
+
+/// `value` rounded to `places` decimal places, halves away from zero
+pub fn round_cost(value: f64, places: u32) -> f64 {
+    if places > MAX_PLACES {
+        return value;
+    }
+    let scale = 10f64.powi(places as i32);
+    (value * scale).round() / scale
+}
+
+/// Round every cost a report emits to `places` decimal places
+pub trait RoundCosts {
+    fn round_costs(&mut self, places: u32);
+}
+
+impl<T: RoundCosts> RoundCosts for Vec<T> {
+    fn round_costs(&mut self, places: u32) {
+        for item in self {
+            item.round_costs(places);
+        }
+    }
+}
+
+impl RoundCosts for TokenCostBreakdown {
+    fn round_costs(&mut self, places: u32) {
+        self.input_cost = round_cost(self.input_cost, places);
+        self.output_cost = round_cost(self.output_cost, places);
+        self.cache_read_cost = round_cost(self.cache_read_cost, places);
+        self.cache_write_cost = round_cost(self.cache_write_cost, places);
+        self.reasoning_cost = round_cost(self.reasoning_cost, places);
+        self.unattributed_cost = round_cost(self.unattributed_cost, places);
+    }
+}
+
+impl RoundCosts for ModelUsage {
+    fn round_costs(&mut self, places: u32) {
+        self.cost = round_cost(self.cost, places);
+        self.cost_breakdown.round_costs(places);
+    }
+}
+
+impl RoundCosts for ModelReport {
+    fn round_costs(&mut self, places: u32) {
+        self.entries.round_costs(places);
+        self.total_cost = round_cost(self.total_cost, places);
+        self.cost_breakdown.round_costs(places);
+        self.cache_savings = round_cost(self.cache_savings, places);
+        for message in self.messages.iter_mut().flatten() {
+            message.cost = message.cost.map(|cost| round_cost(cost, places));
+        }
+    }
+}
+
+impl RoundCosts for SourceUsage {
+    fn round_costs(&mut self, places: u32) {
+        self.cost = round_cost(self.cost, places);
+    }
+}
+
+impl RoundCosts for MonthlyUsage {
+    fn round_costs(&mut self, places: u32) {
+        self.cost = round_cost(self.cost, places);
+        self.source_breakdown.round_costs(places);
+    }
+}
+
+impl RoundCosts for MonthlyReport {
+    fn round_costs(&mut self, places: u32) {
+        self.entries.round_costs(places);
+        self.total_cost = round_cost(self.total_cost, places);
+    }
+}
+
+impl RoundCosts for QuarterlyReport {
+    fn round_costs(&mut self, places: u32) {
+        for entry in &mut self.entries {
+            entry.cost = round_cost(entry.cost, places);
+        }
+        self.total_cost = round_cost(self.total_cost, places);
+    }
+}
+
+impl RoundCosts for ProjectReport {
+    fn round_costs(&mut self, places: u32) {
+        for entry in &mut self.entries {
+            entry.cost = round_cost(entry.cost, places);
+        }
+        self.total_cost = round_cost(self.total_cost, places);
+    }
+}
+
+impl RoundCosts for DailyTotals {
+    fn round_costs(&mut self, places: u32) {
+        self.cost = round_cost(self.cost, places);
+    }
+}
+
+impl RoundCosts for SourceContribution {
+    fn round_costs(&mut self, places: u32) {
+        self.cost = round_cost(self.cost, places);
+    }
+}
+
+impl RoundCosts for ProviderDayContribution {
+    fn round_costs(&mut self, places: u32) {
+        self.cost = round_cost(self.cost, places);
+    }
+}
+
+impl RoundCosts for DailyContribution {
+    fn round_costs(&mut self, places: u32) {
+        self.totals.round_costs(places);
+        self.top_model_cost = round_cost(self.top_model_cost, places);
+        self.sources.round_costs(places);
+        self.provider_breakdown.round_costs(places);
+    }
+}
+
+impl RoundCosts for YearSummary {
+    fn round_costs(&mut self, places: u32) {
+        self.total_cost = round_cost(self.total_cost, places);
+    }
+}
+
+impl RoundCosts for DataSummary {
+    fn round_costs(&mut self, places: u32) {
+        self.total_cost = round_cost(self.total_cost, places);
+        self.average_per_day = round_cost(self.average_per_day, places);
+        self.max_cost_in_single_day = round_cost(self.max_cost_in_single_day, places);
+    }
+}
+
+impl RoundCosts for GraphResult {
+    fn round_costs(&mut self, places: u32) {
+        self.summary.round_costs(places);
+        self.years.round_costs(places);
+        self.contributions.round_costs(places);
+        for cost in &mut self.rolling_cost {
+            *cost = round_cost(*cost, places);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn month(month: &str, cost: f64) -> MonthlyUsage {
+        MonthlyUsage {
+            month: month.to_string(),
+            models: vec![],
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: 0,
+            message_count: 1,
+            cost,
+            source_breakdown: vec![],
+        }
+    }
+
+    #[test]
+    fn test_round_costs_cleans_float_noise() {
+        let noisy = 12.340000000000002;
+        assert_eq!(round_cost(noisy, 2), 12.34);
+        assert_eq!(round_cost(0.125, 2), 0.13);
+        assert_eq!(round_cost(-0.125, 2), -0.13);
+        assert_eq!(round_cost(noisy, 20), noisy);
+
+        let entries = vec![
+            month("2025-01", noisy),
+            month("2025-02", 0.1 + 0.2),
+            month("2025-03", 7.004),
+        ];
+        let unrounded: f64 = entries.iter().map(|e| e.cost).sum();
+        let mut report = MonthlyReport {
+            entries,
+            total_cost: unrounded,
+            processing_time_ms: 0,
+            clamped_messages: 0,
+            dropped_messages: 0,
+        };
+
+        report.round_costs(2);
+
+        assert_eq!(report.entries[0].cost, 12.34);
+        assert_eq!(report.entries[1].cost, 0.3);
+        assert_eq!(report.entries[2].cost, 7.0);
+        assert!((report.total_cost - unrounded).abs() < 0.005);
+        let entry_sum: f64 = report.entries.iter().map(|e| e.cost).sum();
+        assert!((entry_sum - report.total_cost).abs() < 0.01);
+    }
+}
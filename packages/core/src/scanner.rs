@@ -3,7 +3,7 @@
 //! Uses walkdir with rayon for parallel directory traversal.
 
 use rayon::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 /// Session source type
@@ -17,6 +17,52 @@ pub enum SessionType {
     Amp,
     Droid,
     OpenClaw,
+    Warp,
+    Roo,
+    Kilo,
+    Qwen,
+    Copilot,
+    Jules,
+}
+
+impl SessionType {
+    /// Every source, in the order reports list them
+    pub const ALL: [SessionType; 14] = [
+        SessionType::OpenCode,
+        SessionType::Claude,
+        SessionType::Codex,
+        SessionType::Gemini,
+        SessionType::Cursor,
+        SessionType::Amp,
+        SessionType::Droid,
+        SessionType::OpenClaw,
+        SessionType::Warp,
+        SessionType::Roo,
+        SessionType::Kilo,
+        SessionType::Qwen,
+        SessionType::Copilot,
+        SessionType::Jules,
+    ];
+
+    /// Source name as used in `sources` options
+    pub fn source(self) -> &'static str {
+        match self {
+            SessionType::OpenCode => "opencode",
+            SessionType::Claude => "claude",
+            SessionType::Codex => "codex",
+            SessionType::Gemini => "gemini",
+            SessionType::Cursor => "cursor",
+            SessionType::Amp => "amp",
+            SessionType::Droid => "droid",
+            SessionType::OpenClaw => "openclaw",
+            SessionType::Warp => "warp",
+            SessionType::Roo => "roo",
+            SessionType::Kilo => "kilo",
+            SessionType::Qwen => "qwen",
+            SessionType::Copilot => "copilot",
+            SessionType::Jules => "jules",
+        }
+    }
 }
 
 /// Result of scanning all session directories
@@ -30,6 +76,12 @@ pub struct ScanResult {
     pub amp_files: Vec<PathBuf>,
     pub droid_files: Vec<PathBuf>,
     pub openclaw_files: Vec<PathBuf>,
+    pub warp_files: Vec<PathBuf>,
+    pub roo_files: Vec<PathBuf>,
+    pub kilo_files: Vec<PathBuf>,
+    pub qwen_files: Vec<PathBuf>,
+    pub copilot_files: Vec<PathBuf>,
+    pub jules_files: Vec<PathBuf>,
 }
 
 impl ScanResult {
@@ -43,6 +95,61 @@ impl ScanResult {
             + self.amp_files.len()
             + self.droid_files.len()
             + self.openclaw_files.len()
+            + self.warp_files.len()
+            + self.roo_files.len()
+            + self.kilo_files.len()
+            + self.qwen_files.len()
+            + self.copilot_files.len()
+            + self.jules_files.len()
+    }
+
+    /// Per-source file counts and the combined size of every file found
+    pub fn stats(&self) -> crate::ScanStatsDetail {
+        let total_bytes = self
+            .all_files()
+            .par_iter()
+            .filter_map(|(_, path)| std::fs::metadata(path).ok())
+            .map(|meta| meta.len() as i64)
+            .sum();
+
+        crate::ScanStatsDetail {
+            opencode_files: self.opencode_files.len() as i32,
+            claude_files: self.claude_files.len() as i32,
+            codex_files: self.codex_files.len() as i32,
+            gemini_files: self.gemini_files.len() as i32,
+            cursor_files: self.cursor_files.len() as i32,
+            amp_files: self.amp_files.len() as i32,
+            droid_files: self.droid_files.len() as i32,
+            openclaw_files: self.openclaw_files.len() as i32,
+            warp_files: self.warp_files.len() as i32,
+            roo_files: self.roo_files.len() as i32,
+            kilo_files: self.kilo_files.len() as i32,
+            qwen_files: self.qwen_files.len() as i32,
+            copilot_files: self.copilot_files.len() as i32,
+            jules_files: self.jules_files.len() as i32,
+            total_files: self.total_files() as i32,
+            total_bytes,
+        }
+    }
+
+    /// Keep only the files `keep` accepts
+    pub fn retain(&mut self, mut keep: impl FnMut(SessionType, &Path) -> bool) {
+        self.opencode_files
+            .retain(|p| keep(SessionType::OpenCode, p));
+        self.claude_files.retain(|p| keep(SessionType::Claude, p));
+        self.codex_files.retain(|p| keep(SessionType::Codex, p));
+        self.gemini_files.retain(|p| keep(SessionType::Gemini, p));
+        self.cursor_files.retain(|p| keep(SessionType::Cursor, p));
+        self.amp_files.retain(|p| keep(SessionType::Amp, p));
+        self.droid_files.retain(|p| keep(SessionType::Droid, p));
+        self.openclaw_files
+            .retain(|p| keep(SessionType::OpenClaw, p));
+        self.warp_files.retain(|p| keep(SessionType::Warp, p));
+        self.roo_files.retain(|p| keep(SessionType::Roo, p));
+        self.kilo_files.retain(|p| keep(SessionType::Kilo, p));
+        self.qwen_files.retain(|p| keep(SessionType::Qwen, p));
+        self.copilot_files.retain(|p| keep(SessionType::Copilot, p));
+        self.jules_files.retain(|p| keep(SessionType::Jules, p));
     }
 
     /// Get all files as a single vector
@@ -73,6 +180,24 @@ impl ScanResult {
         for path in &self.openclaw_files {
             result.push((SessionType::OpenClaw, path.clone()));
         }
+        for path in &self.warp_files {
+            result.push((SessionType::Warp, path.clone()));
+        }
+        for path in &self.roo_files {
+            result.push((SessionType::Roo, path.clone()));
+        }
+        for path in &self.kilo_files {
+            result.push((SessionType::Kilo, path.clone()));
+        }
+        for path in &self.qwen_files {
+            result.push((SessionType::Qwen, path.clone()));
+        }
+        for path in &self.copilot_files {
+            result.push((SessionType::Copilot, path.clone()));
+        }
+        for path in &self.jules_files {
+            result.push((SessionType::Jules, path.clone()));
+        }
 
         result
     }
@@ -98,14 +223,162 @@ pub fn headless_roots(home_dir: &str) -> Vec<PathBuf> {
     roots
 }
 
+/// Warp AI session store roots: `TOKSCALE_WARP_DIR` if set, otherwise the
+/// Linux and macOS data directories of the stable channel
+fn warp_roots(home_dir: &str) -> Vec<String> {
+    if let Ok(path) = std::env::var("TOKSCALE_WARP_DIR") {
+        return vec![path];
+    }
+
+    let xdg_state =
+        std::env::var("XDG_STATE_HOME").unwrap_or_else(|_| format!("{}/.local/state", home_dir));
+    vec![
+        format!("{}/warp-terminal/ai_sessions", xdg_state),
+        format!(
+            "{}/Library/Application Support/dev.warp.Warp-Stable/ai_sessions",
+            home_dir
+        ),
+    ]
+}
+
+/// Roo Code task roots: `TOKSCALE_ROO_DIR` if set, otherwise the extension's
+/// VS Code global storage on Linux and macOS (VS Code lowercases the id)
+fn roo_roots(home_dir: &str) -> Vec<String> {
+    if let Ok(path) = std::env::var("TOKSCALE_ROO_DIR") {
+        return vec![path];
+    }
+
+    vec![
+        format!(
+            "{}/.config/Code/User/globalStorage/rooveterinaryinc.roo-cline/tasks",
+            home_dir
+        ),
+        format!(
+            "{}/Library/Application Support/Code/User/globalStorage/rooveterinaryinc.roo-cline/tasks",
+            home_dir
+        ),
+    ]
+}
+
+/// Kilo Code task roots: `TOKSCALE_KILO_DIR` if set, otherwise the extension's
+/// VS Code global storage on Linux and macOS
+fn kilo_roots(home_dir: &str) -> Vec<String> {
+    if let Ok(path) = std::env::var("TOKSCALE_KILO_DIR") {
+        return vec![path];
+    }
+
+    vec![
+        format!(
+            "{}/.config/Code/User/globalStorage/kilocode.kilo-code/tasks",
+            home_dir
+        ),
+        format!(
+            "{}/Library/Application Support/Code/User/globalStorage/kilocode.kilo-code/tasks",
+            home_dir
+        ),
+    ]
+}
+
+/// Copilot Chat roots: `TOKSCALE_COPILOT_DIR` if set, otherwise VS Code's
+/// workspace storage on Linux and macOS
+fn copilot_roots(home_dir: &str) -> Vec<String> {
+    if let Ok(path) = std::env::var("TOKSCALE_COPILOT_DIR") {
+        return vec![path];
+    }
+
+    vec![
+        format!("{}/.config/Code/User/workspaceStorage", home_dir),
+        format!(
+            "{}/Library/Application Support/Code/User/workspaceStorage",
+            home_dir
+        ),
+    ]
+}
+
+/// Jules task roots: `TOKSCALE_JULES_DIR` if set, otherwise `~/.jules/tasks`.
+/// Jules has no documented local store yet, so the override is the main way in.
+fn jules_roots(home_dir: &str) -> Vec<String> {
+    if let Ok(path) = std::env::var("TOKSCALE_JULES_DIR") {
+        return vec![path];
+    }
+
+    vec![format!("{}/.jules/tasks", home_dir)]
+}
+
+/// Whether `file_name` is a live Cursor usage cache: `usage.csv` for the active
+/// account or `usage.<account>.csv` for another one. Renamed backups are not:
+/// `usage.backup*.csv`, and purely numeric segments such as `usage.2024.csv`,
+/// which would otherwise be counted as an account named "2024".
+pub fn is_cursor_usage_file(file_name: &str) -> bool {
+    if file_name == "usage.csv" {
+        return true;
+    }
+
+    let Some(account) = file_name
+        .strip_prefix("usage.")
+        .and_then(|s| s.strip_suffix(".csv"))
+    else {
+        return false;
+    };
+
+    !account.is_empty()
+        && !account.starts_with("backup")
+        && !account.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Directory names skipped while scanning unless the caller supplies its own
+pub const DEFAULT_EXCLUDE_DIRS: &[&str] = &["archive"];
+
+/// `exclude_dirs` when given, otherwise `DEFAULT_EXCLUDE_DIRS`
+pub fn exclude_dirs_or_default(exclude_dirs: Option<&[String]>) -> Vec<String> {
+    match exclude_dirs {
+        Some(dirs) => dirs.to_vec(),
+        None => DEFAULT_EXCLUDE_DIRS.iter().map(|d| d.to_string()).collect(),
+    }
+}
+
+/// `sources` when given, otherwise every source in `SessionType::ALL`
+pub fn sources_or_default(sources: Option<&[String]>) -> Vec<String> {
+    match sources {
+        Some(sources) => sources.to_vec(),
+        None => SessionType::ALL
+            .iter()
+            .map(|t| t.source().to_string())
+            .collect(),
+    }
+}
+
 /// Scan a single directory for session files
 pub fn scan_directory(root: &str, pattern: &str) -> Vec<PathBuf> {
+    scan_directory_excluding(root, pattern, &exclude_dirs_or_default(None))
+}
+
+/// Like `scan_directory`, but skips every file with a directory in its path
+/// whose name matches one of `exclude_dirs` (case-insensitive). That includes
+/// `root` and its ancestors, so a root inside an excluded directory finds nothing.
+pub fn scan_directory_excluding(
+    root: &str,
+    pattern: &str,
+    exclude_dirs: &[String],
+) -> Vec<PathBuf> {
     if !std::path::Path::new(root).exists() {
         return Vec::new();
     }
 
+    let excluded = |name: &std::ffi::OsStr| {
+        exclude_dirs
+            .iter()
+            .any(|d| name.to_string_lossy().eq_ignore_ascii_case(d))
+    };
     WalkDir::new(root)
         .into_iter()
+        .filter_entry(|e| {
+            if e.depth() == 0 {
+                !e.path().components().any(|c| excluded(c.as_os_str()))
+            } else {
+                !e.file_type().is_dir() || !excluded(e.file_name())
+            }
+        })
         .par_bridge()
         .filter_map(|e| e.ok())
         .filter(|e| {
@@ -116,43 +389,29 @@ pub fn scan_directory(root: &str, pattern: &str) -> Vec<PathBuf> {
 
             let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
 
-            let is_in_archive_dir = path.components().any(|c| {
-                c.as_os_str()
-                    .to_string_lossy()
-                    .eq_ignore_ascii_case("archive")
-            });
-
             match pattern {
                 "*.json" => file_name.ends_with(".json"),
                 "*.jsonl" => file_name.ends_with(".jsonl"),
-                "*.csv" => file_name.ends_with(".csv"),
-                "usage*.csv" => {
-                    if is_in_archive_dir {
-                        return false;
-                    }
-
-                    if file_name == "usage.csv" {
-                        return true;
-                    }
-
-                    // Accept only per-account files: usage.<account>.csv
-                    if !file_name.starts_with("usage.") || !file_name.ends_with(".csv") {
-                        return false;
-                    }
-
-                    // Exclude legacy backups like usage.backup-<ts>.csv
-                    if file_name.starts_with("usage.backup") {
-                        return false;
-                    }
-
-                    true
+                "*.json[l]" => file_name.ends_with(".json") || file_name.ends_with(".jsonl"),
+                "*.jsonl[.zst]" => {
+                    file_name.ends_with(".jsonl") || file_name.ends_with(".jsonl.zst")
                 }
+                "*.csv" => file_name.ends_with(".csv"),
+                "usage*.csv" => is_cursor_usage_file(file_name),
                 "session-*.json" => {
                     file_name.starts_with("session-") && file_name.ends_with(".json")
                 }
                 "T-*.json" => file_name.starts_with("T-") && file_name.ends_with(".json"),
                 "*.settings.json" => file_name.ends_with(".settings.json"),
                 "sessions.json" => file_name == "sessions.json",
+                "api_conversation_history.json" => file_name == "api_conversation_history.json",
+                "chatSessions/*.json" => {
+                    file_name.ends_with(".json")
+                        && path
+                            .parent()
+                            .and_then(|p| p.file_name())
+                            .is_some_and(|dir| dir == "chatSessions")
+                }
                 _ => false,
             }
         })
@@ -160,10 +419,9 @@ pub fn scan_directory(root: &str, pattern: &str) -> Vec<PathBuf> {
         .collect()
 }
 
-/// Scan all session source directories in parallel
-pub fn scan_all_sources(home_dir: &str, sources: &[String]) -> ScanResult {
-    let mut result = ScanResult::default();
-
+/// Directories `scan_all_sources` walks for `sources` (all when empty), with
+/// the file pattern for each. Env overrides and headless roots are applied.
+pub fn scan_tasks(home_dir: &str, sources: &[String]) -> Vec<(SessionType, String, &'static str)> {
     let include_all = sources.is_empty();
     let include_opencode = include_all || sources.iter().any(|s| s == "opencode");
     let include_claude = include_all || sources.iter().any(|s| s == "claude");
@@ -173,11 +431,17 @@ pub fn scan_all_sources(home_dir: &str, sources: &[String]) -> ScanResult {
     let include_amp = include_all || sources.iter().any(|s| s == "amp");
     let include_droid = include_all || sources.iter().any(|s| s == "droid");
     let include_openclaw = include_all || sources.iter().any(|s| s == "openclaw");
+    let include_warp = include_all || sources.iter().any(|s| s == "warp");
+    let include_roo = include_all || sources.iter().any(|s| s == "roo");
+    let include_kilo = include_all || sources.iter().any(|s| s == "kilo");
+    let include_qwen = include_all || sources.iter().any(|s| s == "qwen");
+    let include_copilot = include_all || sources.iter().any(|s| s == "copilot");
+    let include_jules = include_all || sources.iter().any(|s| s == "jules");
 
     let headless_roots = headless_roots(home_dir);
 
     // Define scan tasks
-    let mut tasks: Vec<(SessionType, String, &str)> = Vec::new();
+    let mut tasks: Vec<(SessionType, String, &'static str)> = Vec::new();
 
     if include_opencode {
         // OpenCode: ~/.local/share/opencode/storage/message/*/*.json
@@ -191,14 +455,21 @@ pub fn scan_all_sources(home_dir: &str, sources: &[String]) -> ScanResult {
         // Claude: ~/.claude/projects/**/*.jsonl
         let claude_path = format!("{}/.claude/projects", home_dir);
         tasks.push((SessionType::Claude, claude_path, "*.jsonl"));
+
+        // Claude headless: <headless_root>/claude/*.json[l] (stream-json or json output)
+        for root in &headless_roots {
+            let claude_headless_path = root.join("claude");
+            let path = claude_headless_path.to_string_lossy().to_string();
+            tasks.push((SessionType::Claude, path, "*.json[l]"));
+        }
     }
 
     if include_codex {
-        // Codex: ~/.codex/sessions/**/*.jsonl
+        // Codex: ~/.codex/sessions/**/*.jsonl (rotated sessions as *.jsonl.zst)
         let codex_home =
             std::env::var("CODEX_HOME").unwrap_or_else(|_| format!("{}/.codex", home_dir));
         let codex_path = format!("{}/sessions", codex_home);
-        tasks.push((SessionType::Codex, codex_path, "*.jsonl"));
+        tasks.push((SessionType::Codex, codex_path, "*.jsonl[.zst]"));
 
         // Codex headless: <headless_root>/codex/*.jsonl
         for root in &headless_roots {
@@ -248,11 +519,79 @@ pub fn scan_all_sources(home_dir: &str, sources: &[String]) -> ScanResult {
         tasks.push((SessionType::OpenClaw, moldbot_path, "sessions.json"));
     }
 
+    if include_warp {
+        // Warp: <warp_root>/**/*.json
+        for warp_path in warp_roots(home_dir) {
+            tasks.push((SessionType::Warp, warp_path, "*.json"));
+        }
+    }
+
+    if include_roo {
+        // Roo Code: <roo_root>/<task_id>/api_conversation_history.json
+        for roo_path in roo_roots(home_dir) {
+            tasks.push((SessionType::Roo, roo_path, "api_conversation_history.json"));
+        }
+    }
+
+    if include_kilo {
+        // Kilo Code: <kilo_root>/<task_id>/api_conversation_history.json
+        for kilo_path in kilo_roots(home_dir) {
+            tasks.push((
+                SessionType::Kilo,
+                kilo_path,
+                "api_conversation_history.json",
+            ));
+        }
+    }
+
+    if include_qwen {
+        // Qwen Code: ~/.qwen/tmp/*/chats/session-*.json
+        let qwen_path = format!("{}/.qwen/tmp", home_dir);
+        tasks.push((SessionType::Qwen, qwen_path, "session-*.json"));
+
+        // Qwen Code headless: <headless_root>/qwen/*.json[l]
+        for root in &headless_roots {
+            let qwen_headless_path = root.join("qwen");
+            let path = qwen_headless_path.to_string_lossy().to_string();
+            tasks.push((SessionType::Qwen, path, "*.json[l]"));
+        }
+    }
+
+    if include_copilot {
+        // Copilot Chat: <copilot_root>/<workspace>/chatSessions/*.json
+        for copilot_path in copilot_roots(home_dir) {
+            tasks.push((SessionType::Copilot, copilot_path, "chatSessions/*.json"));
+        }
+    }
+
+    if include_jules {
+        // Jules: <jules_root>/**/*.json
+        for jules_path in jules_roots(home_dir) {
+            tasks.push((SessionType::Jules, jules_path, "*.json"));
+        }
+    }
+
+    tasks
+}
+
+/// Scan all session source directories in parallel
+pub fn scan_all_sources(home_dir: &str, sources: &[String]) -> ScanResult {
+    scan_all_sources_excluding(home_dir, sources, &exclude_dirs_or_default(None))
+}
+
+/// `scan_all_sources`, skipping directories named in `exclude_dirs`
+pub fn scan_all_sources_excluding(
+    home_dir: &str,
+    sources: &[String],
+    exclude_dirs: &[String],
+) -> ScanResult {
+    let mut result = ScanResult::default();
+
     // Execute scans in parallel
-    let scan_results: Vec<(SessionType, Vec<PathBuf>)> = tasks
+    let scan_results: Vec<(SessionType, Vec<PathBuf>)> = scan_tasks(home_dir, sources)
         .into_par_iter()
         .map(|(session_type, path, pattern)| {
-            let files = scan_directory(&path, pattern);
+            let files = scan_directory_excluding(&path, pattern, exclude_dirs);
             (session_type, files)
         })
         .collect();
@@ -268,12 +607,81 @@ pub fn scan_all_sources(home_dir: &str, sources: &[String]) -> ScanResult {
             SessionType::Amp => result.amp_files.extend(files),
             SessionType::Droid => result.droid_files.extend(files),
             SessionType::OpenClaw => result.openclaw_files.extend(files),
+            SessionType::Warp => result.warp_files.extend(files),
+            SessionType::Roo => result.roo_files.extend(files),
+            SessionType::Kilo => result.kilo_files.extend(files),
+            SessionType::Qwen => result.qwen_files.extend(files),
+            SessionType::Copilot => result.copilot_files.extend(files),
+            SessionType::Jules => result.jules_files.extend(files),
         }
     }
 
     result
 }
 
+/// A directory the scan would walk and what is there now
+#[derive(Debug, Clone)]
+pub struct ScanTarget {
+    pub session_type: SessionType,
+    pub path: String,
+    pub pattern: &'static str,
+    pub exists: bool,
+    pub file_count: usize,
+}
+
+/// A supported source and where it is looked for by default
+#[derive(Debug, Clone)]
+pub struct SourceInfo {
+    pub session_type: SessionType,
+    /// First directory scanned for the source, after env overrides
+    pub default_path: String,
+    pub pattern: &'static str,
+    /// Scanned when no `sources` are given
+    pub enabled_by_default: bool,
+    /// Whether `default_path` exists
+    pub detected: bool,
+}
+
+/// Every source the scanner knows, with its default location under `home_dir`
+pub fn list_sources(home_dir: &str) -> Vec<SourceInfo> {
+    let default_tasks = scan_tasks(home_dir, &[]);
+    SessionType::ALL
+        .into_iter()
+        .filter_map(|session_type| {
+            let (_, default_path, pattern) =
+                scan_tasks(home_dir, &[session_type.source().to_string()])
+                    .into_iter()
+                    .next()?;
+            Some(SourceInfo {
+                session_type,
+                enabled_by_default: default_tasks.iter().any(|(t, _, _)| *t == session_type),
+                detected: std::path::Path::new(&default_path).is_dir(),
+                default_path,
+                pattern,
+            })
+        })
+        .collect()
+}
+
+/// Every directory `scan_all_sources` would walk for `sources`, with how many
+/// matching files each holds, without parsing anything
+pub fn describe_scan(
+    home_dir: &str,
+    sources: &[String],
+    exclude_dirs: &[String],
+) -> Vec<ScanTarget> {
+    scan_tasks(home_dir, sources)
+        .into_par_iter()
+        .map(|(session_type, path, pattern)| ScanTarget {
+            session_type,
+            exists: std::path::Path::new(&path).is_dir(),
+            file_count: scan_directory_excluding(&path, pattern, exclude_dirs).len(),
+            path,
+            pattern,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -300,6 +708,12 @@ mod tests {
             amp_files: vec![],
             droid_files: vec![],
             openclaw_files: vec![],
+            warp_files: vec![],
+            roo_files: vec![],
+            kilo_files: vec![],
+            qwen_files: vec![],
+            copilot_files: vec![],
+            jules_files: vec![],
         };
         assert_eq!(result.total_files(), 4);
     }
@@ -315,6 +729,12 @@ mod tests {
             amp_files: vec![],
             droid_files: vec![],
             openclaw_files: vec![],
+            warp_files: vec![],
+            roo_files: vec![],
+            kilo_files: vec![],
+            qwen_files: vec![],
+            copilot_files: vec![],
+            jules_files: vec![],
         };
 
         let all = result.all_files();
@@ -371,6 +791,23 @@ mod tests {
             .all(|p| p.extension().unwrap() == "jsonl"));
     }
 
+    #[test]
+    fn test_scan_directory_jsonl_zst_pattern() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+
+        File::create(path.join("rollout-1.jsonl")).unwrap();
+        File::create(path.join("rollout-2.jsonl.zst")).unwrap();
+        File::create(path.join("rollout-3.zst")).unwrap();
+
+        let files = scan_directory(path.to_str().unwrap(), "*.jsonl[.zst]");
+        assert_eq!(files.len(), 2);
+
+        // Plain *.jsonl (Claude, headless) stays uncompressed-only
+        let files = scan_directory(path.to_str().unwrap(), "*.jsonl");
+        assert_eq!(files.len(), 1);
+    }
+
     #[test]
     fn test_scan_directory_session_pattern() {
         let dir = TempDir::new().unwrap();
@@ -422,6 +859,35 @@ mod tests {
         assert!(csv_files.iter().all(|p| p.extension().unwrap() == "csv"));
     }
 
+    #[test]
+    fn test_cursor_usage_files_skip_backups() {
+        assert!(is_cursor_usage_file("usage.csv"));
+        assert!(is_cursor_usage_file("usage.work.csv"));
+        assert!(is_cursor_usage_file("usage.team-2024.csv"));
+        assert!(!is_cursor_usage_file("usage.2024.csv"));
+        assert!(!is_cursor_usage_file("usage.1717171717.csv"));
+        assert!(!is_cursor_usage_file("usage.backup-1717171717.csv"));
+        assert!(!is_cursor_usage_file("usage..csv"));
+        assert!(!is_cursor_usage_file("data.csv"));
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        for name in [
+            "usage.csv",
+            "usage.work.csv",
+            "usage.2024.csv",
+            "usage.backup-1.csv",
+        ] {
+            File::create(path.join(name)).unwrap();
+        }
+        let mut names: Vec<String> = scan_directory(path.to_str().unwrap(), "usage*.csv")
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["usage.csv", "usage.work.csv"]);
+    }
+
     #[test]
     fn test_scan_directory_nonexistent() {
         let files = scan_directory("/nonexistent/path/that/does/not/exist", "*.json");
@@ -435,6 +901,74 @@ mod tests {
         assert!(files.is_empty());
     }
 
+    #[test]
+    fn test_scan_directory_exclude_dirs() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        for sub in ["live", "Archive", "old/nested"] {
+            fs::create_dir_all(path.join(sub)).unwrap();
+            File::create(path.join(sub).join("session.json")).unwrap();
+        }
+        let root = path.to_str().unwrap();
+        let found = |files: Vec<PathBuf>| {
+            let mut dirs: Vec<String> = files
+                .iter()
+                .map(|p| {
+                    p.parent()
+                        .unwrap()
+                        .strip_prefix(path)
+                        .unwrap()
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                })
+                .collect();
+            dirs.sort();
+            dirs
+        };
+
+        // The default skips "archive" in any case, for every pattern
+        assert_eq!(
+            found(scan_directory(root, "*.json")),
+            ["live", "old/nested"]
+        );
+        assert_eq!(
+            found(scan_directory_excluding(
+                root,
+                "*.json",
+                &["OLD".to_string()]
+            )),
+            ["Archive", "live"]
+        );
+        // Any directory in the path counts, the root's and its ancestors' too
+        let old_root = path.join("old");
+        assert!(scan_directory_excluding(
+            old_root.to_str().unwrap(),
+            "*.json",
+            &["old".to_string()]
+        )
+        .is_empty());
+        assert!(scan_directory_excluding(
+            old_root.join("nested").to_str().unwrap(),
+            "*.json",
+            &["OLD".to_string()]
+        )
+        .is_empty());
+        assert_eq!(
+            scan_directory_excluding(old_root.to_str().unwrap(), "*.json", &["live".to_string()])
+                .len(),
+            1
+        );
+        // File names never match
+        assert_eq!(
+            found(scan_directory_excluding(
+                root,
+                "*.json",
+                &["session.json".to_string()]
+            )),
+            ["Archive", "live", "old/nested"]
+        );
+    }
+
     fn setup_mock_opencode_dir(base: &std::path::Path) {
         let opencode_path = base.join(".local/share/opencode/storage/message/proj1");
         fs::create_dir_all(&opencode_path).unwrap();
@@ -558,6 +1092,60 @@ mod tests {
         assert!(result.codex_files.is_empty());
     }
 
+    #[test]
+    fn test_scan_stats_match_files_found() {
+        let dir = TempDir::new().unwrap();
+        let home = dir.path();
+
+        setup_mock_claude_dir(home);
+        let extra = home.join(".claude/projects/other");
+        fs::create_dir_all(&extra).unwrap();
+        fs::write(extra.join("second.jsonl"), b"{\"type\":\"user\"}\n").unwrap();
+        setup_mock_gemini_dir(home);
+
+        let result = scan_all_sources(
+            home.to_str().unwrap(),
+            &["claude".to_string(), "gemini".to_string()],
+        );
+        let stats = result.stats();
+
+        assert_eq!(stats.claude_files, 2);
+        assert_eq!(stats.gemini_files, 1);
+        assert_eq!(stats.opencode_files, 0);
+        assert_eq!(stats.codex_files, 0);
+        assert_eq!(stats.total_files, 3);
+        // "" + "{\"type\":\"user\"}\n" + "{}"
+        assert_eq!(stats.total_bytes, 18);
+    }
+
+    #[test]
+    #[serial]
+    fn test_scan_stats_count_amp_and_droid_when_scanning_all_sources() {
+        let previous_xdg = std::env::var("XDG_DATA_HOME").ok();
+
+        let dir = TempDir::new().unwrap();
+        let home = dir.path();
+        let amp_dir = home.join(".local/share/amp/threads");
+        fs::create_dir_all(&amp_dir).unwrap();
+        File::create(amp_dir.join("T-abc123.json")).unwrap();
+        let droid_dir = home.join(".factory/sessions");
+        fs::create_dir_all(&droid_dir).unwrap();
+        File::create(droid_dir.join("session-1.settings.json")).unwrap();
+        File::create(droid_dir.join("session-1.jsonl")).unwrap();
+        setup_mock_claude_dir(home);
+
+        std::env::set_var("XDG_DATA_HOME", home.join(".local/share"));
+
+        // No sources named: every source is scanned
+        let stats = scan_all_sources(home.to_str().unwrap(), &[]).stats();
+        assert_eq!(stats.amp_files, 1);
+        assert_eq!(stats.droid_files, 1);
+        assert_eq!(stats.claude_files, 1);
+        assert!(stats.total_files >= 3);
+
+        restore_env("XDG_DATA_HOME", previous_xdg);
+    }
+
     #[test]
     #[serial]
     fn test_scan_all_sources_headless_paths() {
@@ -575,6 +1163,9 @@ mod tests {
 
         fs::create_dir_all(mac_root.join("codex")).unwrap();
         File::create(mac_root.join("codex").join("codex.jsonl")).unwrap();
+        fs::create_dir_all(mac_root.join("claude")).unwrap();
+        File::create(mac_root.join("claude").join("stream.jsonl")).unwrap();
+        File::create(mac_root.join("claude").join("result.json")).unwrap();
 
         let result = scan_all_sources(
             home.to_str().unwrap(),
@@ -585,7 +1176,7 @@ mod tests {
             ],
         );
 
-        assert!(result.claude_files.is_empty());
+        assert_eq!(result.claude_files.len(), 2);
         assert_eq!(result.codex_files.len(), 1);
         assert!(result.gemini_files.is_empty());
 
@@ -609,4 +1200,241 @@ mod tests {
 
         restore_env("CODEX_HOME", previous_codex);
     }
+
+    #[test]
+    #[serial]
+    fn test_scan_all_sources_warp_with_env() {
+        let previous_warp = std::env::var("TOKSCALE_WARP_DIR").ok();
+
+        let dir = TempDir::new().unwrap();
+        let warp_dir = dir.path().join("warp");
+        fs::create_dir_all(warp_dir.join("2025")).unwrap();
+        File::create(warp_dir.join("2025").join("conv_1.json")).unwrap();
+        File::create(warp_dir.join("notes.txt")).unwrap();
+
+        std::env::set_var("TOKSCALE_WARP_DIR", &warp_dir);
+
+        let result = scan_all_sources(dir.path().to_str().unwrap(), &["warp".to_string()]);
+        assert_eq!(result.warp_files.len(), 1);
+        assert!(result.claude_files.is_empty());
+
+        restore_env("TOKSCALE_WARP_DIR", previous_warp);
+    }
+
+    #[test]
+    #[serial]
+    fn test_scan_all_sources_jules_with_env() {
+        let previous_jules = std::env::var("TOKSCALE_JULES_DIR").ok();
+
+        let dir = TempDir::new().unwrap();
+        std::env::remove_var("TOKSCALE_JULES_DIR");
+        let default_dir = dir.path().join(".jules").join("tasks");
+        fs::create_dir_all(&default_dir).unwrap();
+        File::create(default_dir.join("task_1.json")).unwrap();
+        let result = scan_all_sources(dir.path().to_str().unwrap(), &["jules".to_string()]);
+        assert_eq!(result.jules_files.len(), 1);
+
+        let jules_dir = dir.path().join("jules");
+        fs::create_dir_all(&jules_dir).unwrap();
+        File::create(jules_dir.join("task_2.json")).unwrap();
+        File::create(jules_dir.join("task_3.json")).unwrap();
+        std::env::set_var("TOKSCALE_JULES_DIR", &jules_dir);
+
+        let result = scan_all_sources(dir.path().to_str().unwrap(), &["jules".to_string()]);
+        assert_eq!(result.jules_files.len(), 2);
+        assert!(result.gemini_files.is_empty());
+
+        restore_env("TOKSCALE_JULES_DIR", previous_jules);
+    }
+
+    #[test]
+    #[serial]
+    fn test_scan_all_sources_roo_with_env() {
+        let previous_roo = std::env::var("TOKSCALE_ROO_DIR").ok();
+
+        let dir = TempDir::new().unwrap();
+        let roo_dir = dir.path().join("roo");
+        let task_dir = roo_dir.join("task-abc");
+        fs::create_dir_all(&task_dir).unwrap();
+        File::create(task_dir.join("api_conversation_history.json")).unwrap();
+        File::create(task_dir.join("ui_messages.json")).unwrap();
+
+        std::env::set_var("TOKSCALE_ROO_DIR", &roo_dir);
+
+        let result = scan_all_sources(dir.path().to_str().unwrap(), &["roo".to_string()]);
+        assert_eq!(result.roo_files.len(), 1);
+
+        restore_env("TOKSCALE_ROO_DIR", previous_roo);
+    }
+
+    #[test]
+    #[serial]
+    fn test_scan_all_sources_kilo_with_env() {
+        let previous_kilo = std::env::var("TOKSCALE_KILO_DIR").ok();
+
+        let dir = TempDir::new().unwrap();
+        let kilo_dir = dir.path().join("kilo");
+        let task_dir = kilo_dir.join("task-abc");
+        fs::create_dir_all(&task_dir).unwrap();
+        File::create(task_dir.join("api_conversation_history.json")).unwrap();
+        File::create(task_dir.join("task_metadata.json")).unwrap();
+
+        std::env::set_var("TOKSCALE_KILO_DIR", &kilo_dir);
+
+        let result = scan_all_sources(dir.path().to_str().unwrap(), &["kilo".to_string()]);
+        assert_eq!(result.kilo_files.len(), 1);
+        assert!(result.roo_files.is_empty());
+
+        restore_env("TOKSCALE_KILO_DIR", previous_kilo);
+    }
+
+    #[test]
+    #[serial]
+    fn test_scan_all_sources_copilot_with_env() {
+        let previous_copilot = std::env::var("TOKSCALE_COPILOT_DIR").ok();
+
+        let dir = TempDir::new().unwrap();
+        let copilot_dir = dir.path().join("workspaceStorage");
+        let sessions = copilot_dir.join("a1b2c3").join("chatSessions");
+        fs::create_dir_all(&sessions).unwrap();
+        File::create(sessions.join("chat-1.json")).unwrap();
+        File::create(copilot_dir.join("a1b2c3").join("workspace.json")).unwrap();
+
+        std::env::set_var("TOKSCALE_COPILOT_DIR", &copilot_dir);
+
+        let result = scan_all_sources(dir.path().to_str().unwrap(), &["copilot".to_string()]);
+        assert_eq!(result.copilot_files.len(), 1);
+
+        restore_env("TOKSCALE_COPILOT_DIR", previous_copilot);
+    }
+
+    #[test]
+    #[serial]
+    fn test_scan_all_sources_qwen_sessions_and_headless() {
+        let previous_headless = std::env::var("TOKSCALE_HEADLESS_DIR").ok();
+
+        let dir = TempDir::new().unwrap();
+        let home = dir.path();
+        let chats = home.join(".qwen").join("tmp").join("abc123").join("chats");
+        fs::create_dir_all(&chats).unwrap();
+        File::create(chats.join("session-1.json")).unwrap();
+        File::create(chats.join("checkpoint.json")).unwrap();
+        let headless = home.join("headless").join("qwen");
+        fs::create_dir_all(&headless).unwrap();
+        File::create(headless.join("run.json")).unwrap();
+
+        std::env::set_var("TOKSCALE_HEADLESS_DIR", home.join("headless"));
+
+        let result = scan_all_sources(home.to_str().unwrap(), &["qwen".to_string()]);
+        assert_eq!(result.qwen_files.len(), 2);
+        assert!(result.gemini_files.is_empty());
+
+        restore_env("TOKSCALE_HEADLESS_DIR", previous_headless);
+    }
+
+    #[test]
+    fn test_sources_or_default_covers_every_source() {
+        let all = sources_or_default(None);
+        assert_eq!(all.len(), SessionType::ALL.len());
+        assert!(all.iter().any(|s| s == "jules"));
+        assert_eq!(
+            sources_or_default(Some(&["claude".to_string()])),
+            ["claude"]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_list_sources_covers_every_scanned_source() {
+        let dir = TempDir::new().unwrap();
+        let home = dir.path().to_str().unwrap();
+        setup_mock_claude_dir(dir.path());
+
+        let sources = list_sources(home);
+
+        let listed: Vec<SessionType> = sources.iter().map(|s| s.session_type).collect();
+        assert_eq!(listed, SessionType::ALL);
+        for (session_type, _, _) in scan_tasks(home, &[]) {
+            assert!(
+                listed.contains(&session_type),
+                "{} is scanned but not listed",
+                session_type.source()
+            );
+        }
+        assert!(sources.iter().all(|s| s.enabled_by_default));
+
+        let claude = sources
+            .iter()
+            .find(|s| s.session_type == SessionType::Claude)
+            .unwrap();
+        assert_eq!(claude.default_path, format!("{}/.claude/projects", home));
+        assert_eq!(claude.pattern, "*.jsonl");
+        assert!(claude.detected);
+        let droid = sources
+            .iter()
+            .find(|s| s.session_type == SessionType::Droid)
+            .unwrap();
+        assert!(!droid.detected);
+    }
+
+    #[test]
+    #[serial]
+    fn test_describe_scan_flags_missing_directories() {
+        let previous_codex = std::env::var("CODEX_HOME").ok();
+        let previous_headless = std::env::var("TOKSCALE_HEADLESS_DIR").ok();
+
+        let dir = TempDir::new().unwrap();
+        let home = dir.path();
+        setup_mock_claude_dir(home);
+        std::env::set_var("CODEX_HOME", home.join("elsewhere/codex"));
+        std::env::set_var("TOKSCALE_HEADLESS_DIR", home.join("headless"));
+
+        let targets = describe_scan(
+            home.to_str().unwrap(),
+            &[
+                "claude".to_string(),
+                "codex".to_string(),
+                "gemini".to_string(),
+            ],
+            &exclude_dirs_or_default(None),
+        );
+
+        let claude = targets
+            .iter()
+            .find(|t| t.session_type == SessionType::Claude)
+            .unwrap();
+        assert!(claude.exists);
+        assert_eq!(claude.file_count, 1);
+        assert_eq!(claude.pattern, "*.jsonl");
+
+        let gemini = targets
+            .iter()
+            .find(|t| t.session_type == SessionType::Gemini)
+            .unwrap();
+        assert!(!gemini.exists);
+        assert_eq!(gemini.file_count, 0);
+
+        // The env-overridden session dir and the headless root are both listed
+        let codex: Vec<&str> = targets
+            .iter()
+            .filter(|t| t.session_type == SessionType::Codex)
+            .map(|t| t.path.as_str())
+            .collect();
+        let expected_sessions = format!("{}/sessions", home.join("elsewhere/codex").display());
+        let expected_headless = home
+            .join("headless")
+            .join("codex")
+            .to_string_lossy()
+            .to_string();
+        assert_eq!(
+            codex,
+            [expected_sessions.as_str(), expected_headless.as_str()]
+        );
+        assert!(targets
+            .iter()
+            .all(|t| t.session_type == SessionType::Claude || !t.exists));
+
+        restore_env("CODEX_HOME", previous_codex);
+        restore_env("TOKSCALE_HEADLESS_DIR", previous_headless);
+    }
 }
@@ -2,6 +2,8 @@
 //!
 //! Parses JSON files from ~/.local/share/amp/threads/
 
+use super::providers;
+use super::utils::FileBytes;
 use super::UnifiedMessage;
 use crate::TokenBreakdown;
 use serde::Deserialize;
@@ -89,23 +91,26 @@ fn get_provider_from_model(model: &str) -> &'static str {
 
 /// Parse an Amp thread JSON file
 pub fn parse_amp_file(path: &Path) -> Vec<UnifiedMessage> {
-    let content = match std::fs::read(path) {
+    let content = match FileBytes::read(path) {
         Ok(c) => c,
         Err(_) => return Vec::new(),
     };
+    let session_id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+    parse_amp_reader(content.into_buffer(), session_id)
+}
 
-    let mut bytes = content;
-    let thread: AmpThread = match simd_json::from_slice(&mut bytes) {
+/// Parse the contents of an Amp thread file. `session_id` is used when the
+/// thread has no id of its own.
+pub fn parse_amp_reader(mut data: Vec<u8>, session_id: &str) -> Vec<UnifiedMessage> {
+    let thread: AmpThread = match simd_json::from_slice(&mut data) {
         Ok(t) => t,
         Err(_) => return Vec::new(),
     };
 
-    let thread_id = thread.id.clone().unwrap_or_else(|| {
-        path.file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("unknown")
-            .to_string()
-    });
+    let thread_id = thread.id.clone().unwrap_or_else(|| session_id.to_string());
 
     let mut messages = Vec::new();
 
@@ -135,10 +140,10 @@ pub fn parse_amp_file(path: &Path) -> Vec<UnifiedMessage> {
                     cache_creation_input_tokens: Some(0),
                 });
 
-                messages.push(UnifiedMessage::new(
+                let mut message = UnifiedMessage::new(
                     "amp",
                     &model,
-                    get_provider_from_model(&model),
+                    providers::infer_provider(&model, get_provider_from_model),
                     thread_id.clone(),
                     timestamp,
                     TokenBreakdown {
@@ -148,8 +153,10 @@ pub fn parse_amp_file(path: &Path) -> Vec<UnifiedMessage> {
                         cache_write: tokens.cache_creation_input_tokens.unwrap_or(0),
                         reasoning: 0,
                     },
-                    event.credits.unwrap_or(0.0),
-                ));
+                    0.0,
+                );
+                message.credits = event.credits;
+                messages.push(message);
             }
             if !messages.is_empty() {
                 return messages;
@@ -179,10 +186,10 @@ pub fn parse_amp_file(path: &Path) -> Vec<UnifiedMessage> {
             let message_id = msg.message_id.unwrap_or(0);
             let timestamp = created + (message_id * 1000);
 
-            messages.push(UnifiedMessage::new(
+            let mut message = UnifiedMessage::new(
                 "amp",
                 &model,
-                get_provider_from_model(&model),
+                providers::infer_provider(&model, get_provider_from_model),
                 thread_id.clone(),
                 timestamp,
                 TokenBreakdown {
@@ -192,10 +199,55 @@ pub fn parse_amp_file(path: &Path) -> Vec<UnifiedMessage> {
                     cache_write: usage.cache_creation_input_tokens.unwrap_or(0),
                     reasoning: 0,
                 },
-                usage.credits.unwrap_or(0.0),
-            ));
+                0.0,
+            );
+            message.credits = usage.credits;
+            messages.push(message);
         }
     }
 
     messages
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_amp_credits_kept_out_of_cost() {
+        let json = r#"{
+            "id": "T-abc",
+            "usageLedger": {"events": [
+                {"timestamp": "2025-06-01T10:00:00Z", "model": "claude-sonnet-4", "credits": 1.5,
+                 "tokens": {"input": 100, "output": 50, "cacheReadInputTokens": 20}},
+                {"timestamp": "2025-06-01T10:05:00Z", "model": "gpt-5", "tokens": {"input": 10, "output": 5}}
+            ]}
+        }"#;
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+
+        let messages = parse_amp_file(file.path());
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].session_id, "T-abc");
+        assert_eq!(messages[0].provider_id, "anthropic");
+        assert_eq!(messages[0].tokens.cache_read, 20);
+        assert_eq!(messages[0].credits, Some(1.5));
+        assert_eq!(messages[0].cost, 0.0);
+        assert_eq!(messages[1].provider_id, "openai");
+        assert_eq!(messages[1].credits, None);
+    }
+
+    #[test]
+    fn test_parse_amp_reader_in_memory() {
+        let json = r#"{"usageLedger": {"events": [
+            {"timestamp": "2025-06-01T10:00:00Z", "model": "claude-sonnet-4", "tokens": {"input": 100, "output": 50}}
+        ]}}"#;
+
+        let messages = parse_amp_reader(json.as_bytes().to_vec(), "T-fallback");
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "T-fallback");
+        assert_eq!(messages[0].tokens.input, 100);
+    }
+}
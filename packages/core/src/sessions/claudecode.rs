@@ -10,7 +10,7 @@ use crate::TokenBreakdown;
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashSet;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
 
 /// Claude Code entry structure (from JSONL files)
@@ -23,6 +23,9 @@ pub struct ClaudeEntry {
     /// Request ID for deduplication (used with message.id)
     #[serde(rename = "requestId")]
     pub request_id: Option<String>,
+    /// Cost Claude Code recorded for the entry, in USD
+    #[serde(rename = "costUSD")]
+    pub cost_usd: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,6 +34,9 @@ pub struct ClaudeMessage {
     pub usage: Option<ClaudeUsage>,
     /// Message ID for deduplication (used with requestId)
     pub id: Option<String>,
+    /// Per-message cost written by newer Claude Code versions, in USD
+    #[serde(alias = "costUSD")]
+    pub cost_usd: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,36 +45,175 @@ pub struct ClaudeUsage {
     pub output_tokens: Option<i64>,
     pub cache_read_input_tokens: Option<i64>,
     pub cache_creation_input_tokens: Option<i64>,
+    /// Image input tokens reported separately from `input_tokens` (folded into input)
+    pub image_tokens: Option<i64>,
+    /// "standard", "priority" or "batch"
+    pub service_tier: Option<String>,
 }
 
-/// Parse a Claude Code JSONL file
-pub fn parse_claude_file(path: &Path) -> Vec<UnifiedMessage> {
-    let session_id = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+/// Project directory name from a `.../.claude/projects/<project>/...` path
+fn project_from_path(path: &Path) -> Option<String> {
+    let mut components = path.components().map(|c| c.as_os_str());
+    components.find(|c| *c == "projects")?;
+    let project = components.next()?;
+    // The file itself is not a project directory
+    if project == path.file_name()? {
+        return None;
+    }
+    project.to_str().map(|s| s.to_string())
+}
 
-    let fallback_timestamp = file_modified_timestamp_ms(path);
+/// Key shared by a headless message and its interactive counterpart: model,
+/// timestamp to the second, and token counts
+fn exchange_signature(message: &UnifiedMessage) -> String {
+    let tokens = &message.tokens;
+    format!(
+        "{}:{}:{}:{}:{}:{}",
+        message.model_id,
+        message.timestamp.div_euclid(1000),
+        tokens.input,
+        tokens.output,
+        tokens.cache_read,
+        tokens.cache_write
+    )
+}
 
-    if path.extension().and_then(|s| s.to_str()) == Some("json") {
-        let json_messages = parse_claude_headless_json(path, &session_id, fallback_timestamp);
-        if !json_messages.is_empty() {
-            return json_messages;
+/// Headless messages are keyed by their exchange signature
+fn with_signature_key(mut message: UnifiedMessage) -> UnifiedMessage {
+    message.dedup_key = Some(exchange_signature(&message));
+    message
+}
+
+fn restamped(mut message: UnifiedMessage, restamp: Option<i64>) -> UnifiedMessage {
+    if let Some(timestamp) = restamp {
+        message.set_timestamp(timestamp);
+    }
+    message
+}
+
+/// Drop repeats across Claude files, keeping the first seen. Interactive
+/// messages dedup on `messageId:requestId`; headless messages are dropped when
+/// an interactive message (or an earlier headless one) has the same signature.
+pub fn dedup_claude_messages(messages: Vec<UnifiedMessage>) -> Vec<UnifiedMessage> {
+    let mut dedup = ClaudeDedup::default();
+    let mut kept = dedup.add(messages);
+    kept.extend(dedup.finish());
+    kept
+}
+
+/// [`dedup_claude_messages`] over files parsed in batches. Interactive
+/// messages come back from each [`add`](Self::add) and only their keys are
+/// kept; headless messages are held until [`finish`](Self::finish), so the
+/// interactive copy (with project info) wins even when it arrives later.
+#[derive(Default)]
+pub struct ClaudeDedup {
+    seen: HashSet<String>,
+    /// Held-back headless messages with the timestamp each is restamped to
+    headless: Vec<(UnifiedMessage, Option<i64>)>,
+}
+
+impl ClaudeDedup {
+    /// The interactive messages of `messages` not seen before; headless ones
+    /// are held back
+    pub fn add(&mut self, messages: Vec<UnifiedMessage>) -> Vec<UnifiedMessage> {
+        self.add_restamped(messages, None)
+    }
+
+    /// [`add`](Self::add) for messages that take the timestamp `restamp` (see
+    /// [`DateSource::restamp`](super::DateSource::restamp)). Signatures are
+    /// taken from the recorded timestamps, which headless and interactive
+    /// copies share, and kept messages are restamped afterwards.
+    pub fn add_restamped(
+        &mut self,
+        messages: Vec<UnifiedMessage>,
+        restamp: Option<i64>,
+    ) -> Vec<UnifiedMessage> {
+        let (headless, interactive): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .partition(|m| m.dedup_key.as_deref() == Some(exchange_signature(m).as_str()));
+        self.headless
+            .extend(headless.into_iter().map(|m| (m, restamp)));
+        interactive
+            .into_iter()
+            .filter(|m| self.keep(m))
+            .map(|m| restamped(m, restamp))
+            .collect()
+    }
+
+    /// The held-back headless messages without an interactive counterpart
+    pub fn finish(mut self) -> Vec<UnifiedMessage> {
+        let headless = std::mem::take(&mut self.headless);
+        headless
+            .into_iter()
+            .filter(|(m, _)| self.keep(m))
+            .map(|(m, restamp)| restamped(m, restamp))
+            .collect()
+    }
+
+    fn keep(&mut self, message: &UnifiedMessage) -> bool {
+        let signature = exchange_signature(message);
+        match &message.dedup_key {
+            None => {
+                self.seen.insert(signature);
+                true
+            }
+            Some(key) if *key == signature => self.seen.insert(signature),
+            Some(key) => {
+                if !self.seen.insert(key.clone()) {
+                    return false;
+                }
+                self.seen.insert(signature);
+                true
+            }
         }
     }
+}
 
+/// Parse a Claude Code JSONL file
+pub fn parse_claude_file(path: &Path) -> Vec<UnifiedMessage> {
     let file = match std::fs::File::open(path) {
         Ok(f) => f,
         Err(_) => return Vec::new(),
     };
+    parse_claude_reader(BufReader::new(file), path, file_modified_timestamp_ms(path))
+}
+
+/// Parse a Claude Code session transcript, headless `stream-json` output or
+/// headless `--output-format json` result from `reader`. `path` is where the
+/// data lives (on disk or inside an archive) and supplies the session id,
+/// format and project; `fallback_timestamp` stands in for entries without one.
+pub fn parse_claude_reader(
+    mut reader: impl BufRead,
+    path: &Path,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    let session_id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+    let project = project_from_path(path);
+
+    // A `.json` result is one document, so it is read whole; if it turns out
+    // to be JSONL after all, the lines are parsed from that buffer
+    let mut document = Vec::new();
+    if path.extension().and_then(|s| s.to_str()) == Some("json") {
+        if reader.read_to_end(&mut document).is_err() {
+            return Vec::new();
+        }
+        let mut messages = parse_claude_headless_json(&document, session_id, fallback_timestamp);
+        if !messages.is_empty() {
+            for message in &mut messages {
+                message.project = project.clone();
+            }
+            return messages;
+        }
+    }
 
-    let reader = BufReader::new(file);
     let mut messages = Vec::new();
     let mut processed_hashes: HashSet<String> = HashSet::new();
     let mut headless_state = ClaudeHeadlessState::default();
 
-    for line in reader.lines() {
+    for line in document.as_slice().chain(reader).lines() {
         let line = match line {
             Ok(l) => l,
             Err(_) => continue,
@@ -111,28 +256,38 @@ pub fn parse_claude_file(path: &Path) -> Vec<UnifiedMessage> {
                     None => continue,
                 };
 
+                // Kept as the recorded cost; pricing recalculates it unless
+                // the cost source says otherwise
+                let recorded_cost = entry
+                    .cost_usd
+                    .or(message.cost_usd)
+                    .filter(|c| c.is_finite() && *c >= 0.0)
+                    .unwrap_or(0.0);
+
                 let timestamp = entry
                     .timestamp
                     .and_then(|ts| chrono::DateTime::parse_from_rfc3339(&ts).ok())
                     .map(|dt| dt.timestamp_millis())
                     .unwrap_or(fallback_timestamp);
 
-                messages.push(UnifiedMessage::new_with_dedup(
+                let mut unified = UnifiedMessage::new_with_dedup(
                     "claude",
                     model,
                     "anthropic",
-                    session_id.clone(),
+                    session_id.to_string(),
                     timestamp,
                     TokenBreakdown {
-                        input: usage.input_tokens.unwrap_or(0),
+                        input: usage.input_tokens.unwrap_or(0) + usage.image_tokens.unwrap_or(0),
                         output: usage.output_tokens.unwrap_or(0),
                         cache_read: usage.cache_read_input_tokens.unwrap_or(0),
                         cache_write: usage.cache_creation_input_tokens.unwrap_or(0),
                         reasoning: 0,
                     },
-                    0.0,
+                    recorded_cost,
                     dedup_key,
-                ));
+                );
+                unified.batch = usage.service_tier.as_deref() == Some("batch");
+                messages.push(unified);
                 handled = true;
             }
         }
@@ -143,7 +298,7 @@ pub fn parse_claude_file(path: &Path) -> Vec<UnifiedMessage> {
 
         if let Some(message) = process_claude_headless_line(
             trimmed,
-            &session_id,
+            session_id,
             &mut headless_state,
             fallback_timestamp,
         ) {
@@ -152,11 +307,14 @@ pub fn parse_claude_file(path: &Path) -> Vec<UnifiedMessage> {
     }
 
     if let Some(message) =
-        finalize_headless_state(&mut headless_state, &session_id, fallback_timestamp)
+        finalize_headless_state(&mut headless_state, session_id, fallback_timestamp)
     {
         messages.push(message);
     }
 
+    for message in &mut messages {
+        message.project = project.clone();
+    }
     messages
 }
 
@@ -171,27 +329,20 @@ struct ClaudeHeadlessState {
 }
 
 fn parse_claude_headless_json(
-    path: &Path,
+    data: &[u8],
     session_id: &str,
     fallback_timestamp: i64,
 ) -> Vec<UnifiedMessage> {
-    let data = match std::fs::read(path) {
-        Ok(d) => d,
-        Err(_) => return Vec::new(),
-    };
-
-    let mut bytes = data;
-    let value: Value = match simd_json::from_slice(&mut bytes) {
+    // Parsed in place: simd-json would need its own mutable copy, and `data`
+    // is kept for the JSONL fallback
+    let value: Value = match serde_json::from_slice(data) {
         Ok(v) => v,
         Err(_) => return Vec::new(),
     };
 
-    let mut messages = Vec::new();
-    if let Some(message) = extract_claude_headless_message(&value, session_id, fallback_timestamp) {
-        messages.push(message);
-    }
-
-    messages
+    extract_claude_headless_message(&value, session_id, fallback_timestamp)
+        .into_iter()
+        .collect()
 }
 
 fn process_claude_headless_line(
@@ -254,21 +405,21 @@ fn extract_claude_headless_message(
     let model = extract_claude_model(value)?;
     let timestamp = extract_claude_timestamp(value).unwrap_or(fallback_timestamp);
 
-    Some(UnifiedMessage::new(
+    Some(with_signature_key(UnifiedMessage::new(
         "claude",
         model,
         "anthropic",
         session_id.to_string(),
         timestamp,
         TokenBreakdown {
-            input: extract_i64(usage.get("input_tokens")).unwrap_or(0),
+            input: extract_claude_input(usage).unwrap_or(0),
             output: extract_i64(usage.get("output_tokens")).unwrap_or(0),
             cache_read: extract_i64(usage.get("cache_read_input_tokens")).unwrap_or(0),
             cache_write: extract_i64(usage.get("cache_creation_input_tokens")).unwrap_or(0),
             reasoning: 0,
         },
         0.0,
-    ))
+    )))
 }
 
 fn extract_claude_model(value: &Value) -> Option<String> {
@@ -287,8 +438,18 @@ fn extract_claude_timestamp(value: &Value) -> Option<i64> {
         .and_then(parse_timestamp_value)
 }
 
+/// Input tokens plus any separately reported image tokens
+fn extract_claude_input(usage: &Value) -> Option<i64> {
+    let input = extract_i64(usage.get("input_tokens"));
+    let image = extract_i64(usage.get("image_tokens"));
+    match (input, image) {
+        (None, None) => None,
+        (input, image) => Some(input.unwrap_or(0) + image.unwrap_or(0)),
+    }
+}
+
 fn update_claude_usage(state: &mut ClaudeHeadlessState, usage: &Value) {
-    if let Some(input) = extract_i64(usage.get("input_tokens")) {
+    if let Some(input) = extract_claude_input(usage) {
         state.input = state.input.max(input);
     }
     if let Some(output) = extract_i64(usage.get("output_tokens")) {
@@ -314,7 +475,7 @@ fn finalize_headless_state(
         return None;
     }
 
-    let message = UnifiedMessage::new(
+    let message = with_signature_key(UnifiedMessage::new(
         "claude",
         model,
         "anthropic",
@@ -328,7 +489,7 @@ fn finalize_headless_state(
             reasoning: 0,
         },
         0.0,
-    );
+    ));
 
     *state = ClaudeHeadlessState::default();
     Some(message)
@@ -365,6 +526,63 @@ mod tests {
         assert_eq!(messages[1].tokens.input, 200);
     }
 
+    #[test]
+    fn test_cost_usd_captured_as_recorded_cost() {
+        let content = r#"{"type":"assistant","timestamp":"2024-12-01T10:00:00.000Z","requestId":"req_001","costUSD":0.0123,"message":{"id":"msg_001","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50}}}
+{"type":"assistant","timestamp":"2024-12-01T10:00:01.000Z","requestId":"req_002","message":{"id":"msg_002","model":"claude-3-5-sonnet","cost_usd":0.004,"usage":{"input_tokens":100,"output_tokens":50}}}
+{"type":"assistant","timestamp":"2024-12-01T10:00:02.000Z","requestId":"req_003","message":{"id":"msg_003","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50}}}"#;
+
+        let file = create_test_file(content);
+        let messages = parse_claude_file(file.path());
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].cost, 0.0123);
+        assert_eq!(messages[1].cost, 0.004);
+        assert_eq!(messages[2].cost, 0.0);
+
+        // The default still recalculates; the source mode keeps what was recorded
+        use crate::pricing::{CostSource, PricingService};
+        let service =
+            std::sync::Arc::new(PricingService::new(Default::default(), Default::default()));
+        let mut recalculated = messages[0].clone();
+        service.apply(&mut recalculated);
+        assert_eq!(recalculated.cost, 0.0);
+        let mut recorded = messages[0].clone();
+        PricingService::with_cost_source(service, CostSource::Source).apply(&mut recorded);
+        assert_eq!(recorded.cost, 0.0123);
+    }
+
+    #[test]
+    fn test_batch_service_tier_priced_at_half() {
+        let content = r#"{"type":"assistant","timestamp":"2024-12-01T10:00:00.000Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-3-5-sonnet","usage":{"input_tokens":1000000,"output_tokens":100000,"service_tier":"standard"}}}
+{"type":"assistant","timestamp":"2024-12-01T10:00:01.000Z","requestId":"req_002","message":{"id":"msg_002","model":"claude-3-5-sonnet","usage":{"input_tokens":1000000,"output_tokens":100000,"service_tier":"batch"}}}"#;
+
+        let file = create_test_file(content);
+        let mut messages = parse_claude_file(file.path());
+        assert_eq!(messages.len(), 2);
+        assert!(!messages[0].batch);
+        assert!(messages[1].batch);
+
+        use crate::pricing::{ModelPricing, PricingService};
+        let rates = ModelPricing {
+            input_cost_per_token: Some(0.000003),
+            output_cost_per_token: Some(0.000015),
+            ..Default::default()
+        };
+        let service = PricingService::new(
+            [("claude-3-5-sonnet".to_string(), rates)].into(),
+            Default::default(),
+        );
+        for message in &mut messages {
+            service.apply(message);
+        }
+
+        // $3 input + $1.50 output at standard rates
+        assert!((messages[0].cost - 4.5).abs() < 1e-9);
+        assert!((messages[1].cost - 2.25).abs() < 1e-9);
+        assert!((messages[1].cost_breakdown.total() - messages[1].cost).abs() < 1e-12);
+    }
+
     #[test]
     fn test_deduplication_allows_same_message_different_request() {
         let content = r#"{"type":"assistant","timestamp":"2024-12-01T10:00:00.000Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50}}}
@@ -452,4 +670,112 @@ mod tests {
         assert_eq!(messages[0].tokens.cache_read, 20);
         assert_eq!(messages[0].tokens.cache_write, 5);
     }
+
+    #[test]
+    fn test_image_tokens_folded_into_input() {
+        let content = r#"{"type":"assistant","timestamp":"2024-12-01T10:00:00.000Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-3-5-sonnet","usage":{"input_tokens":1000,"image_tokens":1600,"output_tokens":500}}}"#;
+
+        let file = create_test_file(content);
+        let messages = parse_claude_file(file.path());
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].tokens.input, 2600);
+        assert_eq!(messages[0].tokens.output, 500);
+    }
+
+    #[test]
+    fn test_headless_image_tokens_folded_into_input() {
+        let content = r#"{"type":"message","message":{"model":"claude-3-5-sonnet","usage":{"input_tokens":120,"image_tokens":80,"output_tokens":60}}}"#;
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), content).unwrap();
+
+        let messages = parse_claude_file(file.path());
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].tokens.input, 200);
+    }
+
+    #[test]
+    fn test_project_from_path() {
+        let path = Path::new("/home/me/.claude/projects/-home-me-app/abc.jsonl");
+        assert_eq!(project_from_path(path).as_deref(), Some("-home-me-app"));
+
+        let nested = Path::new("/home/me/.claude/projects/-home-me-app/abc/subagents/x.jsonl");
+        assert_eq!(project_from_path(nested).as_deref(), Some("-home-me-app"));
+
+        assert_eq!(
+            project_from_path(Path::new("/tmp/headless/claude/abc.jsonl")),
+            None
+        );
+        assert_eq!(
+            project_from_path(Path::new("/home/me/.claude/projects/abc.jsonl")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_headless_copy_of_interactive_exchange_counted_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let interactive = dir.path().join(".claude/projects/-home-me-app/abc.jsonl");
+        std::fs::create_dir_all(interactive.parent().unwrap()).unwrap();
+        std::fs::write(
+            &interactive,
+            r#"{"type":"assistant","timestamp":"2024-12-01T10:00:00.412Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50,"cache_read_input_tokens":400}}}
+{"type":"assistant","timestamp":"2024-12-01T10:05:00.000Z","requestId":"req_002","message":{"id":"msg_002","model":"claude-3-5-sonnet","usage":{"input_tokens":200,"output_tokens":80}}}"#,
+        )
+        .unwrap();
+        let headless = dir.path().join("headless/claude/abc.json");
+        std::fs::create_dir_all(headless.parent().unwrap()).unwrap();
+        std::fs::write(
+            &headless,
+            r#"{"type":"result","timestamp":"2024-12-01T10:00:00Z","model":"claude-3-5-sonnet","usage":{"input_tokens":100,"output_tokens":50,"cache_read_input_tokens":400}}"#,
+        )
+        .unwrap();
+
+        for files in [[&headless, &interactive], [&interactive, &headless]] {
+            let merged = files
+                .iter()
+                .flat_map(|path| parse_claude_file(path))
+                .collect();
+            let messages = dedup_claude_messages(merged);
+
+            assert_eq!(messages.len(), 2);
+            let first = messages.iter().find(|m| m.tokens.input == 100).unwrap();
+            assert_eq!(first.project.as_deref(), Some("-home-me-app"));
+            assert_eq!(first.dedup_key.as_deref(), Some("msg_001:req_001"));
+
+            // One file per batch keeps the same messages
+            let mut dedup = ClaudeDedup::default();
+            let mut batched: Vec<_> = files
+                .iter()
+                .flat_map(|path| dedup.add(parse_claude_file(path)))
+                .collect();
+            batched.extend(dedup.finish());
+            assert_eq!(batched.len(), 2);
+            assert!(batched
+                .iter()
+                .all(|m| m.project.as_deref() == Some("-home-me-app")));
+        }
+
+        // The same headless file seen twice collapses too
+        let doubled = [parse_claude_file(&headless), parse_claude_file(&headless)].concat();
+        assert_eq!(dedup_claude_messages(doubled).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_claude_reader_in_memory() {
+        let content = r#"{"type":"assistant","timestamp":"2025-06-01T10:00:00Z","requestId":"req_1","message":{"id":"msg_1","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}
+{"type":"assistant","requestId":"req_2","message":{"id":"msg_2","model":"claude-sonnet-4","usage":{"input_tokens":10,"output_tokens":5}}}"#;
+
+        let messages = parse_claude_reader(
+            content.as_bytes(),
+            Path::new("session-1.jsonl"),
+            1748772000000,
+        );
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(|m| m.session_id == "session-1"));
+        assert_eq!(messages[0].date, "2025-06-01");
+        assert_eq!(messages[1].timestamp, 1748772000000);
+    }
 }
@@ -4,7 +4,8 @@
 //! Note: This parser has stateful logic to track model and delta calculations.
 
 use super::utils::{
-    extract_i64, extract_string, file_modified_timestamp_ms, parse_timestamp_value,
+    encode_project_path, extract_i64, extract_string, file_modified_timestamp_ms,
+    parse_timestamp_value,
 };
 use super::UnifiedMessage;
 use crate::TokenBreakdown;
@@ -30,6 +31,8 @@ pub struct CodexPayload {
     pub model_name: Option<String>,
     pub info: Option<CodexInfo>,
     pub source: Option<String>,
+    /// Working directory recorded in `session_meta`
+    pub cwd: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -54,27 +57,57 @@ pub fn parse_codex_file(path: &Path) -> Vec<UnifiedMessage> {
         Ok(f) => f,
         Err(_) => return Vec::new(),
     };
+    parse_codex_reader(BufReader::new(file), path, file_modified_timestamp_ms(path))
+}
 
-    let session_id = path
+/// Parse Codex JSONL (session rollouts or headless `exec --json` output)
+/// from `reader`. `path` is where the data lives (on disk or inside an
+/// archive) and supplies the session id and whether the data is
+/// zstd-compressed; `fallback_timestamp` stands in for entries without one.
+pub fn parse_codex_reader(
+    reader: impl BufRead,
+    path: &Path,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    let is_zstd = path.extension().and_then(|s| s.to_str()) == Some("zst");
+
+    // Rotated sessions are `<id>.jsonl.zst`; keep the id the plaintext file had
+    let stem_path = if is_zstd {
+        path.with_extension("")
+    } else {
+        path.to_path_buf()
+    };
+    let session_id = stem_path
         .file_stem()
         .and_then(|s| s.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+        .unwrap_or("unknown");
 
-    let fallback_timestamp = file_modified_timestamp_ms(path);
+    let reader: Box<dyn BufRead + '_> = if is_zstd {
+        match zstd::Decoder::with_buffer(reader) {
+            Ok(decoder) => Box::new(BufReader::new(decoder)),
+            Err(_) => return Vec::new(),
+        }
+    } else {
+        Box::new(reader)
+    };
 
-    let reader = BufReader::new(file);
     let mut messages = Vec::new();
 
     // Stateful tracking
     let mut current_model: Option<String> = None;
-    let mut previous_totals: Option<(i64, i64, i64)> = None; // (input, output, cached)
+    // (input, output, cached); starts at zero so a first event carrying only
+    // `total_token_usage` counts as a delta from nothing
+    let mut previous_totals: (i64, i64, i64) = (0, 0, 0);
     let mut session_is_headless = false;
+    let mut project: Option<String> = None;
 
     for line in reader.lines() {
         let line = match line {
             Ok(l) => l,
-            Err(_) => continue,
+            // Skip undecodable lines; any other error (e.g. a corrupt zstd frame)
+            // repeats on every read, so stop there
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => continue,
+            Err(_) => break,
         };
 
         let trimmed = line.trim();
@@ -87,9 +120,12 @@ pub fn parse_codex_file(path: &Path) -> Vec<UnifiedMessage> {
         if let Ok(entry) = simd_json::from_slice::<CodexEntry>(&mut bytes) {
             if let Some(payload) = entry.payload {
                 // Check session_meta for headless exec sessions
+                if entry.entry_type == "session_meta" && payload.source.as_deref() == Some("exec") {
+                    session_is_headless = true;
+                }
                 if entry.entry_type == "session_meta" {
-                    if payload.source.as_deref() == Some("exec") {
-                        session_is_headless = true;
+                    if let Some(cwd) = &payload.cwd {
+                        project = encode_project_path(cwd);
                     }
                 }
                 // Extract model from turn_context
@@ -135,9 +171,8 @@ pub fn parse_codex_file(path: &Path) -> Vec<UnifiedMessage> {
                             last.output_tokens.unwrap_or(0),
                             cached,
                         )
-                    } else if let (Some(total), Some(prev)) =
-                        (&info.total_token_usage, &previous_totals)
-                    {
+                    } else if let Some(total) = &info.total_token_usage {
+                        let prev = previous_totals;
                         let curr_input = total.input_tokens.unwrap_or(0);
                         let curr_output = total.output_tokens.unwrap_or(0);
                         let curr_cached = total
@@ -158,14 +193,14 @@ pub fn parse_codex_file(path: &Path) -> Vec<UnifiedMessage> {
 
                     // Update previous totals
                     if let Some(total) = &info.total_token_usage {
-                        previous_totals = Some((
+                        previous_totals = (
                             total.input_tokens.unwrap_or(0),
                             total.output_tokens.unwrap_or(0),
                             total
                                 .cached_input_tokens
                                 .or(total.cache_read_input_tokens)
                                 .unwrap_or(0),
-                        ));
+                        );
                     }
 
                     // Skip empty deltas
@@ -189,7 +224,7 @@ pub fn parse_codex_file(path: &Path) -> Vec<UnifiedMessage> {
                         "codex",
                         model,
                         "openai",
-                        session_id.clone(),
+                        session_id.to_string(),
                         timestamp,
                         TokenBreakdown {
                             input,
@@ -216,7 +251,7 @@ pub fn parse_codex_file(path: &Path) -> Vec<UnifiedMessage> {
         }
 
         if let Some(msg) =
-            parse_codex_headless_line(trimmed, &session_id, &mut current_model, fallback_timestamp)
+            parse_codex_headless_line(trimmed, session_id, &mut current_model, fallback_timestamp)
         {
             let mut msg = msg;
             if session_is_headless && msg.agent.is_none() {
@@ -226,6 +261,9 @@ pub fn parse_codex_file(path: &Path) -> Vec<UnifiedMessage> {
         }
     }
 
+    for message in &mut messages {
+        message.project = project.clone();
+    }
     messages
 }
 
@@ -403,4 +441,90 @@ mod tests {
         assert_eq!(messages.len(), 1);
         assert_eq!(messages[0].agent.as_deref(), Some("headless"));
     }
+
+    #[test]
+    fn test_session_meta_cwd_sets_project() {
+        let line1 = r#"{"timestamp":"2026-01-01T00:00:00Z","type":"session_meta","payload":{"cwd":"/Users/me/code/app"}}"#;
+        let line2 = r#"{"timestamp":"2026-01-01T00:00:01Z","type":"event_msg","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":10,"output_tokens":3}}}}"#;
+        let file = create_test_file(&format!("{}\n{}", line1, line2));
+
+        let messages = parse_codex_file(file.path());
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].project.as_deref(), Some("-Users-me-code-app"));
+    }
+
+    #[test]
+    fn test_zstd_session_matches_plaintext() {
+        let content = [
+            r#"{"timestamp":"2026-01-01T00:00:00Z","type":"session_meta","payload":{"cwd":"/Users/me/code/app"}}"#,
+            r#"{"timestamp":"2026-01-01T00:00:01Z","type":"turn_context","payload":{"model":"gpt-5"}}"#,
+            r#"{"timestamp":"2026-01-01T00:00:02Z","type":"event_msg","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":100,"cached_input_tokens":20,"output_tokens":10},"total_token_usage":{"input_tokens":100,"cached_input_tokens":20,"output_tokens":10}}}}"#,
+            r#"{"timestamp":"2026-01-01T00:00:03Z","type":"event_msg","payload":{"type":"token_count","info":{"total_token_usage":{"input_tokens":250,"cached_input_tokens":50,"output_tokens":30}}}}"#,
+        ]
+        .join("\n");
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let plain_path = dir.path().join("rollout-abc.jsonl");
+        let zstd_path = dir.path().join("rollout-abc.jsonl.zst");
+        std::fs::write(&plain_path, &content).unwrap();
+        std::fs::write(&zstd_path, zstd::encode_all(content.as_bytes(), 3).unwrap()).unwrap();
+
+        let plain = parse_codex_file(&plain_path);
+        let compressed = parse_codex_file(&zstd_path);
+
+        assert_eq!(plain.len(), 2);
+        assert_eq!(plain[1].tokens.input, 120);
+        assert_eq!(plain[1].tokens.cache_read, 30);
+        assert_eq!(compressed[0].session_id, "rollout-abc");
+        assert_eq!(format!("{:?}", compressed), format!("{:?}", plain));
+    }
+
+    #[test]
+    fn test_first_total_only_event_is_counted() {
+        let content = [
+            r#"{"timestamp":"2026-01-01T00:00:01Z","type":"turn_context","payload":{"model":"gpt-5"}}"#,
+            r#"{"timestamp":"2026-01-01T00:00:02Z","type":"event_msg","payload":{"type":"token_count","info":{"total_token_usage":{"input_tokens":100,"cached_input_tokens":20,"output_tokens":10}}}}"#,
+            r#"{"timestamp":"2026-01-01T00:00:03Z","type":"event_msg","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":50,"output_tokens":5},"total_token_usage":{"input_tokens":150,"cached_input_tokens":20,"output_tokens":15}}}}"#,
+        ]
+        .join("\n");
+
+        let messages = parse_codex_reader(content.as_bytes(), Path::new("rollout-first.jsonl"), 0);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].tokens.input, 80);
+        assert_eq!(messages[0].tokens.cache_read, 20);
+        assert_eq!(messages[0].tokens.output, 10);
+        // The second event's own `last_token_usage` wins over the totals delta
+        assert_eq!(messages[1].tokens.input, 50);
+        assert_eq!(messages[1].tokens.output, 5);
+    }
+
+    #[test]
+    fn test_corrupt_zstd_session_is_empty() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("rollout-bad.jsonl.zst");
+        std::fs::write(&path, b"not zstd").unwrap();
+
+        assert!(parse_codex_file(&path).is_empty());
+    }
+
+    #[test]
+    fn test_parse_codex_reader_in_memory() {
+        let content = r#"{"type":"turn_context","payload":{"model":"gpt-5"}}
+{"type":"event_msg","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":200,"cached_input_tokens":50,"output_tokens":20}}}}"#;
+
+        let messages = parse_codex_reader(
+            content.as_bytes(),
+            Path::new("rollout-1.jsonl"),
+            1748772000000,
+        );
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "rollout-1");
+        assert_eq!(messages[0].model_id, "gpt-5");
+        assert_eq!(messages[0].timestamp, 1748772000000);
+        assert_eq!(messages[0].tokens.input, 150);
+        assert_eq!(messages[0].tokens.cache_read, 50);
+    }
 }
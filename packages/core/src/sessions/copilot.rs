@@ -0,0 +1,193 @@
+//! GitHub Copilot Chat (VS Code) session parser
+//!
+//! Parses the chat session files Copilot Chat keeps in VS Code's workspace
+//! storage, `<workspace>/chatSessions/<session_id>.json`, one usage record per
+//! request.
+
+use super::cursor::infer_provider;
+use super::providers;
+use super::utils::{file_modified_timestamp_ms, parse_timestamp_value, FileBytes};
+use super::UnifiedMessage;
+use crate::TokenBreakdown;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+/// Copilot chat session file structure
+#[derive(Debug, Deserialize)]
+pub struct CopilotSession {
+    #[serde(rename = "sessionId")]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub requests: Vec<CopilotRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CopilotRequest {
+    /// Model the request was routed to, e.g. `copilot/claude-sonnet-4`
+    #[serde(rename = "modelId")]
+    pub model_id: Option<String>,
+    /// RFC 3339 string or Unix time (seconds or milliseconds)
+    pub timestamp: Option<Value>,
+    pub usage: Option<CopilotUsage>,
+    pub result: Option<CopilotResult>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CopilotResult {
+    pub usage: Option<CopilotUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CopilotUsage {
+    #[serde(default, alias = "promptTokens")]
+    pub prompt_tokens: i64,
+    #[serde(default, alias = "completionTokens", alias = "outputTokens")]
+    pub completion_tokens: i64,
+    #[serde(default, alias = "cachedTokens")]
+    pub cached_tokens: i64,
+    /// Premium requests the request was billed as (model multiplier applied)
+    #[serde(default, alias = "premiumRequests")]
+    pub premium_requests: f64,
+}
+
+/// Parse a Copilot Chat session file
+pub fn parse_copilot_file(path: &Path) -> Vec<UnifiedMessage> {
+    let data = match FileBytes::read(path) {
+        Ok(d) => d,
+        Err(_) => return Vec::new(),
+    };
+    let session_id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+    parse_copilot_reader(
+        data.into_buffer(),
+        session_id,
+        file_modified_timestamp_ms(path),
+    )
+}
+
+/// Parse the contents of a Copilot chat session file. `session_id` is used when the file does not
+/// name its own; `fallback_timestamp` stands in for entries without one.
+pub fn parse_copilot_reader(
+    mut data: Vec<u8>,
+    session_id: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    let session: CopilotSession = match simd_json::from_slice(&mut data) {
+        Ok(s) => s,
+        Err(_) => return Vec::new(),
+    };
+
+    let session_id = session.session_id.unwrap_or_else(|| session_id.to_string());
+
+    session
+        .requests
+        .into_iter()
+        .filter_map(|request| {
+            let usage = request.usage.or(request.result.and_then(|r| r.usage))?;
+            if usage.prompt_tokens == 0 && usage.completion_tokens == 0 {
+                return None;
+            }
+
+            let model = request
+                .model_id
+                .map(|m| m.strip_prefix("copilot/").map(str::to_string).unwrap_or(m))
+                .filter(|m| !m.is_empty())?;
+            // Copilot proxies several providers; infer_provider's "cursor"
+            // fallback means nothing here
+            let provider = providers::infer_provider(&model, |model| match infer_provider(model) {
+                "cursor" => "github",
+                provider => provider,
+            });
+            let timestamp = request
+                .timestamp
+                .as_ref()
+                .and_then(parse_timestamp_value)
+                .unwrap_or(fallback_timestamp);
+
+            // Cached tokens are reported as part of the prompt
+            let cache_read = usage.cached_tokens.min(usage.prompt_tokens);
+            let mut message = UnifiedMessage::new(
+                "copilot",
+                model,
+                provider,
+                session_id.clone(),
+                timestamp,
+                TokenBreakdown {
+                    input: usage.prompt_tokens - cache_read,
+                    output: usage.completion_tokens,
+                    cache_read,
+                    cache_write: 0,
+                    reasoning: 0,
+                },
+                0.0,
+            );
+            message.premium_requests = usage.premium_requests;
+            Some(message)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_copilot_session() {
+        let json = r#"{
+            "version": 3,
+            "sessionId": "chat-1",
+            "requests": [
+                {"requestId": "r1", "timestamp": 1748772000000, "modelId": "copilot/claude-sonnet-4",
+                 "result": {"usage": {"promptTokens": 5000, "completionTokens": 400, "cachedTokens": 3000, "premiumRequests": 1}}},
+                {"requestId": "r2", "timestamp": "2025-06-01T10:05:00Z", "modelId": "gpt-4.1",
+                 "usage": {"prompt_tokens": 900, "completion_tokens": 120}},
+                {"requestId": "r3", "timestamp": 1748772600000, "modelId": "copilot/gpt-4o"},
+                {"requestId": "r4", "timestamp": 1748772700000, "modelId": "copilot/grok-code-fast",
+                 "result": {"usage": {"promptTokens": 100, "completionTokens": 20, "premiumRequests": 0.25}}}
+            ]
+        }"#;
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+
+        let messages = parse_copilot_file(file.path());
+
+        assert_eq!(messages.len(), 3);
+        assert!(messages
+            .iter()
+            .all(|m| m.source == "copilot" && m.session_id == "chat-1"));
+
+        assert_eq!(messages[0].model_id, "claude-sonnet-4");
+        assert_eq!(messages[0].provider_id, "anthropic");
+        assert_eq!(messages[0].timestamp, 1748772000000);
+        assert_eq!(messages[0].tokens.input, 2000);
+        assert_eq!(messages[0].tokens.cache_read, 3000);
+        assert_eq!(messages[0].tokens.output, 400);
+        assert_eq!(messages[0].premium_requests, 1.0);
+
+        assert_eq!(messages[1].model_id, "gpt-4.1");
+        assert_eq!(messages[1].provider_id, "openai");
+        assert_eq!(messages[1].timestamp, 1748772300000);
+        assert_eq!(messages[1].premium_requests, 0.0);
+
+        assert_eq!(messages[2].provider_id, "github");
+        assert_eq!(messages[2].premium_requests, 0.25);
+    }
+
+    #[test]
+    fn test_parse_copilot_reader_in_memory() {
+        let json = r#"{"requests": [
+            {"modelId": "gpt-4.1", "usage": {"prompt_tokens": 900, "completion_tokens": 120}}
+        ]}"#;
+
+        let messages =
+            parse_copilot_reader(json.as_bytes().to_vec(), "chat-fallback", 1748772000000);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "chat-fallback");
+        assert_eq!(messages[0].timestamp, 1748772000000);
+        assert_eq!(messages[0].tokens.output, 120);
+    }
+}
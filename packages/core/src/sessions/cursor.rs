@@ -7,8 +7,12 @@
 //! CSV Format (actual from API):
 //! Date,Kind,Model,Max Mode,Input (w/ Cache Write),Input (w/o Cache Write),Cache Read,Output Tokens,Total Tokens,Cost
 
+use super::providers;
 use super::UnifiedMessage;
+use crate::scanner;
 use crate::TokenBreakdown;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::path::Path;
 
 fn account_id_from_cursor_cache_path(path: &Path) -> String {
@@ -21,6 +25,11 @@ fn account_id_from_cursor_cache_path(path: &Path) -> String {
         return "active".to_string();
     }
 
+    // The scanner's rule: backups and numeric segments don't name an account
+    if !scanner::is_cursor_usage_file(file_name) {
+        return "unknown".to_string();
+    }
+
     if let Some(stem) = file_name
         .strip_prefix("usage.")
         .and_then(|s| s.strip_suffix(".csv"))
@@ -36,9 +45,6 @@ fn account_id_from_cursor_cache_path(path: &Path) -> String {
                 }
             })
             .collect::<String>();
-        if cleaned.is_empty() {
-            return "unknown".to_string();
-        }
         return cleaned;
     }
 
@@ -46,7 +52,7 @@ fn account_id_from_cursor_cache_path(path: &Path) -> String {
 }
 
 /// Provider inference from model name
-fn infer_provider(model: &str) -> &'static str {
+pub(crate) fn infer_provider(model: &str) -> &'static str {
     let lower = model.to_lowercase();
 
     if lower.contains("claude")
@@ -88,13 +94,23 @@ fn parse_cost(cost_str: &str) -> f64 {
 /// - New: Date,Kind,Model,Max Mode,Input (w/ Cache Write),Input (w/o Cache Write),Cache Read,Output Tokens,Total Tokens,Cost
 /// - Old: Date,Model,Input (w/ Cache Write),Input (w/o Cache Write),Cache Read,Output Tokens,Total Tokens,Cost,Cost to you
 pub fn parse_cursor_file(path: &Path) -> Vec<UnifiedMessage> {
-    let content = match std::fs::read_to_string(path) {
+    let content = match std::fs::read(path) {
+        Ok(c) => c,
+        Err(_) => return vec![],
+    };
+    parse_cursor_reader(&content, &account_id_from_cursor_cache_path(path))
+}
+
+/// Parse a Cursor usage CSV export belonging to `account_id`
+pub fn parse_cursor_reader(data: &[u8], account_id: &str) -> Vec<UnifiedMessage> {
+    let content = match std::str::from_utf8(data) {
         Ok(c) => c,
         Err(_) => return vec![],
     };
 
     let mut messages = Vec::new();
-    let mut lines = content.lines();
+    // Some exports are BOM-prefixed, which would hide the first header name
+    let mut lines = content.strip_prefix('\u{feff}').unwrap_or(content).lines();
 
     // Parse header line to determine column indices
     let header = match lines.next() {
@@ -108,8 +124,8 @@ pub fn parse_cursor_file(path: &Path) -> Vec<UnifiedMessage> {
     }
 
     // Detect format by checking for "Kind" column
-    let header_fields: Vec<&str> = parse_csv_line(header);
-    let has_kind_column = header_fields.iter().any(|f| f.trim() == "Kind");
+    let header_fields = parse_csv_line(header);
+    let has_kind_column = header_fields.iter().any(|f| f == "Kind");
 
     // Column indices based on format
     let (
@@ -127,15 +143,13 @@ pub fn parse_cursor_file(path: &Path) -> Vec<UnifiedMessage> {
         (1, 2, 3, 4, 5, 7)
     };
 
-    let account_id = account_id_from_cursor_cache_path(path);
-
     for line in lines {
         if line.trim().is_empty() {
             continue;
         }
 
         // Parse CSV line (simple parsing, handles quoted fields)
-        let fields: Vec<&str> = parse_csv_line(line);
+        let fields = parse_csv_line(line);
 
         // Need at least enough columns for the format
         let min_fields = cost_idx + 1;
@@ -143,30 +157,13 @@ pub fn parse_cursor_file(path: &Path) -> Vec<UnifiedMessage> {
             continue;
         }
 
-        let date_str = fields[0].trim().trim_matches('"');
-        let model = fields[model_idx].trim().trim_matches('"');
-        let input_with_cache_write: i64 = fields[input_cache_write_idx]
-            .trim()
-            .trim_matches('"')
-            .parse()
-            .unwrap_or(0);
-        let input_without_cache_write: i64 = fields[input_no_cache_idx]
-            .trim()
-            .trim_matches('"')
-            .parse()
-            .unwrap_or(0);
-        let cache_read: i64 = fields[cache_read_idx]
-            .trim()
-            .trim_matches('"')
-            .parse()
-            .unwrap_or(0);
-        let output_tokens: i64 = fields[output_idx]
-            .trim()
-            .trim_matches('"')
-            .parse()
-            .unwrap_or(0);
-        let cost_str = fields[cost_idx].trim().trim_matches('"');
-        let cost = parse_cost(cost_str);
+        let date_str = fields[0].as_ref();
+        let model = fields[model_idx].as_ref();
+        let input_with_cache_write: i64 = fields[input_cache_write_idx].parse().unwrap_or(0);
+        let input_without_cache_write: i64 = fields[input_no_cache_idx].parse().unwrap_or(0);
+        let cache_read: i64 = fields[cache_read_idx].parse().unwrap_or(0);
+        let output_tokens: i64 = fields[output_idx].parse().unwrap_or(0);
+        let cost = parse_cost(&fields[cost_idx]);
 
         // Skip empty or errored entries
         if model.is_empty() {
@@ -184,10 +181,10 @@ pub fn parse_cursor_file(path: &Path) -> Vec<UnifiedMessage> {
         // Input tokens = input_without_cache_write
         let input = input_without_cache_write;
 
-        messages.push(UnifiedMessage::new(
+        let mut message = UnifiedMessage::new(
             "cursor",
             model,
-            infer_provider(model),
+            providers::infer_provider(model, infer_provider),
             format!("cursor-{}-{}", account_id, date_str),
             timestamp,
             TokenBreakdown {
@@ -198,14 +195,53 @@ pub fn parse_cursor_file(path: &Path) -> Vec<UnifiedMessage> {
                 reasoning: 0,
             },
             cost,
+        );
+        // Overlapping exports repeat rows verbatim; see `merge_cursor_exports`
+        message.dedup_key = Some(format!(
+            "{}|{}|{}|{}|{}|{}|{}",
+            account_id, date_str, model, input, output_tokens, cache_read, cost
         ));
+        messages.push(message);
     }
 
     messages
 }
 
-/// Simple CSV line parser that handles quoted fields
-fn parse_csv_line(line: &str) -> Vec<&str> {
+/// Merge the rows of several exports, one `Vec` per CSV, so rows repeated by
+/// overlapping exports count once. A row that appears several times within
+/// one export is kept as often as the export with the most copies has it.
+pub fn merge_cursor_exports(exports: Vec<Vec<UnifiedMessage>>) -> Vec<UnifiedMessage> {
+    let mut allowed: HashMap<String, usize> = HashMap::new();
+    for export in &exports {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for key in export.iter().filter_map(|m| m.dedup_key.as_deref()) {
+            *counts.entry(key).or_default() += 1;
+        }
+        for (key, count) in counts {
+            let max = allowed.entry(key.to_string()).or_default();
+            *max = (*max).max(count);
+        }
+    }
+
+    exports
+        .into_iter()
+        .flatten()
+        .filter(|message| match &message.dedup_key {
+            Some(key) => match allowed.get_mut(key) {
+                Some(remaining) if *remaining > 0 => {
+                    *remaining -= 1;
+                    true
+                }
+                _ => false,
+            },
+            None => true,
+        })
+        .collect()
+}
+
+/// Simple CSV line parser that handles quoted fields. Fields come back trimmed,
+/// with surrounding quotes removed and `""` unescaped.
+fn parse_csv_line(line: &str) -> Vec<Cow<'_, str>> {
     let mut fields = Vec::new();
     let mut start = 0;
     let mut in_quotes = false;
@@ -213,9 +249,10 @@ fn parse_csv_line(line: &str) -> Vec<&str> {
 
     for (i, &byte) in bytes.iter().enumerate() {
         match byte {
+            // An escaped `""` toggles twice, so it never ends the field
             b'"' => in_quotes = !in_quotes,
             b',' if !in_quotes => {
-                fields.push(&line[start..i]);
+                fields.push(unquote(&line[start..i]));
                 start = i + 1;
             }
             _ => {}
@@ -224,12 +261,23 @@ fn parse_csv_line(line: &str) -> Vec<&str> {
 
     // Add the last field
     if start <= line.len() {
-        fields.push(&line[start..]);
+        fields.push(unquote(&line[start..]));
     }
 
     fields
 }
 
+/// Trim a raw CSV field (including a stray `\r` that survives `lines()` on
+/// mixed endings) and strip its quotes, unescaping `""` inside them
+fn unquote(field: &str) -> Cow<'_, str> {
+    let field = field.trim();
+    match field.strip_prefix('"').and_then(|f| f.strip_suffix('"')) {
+        Some(inner) if inner.contains("\"\"") => Cow::Owned(inner.replace("\"\"", "\"")),
+        Some(inner) => Cow::Borrowed(inner),
+        None => Cow::Borrowed(field),
+    }
+}
+
 /// Parse a date string to Unix milliseconds timestamp
 fn parse_date_to_timestamp(date_str: &str) -> i64 {
     use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
@@ -267,6 +315,19 @@ fn parse_date_to_timestamp(date_str: &str) -> i64 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_account_id_from_cursor_cache_path() {
+        let account = |name: &str| {
+            account_id_from_cursor_cache_path(Path::new("/cache").join(name).as_path())
+        };
+        assert_eq!(account("usage.csv"), "active");
+        assert_eq!(account("usage.work.csv"), "work");
+        assert_eq!(account("usage.me@example.csv"), "me-example");
+        assert_eq!(account("usage.2024.csv"), "unknown");
+        assert_eq!(account("usage.backup-1.csv"), "unknown");
+        assert_eq!(account("usage..csv"), "unknown");
+    }
+
     #[test]
     fn test_infer_provider() {
         assert_eq!(infer_provider("claude-3-sonnet"), "anthropic");
@@ -298,6 +359,23 @@ mod tests {
         assert_eq!(fields[8], "$0.10");
     }
 
+    #[test]
+    fn test_parse_csv_line_trims_carriage_returns() {
+        let fields = parse_csv_line("2025-02-01,gpt-4o,\"$0.10\"\r");
+        assert_eq!(fields, ["2025-02-01", "gpt-4o", "$0.10"]);
+    }
+
+    #[test]
+    fn test_parse_csv_line_quoted_fields() {
+        let fields = parse_csv_line(r#""2025-02-01","gpt-4o, preview","say ""hi""","""",0.10"#);
+        assert_eq!(
+            fields,
+            ["2025-02-01", "gpt-4o, preview", "say \"hi\"", "\"", "0.10"]
+        );
+        assert!(matches!(fields[1], Cow::Borrowed(_)));
+        assert!(matches!(fields[2], Cow::Owned(_)));
+    }
+
     #[test]
     fn test_parse_date_to_timestamp() {
         // ISO with milliseconds and Z (new Cursor format)
@@ -371,4 +449,91 @@ mod tests {
         assert_eq!(messages[1].tokens.input, 8263);
         assert_eq!(messages[1].tokens.cache_read, 66964);
     }
+
+    #[test]
+    fn test_parse_cursor_csv_with_bom_and_crlf() {
+        let csv = r#"Date,Kind,Model,Max Mode,Input (w/ Cache Write),Input (w/o Cache Write),Cache Read,Output Tokens,Total Tokens,Cost
+"2025-11-13T18:36:05.846Z","Included","auto","No","28342","775","105891","21282","156290","0.19"
+"2025-11-13T13:35:04.658Z","On-Demand","gpt-5-codex","No","0","8263","66964","1612","76839","0.03""#;
+        let windows = format!("\u{feff}{}\r\n", csv.replace('\n', "\r\n"));
+
+        let clean_dir = tempfile::TempDir::new().unwrap();
+        let clean_path = clean_dir.path().join("usage.csv");
+        std::fs::write(&clean_path, csv).unwrap();
+        let windows_dir = tempfile::TempDir::new().unwrap();
+        let windows_path = windows_dir.path().join("usage.csv");
+        std::fs::write(&windows_path, windows).unwrap();
+
+        let clean = parse_cursor_file(&clean_path);
+        let parsed = parse_cursor_file(&windows_path);
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", clean));
+    }
+
+    #[test]
+    fn test_parse_cursor_csv_unescapes_model_names() {
+        let csv = r#"Date,Kind,Model,Max Mode,Input (w/ Cache Write),Input (w/o Cache Write),Cache Read,Output Tokens,Total Tokens,Cost
+"2025-11-13T18:36:05.846Z","Included","gpt-4o, preview","No","10","5","0","15","30","0.10"
+"2025-11-13T13:35:04.658Z","Included","custom ""fast"" model","No","0","8","0","2","10","0.01""#;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("usage.csv");
+        std::fs::write(&file_path, csv).unwrap();
+
+        let messages = parse_cursor_file(&file_path);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].model_id, "gpt-4o, preview");
+        assert_eq!(messages[0].tokens.output, 15);
+        assert_eq!(messages[1].model_id, "custom \"fast\" model");
+        assert_eq!(messages[1].tokens.input, 8);
+    }
+
+    #[test]
+    fn test_parse_cursor_reader_in_memory() {
+        let csv = "Date,Model,Input (w/ Cache Write),Input (w/o Cache Write),Cache Read,Output,Total Tokens,Cost ($),Cost to you ($)\n\
+                   2025-02-01T10:00:00.000Z,gpt-4o,150,100,20,30,200,$0.10,$0.10\n";
+
+        let messages = parse_cursor_reader(csv.as_bytes(), "work");
+
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].session_id.starts_with("cursor-work-"));
+        assert_eq!(messages[0].tokens.input, 100);
+        assert_eq!(messages[0].tokens.cache_write, 50);
+        assert!(parse_cursor_reader(&[0xff, 0xfe], "work").is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_exports_count_shared_rows_once() {
+        let header = "Date,Kind,Model,Max Mode,Input (w/ Cache Write),Input (w/o Cache Write),Cache Read,Output Tokens,Total Tokens,Cost\n";
+        let shared = "2025-11-13T18:36:05.846Z,Included,gpt-5,No,150,100,20,30,200,$0.10\n\
+                      2025-11-13T19:00:00.000Z,Included,gpt-5,No,150,100,20,30,200,$0.10\n";
+        let older = format!(
+            "{}2025-11-12T09:00:00.000Z,Included,gpt-5,No,50,50,0,10,60,$0.02\n{}",
+            header, shared
+        );
+        let newer = format!(
+            "{}{}2025-11-14T09:00:00.000Z,Included,gpt-5,No,80,80,0,20,100,$0.05\n",
+            header, shared
+        );
+
+        let merged = merge_cursor_exports(vec![
+            parse_cursor_reader(older.as_bytes(), "active"),
+            parse_cursor_reader(newer.as_bytes(), "active"),
+        ]);
+
+        assert_eq!(merged.len(), 4);
+        let input: i64 = merged.iter().map(|m| m.tokens.input).sum();
+        let cost: f64 = merged.iter().map(|m| m.cost).sum();
+        assert_eq!(input, 50 + 100 + 100 + 80);
+        assert!((cost - 0.27).abs() < 1e-9);
+
+        // Different accounts never merge
+        let other = parse_cursor_reader(older.as_bytes(), "work");
+        assert_eq!(
+            merge_cursor_exports(vec![parse_cursor_reader(older.as_bytes(), "active"), other])
+                .len(),
+            6
+        );
+    }
 }
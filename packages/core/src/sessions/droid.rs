@@ -2,10 +2,11 @@
 //!
 //! Parses JSON files from ~/.factory/sessions/
 
+use super::providers;
+use super::utils::FileBytes;
 use super::UnifiedMessage;
 use crate::TokenBreakdown;
 use serde::Deserialize;
-use std::io::{BufRead, BufReader};
 use std::path::Path;
 
 /// Droid settings.json structure
@@ -128,25 +129,28 @@ fn get_default_model_from_provider(provider: &str) -> String {
 /// Try to extract model name from JSONL file's system-reminder
 /// Looks for pattern: "Model: Claude Opus 4.5 Thinking [Anthropic]"
 fn extract_model_from_jsonl(jsonl_path: &Path) -> Option<String> {
-    let file = std::fs::File::open(jsonl_path).ok()?;
-    let reader = BufReader::new(file);
-
-    // Scan more lines for parity with TypeScript which reads entire file
-    // Cap at 500 lines to avoid performance issues with very large files
-    for line in reader.lines().take(500) {
-        let line = line.ok()?;
-        // Look for Model: pattern in system-reminder
-        if let Some(pos) = line.find("Model:") {
-            let after_model = &line[pos + 6..];
-            // Extract until [ or end of string/newline
-            let model_part: String = after_model
-                .chars()
-                .take_while(|&c| c != '[' && c != '\\' && c != '"')
-                .collect();
-            let model_name = model_part.trim();
-            if !model_name.is_empty() {
-                return Some(normalize_model_name(model_name));
-            }
+    // Large logs are mapped, and the whole file is searched like TypeScript does
+    let data = FileBytes::map(jsonl_path).ok()?;
+    model_from_jsonl_bytes(data.as_slice())
+}
+
+const MODEL_MARKER: &[u8] = b"Model:";
+
+/// The first non-empty `Model:` value in `data`, searched as raw bytes so
+/// lines are never decoded
+pub fn model_from_jsonl_bytes(data: &[u8]) -> Option<String> {
+    let mut rest = data;
+    while let Some(pos) = memchr::memmem::find(rest, MODEL_MARKER) {
+        rest = &rest[pos + MODEL_MARKER.len()..];
+        // Extract until [, an escape, a closing quote or the end of the line
+        let end = rest
+            .iter()
+            .position(|&b| matches!(b, b'[' | b'\\' | b'"' | b'\n'))
+            .unwrap_or(rest.len());
+        let model_name = String::from_utf8_lossy(&rest[..end]);
+        let model_name = model_name.trim();
+        if !model_name.is_empty() {
+            return Some(normalize_model_name(model_name));
         }
     }
 
@@ -155,13 +159,48 @@ fn extract_model_from_jsonl(jsonl_path: &Path) -> Option<String> {
 
 /// Parse a Droid settings.json file
 pub fn parse_droid_file(path: &Path) -> Vec<UnifiedMessage> {
-    let data = match std::fs::read(path) {
+    let data = match FileBytes::read(path) {
         Ok(d) => d,
         Err(_) => return Vec::new(),
     };
 
-    let mut bytes = data;
-    let settings: DroidSettingsJson = match simd_json::from_slice(&mut bytes) {
+    // Extract session ID from filename (e.g., "uuid.settings.json" -> "uuid")
+    let session_id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+        .replace(".settings", "");
+
+    let modified = std::fs::metadata(path)
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .map(|t| {
+            t.duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0)
+        })
+        .unwrap_or(0);
+
+    parse_droid_reader(data.into_buffer(), &session_id, modified, || {
+        // The session transcript sits next to the settings file
+        path.to_str()
+            .map(|s| s.replace(".settings.json", ".jsonl"))
+            .and_then(|jsonl| extract_model_from_jsonl(Path::new(&jsonl)))
+    })
+}
+
+/// Parse the contents of a Droid settings.json for `session_id`.
+/// `transcript_model` is only asked for the session transcript's model when
+/// the settings name none; a `fallback_timestamp` of 0 drops the session when
+/// the settings carry no timestamp either.
+pub fn parse_droid_reader(
+    mut data: Vec<u8>,
+    session_id: &str,
+    fallback_timestamp: i64,
+    transcript_model: impl FnOnce() -> Option<String>,
+) -> Vec<UnifiedMessage> {
+    let settings: DroidSettingsJson = match simd_json::from_slice(&mut data) {
         Ok(s) => s,
         Err(_) => return Vec::new(),
     };
@@ -183,52 +222,25 @@ pub fn parse_droid_file(path: &Path) -> Vec<UnifiedMessage> {
         return Vec::new();
     }
 
-    // Extract session ID from filename (e.g., "uuid.settings.json" -> "uuid")
-    let session_id = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("unknown")
-        .to_string()
-        .replace(".settings", "");
-
     // Get model and provider
     let provider = settings.provider_lock.clone().unwrap_or_else(|| {
-        get_provider_from_model(settings.model.as_deref().unwrap_or("")).to_string()
+        providers::infer_provider(
+            settings.model.as_deref().unwrap_or(""),
+            get_provider_from_model,
+        )
     });
 
-    let model = if let Some(m) = settings.model {
-        normalize_model_name(&m)
-    } else {
-        // Try to extract from JSONL file
-        let jsonl_path = path
-            .to_str()
-            .map(|s| s.replace(".settings.json", ".jsonl"))
-            .map(std::path::PathBuf::from);
-
-        if let Some(ref jsonl) = jsonl_path {
-            extract_model_from_jsonl(jsonl)
-                .unwrap_or_else(|| get_default_model_from_provider(&provider))
-        } else {
-            get_default_model_from_provider(&provider)
-        }
+    let model = match settings.model {
+        Some(m) => normalize_model_name(&m),
+        None => transcript_model().unwrap_or_else(|| get_default_model_from_provider(&provider)),
     };
 
-    // Get timestamp from providerLockTimestamp or file mtime
+    // Get timestamp from providerLockTimestamp or the fallback (file mtime)
     let timestamp = settings
         .provider_lock_timestamp
         .and_then(|ts| chrono::DateTime::parse_from_rfc3339(&ts).ok())
         .map(|dt| dt.timestamp_millis())
-        .or_else(|| {
-            std::fs::metadata(path)
-                .ok()
-                .and_then(|m| m.modified().ok())
-                .map(|t| {
-                    t.duration_since(std::time::UNIX_EPOCH)
-                        .map(|d| d.as_millis() as i64)
-                        .unwrap_or(0)
-                })
-        })
-        .unwrap_or(0);
+        .unwrap_or(fallback_timestamp);
 
     if timestamp == 0 {
         return Vec::new();
@@ -238,7 +250,7 @@ pub fn parse_droid_file(path: &Path) -> Vec<UnifiedMessage> {
         "droid",
         model,
         provider,
-        session_id,
+        session_id.to_string(),
         timestamp,
         TokenBreakdown {
             input: usage.input_tokens.unwrap_or(0),
@@ -305,6 +317,33 @@ mod tests {
         assert_eq!(get_default_model_from_provider("custom"), "custom-unknown");
     }
 
+    #[test]
+    fn test_extract_model_past_line_500() {
+        let mut jsonl = String::new();
+        for i in 0..800 {
+            jsonl.push_str(&format!("{{\"type\":\"message\",\"id\":{}}}\n", i));
+        }
+        jsonl.push_str(
+            r#"{"type":"message","content":"<system-reminder>Model: \n</system-reminder>"}"#,
+        );
+        jsonl.push('\n');
+        jsonl.push_str(r#"{"type":"message","content":"<system-reminder>Model: Claude Opus 4.5 Thinking [Anthropic]</system-reminder>"}"#);
+        jsonl.push('\n');
+        jsonl.push_str(r#"{"type":"message","content":"Model: GPT-5"}"#);
+
+        let file = tempfile::Builder::new()
+            .suffix(".jsonl")
+            .tempfile()
+            .unwrap();
+        std::fs::write(file.path(), &jsonl).unwrap();
+
+        assert_eq!(
+            extract_model_from_jsonl(file.path()),
+            Some(normalize_model_name("Claude Opus 4.5 Thinking"))
+        );
+        assert_eq!(model_from_jsonl_bytes(b"{\"no\":\"marker\"}"), None);
+    }
+
     #[test]
     fn test_parse_droid_settings_structure() {
         let json = r#"{
@@ -336,4 +375,22 @@ mod tests {
         assert_eq!(usage.cache_read_tokens, Some(12));
         assert_eq!(usage.thinking_tokens, Some(34));
     }
+    #[test]
+    fn test_parse_droid_reader_in_memory() {
+        let json = r#"{"providerLock": "anthropic",
+            "tokenUsage": {"inputTokens": 100, "outputTokens": 20}}"#;
+
+        let messages =
+            parse_droid_reader(json.as_bytes().to_vec(), "sess-1", 1748772000000, || {
+                Some("claude-opus-4-5".to_string())
+            });
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "sess-1");
+        assert_eq!(messages[0].model_id, "claude-opus-4-5");
+        assert_eq!(messages[0].timestamp, 1748772000000);
+
+        // Without a timestamp in the settings or a fallback there's no date
+        assert!(parse_droid_reader(json.as_bytes().to_vec(), "sess-1", 0, || None).is_empty());
+    }
 }
@@ -3,7 +3,7 @@
 //! Parses JSON session files from ~/.gemini/tmp/*/chats/session-*.json
 
 use super::utils::{
-    extract_i64, extract_string, file_modified_timestamp_ms, parse_timestamp_value,
+    extract_i64, extract_string, file_modified_timestamp_ms, parse_timestamp_value, FileBytes,
 };
 use super::UnifiedMessage;
 use crate::TokenBreakdown;
@@ -50,49 +50,133 @@ pub struct GeminiTokens {
     pub thoughts: Option<i64>,
     pub tool: Option<i64>,
     pub total: Option<i64>,
+    /// Image input tokens reported separately from `input` (folded into input)
+    pub image: Option<i64>,
 }
 
 /// Parse a Gemini session file
 pub fn parse_gemini_file(path: &Path) -> Vec<UnifiedMessage> {
-    let fallback_timestamp = file_modified_timestamp_ms(path);
+    parse_gemini_cli_file(path, "gemini", "google", parse_gemini_reader)
+}
 
-    if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-        return parse_gemini_headless_jsonl(path, fallback_timestamp);
+/// Parse a session or headless output file in Gemini CLI's format, tagging
+/// messages with `source` and `provider`. `.jsonl` output is streamed through
+/// `read_lines`, the fork's reader core; other files are one document, read
+/// whole and parsed straight from the map when large.
+pub(super) fn parse_gemini_cli_file(
+    path: &Path,
+    source: &str,
+    provider: &str,
+    read_lines: impl FnOnce(BufReader<std::fs::File>, &Path, i64) -> Vec<UnifiedMessage>,
+) -> Vec<UnifiedMessage> {
+    let fallback_timestamp = file_modified_timestamp_ms(path);
+    if is_jsonl(path) {
+        let file = match std::fs::File::open(path) {
+            Ok(f) => f,
+            Err(_) => return Vec::new(),
+        };
+        return read_lines(BufReader::new(file), path, fallback_timestamp);
     }
-
-    let data = match std::fs::read(path) {
+    let data = match FileBytes::read(path) {
         Ok(d) => d,
         Err(_) => return Vec::new(),
     };
+    parse_gemini_cli_document(
+        data.as_slice(),
+        session_id_of(path),
+        source,
+        provider,
+        fallback_timestamp,
+    )
+}
+
+/// Parse Gemini CLI session or headless output from `reader`
+pub fn parse_gemini_reader(
+    reader: impl BufRead,
+    path: &Path,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    parse_gemini_cli_reader(reader, path, "gemini", "google", fallback_timestamp)
+}
 
-    let mut bytes = data.clone();
-    if let Ok(session) = simd_json::from_slice::<GeminiSession>(&mut bytes) {
-        return parse_gemini_session(session, fallback_timestamp);
+/// Parse Gemini CLI data from `reader` in whichever format it is: a saved
+/// session, a headless `--output-format json` result, or `stream-json` lines.
+/// `path` is where the data lives (on disk or inside an archive); `.jsonl`
+/// data is streamed line by line, and the file stem is the session id when
+/// the data does not name its own session. Messages are tagged with `source`
+/// and `provider`, so forks such as Qwen Code share this parser.
+pub fn parse_gemini_cli_reader(
+    mut reader: impl BufRead,
+    path: &Path,
+    source: &str,
+    provider: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    let session_id = session_id_of(path);
+    if is_jsonl(path) {
+        return parse_gemini_headless_jsonl(
+            reader,
+            session_id,
+            source,
+            provider,
+            fallback_timestamp,
+        );
+    }
+
+    let mut data = Vec::new();
+    if reader.read_to_end(&mut data).is_err() {
+        return Vec::new();
     }
+    parse_gemini_cli_document(&data, session_id, source, provider, fallback_timestamp)
+}
+
+fn is_jsonl(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("jsonl")
+}
+
+fn session_id_of(path: &Path) -> &str {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+}
 
-    let mut bytes = data;
-    if let Ok(value) = simd_json::from_slice::<Value>(&mut bytes) {
-        let session_id = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-        let messages = parse_gemini_headless_value(&value, &session_id, fallback_timestamp);
+/// Parse one whole Gemini CLI document: a saved session or a headless
+/// result, falling back to `stream-json` lines
+fn parse_gemini_cli_document(
+    data: &[u8],
+    session_id: &str,
+    source: &str,
+    provider: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    // Parsed once and in place, since `data` is kept for the JSONL fallback
+    if let Ok(value) = serde_json::from_slice::<Value>(data) {
+        if let Ok(session) = GeminiSession::deserialize(&value) {
+            return parse_gemini_session(session, source, provider, fallback_timestamp);
+        }
+        let messages =
+            parse_gemini_headless_value(&value, session_id, source, provider, fallback_timestamp);
         if !messages.is_empty() {
             return messages;
         }
     }
 
-    parse_gemini_headless_jsonl(path, fallback_timestamp)
+    parse_gemini_headless_jsonl(data, session_id, source, provider, fallback_timestamp)
 }
 
-fn parse_gemini_session(session: GeminiSession, fallback_timestamp: i64) -> Vec<UnifiedMessage> {
+fn parse_gemini_session(
+    session: GeminiSession,
+    source: &str,
+    provider: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
     let mut messages = Vec::new();
     let session_id = session.session_id.clone();
 
     for msg in session.messages {
-        // Only process gemini messages with token data
-        if msg.message_type != "gemini" {
+        // Only process model replies with token data (forks may record them
+        // under their own name)
+        if msg.message_type != "gemini" && msg.message_type != source {
             continue;
         }
 
@@ -113,13 +197,13 @@ fn parse_gemini_session(session: GeminiSession, fallback_timestamp: i64) -> Vec<
             .unwrap_or(fallback_timestamp);
 
         messages.push(UnifiedMessage::new(
-            "gemini",
+            source,
             model,
-            "google",
+            provider,
             session_id.clone(),
             timestamp,
             TokenBreakdown {
-                input: tokens.input.unwrap_or(0),
+                input: tokens.input.unwrap_or(0) + tokens.image.unwrap_or(0),
                 output: tokens.output.unwrap_or(0),
                 cache_read: tokens.cached.unwrap_or(0),
                 cache_write: 0,
@@ -132,19 +216,15 @@ fn parse_gemini_session(session: GeminiSession, fallback_timestamp: i64) -> Vec<
     messages
 }
 
-fn parse_gemini_headless_jsonl(path: &Path, fallback_timestamp: i64) -> Vec<UnifiedMessage> {
-    let file = match std::fs::File::open(path) {
-        Ok(f) => f,
-        Err(_) => return Vec::new(),
-    };
-
-    let mut session_id = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+fn parse_gemini_headless_jsonl(
+    reader: impl BufRead,
+    session_id: &str,
+    source: &str,
+    provider: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    let mut session_id = session_id.to_string();
     let mut current_model: Option<String> = None;
-    let reader = BufReader::new(file);
     let mut messages = Vec::new();
 
     for line in reader.lines() {
@@ -186,6 +266,8 @@ fn parse_gemini_headless_jsonl(path: &Path, fallback_timestamp: i64) -> Vec<Unif
                 stats,
                 current_model.clone(),
                 &session_id,
+                source,
+                provider,
                 timestamp,
             ));
         }
@@ -197,6 +279,8 @@ fn parse_gemini_headless_jsonl(path: &Path, fallback_timestamp: i64) -> Vec<Unif
 fn parse_gemini_headless_value(
     value: &Value,
     session_id: &str,
+    source: &str,
+    provider: &str,
     fallback_timestamp: i64,
 ) -> Vec<UnifiedMessage> {
     let stats = match value
@@ -210,13 +294,16 @@ fn parse_gemini_headless_value(
     let model_hint = extract_string(value.get("model"));
     let timestamp = extract_timestamp_from_value(value).unwrap_or(fallback_timestamp);
 
-    build_messages_from_stats(stats, model_hint, session_id, timestamp)
+    build_messages_from_stats(stats, model_hint, session_id, source, provider, timestamp)
 }
 
+/// One message per model in a headless `stats` block
 fn build_messages_from_stats(
     stats: &Value,
     model_hint: Option<String>,
     session_id: &str,
+    source: &str,
+    provider: &str,
     timestamp: i64,
 ) -> Vec<UnifiedMessage> {
     let usages = extract_gemini_usages(stats, model_hint);
@@ -224,9 +311,9 @@ fn build_messages_from_stats(
         .into_iter()
         .map(|usage| {
             UnifiedMessage::new(
-                "gemini",
+                source,
                 usage.model,
-                "google",
+                provider,
                 session_id.to_string(),
                 timestamp,
                 TokenBreakdown {
@@ -258,10 +345,14 @@ fn extract_gemini_usages(stats: &Value, model_hint: Option<String>) -> Vec<Gemin
                 Some(t) => t,
                 None => continue,
             };
+            let image = extract_i64(tokens.get("image"))
+                .or_else(|| extract_i64(tokens.get("image_tokens")))
+                .unwrap_or(0);
             let input = extract_i64(tokens.get("prompt"))
                 .or_else(|| extract_i64(tokens.get("input")))
                 .or_else(|| extract_i64(tokens.get("input_tokens")))
-                .unwrap_or(0);
+                .unwrap_or(0)
+                + image;
             let output = extract_i64(tokens.get("candidates"))
                 .or_else(|| extract_i64(tokens.get("output")))
                 .or_else(|| extract_i64(tokens.get("output_tokens")))
@@ -293,7 +384,8 @@ fn extract_gemini_usages(stats: &Value, model_hint: Option<String>) -> Vec<Gemin
 
     let input = extract_i64(stats.get("input_tokens"))
         .or_else(|| extract_i64(stats.get("prompt_tokens")))
-        .unwrap_or(0);
+        .unwrap_or(0)
+        + extract_i64(stats.get("image_tokens")).unwrap_or(0);
     let output = extract_i64(stats.get("output_tokens"))
         .or_else(|| extract_i64(stats.get("candidates_tokens")))
         .unwrap_or(0);
@@ -403,4 +495,71 @@ mod tests {
         assert_eq!(messages[0].tokens.input, 10);
         assert_eq!(messages[0].tokens.output, 20);
     }
+
+    #[test]
+    fn test_parse_session_image_tokens_folded_into_input() {
+        let json = r#"{
+            "sessionId": "ses_img",
+            "projectHash": "abc123",
+            "startTime": "2025-06-15T12:00:00Z",
+            "lastUpdated": "2025-06-15T12:30:00Z",
+            "messages": [
+                {
+                    "id": "msg_1",
+                    "timestamp": "2025-06-15T12:01:00Z",
+                    "type": "gemini",
+                    "model": "gemini-2.5-pro",
+                    "tokens": { "input": 100, "image": 258, "output": 20, "cached": 0, "thoughts": 0 }
+                }
+            ]
+        }"#;
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+
+        let messages = parse_gemini_file(file.path());
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].tokens.input, 358);
+        assert_eq!(messages[0].tokens.output, 20);
+    }
+
+    #[test]
+    fn test_parse_headless_image_tokens_folded_into_input() {
+        let json = r#"{"stats":{"models":{"gemini-2.5-pro":{"tokens":{"prompt":12,"image":258,"candidates":34}}}}}"#;
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+
+        let messages = parse_gemini_file(file.path());
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].tokens.input, 270);
+    }
+
+    #[test]
+    fn test_parse_gemini_cli_reader_in_memory() {
+        let session = r#"{"sessionId":"ses_1","projectHash":"abc","startTime":"2025-06-15T12:00:00Z","lastUpdated":"2025-06-15T12:30:00Z","messages":[{"id":"m1","timestamp":"2025-06-15T12:01:00Z","type":"gemini","model":"gemini-2.5-pro","tokens":{"input":10,"output":20,"cached":5}}]}"#;
+        let messages = parse_gemini_cli_reader(
+            session.as_bytes(),
+            Path::new("fallback.json"),
+            "gemini",
+            "google",
+            0,
+        );
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "ses_1");
+        assert_eq!(messages[0].tokens.cache_read, 5);
+
+        // Headless stream-json without a session id of its own
+        let stream = r#"{"type":"init","model":"gemini-2.5-flash"}
+{"type":"result","stats":{"models":{"gemini-2.5-flash":{"tokens":{"prompt":100,"candidates":20}}}}}"#;
+        let messages = parse_gemini_cli_reader(
+            stream.as_bytes(),
+            Path::new("headless-1.jsonl"),
+            "gemini",
+            "google",
+            1748772000000,
+        );
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "headless-1");
+    }
 }
@@ -0,0 +1,162 @@
+//! Headless input validation
+//!
+//! Checks a file written to the headless directory line by line so malformed
+//! records can be found before a full run, where the parsers skip them silently.
+
+use super::{claudecode, codex, gemini, qwen, UnifiedMessage};
+use crate::error::ErrorCode;
+use serde_json::Value;
+use std::path::Path;
+
+/// A line that could not be read as a headless record
+#[derive(Debug, Clone)]
+pub struct HeadlessLineError {
+    /// 1-based line number
+    pub line_no: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HeadlessValidation {
+    /// Non-blank lines in the file
+    pub lines_total: usize,
+    /// Lines that parsed as JSON objects
+    pub lines_parsed: usize,
+    /// Messages the source parser produced from the whole file
+    pub messages_emitted: usize,
+    pub errors: Vec<HeadlessLineError>,
+}
+
+/// Validate a headless output file for `source` ("claude", "codex", "gemini"
+/// or "qwen")
+pub fn validate_headless_file(path: &Path, source: &str) -> Result<HeadlessValidation, String> {
+    let parse: fn(&Path) -> Vec<UnifiedMessage> = match source {
+        "claude" => claudecode::parse_claude_file,
+        "codex" => codex::parse_codex_file,
+        "gemini" => gemini::parse_gemini_file,
+        "qwen" => qwen::parse_qwen_file,
+        other => {
+            return Err(
+                ErrorCode::UnknownSource.reason(format!("Unsupported headless source: {}", other))
+            )
+        }
+    };
+
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        ErrorCode::ReadFailed.reason(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+
+    let mut validation = HeadlessValidation {
+        messages_emitted: parse(path).len(),
+        ..Default::default()
+    };
+
+    // Single-document JSON output (`--output-format json`) is valid as a whole
+    // even though its individual lines are not
+    let is_json_document = path.extension().and_then(|s| s.to_str()) == Some("json")
+        && serde_json::from_str::<Value>(&content).is_ok_and(|v| v.is_object());
+
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        validation.lines_total += 1;
+
+        if is_json_document {
+            validation.lines_parsed += 1;
+            continue;
+        }
+
+        match serde_json::from_str::<Value>(trimmed) {
+            Ok(value) if value.is_object() => validation.lines_parsed += 1,
+            Ok(_) => validation.errors.push(HeadlessLineError {
+                line_no: index + 1,
+                reason: "expected a JSON object".to_string(),
+            }),
+            Err(e) => validation.errors.push(HeadlessLineError {
+                line_no: index + 1,
+                reason: format!("invalid JSON: {}", e),
+            }),
+        }
+    }
+
+    Ok(validation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_file(content: &str, suffix: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        std::fs::write(file.path(), content).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_claude_stream_with_bad_line() {
+        let content = r#"{"type":"message_start","timestamp":"2025-01-01T00:00:00Z","message":{"id":"msg_1","model":"claude-3-5-sonnet","usage":{"input_tokens":200}}}
+{"type":"message_delta","usage":{"output_tokens":80}
+{"type":"message_stop"}"#;
+        let file = create_test_file(content, ".jsonl");
+
+        let validation = validate_headless_file(file.path(), "claude").unwrap();
+
+        assert_eq!(validation.lines_total, 3);
+        assert_eq!(validation.lines_parsed, 2);
+        assert_eq!(validation.messages_emitted, 1);
+        assert_eq!(validation.errors.len(), 1);
+        assert_eq!(validation.errors[0].line_no, 2);
+        assert!(validation.errors[0].reason.starts_with("invalid JSON"));
+    }
+
+    #[test]
+    fn test_codex_with_bad_line() {
+        let content = r#"{"type":"turn.completed","model":"gpt-4o-mini","usage":{"input_tokens":120,"cached_input_tokens":20,"output_tokens":30}}
+
+not json at all
+{"type":"turn.completed","model":"gpt-4o-mini","usage":{"input_tokens":50,"output_tokens":10}}"#;
+        let file = create_test_file(content, ".jsonl");
+
+        let validation = validate_headless_file(file.path(), "codex").unwrap();
+
+        assert_eq!(validation.lines_total, 3);
+        assert_eq!(validation.lines_parsed, 2);
+        assert_eq!(validation.messages_emitted, 2);
+        assert_eq!(validation.errors.len(), 1);
+        assert_eq!(validation.errors[0].line_no, 3);
+    }
+
+    #[test]
+    fn test_non_object_line_reported() {
+        let file = create_test_file("[1, 2, 3]\n", ".jsonl");
+
+        let validation = validate_headless_file(file.path(), "codex").unwrap();
+
+        assert_eq!(validation.lines_parsed, 0);
+        assert_eq!(validation.errors[0].reason, "expected a JSON object");
+    }
+
+    #[test]
+    fn test_pretty_printed_json_document_is_valid() {
+        let content = r#"{
+  "type": "message",
+  "message": {"model": "claude-3-5-sonnet", "usage": {"input_tokens": 120, "output_tokens": 60}}
+}"#;
+        let file = create_test_file(content, ".json");
+
+        let validation = validate_headless_file(file.path(), "claude").unwrap();
+
+        assert_eq!(validation.lines_total, 4);
+        assert_eq!(validation.lines_parsed, 4);
+        assert_eq!(validation.messages_emitted, 1);
+        assert!(validation.errors.is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_source() {
+        let file = create_test_file("{}", ".jsonl");
+        assert!(validate_headless_file(file.path(), "cursor").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Jules (Google) task usage parser
+//!
+//! Jules keeps one JSON file per task with a usage record for each model call.
+//! There is no standard location for these yet, so the scanner looks under
+//! `~/.jules/tasks` unless `TOKSCALE_JULES_DIR` points elsewhere.
+
+use super::utils::{file_modified_timestamp_ms, parse_timestamp_value, FileBytes};
+use super::UnifiedMessage;
+use crate::TokenBreakdown;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+/// Jules task file structure
+#[derive(Debug, Deserialize)]
+pub struct JulesTask {
+    #[serde(alias = "taskId")]
+    pub task_id: Option<String>,
+    #[serde(default)]
+    pub usage: Vec<JulesUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JulesUsage {
+    pub model: Option<String>,
+    /// RFC 3339 string or Unix time (seconds or milliseconds)
+    pub timestamp: Option<Value>,
+    #[serde(default, alias = "inputTokens")]
+    pub input_tokens: i64,
+    #[serde(default, alias = "outputTokens")]
+    pub output_tokens: i64,
+    #[serde(default, alias = "cachedTokens")]
+    pub cached_tokens: i64,
+    #[serde(default, alias = "thoughtsTokens")]
+    pub thoughts_tokens: i64,
+}
+
+/// Parse a Jules task usage file
+pub fn parse_jules_file(path: &Path) -> Vec<UnifiedMessage> {
+    let data = match FileBytes::read(path) {
+        Ok(d) => d,
+        Err(_) => return Vec::new(),
+    };
+    let session_id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+    parse_jules_reader(
+        data.into_buffer(),
+        session_id,
+        file_modified_timestamp_ms(path),
+    )
+}
+
+/// Parse the contents of a Jules task usage file. `session_id` is used when the file does not
+/// name its own; `fallback_timestamp` stands in for entries without one.
+pub fn parse_jules_reader(
+    mut data: Vec<u8>,
+    session_id: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    let task: JulesTask = match simd_json::from_slice(&mut data) {
+        Ok(t) => t,
+        Err(_) => return Vec::new(),
+    };
+
+    let session_id = task.task_id.unwrap_or_else(|| session_id.to_string());
+
+    task.usage
+        .into_iter()
+        .filter_map(|usage| {
+            let total_tokens = usage.input_tokens
+                + usage.output_tokens
+                + usage.cached_tokens
+                + usage.thoughts_tokens;
+            if total_tokens == 0 {
+                return None;
+            }
+
+            let model = usage.model.filter(|m| !m.is_empty())?;
+            let timestamp = usage
+                .timestamp
+                .as_ref()
+                .and_then(parse_timestamp_value)
+                .unwrap_or(fallback_timestamp);
+
+            // Thoughts are billed like Gemini's: at the output rate, with
+            // cached tokens free (see `BillingRule::CacheFree`)
+            Some(UnifiedMessage::new(
+                "jules",
+                model,
+                "google",
+                session_id.clone(),
+                timestamp,
+                TokenBreakdown {
+                    input: usage.input_tokens,
+                    output: usage.output_tokens,
+                    cache_read: usage.cached_tokens,
+                    cache_write: 0,
+                    reasoning: usage.thoughts_tokens,
+                },
+                0.0,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_file(content: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), content).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_parse_jules_task() {
+        let content = r#"{
+            "taskId": "task_123",
+            "usage": [
+                {
+                    "model": "gemini-2.5-pro",
+                    "timestamp": "2025-06-01T10:00:00Z",
+                    "inputTokens": 1200, "outputTokens": 300, "cachedTokens": 800, "thoughtsTokens": 150
+                },
+                {
+                    "model": "gemini-2.5-flash",
+                    "timestamp": 1748772000000,
+                    "input_tokens": 500, "output_tokens": 100
+                },
+                { "model": "gemini-2.5-pro", "inputTokens": 0, "outputTokens": 0 }
+            ]
+        }"#;
+        let file = create_test_file(content);
+
+        let messages = parse_jules_file(file.path());
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].source, "jules");
+        assert_eq!(messages[0].provider_id, "google");
+        assert_eq!(messages[0].model_id, "gemini-2.5-pro");
+        assert_eq!(messages[0].session_id, "task_123");
+        assert_eq!(messages[0].date, "2025-06-01");
+        assert_eq!(messages[0].tokens.input, 1200);
+        assert_eq!(messages[0].tokens.output, 300);
+        assert_eq!(messages[0].tokens.cache_read, 800);
+        assert_eq!(messages[0].tokens.reasoning, 150);
+        assert_eq!(messages[1].timestamp, 1748772000000);
+        assert_eq!(messages[1].tokens.output, 100);
+    }
+
+    #[test]
+    fn test_jules_thoughts_billed_as_output_and_cache_free() {
+        use crate::pricing::billing::{provider_billing_rule, BillingRule};
+
+        let content = r#"{"usage":[{"model":"gemini-2.5-pro","inputTokens":100,"outputTokens":50,"cachedTokens":400,"thoughtsTokens":20}]}"#;
+        let file = create_test_file(content);
+        let messages = parse_jules_file(file.path());
+
+        let rule = provider_billing_rule(&messages[0].source);
+        assert_eq!(rule, BillingRule::CacheFree);
+        let billable = rule.billable(&messages[0].tokens);
+        assert_eq!(billable.cache_read, 0);
+        assert_eq!(billable.reasoning, 20);
+        // No task id: fall back to the file name
+        let stem = file.path().file_stem().unwrap().to_str().unwrap();
+        assert_eq!(messages[0].session_id, stem);
+    }
+
+    #[test]
+    fn test_parse_jules_invalid_json() {
+        let file = create_test_file("not json");
+        assert!(parse_jules_file(file.path()).is_empty());
+    }
+
+    #[test]
+    fn test_parse_jules_reader_in_memory() {
+        let content = r#"{"taskId":"task_1","usage":[{"model":"gemini-2.5-pro","inputTokens":100,"outputTokens":50}]}"#;
+
+        let messages = parse_jules_reader(content.as_bytes().to_vec(), "fallback", 1748772000000);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "task_1");
+        assert_eq!(messages[0].timestamp, 1748772000000);
+    }
+}
@@ -0,0 +1,86 @@
+//! Kilo Code (VS Code extension) session parser
+//!
+//! Kilo Code keeps Cline's task layout: `api_conversation_history.json` files
+//! under the extension's global storage, `kilocode.kilo-code/tasks/<task_id>/`.
+
+use super::vscode_task::{dedup_task_messages, parse_vscode_task, parse_vscode_task_reader};
+use super::UnifiedMessage;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Parse a Kilo task's `api_conversation_history.json`
+pub fn parse_kilo_file(path: &Path) -> Vec<UnifiedMessage> {
+    parse_vscode_task(path, parse_kilo_reader)
+}
+
+/// Parse the contents of a Kilo task's `api_conversation_history.json`
+pub fn parse_kilo_reader(
+    data: Vec<u8>,
+    task_id: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    parse_vscode_task_reader(data, "kilo", task_id, fallback_timestamp)
+}
+
+/// Drop repeats of the same `task_id:index` across copies of a task's history,
+/// keeping the first seen; `seen` carries the kept keys across batches
+pub fn dedup_kilo_messages(
+    seen: &mut HashSet<String>,
+    messages: Vec<UnifiedMessage>,
+) -> Vec<UnifiedMessage> {
+    dedup_task_messages(seen, messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const TASK_HISTORY: &str = r#"[
+        {"role": "user", "content": [{"type": "text", "text": "<task>refactor the parser</task>"}], "ts": 1748772000000},
+        {"role": "assistant", "model": "anthropic/claude-sonnet-4", "ts": 1748772004000,
+         "content": [{"type": "text", "text": "Reading the file first."}],
+         "usage": {"input_tokens": 2400, "output_tokens": 180, "cache_creation_input_tokens": 2100, "cache_read_input_tokens": 0}},
+        {"role": "user", "content": [{"type": "tool_result", "content": "fn main() {}"}], "ts": 1748772010000},
+        {"role": "assistant", "model": "anthropic/claude-sonnet-4", "ts": 1748772015000,
+         "content": [{"type": "text", "text": "Done."}],
+         "usage": {"input_tokens": 90, "output_tokens": 420, "cache_creation_input_tokens": 60, "cache_read_input_tokens": 2100}}
+    ]"#;
+
+    #[test]
+    fn test_parse_kilo_task() {
+        let dir = TempDir::new().unwrap();
+        let task_dir = dir.path().join("tasks").join("task-123");
+        std::fs::create_dir_all(&task_dir).unwrap();
+        let path = task_dir.join("api_conversation_history.json");
+        std::fs::write(&path, TASK_HISTORY).unwrap();
+
+        let messages = parse_kilo_file(&path);
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(|m| m.source == "kilo"));
+        assert_eq!(messages[0].model_id, "anthropic/claude-sonnet-4");
+        assert_eq!(messages[0].provider_id, "anthropic");
+        assert_eq!(messages[0].session_id, "task-123");
+        assert_eq!(messages[0].tokens.cache_write, 2100);
+        assert_eq!(messages[1].timestamp, 1748772015000);
+        assert_eq!(messages[1].tokens.cache_read, 2100);
+        assert_eq!(messages[1].tokens.output, 420);
+        assert_eq!(messages[1].dedup_key.as_deref(), Some("task-123:3"));
+
+        let doubled = [parse_kilo_file(&path), parse_kilo_file(&path)].concat();
+        assert_eq!(dedup_kilo_messages(&mut HashSet::new(), doubled).len(), 2);
+    }
+
+    #[test]
+    fn test_parse_kilo_reader_in_memory() {
+        let messages =
+            parse_kilo_reader(TASK_HISTORY.as_bytes().to_vec(), "task-mem", 1748772000000);
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages
+            .iter()
+            .all(|m| m.source == "kilo" && m.session_id == "task-mem"));
+        assert_eq!(messages[0].tokens.input, 2400);
+    }
+}
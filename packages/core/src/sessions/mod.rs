@@ -5,14 +5,26 @@
 pub mod amp;
 pub mod claudecode;
 pub mod codex;
+pub mod copilot;
 pub mod cursor;
 pub mod droid;
 pub mod gemini;
+pub mod headless;
+pub mod jules;
+pub mod kilo;
 pub mod openclaw;
 pub mod opencode;
+pub(crate) mod providers;
+pub mod qwen;
+pub mod roo;
+pub mod selftest;
 pub(crate) mod utils;
+pub(crate) mod vscode_task;
+pub mod warp;
 
-use crate::TokenBreakdown;
+use crate::error::ErrorCode;
+use crate::{TokenBreakdown, TokenCostBreakdown};
+use std::path::Path;
 
 #[derive(Debug, Clone)]
 pub struct UnifiedMessage {
@@ -24,8 +36,22 @@ pub struct UnifiedMessage {
     pub date: String,
     pub tokens: TokenBreakdown,
     pub cost: f64,
+    /// `cost` split by token type; set when the message is priced
+    pub cost_breakdown: TokenCostBreakdown,
+    /// `cost` came from the unknown-model rate rather than the model's pricing
+    pub cost_estimated: bool,
+    /// Copilot premium requests the message was billed as; 0 for other sources
+    pub premium_requests: f64,
+    /// Amp credits the message used; never folded into `cost`
+    pub credits: Option<f64>,
     pub agent: Option<String>,
     pub dedup_key: Option<String>,
+    /// Project the session belongs to, in Claude's encoded-path form
+    /// (e.g. `-Users-me-code-app`) so keys line up across sources
+    pub project: Option<String>,
+    /// Sent through Claude's Batch API (`service_tier: "batch"`), which bills
+    /// at a discount
+    pub batch: bool,
 }
 
 pub fn normalize_agent_name(agent: &str) -> String {
@@ -68,6 +94,7 @@ impl UnifiedMessage {
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_with_agent(
         source: impl Into<String>,
         model_id: impl Into<String>,
@@ -91,6 +118,7 @@ impl UnifiedMessage {
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_with_dedup(
         source: impl Into<String>,
         model_id: impl Into<String>,
@@ -114,6 +142,7 @@ impl UnifiedMessage {
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn new_full(
         source: impl Into<String>,
         model_id: impl Into<String>,
@@ -125,7 +154,7 @@ impl UnifiedMessage {
         agent: Option<String>,
         dedup_key: Option<String>,
     ) -> Self {
-        let date = timestamp_to_date(timestamp);
+        let date = date_for_timestamp(timestamp);
         Self {
             source: source.into(),
             model_id: model_id.into(),
@@ -135,10 +164,185 @@ impl UnifiedMessage {
             date,
             tokens,
             cost,
+            cost_breakdown: TokenCostBreakdown::default(),
+            cost_estimated: false,
+            premium_requests: 0.0,
+            credits: None,
             agent,
             dedup_key,
+            project: None,
+            batch: false,
         }
     }
+
+    /// Move the message to `timestamp`, recomputing its date
+    pub fn set_timestamp(&mut self, timestamp: i64) {
+        self.timestamp = timestamp;
+        self.date = date_for_timestamp(timestamp);
+    }
+}
+
+/// Where report dates come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateSource {
+    /// The timestamp the tool recorded for each message
+    #[default]
+    Timestamp,
+    /// The session file's modification time, for tools that write unreliable
+    /// timestamps (e.g. epoch 0)
+    Mtime,
+}
+
+impl DateSource {
+    /// Parse a `date_source` option; unset means [`DateSource::Timestamp`]
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("timestamp") => Ok(DateSource::Timestamp),
+            Some("mtime") => Ok(DateSource::Mtime),
+            Some(other) => Err(format!(
+                "Unknown date source: {} (expected \"timestamp\" or \"mtime\")",
+                other
+            )),
+        }
+    }
+
+    /// Restamp `messages` parsed from `path` under this policy
+    pub fn apply(self, path: &Path, messages: &mut [UnifiedMessage]) {
+        if messages.is_empty() {
+            return;
+        }
+        if let Some(modified) = self.restamp(path) {
+            for msg in messages {
+                msg.set_timestamp(modified);
+            }
+        }
+    }
+
+    /// The timestamp messages parsed from `path` take under this policy, or
+    /// `None` to keep the recorded ones
+    pub fn restamp(self, path: &Path) -> Option<i64> {
+        (self == DateSource::Mtime).then(|| utils::file_modified_timestamp_ms(path))
+    }
+}
+
+/// 2020-01-01T00:00:00Z. No supported tool predates it, so anything earlier is
+/// a placeholder or corrupt value.
+const MIN_PLAUSIBLE_TIMESTAMP_MS: i64 = 1_577_836_800_000;
+
+/// Clock skew allowed past the current time
+const MAX_FUTURE_SKEW_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Whether a message timestamp is in the window a real session can have:
+/// 2020-01-01 up to one day from now. Messages outside it are parsed but left
+/// undated (empty `date`), and every report drops undated messages, so they
+/// count toward no total, day, month or graph range.
+pub fn is_plausible_timestamp(timestamp_ms: i64) -> bool {
+    let latest = chrono::Utc::now().timestamp_millis() + MAX_FUTURE_SKEW_MS;
+    (MIN_PLAUSIBLE_TIMESTAMP_MS..=latest).contains(&timestamp_ms)
+}
+
+fn date_for_timestamp(timestamp: i64) -> String {
+    if is_plausible_timestamp(timestamp) {
+        timestamp_to_date(timestamp)
+    } else {
+        String::new()
+    }
+}
+
+/// What [`sanitize_tokens`] changed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TokenSanitizeCounts {
+    /// Messages with a negative token count, clamped to 0
+    pub clamped: usize,
+    /// Messages dropped for exceeding the per-message ceiling
+    pub dropped: usize,
+}
+
+impl std::ops::AddAssign for TokenSanitizeCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.clamped += other.clamped;
+        self.dropped += other.dropped;
+    }
+}
+
+/// Repair token counts from corrupted sessions: negative counts become 0,
+/// along with the cost they were priced at, and when `max_tokens_per_message`
+/// is set, messages whose total exceeds it are dropped
+pub fn sanitize_tokens(
+    messages: &mut Vec<UnifiedMessage>,
+    max_tokens_per_message: Option<i64>,
+) -> TokenSanitizeCounts {
+    let mut counts = TokenSanitizeCounts::default();
+    messages.retain_mut(|msg| {
+        let tokens = &mut msg.tokens;
+        let costs = &mut msg.cost_breakdown;
+        let mut clamped = false;
+        for (count, cost) in [
+            (&mut tokens.input, &mut costs.input_cost),
+            (&mut tokens.output, &mut costs.output_cost),
+            (&mut tokens.cache_read, &mut costs.cache_read_cost),
+            (&mut tokens.cache_write, &mut costs.cache_write_cost),
+            (&mut tokens.reasoning, &mut costs.reasoning_cost),
+        ] {
+            if *count < 0 {
+                *count = 0;
+                *cost = 0.0;
+                clamped = true;
+            }
+        }
+        if clamped {
+            counts.clamped += 1;
+            if msg.cost_breakdown.unattributed_cost == 0.0 {
+                msg.cost = msg.cost_breakdown.total();
+            }
+        }
+
+        let tokens = &msg.tokens;
+        // Overflow artifacts can sit near i64::MAX
+        let total = [
+            tokens.input,
+            tokens.output,
+            tokens.cache_read,
+            tokens.cache_write,
+            tokens.reasoning,
+        ]
+        .into_iter()
+        .fold(0i64, i64::saturating_add);
+        let keep = max_tokens_per_message.is_none_or(|max| total <= max);
+        if !keep {
+            counts.dropped += 1;
+        }
+        keep
+    });
+    counts
+}
+
+/// Parse one file with `source`'s parser, without scanning. Costs are whatever
+/// the parser recorded; callers price the messages themselves.
+pub fn parse_source_file(path: &Path, source: &str) -> Result<Vec<UnifiedMessage>, String> {
+    let parse: fn(&Path) -> Vec<UnifiedMessage> = match source {
+        "opencode" => |p| opencode::parse_opencode_file(p).into_iter().collect(),
+        "claude" => claudecode::parse_claude_file,
+        "codex" => codex::parse_codex_file,
+        "gemini" => gemini::parse_gemini_file,
+        "cursor" => cursor::parse_cursor_file,
+        "amp" => amp::parse_amp_file,
+        "droid" => droid::parse_droid_file,
+        "openclaw" => openclaw::parse_openclaw_index,
+        "warp" => warp::parse_warp_file,
+        "roo" => roo::parse_roo_file,
+        "kilo" => kilo::parse_kilo_file,
+        "qwen" => qwen::parse_qwen_file,
+        "copilot" => copilot::parse_copilot_file,
+        "jules" => jules::parse_jules_file,
+        other => return Err(ErrorCode::UnknownSource.reason(format!("Unknown source: {}", other))),
+    };
+
+    std::fs::metadata(path).map_err(|e| {
+        ErrorCode::ReadFailed.reason(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+
+    Ok(parse(path))
 }
 
 /// Convert Unix milliseconds timestamp to YYYY-MM-DD date string (local timezone)
@@ -165,10 +369,96 @@ mod tests {
     fn with_tz<F: FnOnce()>(tz: &str, f: F) {
         std::env::set_var("TZ", tz);
         // Force libc to re-read TZ
-        unsafe { tzset(); }
+        unsafe {
+            tzset();
+        }
         f();
     }
 
+    #[test]
+    fn test_sanitize_tokens_clamps_negative_and_drops_absurd_counts() {
+        let tokens = |input: i64, output: i64| TokenBreakdown {
+            input,
+            output,
+            ..Default::default()
+        };
+        let priced = |mut msg: UnifiedMessage, input_cost: f64, output_cost: f64| {
+            msg.cost_breakdown = TokenCostBreakdown {
+                input_cost,
+                output_cost,
+                ..Default::default()
+            };
+            msg.cost = msg.cost_breakdown.total();
+            msg
+        };
+        let mut messages = vec![
+            priced(
+                UnifiedMessage::new(
+                    "claude",
+                    "claude-sonnet-4",
+                    "anthropic",
+                    "s",
+                    1748772000000,
+                    tokens(1_000, -500),
+                    0.0,
+                ),
+                0.003,
+                -0.0075,
+            ),
+            UnifiedMessage::new(
+                "codex",
+                "gpt-5",
+                "openai",
+                "s",
+                1748772000000,
+                tokens(i64::MAX - 10, 100),
+                0.0,
+            ),
+            UnifiedMessage::new(
+                "codex",
+                "gpt-5",
+                "openai",
+                "s",
+                1748772000000,
+                tokens(2_000, 100),
+                0.0,
+            ),
+        ];
+
+        // Without a ceiling only the negative count is repaired
+        let mut unbounded = messages.clone();
+        let counts = sanitize_tokens(&mut unbounded, None);
+        assert_eq!(
+            counts,
+            TokenSanitizeCounts {
+                clamped: 1,
+                dropped: 0
+            }
+        );
+        assert_eq!(unbounded.len(), 3);
+
+        let counts = sanitize_tokens(&mut messages, Some(10_000_000));
+        assert_eq!(
+            counts,
+            TokenSanitizeCounts {
+                clamped: 1,
+                dropped: 1
+            }
+        );
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].tokens.output, 0);
+        assert_eq!(messages[0].tokens.input, 1_000);
+        assert_eq!(messages[0].cost_breakdown.output_cost, 0.0);
+        assert!((messages[0].cost - 0.003).abs() < 1e-12);
+        assert_eq!(messages[1].tokens.input, 2_000);
+
+        // Clean messages are left alone
+        assert_eq!(
+            sanitize_tokens(&mut messages, Some(10_000_000)),
+            TokenSanitizeCounts::default()
+        );
+    }
+
     #[test]
     #[serial]
     fn test_timestamp_to_date() {
@@ -245,6 +535,85 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_implausible_timestamps_are_undated() {
+        let tokens = TokenBreakdown {
+            input: 100,
+            output: 50,
+            cache_read: 0,
+            cache_write: 0,
+            reasoning: 0,
+        };
+        // 9999-12-31T00:00:00Z
+        let far_future = 253402214400000_i64;
+        let msg = UnifiedMessage::new(
+            "claude",
+            "claude-sonnet-4",
+            "anthropic",
+            "s",
+            far_future,
+            tokens.clone(),
+            0.0,
+        );
+        assert_eq!(msg.date, "");
+        assert_eq!(msg.timestamp, far_future);
+
+        let placeholder = UnifiedMessage::new(
+            "claude",
+            "claude-sonnet-4",
+            "anthropic",
+            "s",
+            0,
+            tokens,
+            0.0,
+        );
+        assert_eq!(placeholder.date, "");
+
+        let now = chrono::Utc::now().timestamp_millis();
+        assert!(is_plausible_timestamp(now));
+        assert!(is_plausible_timestamp(now + 60 * 60 * 1000));
+        assert!(!is_plausible_timestamp(now + 2 * MAX_FUTURE_SKEW_MS));
+        assert!(!is_plausible_timestamp(MIN_PLAUSIBLE_TIMESTAMP_MS - 1));
+    }
+
+    #[test]
+    fn test_date_source_picks_timestamp_or_mtime() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("conversation.jsonl");
+        std::fs::write(
+            &path,
+            r#"{"type":"assistant","timestamp":"2025-06-01T10:00:00.000Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}"#,
+        )
+        .unwrap();
+        // 2025-03-15T12:00:00Z
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1742040000000);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let mut messages = parse_source_file(&path, "claude").unwrap();
+        DateSource::Timestamp.apply(&path, &mut messages);
+        assert_eq!(messages[0].date, "2025-06-01");
+
+        DateSource::Mtime.apply(&path, &mut messages);
+        assert_eq!(messages[0].date, "2025-03-15");
+        assert_eq!(messages[0].timestamp, 1742040000000);
+    }
+
+    #[test]
+    fn test_date_source_parse() {
+        assert_eq!(DateSource::parse(None), Ok(DateSource::Timestamp));
+        assert_eq!(
+            DateSource::parse(Some("timestamp")),
+            Ok(DateSource::Timestamp)
+        );
+        assert_eq!(DateSource::parse(Some("mtime")), Ok(DateSource::Mtime));
+        assert!(DateSource::parse(Some("ctime")).is_err());
+    }
+
     #[test]
     fn test_normalize_agent_name() {
         assert_eq!(normalize_agent_name("OmO"), "Sisyphus");
@@ -259,4 +628,60 @@ mod tests {
         assert_eq!(normalize_agent_name("explore"), "explore");
         assert_eq!(normalize_agent_name("CustomAgent"), "CustomAgent");
     }
+
+    #[test]
+    fn test_parse_source_file_each_source() {
+        for fixture in selftest::FIXTURES {
+            let dir = tempfile::TempDir::new().unwrap();
+            let path = selftest::write_fixture(dir.path(), fixture).unwrap();
+
+            let messages = parse_source_file(&path, fixture.source).unwrap();
+
+            assert_eq!(messages.len(), 1, "{}", fixture.source);
+            assert_eq!(messages[0].source, fixture.source);
+            assert_eq!(messages[0].model_id, fixture.model, "{}", fixture.source);
+        }
+    }
+
+    #[test]
+    fn test_parse_source_file_openclaw_index() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let session_path = dir.path().join("session-abc.jsonl");
+        std::fs::write(
+            &session_path,
+            r#"{"type":"model_change","provider":"anthropic","modelId":"claude-3.5-sonnet"}
+{"type":"message","id":"msg1","message":{"role":"assistant","content":[],"usage":{"input":100,"output":50},"timestamp":1700000000000}}"#,
+        )
+        .unwrap();
+        let index_path = dir.path().join("sessions.json");
+        std::fs::write(
+            &index_path,
+            format!(
+                r#"{{"agent:main:main": {{"sessionId": "abc-123", "sessionFile": "{}"}}}}"#,
+                session_path.to_string_lossy().replace('\\', "\\\\")
+            ),
+        )
+        .unwrap();
+
+        let messages = parse_source_file(&index_path, "openclaw").unwrap();
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].source, "openclaw");
+        assert_eq!(messages[0].session_id, "abc-123");
+    }
+
+    #[test]
+    fn test_parse_source_file_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("conversation.jsonl");
+        std::fs::write(&path, "").unwrap();
+
+        let err = parse_source_file(&path, "chatgpt").unwrap_err();
+        assert_eq!(err, "E_UNKNOWN_SOURCE: Unknown source: chatgpt");
+
+        let missing = dir.path().join("missing.jsonl");
+        assert!(parse_source_file(&missing, "claude")
+            .unwrap_err()
+            .starts_with("E_READ_FAILED: Failed to read"));
+    }
 }
@@ -50,6 +50,7 @@ struct OpenClawUsage {
     #[serde(rename = "cacheWrite")]
     cache_write: Option<i64>,
     #[serde(rename = "totalTokens")]
+    #[allow(dead_code)]
     total_tokens: Option<i64>,
     cost: Option<OpenClawCost>,
 }
@@ -91,8 +92,11 @@ fn parse_openclaw_session(session_path: &Path, session_id: &str) -> Vec<UnifiedM
         Ok(f) => f,
         Err(_) => return Vec::new(),
     };
+    parse_openclaw_reader(BufReader::new(file), session_id)
+}
 
-    let reader = BufReader::new(file);
+/// Parse OpenClaw session JSONL for `session_id` from `reader`
+pub fn parse_openclaw_reader(reader: impl BufRead, session_id: &str) -> Vec<UnifiedMessage> {
     let mut messages = Vec::new();
     let mut current_model: Option<String> = None;
     let mut current_provider: Option<String> = None;
@@ -253,4 +257,16 @@ mod tests {
         assert_eq!(messages[0].model_id, "claude-3.5-sonnet");
         assert_eq!(messages[0].session_id, "abc-123");
     }
+    #[test]
+    fn test_parse_openclaw_reader_in_memory() {
+        let content = r#"{"type":"model_change","provider":"openai-codex","modelId":"gpt-5.2"}
+{"type":"message","message":{"role":"assistant","usage":{"input":10,"output":5,"cost":{"total":0.01}},"timestamp":1700000000000}}"#;
+
+        let messages = parse_openclaw_reader(content.as_bytes(), "mem-session");
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "mem-session");
+        assert_eq!(messages[0].model_id, "gpt-5.2");
+        assert_eq!(messages[0].cost, 0.01);
+    }
 }
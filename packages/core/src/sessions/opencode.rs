@@ -1,7 +1,12 @@
 //! OpenCode session parser
 //!
 //! Parses individual JSON files from ~/.local/share/opencode/storage/message/
+//!
+//! Newer versions can split a message across its own file and part files next
+//! to it, listed in `parts`; when the message file has no tokens, the parts'
+//! tokens and cost are summed in its place.
 
+use super::utils::{encode_project_path, FileBytes};
 use super::{normalize_agent_name, UnifiedMessage};
 use crate::TokenBreakdown;
 use serde::Deserialize;
@@ -24,6 +29,27 @@ pub struct OpenCodeMessage {
     pub time: OpenCodeTime,
     pub agent: Option<String>,
     pub mode: Option<String>,
+    pub path: Option<OpenCodePath>,
+    /// Ids of part files (`<id>.json`) in the same directory
+    #[serde(default)]
+    pub parts: Vec<String>,
+}
+
+/// A part file of a split message; only parts that recorded usage matter
+#[derive(Debug, Deserialize)]
+pub struct OpenCodePart {
+    pub cost: Option<f64>,
+    pub tokens: Option<OpenCodeTokens>,
+}
+
+/// Model id for assistant messages that recorded tokens but no model (some
+/// OpenCode versions keep it in a sibling part file)
+pub const UNKNOWN_MODEL_ID: &str = "opencode-unknown";
+
+#[derive(Debug, Deserialize)]
+pub struct OpenCodePath {
+    pub cwd: Option<String>,
+    pub root: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -32,6 +58,8 @@ pub struct OpenCodeTokens {
     pub output: i64,
     pub reasoning: Option<i64>,
     pub cache: OpenCodeCache,
+    /// Image input tokens reported separately from `input` (folded into input)
+    pub image: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,37 +75,115 @@ pub struct OpenCodeTime {
     pub completed: Option<f64>,
 }
 
+impl OpenCodeTokens {
+    fn breakdown(&self) -> TokenBreakdown {
+        TokenBreakdown {
+            input: self.input + self.image.unwrap_or(0),
+            output: self.output,
+            cache_read: self.cache.read,
+            cache_write: self.cache.write,
+            reasoning: self.reasoning.unwrap_or(0),
+        }
+    }
+}
+
 pub fn parse_opencode_file(path: &Path) -> Option<UnifiedMessage> {
-    let data = std::fs::read(path).ok()?;
-    let mut bytes = data;
+    let data = FileBytes::read(path).ok()?;
+    parse_opencode_reader(data.into_buffer(), path.parent())
+}
+
+/// Parse the contents of an OpenCode message file, which carries its own
+/// session id and timestamps. Part files are looked up in `parts_dir`, if
+/// given, only when the message itself recorded no tokens.
+pub fn parse_opencode_reader(
+    mut data: Vec<u8>,
+    parts_dir: Option<&Path>,
+) -> Option<UnifiedMessage> {
+    let msg: OpenCodeMessage = simd_json::from_slice(&mut data).ok()?;
+
+    let has_tokens = msg
+        .tokens
+        .as_ref()
+        .is_some_and(|t| t.breakdown().total() > 0);
+    let recovered = match parts_dir {
+        Some(dir) if !has_tokens && !msg.parts.is_empty() => read_parts(dir, &msg.parts),
+        _ => None,
+    };
+    to_unified(msg, recovered)
+}
 
-    let msg: OpenCodeMessage = simd_json::from_slice(&mut bytes).ok()?;
+/// Tokens and cost summed over the part files in `dir` named by `part_ids`;
+/// `None` when none of them recorded usage
+fn read_parts(dir: &Path, part_ids: &[String]) -> Option<(TokenBreakdown, f64)> {
+    let mut tokens = TokenBreakdown::default();
+    let mut cost = 0.0;
+    let mut found = false;
 
+    for id in part_ids {
+        // Ids name files in this directory, never paths out of it
+        if Path::new(id).file_name().and_then(|n| n.to_str()) != Some(id.as_str()) {
+            continue;
+        }
+        let Ok(data) = FileBytes::read(&dir.join(format!("{}.json", id))) else {
+            continue;
+        };
+        let mut bytes = data.into_buffer();
+        let Ok(part) = simd_json::from_slice::<OpenCodePart>(&mut bytes) else {
+            continue;
+        };
+        if let Some(part_tokens) = part.tokens {
+            let part_tokens = part_tokens.breakdown();
+            tokens.input += part_tokens.input;
+            tokens.output += part_tokens.output;
+            tokens.cache_read += part_tokens.cache_read;
+            tokens.cache_write += part_tokens.cache_write;
+            tokens.reasoning += part_tokens.reasoning;
+            found = true;
+        }
+        cost += part.cost.unwrap_or(0.0);
+    }
+
+    found.then_some((tokens, cost))
+}
+
+/// `msg` as a unified message, with `recovered` tokens and cost from its part
+/// files standing in for its own
+fn to_unified(
+    msg: OpenCodeMessage,
+    recovered: Option<(TokenBreakdown, f64)>,
+) -> Option<UnifiedMessage> {
     if msg.role != "assistant" {
         return None;
     }
 
-    let tokens = msg.tokens?;
-    let model_id = msg.model_id?;
+    let (tokens, cost) = match recovered {
+        Some((tokens, cost)) => (tokens, Some(cost).filter(|c| *c > 0.0).or(msg.cost)),
+        None => (msg.tokens?.breakdown(), msg.cost),
+    };
+    let model_id = match msg.model_id {
+        Some(model_id) => model_id,
+        None if tokens.total() > 0 => UNKNOWN_MODEL_ID.to_string(),
+        None => return None,
+    };
     let agent_or_mode = msg.mode.or(msg.agent);
     let agent = agent_or_mode.map(|a| normalize_agent_name(&a));
+    let project = msg
+        .path
+        .and_then(|p| p.root.or(p.cwd))
+        .and_then(|dir| encode_project_path(&dir));
 
-    Some(UnifiedMessage::new_with_agent(
+    let mut message = UnifiedMessage::new_with_agent(
         "opencode",
         model_id,
         msg.provider_id.unwrap_or_else(|| "unknown".to_string()),
         msg.session_id.clone(),
         msg.time.created as i64,
-        TokenBreakdown {
-            input: tokens.input,
-            output: tokens.output,
-            cache_read: tokens.cache.read,
-            cache_write: tokens.cache.write,
-            reasoning: tokens.reasoning.unwrap_or(0),
-        },
-        msg.cost.unwrap_or(0.0),
+        tokens,
+        cost.unwrap_or(0.0),
         agent,
-    ))
+    );
+    message.project = project;
+    Some(message)
 }
 
 #[cfg(test)]
@@ -134,4 +240,179 @@ mod tests {
 
         assert_eq!(msg.agent, Some("OmO".to_string()));
     }
+
+    #[test]
+    fn test_parse_opencode_image_tokens_folded_into_input() {
+        let json = r#"{
+            "id": "msg_123",
+            "sessionID": "ses_456",
+            "role": "assistant",
+            "modelID": "claude-sonnet-4",
+            "providerID": "anthropic",
+            "tokens": {
+                "input": 1000,
+                "output": 500,
+                "image": 1500,
+                "cache": { "read": 0, "write": 0 }
+            },
+            "time": { "created": 1700000000000.0 }
+        }"#;
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+
+        let msg = parse_opencode_file(file.path()).unwrap();
+
+        assert_eq!(msg.tokens.input, 2500);
+        assert_eq!(msg.tokens.output, 500);
+    }
+
+    #[test]
+    fn test_parse_opencode_without_model_falls_back() {
+        let json = r#"{
+            "id": "msg_123",
+            "sessionID": "ses_456",
+            "role": "assistant",
+            "tokens": { "input": 800, "output": 200, "cache": { "read": 0, "write": 0 } },
+            "time": { "created": 1700000000000.0 }
+        }"#;
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+
+        let msg = parse_opencode_file(file.path()).unwrap();
+
+        assert_eq!(msg.model_id, UNKNOWN_MODEL_ID);
+        assert_eq!(msg.provider_id, "unknown");
+        assert_eq!(msg.tokens.input, 800);
+        assert_eq!(msg.tokens.output, 200);
+
+        let empty = json.replace("800", "0").replace("200", "0");
+        std::fs::write(file.path(), empty).unwrap();
+        assert!(parse_opencode_file(file.path()).is_none());
+    }
+
+    #[test]
+    fn test_parse_opencode_project_from_path_root() {
+        let json = r#"{
+            "id": "msg_123",
+            "sessionID": "ses_456",
+            "role": "assistant",
+            "modelID": "claude-sonnet-4",
+            "providerID": "anthropic",
+            "tokens": { "input": 10, "output": 5, "cache": { "read": 0, "write": 0 } },
+            "time": { "created": 1700000000000.0 },
+            "path": { "cwd": "/Users/me/code/app/src", "root": "/Users/me/code/app" }
+        }"#;
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+
+        let message = parse_opencode_file(file.path()).unwrap();
+        assert_eq!(message.project.as_deref(), Some("-Users-me-code-app"));
+    }
+
+    #[test]
+    fn test_parse_opencode_large_file() {
+        let summary = "x".repeat(crate::sessions::utils::MMAP_THRESHOLD_BYTES as usize);
+        let json = format!(
+            r#"{{
+            "id": "msg_123",
+            "sessionID": "ses_456",
+            "role": "assistant",
+            "modelID": "claude-sonnet-4",
+            "providerID": "anthropic",
+            "summary": "{}",
+            "tokens": {{ "input": 1000, "output": 500, "cache": {{ "read": 0, "write": 0 }} }},
+            "time": {{ "created": 1700000000000.0 }}
+        }}"#,
+            summary
+        );
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), json).unwrap();
+
+        let msg = parse_opencode_file(file.path()).unwrap();
+
+        assert_eq!(msg.model_id, "claude-sonnet-4");
+        assert_eq!(msg.tokens.input, 1000);
+        assert_eq!(msg.tokens.output, 500);
+    }
+
+    #[test]
+    fn test_parse_opencode_reader_in_memory() {
+        let json = r#"{
+            "id": "msg_1", "sessionID": "ses_1", "role": "assistant",
+            "modelID": "claude-sonnet-4", "providerID": "anthropic",
+            "tokens": {"input": 1000, "output": 500, "cache": {"read": 200, "write": 50}},
+            "time": {"created": 1700000000000.0}
+        }"#;
+
+        let message = parse_opencode_reader(json.as_bytes().to_vec(), None).unwrap();
+
+        assert_eq!(message.session_id, "ses_1");
+        assert_eq!(message.tokens.cache_write, 50);
+        assert!(parse_opencode_reader(b"{}".to_vec(), None).is_none());
+    }
+
+    #[test]
+    fn test_parse_opencode_split_message_recovers_part_tokens() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let message = r#"{
+            "id": "msg_1", "sessionID": "ses_1", "role": "assistant",
+            "modelID": "claude-sonnet-4", "providerID": "anthropic",
+            "tokens": {"input": 0, "output": 0, "cache": {"read": 0, "write": 0}},
+            "time": {"created": 1700000000000.0},
+            "parts": ["prt_1", "prt_2", "prt_text", "prt_missing", "../escape"]
+        }"#;
+        let parts = [
+            (
+                "prt_1",
+                r#"{"id":"prt_1","type":"step-finish","cost":0.02,"tokens":{"input":100,"output":40,"reasoning":5,"cache":{"read":300,"write":0}}}"#,
+            ),
+            (
+                "prt_2",
+                r#"{"id":"prt_2","type":"step-finish","cost":0.01,"tokens":{"input":50,"output":10,"cache":{"read":0,"write":20}}}"#,
+            ),
+            (
+                "prt_text",
+                r#"{"id":"prt_text","type":"text","text":"hello"}"#,
+            ),
+        ];
+        std::fs::write(dir.path().join("msg_1.json"), message).unwrap();
+        for (id, content) in parts {
+            std::fs::write(dir.path().join(format!("{}.json", id)), content).unwrap();
+        }
+
+        let parsed = parse_opencode_file(&dir.path().join("msg_1.json")).unwrap();
+
+        assert_eq!(parsed.model_id, "claude-sonnet-4");
+        assert_eq!(parsed.tokens.input, 150);
+        assert_eq!(parsed.tokens.output, 50);
+        assert_eq!(parsed.tokens.reasoning, 5);
+        assert_eq!(parsed.tokens.cache_read, 300);
+        assert_eq!(parsed.tokens.cache_write, 20);
+        assert!((parsed.cost - 0.03).abs() < 1e-9);
+        // Part files alone are not messages
+        assert!(parse_opencode_file(&dir.path().join("prt_1.json")).is_none());
+    }
+
+    #[test]
+    fn test_parse_opencode_parts_ignored_when_message_has_tokens() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let message = r#"{
+            "id": "msg_1", "sessionID": "ses_1", "role": "assistant",
+            "modelID": "claude-sonnet-4", "providerID": "anthropic", "cost": 0.05,
+            "tokens": {"input": 10, "output": 5, "cache": {"read": 0, "write": 0}},
+            "time": {"created": 1700000000000.0},
+            "parts": ["prt_1"]
+        }"#;
+        std::fs::write(dir.path().join("msg_1.json"), message).unwrap();
+        std::fs::write(
+            dir.path().join("prt_1.json"),
+            r#"{"cost":1.0,"tokens":{"input":999,"output":999,"cache":{"read":0,"write":0}}}"#,
+        )
+        .unwrap();
+
+        let parsed = parse_opencode_file(&dir.path().join("msg_1.json")).unwrap();
+
+        assert_eq!(parsed.tokens.input, 10);
+        assert!((parsed.cost - 0.05).abs() < 1e-9);
+    }
 }
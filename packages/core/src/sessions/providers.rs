@@ -0,0 +1,172 @@
+//! Model → provider inference for sources that don't record the provider
+//!
+//! A user map in `<config dir>/tokscale/model-providers.json` is consulted
+//! first, e.g. `{"command-r": "cohere", "mistral": "mistral"}`. A key equal to
+//! the model id wins; otherwise the longest key contained in it does. Keys
+//! match case-insensitively. Models the map doesn't cover fall back to the
+//! source's built-in name heuristic.
+//!
+//! The map is read once, the first time a provider is inferred, and kept for
+//! the life of the process: edits take effect after Node restarts. A map that
+//! isn't valid JSON fails every report with `E_INVALID_PROVIDER_MAP` (see
+//! `check_overrides`); paths that don't report it fall back to the heuristics.
+
+use crate::error::ErrorCode;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+static OVERRIDES: Lazy<Result<ProviderOverrides, String>> =
+    Lazy::new(|| ProviderOverrides::load_at(&overrides_path()));
+
+pub fn overrides_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("tokscale")
+        .join("model-providers.json")
+}
+
+/// The user's model → provider map, keys lowercased
+#[derive(Debug, Default)]
+pub struct ProviderOverrides {
+    entries: Vec<(String, String)>,
+}
+
+impl ProviderOverrides {
+    /// The map at `path`; a missing or unreadable file means none, and one
+    /// that isn't a JSON object of strings is an `E_INVALID_PROVIDER_MAP` error
+    pub fn load_at(path: &Path) -> Result<Self, String> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Ok(Self::default());
+        };
+        let map: HashMap<String, String> = serde_json::from_str(&content).map_err(|e| {
+            ErrorCode::InvalidProviderMap.reason(format!("{}: {}", path.display(), e))
+        })?;
+        let mut entries: Vec<(String, String)> = map
+            .into_iter()
+            .filter(|(model, provider)| !model.trim().is_empty() && !provider.trim().is_empty())
+            .map(|(model, provider)| (model.trim().to_lowercase(), provider.trim().to_string()))
+            .collect();
+        // Longest key first, so the most specific substring wins
+        entries.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Ok(ProviderOverrides { entries })
+    }
+
+    /// The configured provider for `model`, if any key matches it
+    pub fn provider_for(&self, model: &str) -> Option<&str> {
+        let lower = model.to_lowercase();
+        self.entries
+            .iter()
+            .find(|(key, _)| *key == lower)
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .find(|(key, _)| lower.contains(key.as_str()))
+            })
+            .map(|(_, provider)| provider.as_str())
+    }
+}
+
+/// The error from loading the user's map, if it was malformed
+pub fn check_overrides() -> Result<(), String> {
+    OVERRIDES.as_ref().map(|_| ()).map_err(Clone::clone)
+}
+
+/// Provider for `model`: the user's configured one, else what the source's
+/// `builtin` heuristic guesses
+pub fn infer_provider(model: &str, builtin: fn(&str) -> &'static str) -> String {
+    match OVERRIDES.as_ref() {
+        Ok(overrides) => infer_provider_with(overrides, model, builtin),
+        Err(_) => builtin(model).to_string(),
+    }
+}
+
+fn infer_provider_with(
+    overrides: &ProviderOverrides,
+    model: &str,
+    builtin: fn(&str) -> &'static str,
+) -> String {
+    overrides
+        .provider_for(model)
+        .map_or_else(|| builtin(model).to_string(), str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn heuristic(model: &str) -> &'static str {
+        if model.contains("sonnet") {
+            "anthropic"
+        } else {
+            "cursor"
+        }
+    }
+
+    #[test]
+    fn test_configured_provider_precedes_heuristics() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("model-providers.json");
+        fs::write(
+            &path,
+            r#"{"command-r": "cohere", "Command-R-Plus": "cohere-plus", "sonnet-x": "acme"}"#,
+        )
+        .unwrap();
+        let overrides = ProviderOverrides::load_at(&path).unwrap();
+
+        assert_eq!(
+            infer_provider_with(&overrides, "command-r", heuristic),
+            "cohere"
+        );
+        assert_eq!(
+            infer_provider_with(&overrides, "command-r-08-2024", heuristic),
+            "cohere"
+        );
+        // The longer, more specific key wins
+        assert_eq!(
+            infer_provider_with(&overrides, "command-r-plus", heuristic),
+            "cohere-plus"
+        );
+        // A configured entry beats the built-in "sonnet" rule
+        assert_eq!(
+            infer_provider_with(&overrides, "sonnet-x-1", heuristic),
+            "acme"
+        );
+
+        // Unconfigured models still use the heuristic
+        assert_eq!(
+            infer_provider_with(&overrides, "claude-sonnet-4", heuristic),
+            "anthropic"
+        );
+        assert_eq!(
+            infer_provider_with(&overrides, "mystery-model", heuristic),
+            "cursor"
+        );
+    }
+
+    #[test]
+    fn test_missing_config_means_no_overrides_and_invalid_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let missing = ProviderOverrides::load_at(&dir.path().join("absent.json")).unwrap();
+        assert_eq!(missing.provider_for("command-r"), None);
+        assert_eq!(
+            infer_provider_with(&missing, "command-r", heuristic),
+            "cursor"
+        );
+
+        let invalid = dir.path().join("model-providers.json");
+        fs::write(&invalid, "not json").unwrap();
+        let err = ProviderOverrides::load_at(&invalid).unwrap_err();
+        assert_eq!(
+            ErrorCode::of(&err),
+            Some(ErrorCode::InvalidProviderMap),
+            "{err}"
+        );
+        assert!(err.contains("model-providers.json"), "{err}");
+
+        fs::write(&invalid, r#"{"command-r": 1}"#).unwrap();
+        assert!(ProviderOverrides::load_at(&invalid).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! Qwen Code CLI session parser
+//!
+//! Qwen Code is a Gemini CLI fork and keeps its layout: session files under
+//! `~/.qwen/tmp/*/chats/session-*.json` and headless output with a `stats` block.
+
+use super::gemini::{parse_gemini_cli_file, parse_gemini_cli_reader};
+use super::UnifiedMessage;
+use std::io::BufRead;
+use std::path::Path;
+
+/// Parse a Qwen Code session or headless output file
+pub fn parse_qwen_file(path: &Path) -> Vec<UnifiedMessage> {
+    parse_gemini_cli_file(path, "qwen", "alibaba", parse_qwen_reader)
+}
+
+/// Parse Qwen Code session or headless output from `reader`; `path` is where
+/// the data lives and names the session when the data does not
+pub fn parse_qwen_reader(
+    reader: impl BufRead,
+    path: &Path,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    parse_gemini_cli_reader(reader, path, "qwen", "alibaba", fallback_timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_qwen_session() {
+        let json = r#"{
+            "sessionId": "ses_qwen",
+            "projectHash": "abc123",
+            "startTime": "2025-06-15T12:00:00Z",
+            "lastUpdated": "2025-06-15T12:30:00Z",
+            "messages": [
+                {"id": "m1", "timestamp": "2025-06-15T12:00:00Z", "type": "user", "content": "Hello"},
+                {"id": "m2", "timestamp": "2025-06-15T12:01:00Z", "type": "qwen", "content": "Hi",
+                 "model": "qwen3-coder-plus",
+                 "tokens": {"input": 1200, "output": 300, "cached": 800, "thoughts": 40, "tool": 0, "total": 2340}}
+            ]
+        }"#;
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session-abc.json");
+        std::fs::write(&path, json).unwrap();
+
+        let mut messages = parse_qwen_file(&path);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].source, "qwen");
+        assert_eq!(messages[0].provider_id, "alibaba");
+        assert_eq!(messages[0].model_id, "qwen3-coder-plus");
+        assert_eq!(messages[0].session_id, "ses_qwen");
+        assert_eq!(messages[0].tokens.input, 1200);
+        assert_eq!(messages[0].tokens.cache_read, 800);
+        assert_eq!(messages[0].tokens.reasoning, 40);
+
+        use crate::pricing::{ModelPricing, PricingService};
+        let rates = ModelPricing {
+            input_cost_per_token: Some(0.000001),
+            output_cost_per_token: Some(0.000005),
+            cache_read_input_token_cost: Some(0.0000001),
+            ..Default::default()
+        };
+        let service = PricingService::new(
+            [("qwen3-coder-plus".to_string(), rates)].into(),
+            Default::default(),
+        );
+        service.apply(&mut messages[0]);
+
+        // The 800 cached tokens are already part of the 1200 input, so they
+        // aren't billed again: 1200 input + (300 output + 40 thoughts) at output
+        assert!((messages[0].cost - (1200.0 * 0.000001 + 340.0 * 0.000005)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parse_qwen_headless_stats() {
+        let json = r#"{"response":"Done","stats":{"models":{"qwen3-coder-plus":{"tokens":{"prompt":500,"candidates":120,"cached":200,"thoughts":10}}}}}"#;
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("run-1.json");
+        std::fs::write(&path, json).unwrap();
+
+        let messages = parse_qwen_file(&path);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].source, "qwen");
+        assert_eq!(messages[0].provider_id, "alibaba");
+        assert_eq!(messages[0].session_id, "run-1");
+        assert_eq!(messages[0].tokens.input, 500);
+        assert_eq!(messages[0].tokens.output, 120);
+        assert_eq!(messages[0].tokens.cache_read, 200);
+    }
+
+    #[test]
+    fn test_parse_qwen_reader_in_memory() {
+        let stream = r#"{"type":"init","model":"qwen3-coder-plus"}
+{"type":"result","stats":{"models":{"qwen3-coder-plus":{"tokens":{"prompt":300,"candidates":40}}}}}"#;
+
+        let messages =
+            parse_qwen_reader(stream.as_bytes(), Path::new("run-2.jsonl"), 1748772000000);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].source, "qwen");
+        assert_eq!(messages[0].session_id, "run-2");
+        assert_eq!(messages[0].tokens.input, 300);
+        assert_eq!(messages[0].timestamp, 1748772000000);
+    }
+}
@@ -0,0 +1,126 @@
+//! Roo Code (VS Code extension) session parser
+//!
+//! Parses `api_conversation_history.json` files from the extension's global
+//! storage, `rooveterinaryinc.roo-cline/tasks/<task_id>/`.
+
+use super::vscode_task::{dedup_task_messages, parse_vscode_task, parse_vscode_task_reader};
+use super::UnifiedMessage;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Parse a Roo task's `api_conversation_history.json`
+pub fn parse_roo_file(path: &Path) -> Vec<UnifiedMessage> {
+    parse_vscode_task(path, parse_roo_reader)
+}
+
+/// Parse the contents of a Roo task's `api_conversation_history.json`
+pub fn parse_roo_reader(
+    data: Vec<u8>,
+    task_id: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    parse_vscode_task_reader(data, "roo", task_id, fallback_timestamp)
+}
+
+/// Drop repeats of the same `task_id:index` across copies of a task's history,
+/// keeping the first seen; `seen` carries the kept keys across batches
+pub fn dedup_roo_messages(
+    seen: &mut HashSet<String>,
+    messages: Vec<UnifiedMessage>,
+) -> Vec<UnifiedMessage> {
+    dedup_task_messages(seen, messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_task(root: &Path, task_id: &str, content: &str) -> std::path::PathBuf {
+        let task_dir = root.join("tasks").join(task_id);
+        std::fs::create_dir_all(&task_dir).unwrap();
+        let path = task_dir.join("api_conversation_history.json");
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    const FIRST_TURN: &str = r#"
+        {"role": "user", "content": [{"type": "text", "text": "fix the bug"}], "ts": 1748772000000},
+        {"role": "assistant", "model": "claude-sonnet-4", "ts": 1748772005000,
+         "usage": {"input_tokens": 1200, "output_tokens": 300, "cache_creation_input_tokens": 900, "cache_read_input_tokens": 0}}"#;
+
+    const SECOND_TURN: &str = r#"
+        {"role": "user", "content": [{"type": "text", "text": "now add a test"}], "ts": 1748775600000},
+        {"role": "assistant", "model": "claude-sonnet-4", "ts": 1748775610000,
+         "usage": {"input_tokens": 150, "output_tokens": 400, "cache_creation_input_tokens": 0, "cache_read_input_tokens": 2100}}"#;
+
+    #[test]
+    fn test_parse_roo_task() {
+        let dir = TempDir::new().unwrap();
+        let path = write_task(dir.path(), "task-abc", &format!("[{}]", FIRST_TURN));
+
+        let messages = parse_roo_file(&path);
+
+        assert_eq!(messages.len(), 1);
+        let msg = &messages[0];
+        assert_eq!(msg.source, "roo");
+        assert_eq!(msg.model_id, "claude-sonnet-4");
+        assert_eq!(msg.provider_id, "anthropic");
+        assert_eq!(msg.session_id, "task-abc");
+        assert_eq!(msg.timestamp, 1748772005000);
+        assert_eq!(msg.tokens.input, 1200);
+        assert_eq!(msg.tokens.output, 300);
+        assert_eq!(msg.tokens.cache_write, 900);
+        assert_eq!(msg.dedup_key.as_deref(), Some("task-abc:1"));
+    }
+
+    #[test]
+    fn test_resumed_task_duplicates_are_dropped() {
+        let original_root = TempDir::new().unwrap();
+        let resumed_root = TempDir::new().unwrap();
+        let original = write_task(
+            original_root.path(),
+            "task-abc",
+            &format!("[{}]", FIRST_TURN),
+        );
+        let resumed = write_task(
+            resumed_root.path(),
+            "task-abc",
+            &format!("[{},{}]", FIRST_TURN, SECOND_TURN),
+        );
+
+        let mut messages = parse_roo_file(&original);
+        messages.extend(parse_roo_file(&resumed));
+        assert_eq!(messages.len(), 3);
+
+        let messages = dedup_roo_messages(&mut HashSet::new(), messages);
+
+        assert_eq!(messages.len(), 2);
+        let cache_read: i64 = messages.iter().map(|m| m.tokens.cache_read).sum();
+        assert_eq!(cache_read, 2100);
+        let output: i64 = messages.iter().map(|m| m.tokens.output).sum();
+        assert_eq!(output, 700);
+    }
+
+    #[test]
+    fn test_parse_roo_invalid_json() {
+        let dir = TempDir::new().unwrap();
+        let path = write_task(dir.path(), "task-bad", "{not json");
+        assert!(parse_roo_file(&path).is_empty());
+    }
+
+    #[test]
+    fn test_parse_roo_reader_in_memory() {
+        let messages = parse_roo_reader(
+            format!("[{}]", SECOND_TURN).into_bytes(),
+            "task-mem",
+            1748772000000,
+        );
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].source, "roo");
+        assert_eq!(messages[0].session_id, "task-mem");
+        assert_eq!(messages[0].tokens.cache_read, 2100);
+        assert_eq!(messages[0].dedup_key.as_deref(), Some("task-mem:1"));
+    }
+}
@@ -0,0 +1,243 @@
+//! Parser self-test
+//!
+//! Runs every source parser against a minimal embedded fixture, so a parser that
+//! starts returning nothing after an upgrade shows up without real session data.
+//! The same fixtures back the `parse_source_file` tests.
+
+use super::parse_source_file;
+use std::path::{Path, PathBuf};
+
+/// Embedded fixture for one source. `{dir}` in a file's content is replaced
+/// with the directory the fixture is written to.
+pub(super) struct Fixture {
+    pub(super) source: &'static str,
+    /// Files to write, relative to the fixture directory
+    files: &'static [(&'static str, &'static str)],
+    /// The file handed to the parser
+    entry: &'static str,
+    /// Model of the one message the fixture holds
+    pub(super) model: &'static str,
+}
+
+pub(super) const FIXTURES: &[Fixture] = &[
+    Fixture {
+        source: "opencode",
+        files: &[(
+            "msg_001.json",
+            r#"{"id":"msg_001","sessionID":"ses_1","role":"assistant","modelID":"claude-sonnet-4","providerID":"anthropic","tokens":{"input":100,"output":50,"cache":{"read":0,"write":0}},"time":{"created":1748772000000.0}}"#,
+        )],
+        entry: "msg_001.json",
+        model: "claude-sonnet-4",
+    },
+    Fixture {
+        source: "claude",
+        files: &[(
+            "conversation.jsonl",
+            r#"{"type":"assistant","timestamp":"2025-06-01T10:00:00.000Z","requestId":"req_001","message":{"id":"msg_001","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50}}}"#,
+        )],
+        entry: "conversation.jsonl",
+        model: "claude-sonnet-4",
+    },
+    Fixture {
+        source: "codex",
+        files: &[(
+            "session.jsonl",
+            r#"{"timestamp":"2025-06-01T10:00:00Z","type":"turn_context","payload":{"model":"gpt-5-codex"}}
+{"timestamp":"2025-06-01T10:00:01Z","type":"event_msg","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":120,"cached_input_tokens":20,"output_tokens":30}}}}"#,
+        )],
+        entry: "session.jsonl",
+        model: "gpt-5-codex",
+    },
+    Fixture {
+        source: "gemini",
+        files: &[(
+            "session-abc.json",
+            r#"{"sessionId":"ses_1","projectHash":"abc","startTime":"2025-06-01T10:00:00Z","lastUpdated":"2025-06-01T10:01:00Z","messages":[{"id":"m1","timestamp":"2025-06-01T10:01:00Z","type":"gemini","content":"Hi","model":"gemini-2.5-pro","tokens":{"input":10,"output":20,"cached":0,"thoughts":0,"tool":0,"total":30}}]}"#,
+        )],
+        entry: "session-abc.json",
+        model: "gemini-2.5-pro",
+    },
+    Fixture {
+        source: "cursor",
+        files: &[(
+            "usage.csv",
+            "Date,Model,Input (w/ Cache Write),Input (w/o Cache Write),Cache Read,Output Tokens,Total Tokens,Cost,Cost to you\n2025-06-01,gpt-4o,10,5,0,15,30,$0.10,$0.10",
+        )],
+        entry: "usage.csv",
+        model: "gpt-4o",
+    },
+    Fixture {
+        source: "amp",
+        files: &[(
+            "T-abc.json",
+            r#"{"id":"T-abc","usageLedger":{"events":[{"timestamp":"2025-06-01T10:00:00Z","model":"claude-sonnet-4","credits":1.5,"tokens":{"input":100,"output":50}}]}}"#,
+        )],
+        entry: "T-abc.json",
+        model: "claude-sonnet-4",
+    },
+    Fixture {
+        source: "droid",
+        files: &[(
+            "abc.settings.json",
+            r#"{"model":"claude-sonnet-4","providerLock":"anthropic","providerLockTimestamp":"2025-06-01T10:00:00Z","tokenUsage":{"inputTokens":100,"outputTokens":50}}"#,
+        )],
+        entry: "abc.settings.json",
+        model: "claude-sonnet-4",
+    },
+    Fixture {
+        source: "openclaw",
+        files: &[
+            (
+                "session-abc.jsonl",
+                r#"{"type":"model_change","provider":"anthropic","modelId":"claude-sonnet-4"}
+{"type":"message","id":"msg1","message":{"role":"assistant","content":[],"usage":{"input":100,"output":50},"timestamp":1748772000000}}"#,
+            ),
+            (
+                "sessions.json",
+                r#"{"agent:main:main": {"sessionId": "abc-123", "sessionFile": "{dir}/session-abc.jsonl"}}"#,
+            ),
+        ],
+        entry: "sessions.json",
+        model: "claude-sonnet-4",
+    },
+    Fixture {
+        source: "warp",
+        files: &[(
+            "conv_1.json",
+            r#"{"conversation_id":"conv_1","requests":[{"model":"gpt-4.1","timestamp":1748772000000,"usage":{"input_tokens":500,"output_tokens":100}}]}"#,
+        )],
+        entry: "conv_1.json",
+        model: "gpt-4.1",
+    },
+    Fixture {
+        source: "roo",
+        files: &[(
+            "task-1/api_conversation_history.json",
+            r#"[{"role":"assistant","model":"claude-sonnet-4","ts":1748772005000,"usage":{"input_tokens":1200,"output_tokens":300}}]"#,
+        )],
+        entry: "task-1/api_conversation_history.json",
+        model: "claude-sonnet-4",
+    },
+    Fixture {
+        source: "kilo",
+        files: &[(
+            "task-1/api_conversation_history.json",
+            r#"[{"role":"assistant","model":"claude-sonnet-4","ts":1748772005000,"usage":{"input_tokens":1200,"output_tokens":300,"cache_read_input_tokens":800}}]"#,
+        )],
+        entry: "task-1/api_conversation_history.json",
+        model: "claude-sonnet-4",
+    },
+    Fixture {
+        source: "qwen",
+        files: &[(
+            "session-abc.json",
+            r#"{"sessionId":"ses_1","projectHash":"abc","startTime":"2025-06-01T10:00:00Z","lastUpdated":"2025-06-01T10:01:00Z","messages":[{"id":"m1","timestamp":"2025-06-01T10:01:00Z","type":"qwen","content":"Hi","model":"qwen3-coder-plus","tokens":{"input":10,"output":20,"cached":0,"thoughts":0,"tool":0,"total":30}}]}"#,
+        )],
+        entry: "session-abc.json",
+        model: "qwen3-coder-plus",
+    },
+    Fixture {
+        source: "copilot",
+        files: &[(
+            "chatSessions/chat-1.json",
+            r#"{"sessionId":"chat-1","requests":[{"requestId":"r1","timestamp":1748772000000,"modelId":"copilot/gpt-4o","result":{"usage":{"promptTokens":900,"completionTokens":120}}}]}"#,
+        )],
+        entry: "chatSessions/chat-1.json",
+        model: "gpt-4o",
+    },
+    Fixture {
+        source: "jules",
+        files: &[(
+            "task_1.json",
+            r#"{"taskId":"task_1","usage":[{"model":"gemini-2.5-pro","timestamp":1748772000000,"inputTokens":500,"outputTokens":100,"thoughtsTokens":20}]}"#,
+        )],
+        entry: "task_1.json",
+        model: "gemini-2.5-pro",
+    },
+];
+
+/// Outcome of one parser against its fixture
+#[derive(Debug, Clone)]
+pub struct SelfTestResult {
+    pub source: &'static str,
+    /// Messages parsed, or why the fixture could not be set up or parsed
+    pub messages: Result<usize, String>,
+}
+
+impl SelfTestResult {
+    /// A parser works if it produced at least one message
+    pub fn ok(&self) -> bool {
+        matches!(self.messages, Ok(n) if n > 0)
+    }
+}
+
+/// Run every source parser against its embedded fixture
+pub fn run_self_test() -> Vec<SelfTestResult> {
+    let root = std::env::temp_dir().join(format!(
+        "tokscale-self-test-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+
+    let results = FIXTURES
+        .iter()
+        .map(|fixture| SelfTestResult {
+            source: fixture.source,
+            messages: run_fixture(&root.join(fixture.source), fixture),
+        })
+        .collect();
+
+    let _ = std::fs::remove_dir_all(&root);
+    results
+}
+
+fn run_fixture(dir: &Path, fixture: &Fixture) -> Result<usize, String> {
+    let messages = parse_source_file(&write_fixture(dir, fixture)?, fixture.source)?;
+    match messages.first() {
+        Some(message) if message.model_id != fixture.model => Err(format!(
+            "Parsed model {} instead of {}",
+            message.model_id, fixture.model
+        )),
+        _ => Ok(messages.len()),
+    }
+}
+
+/// Write `fixture`'s files under `dir`, returning the file to parse
+pub(super) fn write_fixture(dir: &Path, fixture: &Fixture) -> Result<PathBuf, String> {
+    // Forward slashes keep `{dir}` valid inside JSON strings on Windows
+    let dir_str = dir.to_string_lossy().replace('\\', "/");
+
+    for (name, content) in fixture.files {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&path, content.replace("{dir}", &dir_str))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+
+    Ok(dir.join(fixture.entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_parser_passes_self_test() {
+        let results = run_self_test();
+
+        let sources: Vec<&str> = results.iter().map(|r| r.source).collect();
+        assert_eq!(
+            sources,
+            [
+                "opencode", "claude", "codex", "gemini", "cursor", "amp", "droid", "openclaw",
+                "warp", "roo", "kilo", "qwen", "copilot", "jules"
+            ]
+        );
+        for result in &results {
+            assert!(result.ok(), "{}: {:?}", result.source, result.messages);
+        }
+    }
+}
@@ -4,6 +4,71 @@ use serde_json::Value;
 use std::path::Path;
 use std::time::SystemTime;
 
+/// Files at least this large are memory-mapped by [`FileBytes::map`]
+pub(crate) const MMAP_THRESHOLD_BYTES: u64 = 1024 * 1024;
+
+/// Raw contents of a session file
+pub enum FileBytes {
+    Owned(Vec<u8>),
+    Mapped(memmap2::Mmap),
+}
+
+impl FileBytes {
+    /// Read `path` onto the heap, for parsers that need the whole file as a
+    /// mutable buffer. Mapping buys nothing there: simd-json needs its own
+    /// copy, so the heap peak is the same, and the mapped pages add to RSS
+    /// (see `benches/file_reading.rs`).
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        std::fs::read(path).map(Self::Owned)
+    }
+
+    /// Read `path` for searching in place, mapping it instead when it is at
+    /// least `MMAP_THRESHOLD_BYTES`. A mapped file is backed by the page cache,
+    /// so nothing lands on the heap.
+    pub fn map(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        if file.metadata()?.len() < MMAP_THRESHOLD_BYTES {
+            return Self::read(path);
+        }
+        // SAFETY: the map is only read, and only for as long as it takes to
+        // search it. Session files are written once by the tools that own them;
+        // a concurrent truncation is the accepted risk of mmap.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self::Mapped(map))
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Owned(data) => data,
+            Self::Mapped(map) => map,
+        }
+    }
+
+    /// Mutable buffer for simd-json. Owned bytes are handed over; mapped ones
+    /// are copied once.
+    pub fn into_buffer(self) -> Vec<u8> {
+        match self {
+            Self::Owned(data) => data,
+            Self::Mapped(map) => map.to_vec(),
+        }
+    }
+}
+
+/// Encode a project directory the way Claude names `~/.claude/projects/` entries:
+/// every non-alphanumeric character becomes `-`
+pub(crate) fn encode_project_path(path: &str) -> Option<String> {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(
+        trimmed
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect(),
+    )
+}
+
 pub(crate) fn extract_i64(value: Option<&Value>) -> Option<i64> {
     value.and_then(|val| {
         val.as_i64()
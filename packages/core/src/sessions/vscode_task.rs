@@ -0,0 +1,277 @@
+//! Shared reader for Cline-style VS Code extension task storage
+//!
+//! Roo Code and Kilo Code keep each task under `<extension>/tasks/<task_id>/`
+//! with an `api_conversation_history.json` array. Each assistant entry with a
+//! usage block becomes one message.
+
+use super::cursor::infer_provider;
+use super::providers;
+use super::utils::{file_modified_timestamp_ms, FileBytes};
+use super::UnifiedMessage;
+use crate::TokenBreakdown;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// One entry of a task's API conversation history
+#[derive(Debug, Deserialize)]
+pub struct TaskHistoryEntry {
+    pub role: Option<String>,
+    pub model: Option<String>,
+    /// Unix milliseconds
+    pub ts: Option<i64>,
+    pub usage: Option<TaskUsage>,
+}
+
+/// Usage block in either the Anthropic shape (`input_tokens`, cache fields) or
+/// the OpenAI shape (`prompt_tokens`, with cached and reasoning detail), as the
+/// extensions record whichever the provider returned
+#[derive(Debug, Default, Deserialize)]
+pub struct TaskUsage {
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub cache_creation_input_tokens: Option<i64>,
+    pub cache_read_input_tokens: Option<i64>,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PromptTokensDetails {
+    pub cached_tokens: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CompletionTokensDetails {
+    pub reasoning_tokens: Option<i64>,
+}
+
+impl TaskUsage {
+    pub fn tokens(&self) -> TokenBreakdown {
+        if self.input_tokens.is_none() && self.prompt_tokens.is_some() {
+            // OpenAI counts cached prompt tokens inside prompt_tokens and
+            // reasoning inside completion_tokens
+            let cached = self
+                .prompt_tokens_details
+                .as_ref()
+                .and_then(|d| d.cached_tokens)
+                .unwrap_or(0);
+            let reasoning = self
+                .completion_tokens_details
+                .as_ref()
+                .and_then(|d| d.reasoning_tokens)
+                .unwrap_or(0);
+            return TokenBreakdown {
+                input: (self.prompt_tokens.unwrap_or(0) - cached).max(0),
+                output: (self.completion_tokens.unwrap_or(0) - reasoning).max(0),
+                cache_read: cached,
+                cache_write: 0,
+                reasoning,
+            };
+        }
+
+        TokenBreakdown {
+            input: self.input_tokens.unwrap_or(0),
+            output: self.output_tokens.unwrap_or(0),
+            cache_read: self.cache_read_input_tokens.unwrap_or(0),
+            cache_write: self.cache_creation_input_tokens.unwrap_or(0),
+            reasoning: 0,
+        }
+    }
+}
+
+/// Read a task's `api_conversation_history.json` and hand its contents, task
+/// id and modification time to `parse`, the extension's reader core
+pub fn parse_vscode_task(
+    path: &Path,
+    parse: impl FnOnce(Vec<u8>, &str, i64) -> Vec<UnifiedMessage>,
+) -> Vec<UnifiedMessage> {
+    let data = match FileBytes::read(path) {
+        Ok(d) => d,
+        Err(_) => return Vec::new(),
+    };
+    // The task directory name is the task id
+    let task_id = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+    parse(
+        data.into_buffer(),
+        task_id,
+        file_modified_timestamp_ms(path),
+    )
+}
+
+/// Parse the contents of an `api_conversation_history.json` for `task_id` as
+/// messages from `source`. Each message's dedup key is its `task_id:index`
+/// position in the history.
+pub fn parse_vscode_task_reader(
+    mut data: Vec<u8>,
+    source: &str,
+    task_id: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    let history: Vec<TaskHistoryEntry> = match simd_json::from_slice(&mut data) {
+        Ok(h) => h,
+        Err(_) => return Vec::new(),
+    };
+
+    history
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            if entry.role.as_deref() != Some("assistant") {
+                return None;
+            }
+            let tokens = entry.usage?.tokens();
+            if tokens.input
+                + tokens.output
+                + tokens.cache_read
+                + tokens.cache_write
+                + tokens.reasoning
+                == 0
+            {
+                return None;
+            }
+
+            let model = entry
+                .model
+                .filter(|m| !m.is_empty())
+                .unwrap_or_else(|| "unknown".to_string());
+            // infer_provider falls back to "cursor", which means nothing here
+            let provider = providers::infer_provider(&model, |model| match infer_provider(model) {
+                "cursor" => "unknown",
+                provider => provider,
+            });
+
+            // A resumed task keeps its earlier entries at the same positions
+            Some(UnifiedMessage::new_with_dedup(
+                source,
+                model,
+                provider,
+                task_id.to_string(),
+                entry.ts.unwrap_or(fallback_timestamp),
+                tokens,
+                0.0,
+                Some(format!("{}:{}", task_id, index)),
+            ))
+        })
+        .collect()
+}
+
+/// Drop repeats of the same `task_id:index` across copies of a task's history,
+/// keeping the first seen. `seen` holds the keys kept so far, so histories
+/// parsed in batches dedup against the earlier batches too.
+pub fn dedup_task_messages(
+    seen: &mut HashSet<String>,
+    messages: Vec<UnifiedMessage>,
+) -> Vec<UnifiedMessage> {
+    messages
+        .into_iter()
+        .filter(|msg| match &msg.dedup_key {
+            Some(key) => seen.insert(key.clone()),
+            None => true,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_history(dir: &TempDir, content: &str) -> std::path::PathBuf {
+        let task_dir = dir.path().join("task-42");
+        std::fs::create_dir_all(&task_dir).unwrap();
+        let path = task_dir.join("api_conversation_history.json");
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn parse_as(path: &Path, source: &str) -> Vec<UnifiedMessage> {
+        parse_vscode_task(path, |data, task_id, ts| {
+            parse_vscode_task_reader(data, source, task_id, ts)
+        })
+    }
+
+    #[test]
+    fn test_anthropic_usage() {
+        let dir = TempDir::new().unwrap();
+        let path = write_history(
+            &dir,
+            r#"[
+                {"role": "user", "ts": 1748772000000},
+                {"role": "assistant", "model": "claude-sonnet-4", "ts": 1748772005000,
+                 "usage": {"input_tokens": 120, "output_tokens": 80, "cache_creation_input_tokens": 900, "cache_read_input_tokens": 3000}}
+            ]"#,
+        );
+
+        let messages = parse_as(&path, "kilo");
+
+        assert_eq!(messages.len(), 1);
+        let msg = &messages[0];
+        assert_eq!(msg.source, "kilo");
+        assert_eq!(msg.provider_id, "anthropic");
+        assert_eq!(msg.session_id, "task-42");
+        assert_eq!(msg.tokens.input, 120);
+        assert_eq!(msg.tokens.output, 80);
+        assert_eq!(msg.tokens.cache_write, 900);
+        assert_eq!(msg.tokens.cache_read, 3000);
+        assert_eq!(msg.dedup_key.as_deref(), Some("task-42:1"));
+    }
+
+    #[test]
+    fn test_openai_usage() {
+        let dir = TempDir::new().unwrap();
+        let path = write_history(
+            &dir,
+            r#"[
+                {"role": "assistant", "model": "gpt-4.1", "ts": 1748772005000,
+                 "usage": {"prompt_tokens": 1500, "completion_tokens": 400,
+                           "prompt_tokens_details": {"cached_tokens": 1000},
+                           "completion_tokens_details": {"reasoning_tokens": 150}}}
+            ]"#,
+        );
+
+        let messages = parse_as(&path, "roo");
+
+        assert_eq!(messages.len(), 1);
+        let msg = &messages[0];
+        assert_eq!(msg.provider_id, "openai");
+        assert_eq!(msg.tokens.input, 500);
+        assert_eq!(msg.tokens.cache_read, 1000);
+        assert_eq!(msg.tokens.output, 250);
+        assert_eq!(msg.tokens.reasoning, 150);
+        assert_eq!(msg.tokens.cache_write, 0);
+    }
+
+    #[test]
+    fn test_dedup_by_index() {
+        let dir = TempDir::new().unwrap();
+        let path = write_history(
+            &dir,
+            r#"[{"role": "assistant", "model": "gpt-4.1", "usage": {"prompt_tokens": 10, "completion_tokens": 5}}]"#,
+        );
+
+        let messages = [parse_as(&path, "roo"), parse_as(&path, "roo")].concat();
+
+        assert_eq!(dedup_task_messages(&mut HashSet::new(), messages).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_vscode_task_reader_in_memory() {
+        let history = r#"[{"role": "assistant", "model": "claude-sonnet-4",
+            "usage": {"input_tokens": 120, "output_tokens": 80}}]"#;
+
+        let messages =
+            parse_vscode_task_reader(history.as_bytes().to_vec(), "roo", "task-7", 1748772000000);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "task-7");
+        assert_eq!(messages[0].timestamp, 1748772000000);
+        assert_eq!(messages[0].dedup_key.as_deref(), Some("task-7:0"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Warp terminal (Agent Mode) session parser
+//!
+//! Parses the JSON conversation files Warp keeps in its local AI session store,
+//! one file per conversation with a usage record for each request.
+
+use super::cursor::infer_provider;
+use super::providers;
+use super::utils::{file_modified_timestamp_ms, parse_timestamp_value, FileBytes};
+use super::UnifiedMessage;
+use crate::TokenBreakdown;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+/// Warp conversation file structure
+#[derive(Debug, Deserialize)]
+pub struct WarpConversation {
+    #[serde(alias = "conversationId")]
+    pub conversation_id: Option<String>,
+    #[serde(default)]
+    pub requests: Vec<WarpRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WarpRequest {
+    pub model: Option<String>,
+    /// RFC 3339 string or Unix time (seconds or milliseconds)
+    pub timestamp: Option<Value>,
+    pub usage: Option<WarpUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WarpUsage {
+    #[serde(default, alias = "inputTokens")]
+    pub input_tokens: i64,
+    #[serde(default, alias = "outputTokens")]
+    pub output_tokens: i64,
+    #[serde(default, alias = "cacheReadTokens")]
+    pub cache_read_tokens: i64,
+    #[serde(default, alias = "cacheWriteTokens")]
+    pub cache_write_tokens: i64,
+    #[serde(default, alias = "reasoningTokens")]
+    pub reasoning_tokens: i64,
+}
+
+/// Parse a Warp conversation file
+pub fn parse_warp_file(path: &Path) -> Vec<UnifiedMessage> {
+    let data = match FileBytes::read(path) {
+        Ok(d) => d,
+        Err(_) => return Vec::new(),
+    };
+    let session_id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+    parse_warp_reader(
+        data.into_buffer(),
+        session_id,
+        file_modified_timestamp_ms(path),
+    )
+}
+
+/// Parse the contents of a Warp conversation file. `session_id` is used when the file does not
+/// name its own; `fallback_timestamp` stands in for entries without one.
+pub fn parse_warp_reader(
+    mut data: Vec<u8>,
+    session_id: &str,
+    fallback_timestamp: i64,
+) -> Vec<UnifiedMessage> {
+    let conversation: WarpConversation = match simd_json::from_slice(&mut data) {
+        Ok(c) => c,
+        Err(_) => return Vec::new(),
+    };
+
+    let session_id = conversation
+        .conversation_id
+        .unwrap_or_else(|| session_id.to_string());
+
+    conversation
+        .requests
+        .into_iter()
+        .filter_map(|request| {
+            let usage = request.usage?;
+            let total_tokens = usage.input_tokens
+                + usage.output_tokens
+                + usage.cache_read_tokens
+                + usage.cache_write_tokens
+                + usage.reasoning_tokens;
+            if total_tokens == 0 {
+                return None;
+            }
+
+            let model = request.model.filter(|m| !m.is_empty())?;
+            // infer_provider falls back to "cursor", which means nothing here
+            let provider = providers::infer_provider(&model, |model| match infer_provider(model) {
+                "cursor" => "unknown",
+                provider => provider,
+            });
+            let timestamp = request
+                .timestamp
+                .as_ref()
+                .and_then(parse_timestamp_value)
+                .unwrap_or(fallback_timestamp);
+
+            Some(UnifiedMessage::new(
+                "warp",
+                model,
+                provider,
+                session_id.clone(),
+                timestamp,
+                TokenBreakdown {
+                    input: usage.input_tokens,
+                    output: usage.output_tokens,
+                    cache_read: usage.cache_read_tokens,
+                    cache_write: usage.cache_write_tokens,
+                    reasoning: usage.reasoning_tokens,
+                },
+                0.0,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_file(content: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        std::fs::write(file.path(), content).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_parse_warp_conversation() {
+        let content = r#"{
+            "conversation_id": "conv_123",
+            "requests": [
+                {
+                    "model": "claude-4-sonnet",
+                    "timestamp": "2025-06-01T10:00:00Z",
+                    "usage": { "input_tokens": 1200, "output_tokens": 300, "cache_read_tokens": 800 }
+                },
+                {
+                    "model": "gpt-4.1",
+                    "timestamp": 1748772000000,
+                    "usage": { "inputTokens": 500, "outputTokens": 100 }
+                }
+            ]
+        }"#;
+        let file = create_test_file(content);
+
+        let messages = parse_warp_file(file.path());
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].source, "warp");
+        assert_eq!(messages[0].model_id, "claude-4-sonnet");
+        assert_eq!(messages[0].provider_id, "anthropic");
+        assert_eq!(messages[0].session_id, "conv_123");
+        assert_eq!(messages[0].date, "2025-06-01");
+        assert_eq!(messages[0].tokens.input, 1200);
+        assert_eq!(messages[0].tokens.cache_read, 800);
+        assert_eq!(messages[1].provider_id, "openai");
+        assert_eq!(messages[1].timestamp, 1748772000000);
+        assert_eq!(messages[1].tokens.output, 100);
+    }
+
+    #[test]
+    fn test_parse_warp_skips_zero_usage() {
+        let content = r#"{
+            "requests": [
+                { "model": "claude-4-sonnet", "usage": { "input_tokens": 0, "output_tokens": 0 } },
+                { "model": "claude-4-sonnet" },
+                { "model": "mystery-model", "usage": { "input_tokens": 10, "output_tokens": 5 } }
+            ]
+        }"#;
+        let file = create_test_file(content);
+
+        let messages = parse_warp_file(file.path());
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].model_id, "mystery-model");
+        assert_eq!(messages[0].provider_id, "unknown");
+        // No conversation id: fall back to the file name
+        let stem = file.path().file_stem().unwrap().to_str().unwrap();
+        assert_eq!(messages[0].session_id, stem);
+    }
+
+    #[test]
+    fn test_parse_warp_invalid_json() {
+        let file = create_test_file("not json");
+        assert!(parse_warp_file(file.path()).is_empty());
+    }
+
+    #[test]
+    fn test_parse_warp_reader_in_memory() {
+        let content = r#"{"requests": [{"model": "gpt-4.1", "usage": {"input_tokens": 500, "output_tokens": 100}}]}"#;
+
+        let messages =
+            parse_warp_reader(content.as_bytes().to_vec(), "conv_fallback", 1748772000000);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_id, "conv_fallback");
+        assert_eq!(messages[0].timestamp, 1748772000000);
+    }
+}
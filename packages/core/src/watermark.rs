@@ -0,0 +1,196 @@
+//! "Since last run" watermark
+//!
+//! Remembers the newest message timestamp a delta report has seen, so the
+//! next run reports only what arrived after it. Each report scope (home dir,
+//! sources and filters) keeps its own file under
+//! `<config dir>/tokscale/watermarks/`, so a run scoped to one source or model
+//! never skips past messages another scope hasn't reported yet.
+
+use crate::error::ErrorCode;
+use crate::sessions::{self, UnifiedMessage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Watermark {
+    /// Newest message timestamp seen, in Unix milliseconds
+    timestamp: i64,
+}
+
+pub fn watermark_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("tokscale")
+        .join("watermarks")
+}
+
+/// The watermark file in `dir` for the report scope described by `scope`
+pub fn watermark_path_in(dir: &Path, scope: &[String]) -> PathBuf {
+    let mut hasher = Sha256::new();
+    for part in scope {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    let digest = hasher.finalize();
+    let name: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    dir.join(format!("{}.json", name))
+}
+
+/// The stored watermark; a missing or unreadable file means none
+pub fn load_at(path: &Path) -> Option<i64> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str::<Watermark>(&content)
+        .ok()
+        .map(|w| w.timestamp)
+}
+
+/// Store `timestamp`, replacing the file atomically
+pub fn save_at(path: &Path, timestamp: i64) -> Result<(), String> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("Invalid watermark path: {}", path.display()))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let content = serde_json::to_string(&Watermark { timestamp }).map_err(|e| e.to_string())?;
+    let tmp_path = dir.join(format!(".watermark.json.{}.tmp", std::process::id()));
+    let write_result = fs::write(&tmp_path, content).and_then(|_| fs::rename(&tmp_path, path));
+    if write_result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    write_result.map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Forget every scope's watermark in `dir` so the next delta reports cover
+/// everything
+pub fn reset_all_at(dir: &Path) -> Result<(), String> {
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(ErrorCode::ReadFailed.reason(format!("Failed to remove {}: {}", dir.display(), e)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Keep only messages newer than the watermark at `path`, then advance it to
+/// the newest plausible timestamp among `messages`. An implausible one (e.g. a
+/// corrupted year-9999 line) never moves it, or it would hide every later
+/// message.
+pub fn take_since_at(
+    path: &Path,
+    messages: Vec<UnifiedMessage>,
+) -> Result<Vec<UnifiedMessage>, String> {
+    let watermark = load_at(path);
+    let newest = messages
+        .iter()
+        .map(|m| m.timestamp)
+        .filter(|&t| sessions::is_plausible_timestamp(t))
+        .max();
+
+    let fresh: Vec<UnifiedMessage> = match watermark {
+        Some(watermark) => messages
+            .into_iter()
+            .filter(|m| m.timestamp > watermark)
+            .collect(),
+        None => messages,
+    };
+
+    if let Some(newest) = newest {
+        if watermark.is_none_or(|w| newest > w) {
+            save_at(path, newest)?;
+        }
+    }
+    Ok(fresh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenBreakdown;
+    use tempfile::TempDir;
+
+    fn message(timestamp: i64) -> UnifiedMessage {
+        let tokens = TokenBreakdown {
+            input: 100,
+            output: 50,
+            ..Default::default()
+        };
+        UnifiedMessage::new(
+            "claude",
+            "claude-sonnet-4",
+            "anthropic",
+            "s",
+            timestamp,
+            tokens,
+            0.0,
+        )
+    }
+
+    #[test]
+    fn test_take_since_reports_only_new_messages() {
+        let dir = TempDir::new().unwrap();
+        let path = watermark_path_in(dir.path(), &["home".to_string()]);
+        let mut dataset = vec![message(1748772000000), message(1748775600000)];
+
+        let first = take_since_at(&path, dataset.clone()).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(load_at(&path), Some(1748775600000));
+
+        dataset.push(message(1748779200000));
+        let second = take_since_at(&path, dataset.clone()).unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].timestamp, 1748779200000);
+
+        // Nothing new leaves the watermark where it was
+        assert!(take_since_at(&path, dataset.clone()).unwrap().is_empty());
+        assert_eq!(load_at(&path), Some(1748779200000));
+
+        reset_all_at(dir.path()).unwrap();
+        assert_eq!(load_at(&path), None);
+        assert_eq!(take_since_at(&path, dataset).unwrap().len(), 3);
+        reset_all_at(&dir.path().join("missing")).unwrap();
+    }
+
+    #[test]
+    fn test_implausible_timestamp_does_not_advance_watermark() {
+        let dir = TempDir::new().unwrap();
+        let path = watermark_path_in(dir.path(), &["home".to_string()]);
+        // 9999-01-01T00:00:00Z
+        let corrupted = 253370764800000;
+        let mut dataset = vec![message(corrupted), message(1748772000000)];
+
+        assert_eq!(take_since_at(&path, dataset.clone()).unwrap().len(), 2);
+        assert_eq!(load_at(&path), Some(1748772000000));
+
+        dataset.push(message(1748775600000));
+        let second = take_since_at(&path, dataset).unwrap();
+        assert!(second.iter().any(|m| m.timestamp == 1748775600000));
+        assert_eq!(load_at(&path), Some(1748775600000));
+    }
+
+    #[test]
+    fn test_scopes_get_their_own_files() {
+        let dir = TempDir::new().unwrap();
+        let scope = |parts: &[&str]| parts.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        let claude = watermark_path_in(dir.path(), &scope(&["/home/me", "claude"]));
+        assert_eq!(
+            claude,
+            watermark_path_in(dir.path(), &scope(&["/home/me", "claude"]))
+        );
+        assert_ne!(
+            claude,
+            watermark_path_in(dir.path(), &scope(&["/home/me", "codex"]))
+        );
+        assert_ne!(
+            claude,
+            watermark_path_in(dir.path(), &scope(&["/home/you", "claude"]))
+        );
+        // Parts are delimited, so shifting text between them changes the key
+        assert_ne!(
+            watermark_path_in(dir.path(), &scope(&["ab", "c"])),
+            watermark_path_in(dir.path(), &scope(&["a", "bc"]))
+        );
+    }
+}